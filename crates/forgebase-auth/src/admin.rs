@@ -0,0 +1,56 @@
+// Helpers for the service-role user management API
+use crate::models::ListUsersQuery;
+use forgebase_core::PaginationParams;
+
+/// Lifetime of impersonation tokens; they have no session and cannot be refreshed
+pub const IMPERSONATION_TOKEN_SECONDS: i64 = 900;
+
+const MAX_USERS_PER_PAGE: u32 = 100;
+
+/// Page and page size of a user listing, clamped to sane bounds
+pub fn pagination(query: &ListUsersQuery) -> PaginationParams {
+    page_params(query.page, query.per_page)
+}
+
+/// Requested page and page size of an admin listing, clamped to sane bounds
+pub fn page_params(page: Option<u32>, per_page: Option<u32>) -> PaginationParams {
+    PaginationParams {
+        page: page.unwrap_or(1).max(1),
+        per_page: per_page.unwrap_or(20).clamp(1, MAX_USERS_PER_PAGE),
+    }
+}
+
+/// `ILIKE` pattern matching `search` anywhere, with wildcards in the input taken literally
+pub fn like_pattern(search: &str) -> String {
+    let escaped = search
+        .trim()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern(" alice "), "%alice%");
+        assert_eq!(like_pattern("100%_off\\"), "%100\\%\\_off\\\\%");
+    }
+
+    #[test]
+    fn test_pagination_is_clamped() {
+        let params = pagination(&ListUsersQuery {
+            page: Some(0),
+            per_page: Some(1000),
+            ..Default::default()
+        });
+        assert_eq!((params.page, params.per_page), (1, 100));
+        assert_eq!(params.offset(), 0);
+
+        let params = pagination(&ListUsersQuery::default());
+        assert_eq!((params.page, params.per_page), (1, 20));
+    }
+}
@@ -0,0 +1,81 @@
+// API key generation and hashing
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// Marks ForgeBase API keys so they can be told apart from JWTs
+pub const API_KEY_PREFIX: &str = "fb_";
+
+const PREFIX_ID_LENGTH: usize = 8;
+const SECRET_LENGTH: usize = 40;
+
+/// A freshly generated key; `key` is only ever shown to the user once
+pub struct GeneratedApiKey {
+    pub key: String,
+    pub prefix: String,
+    pub key_hash: String,
+}
+
+/// Generate a key of the form `fb_<id>_<secret>`
+pub fn generate_api_key() -> GeneratedApiKey {
+    let prefix = format!("{}{}", API_KEY_PREFIX, random_alphanumeric(PREFIX_ID_LENGTH));
+    let key = format!("{}_{}", prefix, random_alphanumeric(SECRET_LENGTH));
+
+    GeneratedApiKey {
+        key_hash: hash_api_key(&key),
+        prefix,
+        key,
+    }
+}
+
+/// SHA-256 hash of a key (keys carry enough entropy that a slow hash adds nothing)
+pub fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Whether a credential looks like an API key rather than a JWT
+pub fn is_api_key(credential: &str) -> bool {
+    credential.starts_with(API_KEY_PREFIX)
+}
+
+/// The lookup prefix of a presented key, if it is well formed
+pub fn parse_prefix(key: &str) -> Option<&str> {
+    let prefix_len = API_KEY_PREFIX.len() + PREFIX_ID_LENGTH;
+    if !is_api_key(key) || key.len() != prefix_len + 1 + SECRET_LENGTH {
+        return None;
+    }
+    if key.as_bytes()[prefix_len] != b'_' {
+        return None;
+    }
+    key.get(..prefix_len)
+}
+
+fn random_alphanumeric(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_parse_api_key() {
+        let generated = generate_api_key();
+        assert!(generated.key.starts_with("fb_"));
+        assert_eq!(parse_prefix(&generated.key), Some(generated.prefix.as_str()));
+        assert_eq!(hash_api_key(&generated.key), generated.key_hash);
+        assert_ne!(generate_api_key().key, generated.key);
+    }
+
+    #[test]
+    fn test_parse_prefix_rejects_malformed_keys() {
+        assert_eq!(parse_prefix("fb_short"), None);
+        assert_eq!(parse_prefix("eyJhbGciOiJIUzI1NiJ9.e30.sig"), None);
+        let generated = generate_api_key();
+        let without_separator = generated.key.replacen('_', "x", 2);
+        assert_eq!(parse_prefix(&without_separator), None);
+    }
+}
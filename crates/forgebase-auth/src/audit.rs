@@ -0,0 +1,384 @@
+// Audit log of security events: sign-ins, failures, password and session changes, admin actions
+use crate::client::ClientInfo;
+use crate::jwt::Claims;
+use crate::middleware::AuthState;
+use crate::models::{AuditLogEntry, AuditLogQuery};
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use chrono::{DateTime, Duration, Utc};
+use forgebase_core::{AuditLogConfig, ForgeBaseError, Result};
+use sqlx::PgPool;
+use std::convert::Infallible;
+use uuid::Uuid;
+
+pub const OUTCOME_SUCCESS: &str = "success";
+pub const OUTCOME_FAILURE: &str = "failure";
+
+pub const ACTION_SIGN_UP: &str = "sign_up";
+pub const ACTION_SIGN_IN: &str = "sign_in";
+pub const ACTION_SIGN_OUT: &str = "sign_out";
+pub const ACTION_TOKEN_REFRESH: &str = "token_refresh";
+pub const ACTION_ACCOUNT_LOCKED: &str = "account_locked";
+pub const ACTION_PASSWORD_CHANGE: &str = "password_change";
+pub const ACTION_PASSWORD_RESET_REQUEST: &str = "password_reset_request";
+pub const ACTION_PASSWORD_RESET: &str = "password_reset";
+pub const ACTION_EMAIL_VERIFY: &str = "email_verify";
+pub const ACTION_USER_UPGRADE: &str = "user_upgrade";
+pub const ACTION_IDENTITY_LINK: &str = "identity_link";
+pub const ACTION_MFA_ENROLL: &str = "mfa_enroll";
+pub const ACTION_MFA_VERIFY: &str = "mfa_verify";
+pub const ACTION_MFA_FACTOR_DELETE: &str = "mfa_factor_delete";
+pub const ACTION_SESSION_REVOKE: &str = "session_revoke";
+pub const ACTION_API_KEY_CREATE: &str = "api_key_create";
+pub const ACTION_API_KEY_REVOKE: &str = "api_key_revoke";
+pub const ACTION_USER_CREATE: &str = "user_create";
+pub const ACTION_USER_BAN: &str = "user_ban";
+pub const ACTION_USER_UNBAN: &str = "user_unban";
+pub const ACTION_USER_DELETE: &str = "user_delete";
+pub const ACTION_USER_IMPERSONATE: &str = "user_impersonate";
+pub const ACTION_FORCE_PASSWORD_RESET: &str = "force_password_reset";
+pub const ACTION_ROLE_ASSIGN: &str = "role_assign";
+pub const ACTION_ROLE_UNASSIGN: &str = "role_unassign";
+
+pub const FACTOR_PASSWORD: &str = "password";
+pub const FACTOR_ANONYMOUS: &str = "anonymous";
+pub const FACTOR_SSO: &str = "sso";
+pub const FACTOR_PHONE_OTP: &str = "phone_otp";
+pub const FACTOR_SMS: &str = "sms";
+pub const FACTOR_REFRESH_TOKEN: &str = "refresh_token";
+pub const FACTOR_OAUTH: &str = "oauth";
+
+/// Entries read per query while exporting
+pub const EXPORT_BATCH_SIZE: i64 = 1000;
+
+/// Filters shared by listing, counting and export; binds user_id, action, outcome,
+/// ip_address, actor_id, since and until as $1..$7 (NULL skips a filter)
+const AUDIT_FILTER: &str = r#"
+    ($1::uuid IS NULL OR user_id = $1)
+    AND ($2::text IS NULL OR action = $2)
+    AND ($3::text IS NULL OR outcome = $3)
+    AND ($4::text IS NULL OR ip_address = $4)
+    AND ($5::text IS NULL OR actor_id = $5)
+    AND ($6::timestamptz IS NULL OR created_at >= $6)
+    AND ($7::timestamptz IS NULL OR created_at < $7)
+"#;
+
+/// Where a request came from and who made it, for audit entries
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// Who acted when not the user: an impersonating admin or a service-role caller
+    pub actor_id: Option<String>,
+}
+
+impl AuditContext {
+    pub fn client(user_agent: Option<String>, ip_address: Option<String>) -> Self {
+        Self {
+            ip_address,
+            user_agent,
+            actor_id: None,
+        }
+    }
+
+    /// Take the actor from the request's token (`act` claim, or the service role itself)
+    pub fn with_claims(mut self, claims: &Claims) -> Self {
+        self.actor_id = claims
+            .act
+            .as_ref()
+            .map(|act| act.sub.clone())
+            .or_else(|| claims.is_service_role().then(|| claims.sub.clone()));
+        self
+    }
+
+    /// Record the caller as the actor when they act on another user's account
+    pub fn with_caller(mut self, claims: &Claims) -> Self {
+        self.actor_id.get_or_insert_with(|| claims.sub.clone());
+        self
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AuthState> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AuthState,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let client = ClientInfo::from_request_parts(parts, state)
+            .await
+            .unwrap_or_default();
+        let context = AuditContext::client(client.user_agent.clone(), client.ip_address());
+
+        Ok(match parts.extensions.get::<Claims>() {
+            Some(claims) => context.with_claims(claims),
+            None => context,
+        })
+    }
+}
+
+/// An audit entry being built
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    action: &'static str,
+    outcome: &'static str,
+    user_id: Option<Uuid>,
+    actor_id: Option<String>,
+    email: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    factor: Option<&'static str>,
+    session_id: Option<Uuid>,
+    reason: Option<String>,
+    metadata: serde_json::Value,
+}
+
+impl AuditEntry {
+    pub fn success(action: &'static str) -> Self {
+        Self::new(action, OUTCOME_SUCCESS, None)
+    }
+
+    pub fn failure(action: &'static str, reason: impl Into<String>) -> Self {
+        Self::new(action, OUTCOME_FAILURE, Some(reason.into()))
+    }
+
+    fn new(action: &'static str, outcome: &'static str, reason: Option<String>) -> Self {
+        Self {
+            action,
+            outcome,
+            user_id: None,
+            actor_id: None,
+            email: None,
+            ip_address: None,
+            user_agent: None,
+            factor: None,
+            session_id: None,
+            reason,
+            metadata: serde_json::json!({}),
+        }
+    }
+
+    pub fn user(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    pub fn email(mut self, email: Option<&str>) -> Self {
+        self.email = email.map(str::to_string);
+        self
+    }
+
+    pub fn factor(mut self, factor: &'static str) -> Self {
+        self.factor = Some(factor);
+        self
+    }
+
+    pub fn session(mut self, session_id: Uuid) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+
+    pub fn context(mut self, context: &AuditContext) -> Self {
+        self.ip_address = context.ip_address.clone();
+        self.user_agent = context.user_agent.clone();
+        self.actor_id = context.actor_id.clone();
+        self
+    }
+
+    pub fn metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+/// Audit log storage and retention
+pub struct AuditLog {
+    pool: PgPool,
+    config: AuditLogConfig,
+}
+
+impl AuditLog {
+    pub fn new(pool: PgPool, config: AuditLogConfig) -> Self {
+        Self { pool, config }
+    }
+
+    pub fn config(&self) -> &AuditLogConfig {
+        &self.config
+    }
+
+    /// Store an entry; failures are logged, never surfaced
+    pub async fn record(&self, entry: AuditEntry) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO auth_audit_log
+            (id, action, outcome, user_id, actor_id, email, ip_address, user_agent, factor, session_id, reason, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(entry.action)
+        .bind(entry.outcome)
+        .bind(entry.user_id)
+        .bind(&entry.actor_id)
+        .bind(&entry.email)
+        .bind(&entry.ip_address)
+        .bind(&entry.user_agent)
+        .bind(entry.factor)
+        .bind(entry.session_id)
+        .bind(&entry.reason)
+        .bind(&entry.metadata)
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = result {
+            tracing::error!("Failed to write {} audit entry: {}", entry.action, e);
+        }
+    }
+
+    /// Entries matching the filters, newest first
+    pub async fn list(&self, query: &AuditLogQuery, limit: i64, offset: i64) -> Result<Vec<AuditLogEntry>> {
+        bind_filter(
+            sqlx::query_as::<_, AuditLogEntry>(&format!(
+                "SELECT * FROM auth_audit_log WHERE {} ORDER BY created_at DESC, id DESC LIMIT $8 OFFSET $9",
+                AUDIT_FILTER
+            )),
+            query,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list audit log: {}", e)))
+    }
+
+    pub async fn count(&self, query: &AuditLogQuery) -> Result<i64> {
+        let sql = format!("SELECT COUNT(*) FROM auth_audit_log WHERE {}", AUDIT_FILTER);
+        sqlx::query_scalar(&sql)
+            .bind(query.user_id)
+            .bind(&query.action)
+            .bind(&query.outcome)
+            .bind(&query.ip_address)
+            .bind(&query.actor_id)
+            .bind(query.since)
+            .bind(query.until)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to count audit log: {}", e)))
+    }
+
+    /// Entries matching the filters oldest first, after the `(created_at, id)` cursor
+    pub async fn export_batch(
+        &self,
+        query: &AuditLogQuery,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<AuditLogEntry>> {
+        bind_filter(
+            sqlx::query_as::<_, AuditLogEntry>(&format!(
+                r#"
+                SELECT * FROM auth_audit_log
+                WHERE {} AND ($8::timestamptz IS NULL OR (created_at, id) > ($8, $9))
+                ORDER BY created_at, id
+                LIMIT $10
+                "#,
+                AUDIT_FILTER
+            )),
+            query,
+        )
+        .bind(after.map(|(created_at, _)| created_at))
+        .bind(after.map(|(_, id)| id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to export audit log: {}", e)))
+    }
+
+    /// Delete entries older than the retention period
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let cutoff = Utc::now() - Duration::days(self.config.retention_days);
+        let result = sqlx::query("DELETE FROM auth_audit_log WHERE created_at < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to prune audit log: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn bind_filter<'q>(
+    statement: sqlx::query::QueryAs<'q, sqlx::Postgres, AuditLogEntry, sqlx::postgres::PgArguments>,
+    query: &'q AuditLogQuery,
+) -> sqlx::query::QueryAs<'q, sqlx::Postgres, AuditLogEntry, sqlx::postgres::PgArguments> {
+    statement
+        .bind(query.user_id)
+        .bind(&query.action)
+        .bind(&query.outcome)
+        .bind(&query.ip_address)
+        .bind(&query.actor_id)
+        .bind(query.since)
+        .bind(query.until)
+}
+
+/// An entry as one line of JSON Lines output
+pub fn json_line(entry: &AuditLogEntry) -> Result<String> {
+    let mut line = serde_json::to_string(entry)
+        .map_err(|e| ForgeBaseError::Internal(format!("Failed to encode audit entry: {}", e)))?;
+    line.push('\n');
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwt::SERVICE_ROLE;
+
+    #[test]
+    fn test_actor_comes_from_impersonation_or_service_role() {
+        let user_id = Uuid::new_v4();
+        let claims = Claims::new(user_id, "ada@example.com".to_string(), 3600);
+        assert_eq!(AuditContext::default().with_claims(&claims).actor_id, None);
+
+        let impersonated = claims.clone().with_actor("admin-1".to_string());
+        assert_eq!(
+            AuditContext::default().with_claims(&impersonated).actor_id.as_deref(),
+            Some("admin-1")
+        );
+
+        let service = claims.with_role(SERVICE_ROLE.to_string());
+        assert_eq!(
+            AuditContext::default().with_claims(&service).actor_id,
+            Some(user_id.to_string())
+        );
+    }
+
+    #[test]
+    fn test_json_line() {
+        let entry = AuditLogEntry {
+            id: Uuid::nil(),
+            action: ACTION_SIGN_IN.to_string(),
+            outcome: OUTCOME_FAILURE.to_string(),
+            user_id: None,
+            actor_id: None,
+            email: Some("ada@example.com".to_string()),
+            ip_address: Some("203.0.113.7".to_string()),
+            user_agent: None,
+            factor: Some(FACTOR_PASSWORD.to_string()),
+            session_id: None,
+            reason: Some("invalid_credentials".to_string()),
+            metadata: serde_json::json!({}),
+            created_at: Utc::now(),
+        };
+
+        let line = json_line(&entry).unwrap();
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        let parsed: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(parsed["action"], "sign_in");
+        assert_eq!(parsed["reason"], "invalid_credentials");
+    }
+}
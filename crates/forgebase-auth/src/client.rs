@@ -0,0 +1,226 @@
+// Client IP address (behind trusted proxies) and user agent of a request
+use crate::middleware::AuthState;
+use crate::models::DeviceInfo;
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, HeaderMap},
+};
+use forgebase_core::{ForgeBaseError, Result};
+use ipnet::IpNet;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+/// Proxies whose `X-Forwarded-For` / `X-Real-IP` headers are believed
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    /// Parse addresses and CIDR ranges, e.g. `10.0.0.0/8` or `127.0.0.1`
+    pub fn parse(entries: &[String]) -> Result<Self> {
+        entries
+            .iter()
+            .map(|entry| {
+                entry
+                    .parse::<IpNet>()
+                    .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| {
+                        ForgeBaseError::Config(format!("Invalid trusted proxy: {}", entry))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Self)
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|net| net.contains(ip))
+    }
+
+    /// The client address: the peer itself, or when the peer is a trusted proxy, the
+    /// nearest untrusted address in `X-Forwarded-For` (then `X-Real-IP`).
+    pub fn client_ip(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let peer = peer?;
+        if !self.contains(&peer) {
+            return Some(peer);
+        }
+
+        let forwarded: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|entry| entry.trim().parse().ok())
+            .collect();
+        if let Some(first) = forwarded.first() {
+            // Each proxy appends the address it received from, so read right to left
+            return Some(
+                forwarded
+                    .iter()
+                    .rev()
+                    .find(|ip| !self.contains(ip))
+                    .unwrap_or(first)
+                    .to_owned(),
+            );
+        }
+
+        headers
+            .get("x-real-ip")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .or(Some(peer))
+    }
+}
+
+/// Client address and user agent, extracted in handlers that create sessions
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn ip_address(&self) -> Option<String> {
+        self.ip.map(|ip| ip.to_string())
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AuthState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AuthState,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(512).collect());
+
+        Ok(Self {
+            ip: state
+                .service
+                .trusted_proxies()
+                .client_ip(peer, &parts.headers),
+            user_agent,
+        })
+    }
+}
+
+/// Best-effort browser, OS and device type from a user agent string
+pub fn parse_user_agent(user_agent: &str) -> DeviceInfo {
+    let ua = user_agent.to_lowercase();
+
+    let device_type = if ["bot", "crawler", "spider", "curl/", "wget/"]
+        .iter()
+        .any(|marker| ua.contains(marker))
+    {
+        "bot"
+    } else if ua.contains("ipad") || ua.contains("tablet") {
+        "tablet"
+    } else if ua.contains("mobile") || ua.contains("iphone") || ua.contains("android") {
+        "mobile"
+    } else if ua.contains("windows") || ua.contains("macintosh") || ua.contains("linux") {
+        "desktop"
+    } else {
+        "unknown"
+    };
+
+    // Order matters: Edge and Opera also claim Chrome, Chrome also claims Safari
+    let browser = [
+        ("edg/", "Edge"),
+        ("opr/", "Opera"),
+        ("firefox/", "Firefox"),
+        ("chrome/", "Chrome"),
+        ("crios/", "Chrome"),
+        ("safari/", "Safari"),
+        ("curl/", "curl"),
+    ]
+    .iter()
+    .find(|(marker, _)| ua.contains(marker))
+    .map(|(_, name)| name.to_string());
+
+    let os = [
+        ("iphone", "iOS"),
+        ("ipad", "iOS"),
+        ("android", "Android"),
+        ("windows", "Windows"),
+        ("mac os x", "macOS"),
+        ("cros", "ChromeOS"),
+        ("linux", "Linux"),
+    ]
+    .iter()
+    .find(|(marker, _)| ua.contains(marker))
+    .map(|(_, name)| name.to_string());
+
+    DeviceInfo {
+        browser,
+        os,
+        device_type: device_type.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::parse(&["10.0.0.0/8".to_string(), "127.0.0.1".to_string()]).unwrap()
+    }
+
+    #[test]
+    fn test_client_ip_ignores_headers_from_untrusted_peers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("1.2.3.4"));
+
+        let peer: IpAddr = "203.0.113.9".parse().unwrap();
+        assert_eq!(proxies().client_ip(Some(peer), &headers), Some(peer));
+    }
+
+    #[test]
+    fn test_client_ip_skips_trusted_hops() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("6.6.6.6, 198.51.100.7, 10.1.2.3"),
+        );
+
+        let ip = proxies().client_ip(Some("127.0.0.1".parse().unwrap()), &headers);
+        assert_eq!(ip, Some("198.51.100.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_client_ip_falls_back_to_real_ip() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", HeaderValue::from_static("198.51.100.7"));
+
+        let ip = proxies().client_ip(Some("10.0.0.2".parse().unwrap()), &headers);
+        assert_eq!(ip, Some("198.51.100.7".parse().unwrap()));
+        assert!(TrustedProxies::parse(&["not-an-ip".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_user_agent() {
+        let chrome = parse_user_agent(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+        );
+        assert_eq!(chrome.browser.as_deref(), Some("Chrome"));
+        assert_eq!(chrome.os.as_deref(), Some("Windows"));
+        assert_eq!(chrome.device_type, "desktop");
+
+        let iphone = parse_user_agent(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1",
+        );
+        assert_eq!(iphone.browser.as_deref(), Some("Safari"));
+        assert_eq!(iphone.os.as_deref(), Some("iOS"));
+        assert_eq!(iphone.device_type, "mobile");
+
+        assert_eq!(parse_user_agent("curl/8.4.0").device_type, "bot");
+    }
+}
@@ -1,56 +1,804 @@
-use forgebase_core::Result;
+// Templated email delivery through a persistent outbox
+use crate::models::OutboxEmail;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use forgebase_core::{EmailConfig, EmailTransportKind, ForgeBaseError, Result, SmtpTlsMode};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use sqlx::PgPool;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tera::{Context, Tera};
+use uuid::Uuid;
 
-/// Email service - to be fully implemented with proper mail backend
+pub const OUTBOX_STATUS_PENDING: &str = "pending";
+pub const OUTBOX_STATUS_SENT: &str = "sent";
+pub const OUTBOX_STATUS_FAILED: &str = "failed";
+
+/// How long a claimed message is hidden from other workers while it is being sent
+const DELIVERY_LEASE_SECONDS: i64 = 300;
+const OUTBOX_BATCH_SIZE: i64 = 50;
+
+const BUILT_IN_LOCALE: &str = "en";
+const BUILT_IN_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "en/verification_email.subject",
+        include_str!("../templates/en/verification_email.subject"),
+    ),
+    (
+        "en/verification_email.html",
+        include_str!("../templates/en/verification_email.html"),
+    ),
+    (
+        "en/verification_email.txt",
+        include_str!("../templates/en/verification_email.txt"),
+    ),
+    (
+        "en/password_reset.subject",
+        include_str!("../templates/en/password_reset.subject"),
+    ),
+    (
+        "en/password_reset.html",
+        include_str!("../templates/en/password_reset.html"),
+    ),
+    (
+        "en/password_reset.txt",
+        include_str!("../templates/en/password_reset.txt"),
+    ),
+    (
+        "en/magic_link.subject",
+        include_str!("../templates/en/magic_link.subject"),
+    ),
+    (
+        "en/magic_link.html",
+        include_str!("../templates/en/magic_link.html"),
+    ),
+    (
+        "en/magic_link.txt",
+        include_str!("../templates/en/magic_link.txt"),
+    ),
+    (
+        "en/invite.subject",
+        include_str!("../templates/en/invite.subject"),
+    ),
+    (
+        "en/invite.html",
+        include_str!("../templates/en/invite.html"),
+    ),
+    ("en/invite.txt", include_str!("../templates/en/invite.txt")),
+    (
+        "en/account_locked.subject",
+        include_str!("../templates/en/account_locked.subject"),
+    ),
+    (
+        "en/account_locked.html",
+        include_str!("../templates/en/account_locked.html"),
+    ),
+    (
+        "en/account_locked.txt",
+        include_str!("../templates/en/account_locked.txt"),
+    ),
+];
+
+/// Built-in email templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailTemplate {
+    Verification,
+    PasswordReset,
+    MagicLink,
+    Invite,
+    AccountLocked,
+}
+
+impl EmailTemplate {
+    /// File name stem of the template, e.g. `en/<name>.html`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Verification => "verification_email",
+            Self::PasswordReset => "password_reset",
+            Self::MagicLink => "magic_link",
+            Self::Invite => "invite",
+            Self::AccountLocked => "account_locked",
+        }
+    }
+}
+
+/// Subject, HTML and plain text bodies of a rendered email
+#[derive(Debug, Clone)]
+pub struct RenderedEmail {
+    pub locale: String,
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+/// Per-locale templates: `<locale>/<template>.subject`, `.html` and `.txt`.
+///
+/// Files in the configured templates directory override the built-in English templates
+/// and add further locales. Missing files fall back to the base language, then the
+/// default locale, then English.
+pub struct EmailTemplates {
+    tera: Tera,
+    default_locale: String,
+}
+
+impl EmailTemplates {
+    pub fn new(templates_dir: Option<&Path>, default_locale: &str) -> Result<Self> {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(BUILT_IN_TEMPLATES.iter().copied())
+            .map_err(template_error)?;
+
+        if let Some(dir) = templates_dir {
+            let mut overrides = Vec::new();
+            for locale in read_dir(dir)? {
+                if !locale.is_dir() {
+                    continue;
+                }
+                for file in read_dir(&locale)? {
+                    if let (Some(locale), Some(file_name)) = (file_name(&locale), file_name(&file))
+                    {
+                        let content = std::fs::read_to_string(&file).map_err(|e| {
+                            ForgeBaseError::Config(format!(
+                                "Failed to read email template {}: {}",
+                                file.display(),
+                                e
+                            ))
+                        })?;
+                        overrides.push((format!("{}/{}", locale, file_name), content));
+                    }
+                }
+            }
+            tera.add_raw_templates(overrides).map_err(template_error)?;
+        }
+
+        Ok(Self {
+            tera,
+            default_locale: default_locale.to_string(),
+        })
+    }
+
+    /// Render a template in the closest available locale
+    pub fn render(
+        &self,
+        template: EmailTemplate,
+        locale: Option<&str>,
+        context: &Context,
+    ) -> Result<RenderedEmail> {
+        let candidates = locale_candidates(locale, &self.default_locale);
+        let render = |extension: &str| -> Result<(String, String)> {
+            for locale in &candidates {
+                let name = format!("{}/{}.{}", locale, template.name(), extension);
+                if self.tera.get_template_names().any(|n| n == name) {
+                    let output = self.tera.render(&name, context).map_err(template_error)?;
+                    return Ok((locale.clone(), output));
+                }
+            }
+            Err(ForgeBaseError::Config(format!(
+                "Email template {}.{} not found",
+                template.name(),
+                extension
+            )))
+        };
+
+        let (locale, html) = render("html")?;
+        let (_, subject) = render("subject")?;
+        let (_, text) = render("txt")?;
+
+        Ok(RenderedEmail {
+            locale,
+            subject: subject.trim().to_string(),
+            html,
+            text,
+        })
+    }
+}
+
+/// Locales to try in order: `pt-BR`, `pt`, the default locale, then English
+fn locale_candidates(locale: Option<&str>, default_locale: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    let mut push = |locale: &str| {
+        if !locale.is_empty() && !candidates.iter().any(|c| c == locale) {
+            candidates.push(locale.to_string());
+        }
+    };
+
+    if let Some(locale) = locale.map(|l| l.trim().replace('_', "-")) {
+        push(&locale);
+        if let Some((language, _)) = locale.split_once('-') {
+            push(language);
+        }
+    }
+    push(default_locale);
+    push(BUILT_IN_LOCALE);
+    candidates
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        ForgeBaseError::Config(format!(
+            "Failed to read email templates {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    Ok(entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect())
+}
+
+fn file_name(path: &Path) -> Option<&str> {
+    path.file_name().and_then(|name| name.to_str())
+}
+
+fn template_error(e: tera::Error) -> ForgeBaseError {
+    ForgeBaseError::Config(format!("Email template error: {:?}", e))
+}
+
+/// Delivers a built message
+#[async_trait]
+pub trait MailTransport: Send + Sync {
+    async fn send(&self, message: &Message) -> Result<()>;
+}
+
+/// SMTP delivery with STARTTLS, implicit TLS or (for local catchers) no TLS
+pub struct SmtpMailTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailTransport {
+    pub fn new(config: &EmailConfig) -> Result<Self> {
+        let builder = match config.smtp_tls {
+            SmtpTlsMode::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+                    .map_err(smtp_error)?
+            }
+            SmtpTlsMode::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)
+                .map_err(smtp_error)?,
+            SmtpTlsMode::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)
+            }
+        };
+
+        let mut builder = builder
+            .port(config.smtp_port)
+            .timeout(Some(std::time::Duration::from_secs(30)));
+        if !config.smtp_username.is_empty() {
+            builder = builder.credentials(Credentials::new(
+                config.smtp_username.clone(),
+                config.smtp_password.clone(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl MailTransport for SmtpMailTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        self.transport
+            .send(message.clone())
+            .await
+            .map_err(smtp_error)?;
+        Ok(())
+    }
+}
+
+fn smtp_error(e: lettre::transport::smtp::Error) -> ForgeBaseError {
+    ForgeBaseError::ExternalService(format!("SMTP error: {}", e))
+}
+
+/// Writes each message to `<dir>/<id>.eml` instead of sending it
+pub struct FileMailTransport {
+    dir: PathBuf,
+}
+
+impl FileMailTransport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl MailTransport for FileMailTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        let path = self.dir.join(format!("{}.eml", Uuid::new_v4()));
+        let write_error = |e: std::io::Error| {
+            ForgeBaseError::Internal(format!("Failed to write {}: {}", path.display(), e))
+        };
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(write_error)?;
+        tokio::fs::write(&path, message.formatted())
+            .await
+            .map_err(write_error)?;
+        tracing::debug!("Wrote email to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Logs each message instead of sending it
+pub struct LogMailTransport;
+
+#[async_trait]
+impl MailTransport for LogMailTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        let to: Vec<String> = message
+            .envelope()
+            .to()
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        tracing::info!(
+            "Email to {}: {}",
+            to.join(", "),
+            message.headers().get_raw("Subject").unwrap_or_default()
+        );
+        tracing::debug!("{}", String::from_utf8_lossy(&message.formatted()));
+        Ok(())
+    }
+}
+
+/// Transport selected by configuration
+pub fn transport_from_config(config: &EmailConfig) -> Result<Arc<dyn MailTransport>> {
+    Ok(match config.transport {
+        EmailTransportKind::Smtp => Arc::new(SmtpMailTransport::new(config)?),
+        EmailTransportKind::File => Arc::new(FileMailTransport::new(&config.file_dir)),
+        EmailTransportKind::Log => Arc::new(LogMailTransport),
+    })
+}
+
+/// Delay before retrying after `attempts` failed deliveries: 30s doubling up to an hour
+pub fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 7) as u32;
+    Duration::seconds((30i64 << exponent).min(3600))
+}
+
+/// Persistent queue of rendered emails
+pub struct EmailOutbox {
+    pool: PgPool,
+}
+
+impl EmailOutbox {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn enqueue(
+        &self,
+        template: EmailTemplate,
+        to_email: &str,
+        to_name: Option<&str>,
+        email: &RenderedEmail,
+    ) -> Result<Uuid> {
+        let id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO email_outbox (
+                id, to_email, to_name, template, locale, subject, html_body, text_body,
+                status, attempts, next_attempt_at, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 0, NOW(), NOW())
+            "#,
+        )
+        .bind(id)
+        .bind(to_email)
+        .bind(to_name)
+        .bind(template.name())
+        .bind(&email.locale)
+        .bind(&email.subject)
+        .bind(&email.html)
+        .bind(&email.text)
+        .bind(OUTBOX_STATUS_PENDING)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to queue email: {}", e)))?;
+
+        Ok(id)
+    }
+
+    /// Claim due messages, counting the attempt and leasing them from other workers
+    pub async fn claim_due(&self, limit: i64) -> Result<Vec<OutboxEmail>> {
+        sqlx::query_as::<_, OutboxEmail>(
+            r#"
+            UPDATE email_outbox
+            SET attempts = attempts + 1, next_attempt_at = NOW() + make_interval(secs => $2)
+            WHERE id IN (
+                SELECT id FROM email_outbox
+                WHERE status = $3 AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(DELIVERY_LEASE_SECONDS as f64)
+        .bind(OUTBOX_STATUS_PENDING)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to claim emails: {}", e)))
+    }
+
+    pub async fn mark_sent(&self, id: Uuid) -> Result<()> {
+        sqlx::query(
+            "UPDATE email_outbox SET status = $2, sent_at = NOW(), last_error = NULL WHERE id = $1",
+        )
+        .bind(id)
+        .bind(OUTBOX_STATUS_SENT)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to mark email sent: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Schedule a retry, or mark the message failed when no retry is due
+    pub async fn mark_failed(&self, email: &OutboxEmail, error: &str, retry: bool) -> Result<()> {
+        let status = if retry {
+            OUTBOX_STATUS_PENDING
+        } else {
+            OUTBOX_STATUS_FAILED
+        };
+        sqlx::query("UPDATE email_outbox SET status = $2, last_error = $3, next_attempt_at = $4 WHERE id = $1")
+            .bind(email.id)
+            .bind(status)
+            .bind(error)
+            .bind(Utc::now() + retry_delay(email.attempts))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to record email failure: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Remove sent messages older than `days`
+    pub async fn delete_sent(&self, days: i64) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM email_outbox WHERE status = $1 AND sent_at < $2")
+            .bind(OUTBOX_STATUS_SENT)
+            .bind(Utc::now() - Duration::days(days))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ForgeBaseError::Database(format!("Failed to delete sent emails: {}", e))
+            })?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Renders account emails and delivers them through the outbox
 pub struct EmailService {
-    from_email: String,
-    from_name: String,
+    templates: EmailTemplates,
+    transport: Arc<dyn MailTransport>,
+    outbox: EmailOutbox,
+    from: Mailbox,
+    site_url: String,
+    max_attempts: i32,
 }
 
 impl EmailService {
-    pub fn new(
-        _smtp_host: String,
-        _smtp_port: u16,
-        _smtp_username: String,
-        _smtp_password: String,
-        from_email: String,
-        from_name: String,
-    ) -> Result<Self> {
+    pub fn new(pool: PgPool, config: &EmailConfig) -> Result<Self> {
+        let from = Mailbox::new(
+            Some(config.from_name.clone()),
+            config.from_email.parse().map_err(|e| {
+                ForgeBaseError::Config(format!("Invalid from address {}: {}", config.from_email, e))
+            })?,
+        );
+
         Ok(Self {
-            from_email,
-            from_name,
+            templates: EmailTemplates::new(
+                config.templates_dir.as_deref().map(Path::new),
+                &config.default_locale,
+            )?,
+            transport: transport_from_config(config)?,
+            outbox: EmailOutbox::new(pool),
+            from,
+            site_url: config.site_url.trim_end_matches('/').to_string(),
+            max_attempts: config.max_attempts,
         })
     }
 
+    /// Deliver through a different transport
+    pub fn with_transport(mut self, transport: Arc<dyn MailTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Absolute link to `path` on the site, carrying `token`
+    pub fn link(&self, path: &str, token: &str) -> String {
+        format!("{}{}?token={}", self.site_url, path, token)
+    }
+
     /// Send verification email
     pub async fn send_verification_email(
         &self,
-        _to_email: &str,
-        _to_name: &str,
-        _verification_link: &str,
+        to_email: &str,
+        to_name: &str,
+        locale: Option<&str>,
+        verification_link: &str,
     ) -> Result<()> {
-        // TODO: Implement email sending with lettre
-        Ok(())
+        let mut context = Context::new();
+        context.insert("verification_link", verification_link);
+        self.enqueue(
+            EmailTemplate::Verification,
+            to_email,
+            to_name,
+            locale,
+            context,
+        )
+        .await
     }
 
     /// Send password reset email
     pub async fn send_password_reset_email(
         &self,
-        _to_email: &str,
-        _to_name: &str,
-        _reset_link: &str,
+        to_email: &str,
+        to_name: &str,
+        locale: Option<&str>,
+        reset_link: &str,
     ) -> Result<()> {
-        // TODO: Implement email sending with lettre
-        Ok(())
+        let mut context = Context::new();
+        context.insert("reset_link", reset_link);
+        self.enqueue(
+            EmailTemplate::PasswordReset,
+            to_email,
+            to_name,
+            locale,
+            context,
+        )
+        .await
     }
 
     /// Send magic link email
     pub async fn send_magic_link_email(
         &self,
-        _to_email: &str,
-        _to_name: &str,
-        _magic_link: &str,
+        to_email: &str,
+        to_name: &str,
+        locale: Option<&str>,
+        magic_link: &str,
+    ) -> Result<()> {
+        let mut context = Context::new();
+        context.insert("magic_link", magic_link);
+        self.enqueue(EmailTemplate::MagicLink, to_email, to_name, locale, context)
+            .await
+    }
+
+    /// Send invitation email
+    pub async fn send_invite_email(
+        &self,
+        to_email: &str,
+        to_name: &str,
+        locale: Option<&str>,
+        inviter: &str,
+        organization: &str,
+        invite_link: &str,
+    ) -> Result<()> {
+        let mut context = Context::new();
+        context.insert("inviter", inviter);
+        context.insert("organization", organization);
+        context.insert("invite_link", invite_link);
+        self.enqueue(EmailTemplate::Invite, to_email, to_name, locale, context)
+            .await
+    }
+
+    /// Send account locked notification
+    pub async fn send_account_locked_email(
+        &self,
+        to_email: &str,
+        to_name: &str,
+        locale: Option<&str>,
+        locked_until: &str,
+    ) -> Result<()> {
+        let mut context = Context::new();
+        context.insert("locked_until", locked_until);
+        self.enqueue(
+            EmailTemplate::AccountLocked,
+            to_email,
+            to_name,
+            locale,
+            context,
+        )
+        .await
+    }
+
+    async fn enqueue(
+        &self,
+        template: EmailTemplate,
+        to_email: &str,
+        to_name: &str,
+        locale: Option<&str>,
+        mut context: Context,
     ) -> Result<()> {
-        // TODO: Implement email sending with lettre
+        context.insert("name", to_name);
+        let email = self.templates.render(template, locale, &context)?;
+        // Reject bad addresses now rather than on every delivery attempt
+        self.build_message(
+            to_email,
+            Some(to_name),
+            &email.subject,
+            &email.text,
+            &email.html,
+        )?;
+        self.outbox
+            .enqueue(template, to_email, Some(to_name), &email)
+            .await?;
+
         Ok(())
     }
+
+    /// Deliver due outbox messages, returning how many were sent
+    pub async fn process_outbox(&self) -> Result<usize> {
+        let mut sent = 0;
+        for email in self.outbox.claim_due(OUTBOX_BATCH_SIZE).await? {
+            let message = match self.build_message(
+                &email.to_email,
+                email.to_name.as_deref(),
+                &email.subject,
+                &email.text_body,
+                &email.html_body,
+            ) {
+                Ok(message) => message,
+                Err(e) => {
+                    self.outbox
+                        .mark_failed(&email, &e.to_string(), false)
+                        .await?;
+                    continue;
+                }
+            };
+
+            match self.transport.send(&message).await {
+                Ok(()) => {
+                    self.outbox.mark_sent(email.id).await?;
+                    sent += 1;
+                }
+                Err(e) => {
+                    let retry = email.attempts < self.max_attempts;
+                    tracing::warn!(
+                        "Failed to deliver email {} (attempt {}): {}",
+                        email.id,
+                        email.attempts,
+                        e
+                    );
+                    self.outbox
+                        .mark_failed(&email, &e.to_string(), retry)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Poll the outbox every `interval` until the task is aborted
+    pub fn spawn_outbox_worker(
+        self: Arc<Self>,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.process_outbox().await {
+                    tracing::error!("Email outbox processing failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Remove sent messages older than `days`
+    pub async fn delete_sent(&self, days: i64) -> Result<u64> {
+        self.outbox.delete_sent(days).await
+    }
+
+    fn build_message(
+        &self,
+        to_email: &str,
+        to_name: Option<&str>,
+        subject: &str,
+        text: &str,
+        html: &str,
+    ) -> Result<Message> {
+        let to = Mailbox::new(
+            to_name.map(str::to_string),
+            to_email.parse().map_err(|e| {
+                ForgeBaseError::Validation(format!("Invalid email address {}: {}", to_email, e))
+            })?,
+        );
+
+        Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(
+                text.to_string(),
+                html.to_string(),
+            ))
+            .map_err(|e| ForgeBaseError::Internal(format!("Failed to build email: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_candidates() {
+        assert_eq!(
+            locale_candidates(Some("pt_BR"), "de"),
+            vec!["pt-BR", "pt", "de", "en"]
+        );
+        assert_eq!(locale_candidates(None, "en"), vec!["en"]);
+    }
+
+    #[test]
+    fn test_render_falls_back_and_overrides() {
+        let dir = std::env::temp_dir().join(format!("forgebase-email-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("fr")).unwrap();
+        std::fs::write(
+            dir.join("fr/password_reset.subject"),
+            "Réinitialisez votre mot de passe",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("fr/password_reset.txt"),
+            "Bonjour {{ name }}, {{ reset_link }}",
+        )
+        .unwrap();
+
+        let templates = EmailTemplates::new(Some(&dir), "en").unwrap();
+        let mut context = Context::new();
+        context.insert("name", "Ada <admin>");
+        context.insert("reset_link", "https://example.com/reset?token=abc");
+
+        let email = templates
+            .render(EmailTemplate::PasswordReset, Some("fr-CA"), &context)
+            .unwrap();
+        assert_eq!(email.subject, "Réinitialisez votre mot de passe");
+        assert_eq!(
+            email.text,
+            "Bonjour Ada <admin>, https://example.com/reset?token=abc"
+        );
+        // No French HTML, so the English one is used, with escaping
+        assert_eq!(email.locale, "en");
+        assert!(email.html.contains("Ada &lt;admin&gt;"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1).num_seconds(), 30);
+        assert_eq!(retry_delay(2).num_seconds(), 60);
+        assert_eq!(retry_delay(5).num_seconds(), 480);
+        assert_eq!(retry_delay(20).num_seconds(), 3600);
+    }
+
+    #[tokio::test]
+    async fn test_file_transport_captures_mail() {
+        let dir = std::env::temp_dir().join(format!("forgebase-mail-{}", Uuid::new_v4()));
+        let message = Message::builder()
+            .from("ForgeBase <noreply@forgebase.dev>".parse().unwrap())
+            .to("ada@example.com".parse().unwrap())
+            .subject("Hello")
+            .multipart(MultiPart::alternative_plain_html(
+                "plain".to_string(),
+                "<p>html</p>".to_string(),
+            ))
+            .unwrap();
+
+        FileMailTransport::new(&dir).send(&message).await.unwrap();
+
+        let files = read_dir(&dir).unwrap();
+        assert_eq!(files.len(), 1);
+        let eml = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(eml.contains("To: ada@example.com"));
+        assert!(eml.contains("Subject: Hello"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -1,43 +1,119 @@
 use crate::{
+    audit::AuditContext,
+    client::ClientInfo,
     middleware::{extract_claims, AuthState},
     models::*,
+    oidc::{client_credentials, OAuthError, UserInfo},
     AuthService,
 };
 use axum::{
-    extract::{Request, State, Extension},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-    routing::{get, post},
+    extract::{Extension, Form, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{delete, get, post, put},
     Json, Router,
 };
-use forgebase_core::{ApiResponse, ErrorResponse, ForgeBaseError};
+use forgebase_core::{ApiResponse, ErrorResponse, ForgeBaseError, PaginatedResponse};
+use serde::Deserialize;
 use std::sync::Arc;
+use uuid::Uuid;
 use validator::Validate;
 
 /// Create authentication routes
 pub fn create_auth_routes() -> Router<AuthState> {
     Router::new()
         .route("/auth/signup", post(sign_up_handler))
+        .route("/auth/signup/anonymous", post(anonymous_sign_up_handler))
         .route("/auth/signin", post(sign_in_handler))
         .route("/auth/signout", post(sign_out_handler))
         .route("/auth/refresh", post(refresh_token_handler))
         .route("/auth/user", get(get_user_handler))
         .route("/auth/user", post(update_profile_handler))
+        .route("/auth/user/upgrade", post(upgrade_anonymous_user_handler))
+        .route("/auth/user/identities", post(link_identity_handler))
         .route("/auth/password/change", post(change_password_handler))
         .route("/auth/password/reset", post(request_password_reset_handler))
         .route("/auth/password/update", post(reset_password_handler))
         .route("/auth/verify", post(verify_email_handler))
+        .route("/auth/otp", post(send_phone_otp_handler))
+        .route("/auth/otp/verify", post(verify_phone_otp_handler))
+        .route("/auth/otp/phone", post(request_phone_change_handler))
+        .route("/auth/otp/phone/verify", post(verify_phone_change_handler))
+        .route("/auth/mfa/factors", get(list_mfa_factors_handler))
+        .route("/auth/mfa/factors/sms", post(enroll_sms_factor_handler))
+        .route("/auth/mfa/factors/:id", delete(delete_mfa_factor_handler))
+        .route("/auth/mfa/factors/:id/challenge", post(challenge_mfa_factor_handler))
+        .route("/auth/mfa/factors/:id/verify", post(verify_mfa_factor_handler))
+        .route("/auth/sessions", get(list_sessions_handler))
+        .route("/auth/sessions", delete(revoke_other_sessions_handler))
+        .route("/auth/sessions/:id", delete(revoke_session_handler))
+        .route("/auth/user/audit", get(list_own_audit_log_handler))
+        .route("/auth/organizations", get(list_organizations_handler))
+        .route("/auth/organizations", post(create_organization_handler))
+        .route("/auth/organizations/:id", get(get_organization_handler))
+        .route("/auth/organizations/:id", put(update_organization_handler))
+        .route("/auth/organizations/:id", delete(delete_organization_handler))
+        .route("/auth/organizations/:id/members", get(list_organization_members_handler))
+        .route("/auth/organizations/:id/members/:user_id", put(update_member_role_handler))
+        .route("/auth/organizations/:id/members/:user_id", delete(remove_organization_member_handler))
+        .route("/auth/organizations/:id/invitations", get(list_organization_invitations_handler))
+        .route("/auth/organizations/:id/invitations", post(invite_organization_member_handler))
+        .route("/auth/organizations/:id/invitations/:invitation_id", delete(revoke_organization_invitation_handler))
+        .route("/auth/organizations/:id/transfer", post(transfer_organization_ownership_handler))
+        .route("/auth/invitations/accept", post(accept_organization_invitation_handler))
+        .route("/auth/api-keys", get(list_api_keys_handler))
+        .route("/auth/api-keys", post(create_api_key_handler))
+        .route("/auth/api-keys/:id", delete(revoke_api_key_handler))
+        .route("/.well-known/jwks.json", get(jwks_handler))
+        .route("/.well-known/openid-configuration", get(openid_configuration_handler))
+        .route("/oauth/authorize", get(authorization_details_handler))
+        .route("/oauth/authorize", post(authorize_handler))
+        .route("/oauth/token", post(oauth_token_handler))
+        .route("/oauth/userinfo", get(oauth_userinfo_handler).post(oauth_userinfo_handler))
+        .route("/oauth/introspect", post(oauth_introspect_handler))
+        .route("/oauth/revoke", post(oauth_revoke_handler))
+        .route("/auth/user/authorizations", get(list_oauth_consents_handler))
+        .route("/auth/user/authorizations/:client_id", delete(revoke_oauth_consent_handler))
+        .route("/auth/admin/oauth/clients", get(list_oauth_clients_handler))
+        .route("/auth/admin/oauth/clients", post(register_oauth_client_handler))
+        .route("/auth/admin/oauth/clients/:id", delete(delete_oauth_client_handler))
+        .route("/auth/admin/keys", get(list_signing_keys_handler))
+        .route("/auth/admin/keys/stage", post(stage_signing_key_handler))
+        .route("/auth/admin/keys/rotate", post(rotate_signing_keys_handler))
+        .route("/auth/admin/keys/:kid", delete(revoke_signing_key_handler))
+        .route("/auth/admin/roles", get(list_roles_handler))
+        .route("/auth/admin/roles", post(create_role_handler))
+        .route("/auth/admin/roles/:id", put(update_role_handler))
+        .route("/auth/admin/roles/:id", delete(delete_role_handler))
+        .route("/auth/admin/audit", get(list_audit_log_handler))
+        .route("/auth/admin/audit/export", get(export_audit_log_handler))
+        .route("/auth/admin/users", get(list_users_handler))
+        .route("/auth/admin/users", post(admin_create_user_handler))
+        .route("/auth/admin/users/:id", get(admin_get_user_handler))
+        .route("/auth/admin/users/:id", delete(admin_delete_user_handler))
+        .route("/auth/admin/users/:id/ban", post(ban_user_handler))
+        .route("/auth/admin/users/:id/ban", delete(unban_user_handler))
+        .route("/auth/admin/users/:id/password-reset", post(force_password_reset_handler))
+        .route("/auth/admin/users/:id/impersonate", post(impersonate_user_handler))
+        .route("/auth/admin/users/:id/roles", get(list_user_roles_handler))
+        .route("/auth/admin/users/:id/roles", post(assign_role_handler))
+        .route("/auth/admin/users/:id/roles/:role_id", delete(unassign_role_handler))
+        .route("/auth/sso", post(sso_sign_in_handler))
+        .route("/auth/sso/saml/metadata", get(saml_metadata_handler))
+        .route("/auth/sso/saml/acs", post(saml_acs_handler))
+        .route("/auth/admin/sso/providers", post(create_sso_provider_handler))
+        .route("/auth/admin/sso/providers", get(list_sso_providers_handler))
+        .route("/auth/admin/sso/providers/:id", delete(delete_sso_provider_handler))
 }
 
 /// Sign up handler
 async fn sign_up_handler(
     State(state): State<AuthState>,
+    client: ClientInfo,
     Json(payload): Json<SignUpRequest>,
 ) -> Result<Json<ApiResponse<AuthResponse>>, ApiError> {
-    // Extract user agent and IP from request
-    // Note: In real implementation, would need proper request extraction
-    let user_agent = None;
-    let ip_address = None;
+    let ip_address = client.ip_address();
+    let user_agent = client.user_agent;
 
     let response = state
         .service
@@ -47,15 +123,33 @@ async fn sign_up_handler(
     Ok(Json(ApiResponse::success(response)))
 }
 
+/// Anonymous sign up handler
+async fn anonymous_sign_up_handler(
+    State(state): State<AuthState>,
+    client: ClientInfo,
+    payload: Option<Json<AnonymousSignUpRequest>>,
+) -> Result<Json<ApiResponse<AuthResponse>>, ApiError> {
+    let ip_address = client.ip_address();
+    let user_agent = client.user_agent;
+
+    let response = state
+        .service
+        .sign_up_anonymous(payload.map(|Json(p)| p).unwrap_or_default(), user_agent, ip_address)
+        .await?;
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
 /// Sign in handler
 async fn sign_in_handler(
     State(state): State<AuthState>,
+    client: ClientInfo,
     Json(payload): Json<SignInRequest>,
 ) -> Result<Json<ApiResponse<AuthResponse>>, ApiError> {
     payload.validate()?;
 
-    let user_agent = None;
-    let ip_address = None;
+    let ip_address = client.ip_address();
+    let user_agent = client.user_agent;
 
     let response = state
         .service
@@ -68,18 +162,23 @@ async fn sign_in_handler(
 /// Refresh token handler
 async fn refresh_token_handler(
     State(state): State<AuthState>,
+    audit: AuditContext,
     Json(payload): Json<RefreshTokenRequest>,
 ) -> Result<Json<ApiResponse<AuthResponse>>, ApiError> {
-    let response = state.service.refresh_token(&payload.refresh_token).await?;
+    let response = state
+        .service
+        .refresh_token(&payload.refresh_token, &audit)
+        .await?;
     Ok(Json(ApiResponse::success(response)))
 }
 
 /// Sign out handler
 async fn sign_out_handler(
     State(state): State<AuthState>,
+    audit: AuditContext,
     Json(payload): Json<RefreshTokenRequest>,
 ) -> Result<Json<ApiResponse<()>>, ApiError> {
-    state.service.sign_out(&payload.refresh_token).await?;
+    state.service.sign_out(&payload.refresh_token, &audit).await?;
     Ok(Json(ApiResponse::success(())))
 }
 
@@ -100,22 +199,61 @@ async fn update_profile_handler(
     Extension(claims): Extension<crate::jwt::Claims>,
     Json(payload): Json<UpdateProfileRequest>,
 ) -> Result<Json<ApiResponse<UserProfile>>, ApiError> {
+    claims.forbid_impersonation()?;
     let user_id = claims.user_id()?;
     let user = state.service.update_profile(user_id, payload).await?;
 
     Ok(Json(ApiResponse::success(user)))
 }
 
+/// Upgrade anonymous user handler
+async fn upgrade_anonymous_user_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<UpgradeAnonymousRequest>,
+) -> Result<Json<ApiResponse<UserProfile>>, ApiError> {
+    claims.forbid_impersonation()?;
+    payload.validate()?;
+
+    let user_id = claims.user_id()?;
+    let user = state
+        .service
+        .upgrade_anonymous_user(user_id, payload, &audit)
+        .await?;
+
+    Ok(Json(ApiResponse::success(user.into())))
+}
+
+/// Link OAuth identity handler
+async fn link_identity_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<OAuthSignInRequest>,
+) -> Result<Json<ApiResponse<UserProfile>>, ApiError> {
+    claims.forbid_impersonation()?;
+    let user_id = claims.user_id()?;
+    let user = state
+        .service
+        .link_oauth_identity(user_id, &payload.provider, &payload.code, &audit)
+        .await?;
+
+    Ok(Json(ApiResponse::success(user.into())))
+}
+
 /// Change password handler
 async fn change_password_handler(
     State(state): State<AuthState>,
+    audit: AuditContext,
     Extension(claims): Extension<crate::jwt::Claims>,
     Json(payload): Json<ChangePasswordRequest>,
 ) -> Result<Json<ApiResponse<()>>, ApiError> {
+    claims.forbid_impersonation()?;
     payload.validate()?;
 
     let user_id = claims.user_id()?;
-    state.service.change_password(user_id, payload).await?;
+    state.service.change_password(user_id, payload, &audit).await?;
 
     Ok(Json(ApiResponse::success(())))
 }
@@ -123,12 +261,16 @@ async fn change_password_handler(
 /// Request password reset handler
 async fn request_password_reset_handler(
     State(state): State<AuthState>,
+    audit: AuditContext,
     Json(payload): Json<PasswordResetRequest>,
 ) -> Result<Json<ApiResponse<()>>, ApiError> {
     payload.validate()?;
 
     // Always return success even if email doesn't exist (security)
-    let _ = state.service.request_password_reset(&payload.email).await;
+    let _ = state
+        .service
+        .request_password_reset(&payload.email, &audit)
+        .await;
 
     Ok(Json(ApiResponse::success(())))
 }
@@ -136,26 +278,980 @@ async fn request_password_reset_handler(
 /// Reset password handler
 async fn reset_password_handler(
     State(state): State<AuthState>,
+    audit: AuditContext,
     Json(payload): Json<PasswordUpdateRequest>,
 ) -> Result<Json<ApiResponse<()>>, ApiError> {
     payload.validate()?;
-    state.service.reset_password(payload).await?;
+    state.service.reset_password(payload, &audit).await?;
     Ok(Json(ApiResponse::success(())))
 }
 
 /// Verify email handler
 async fn verify_email_handler(
     State(state): State<AuthState>,
+    audit: AuditContext,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Json<ApiResponse<()>>, ApiError> {
     let token = payload["token"]
         .as_str()
         .ok_or_else(|| ForgeBaseError::InvalidInput("Missing token".to_string()))?;
 
-    state.service.verify_email(token).await?;
+    state.service.verify_email(token, &audit).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Send phone sign-in code handler
+async fn send_phone_otp_handler(
+    State(state): State<AuthState>,
+    Json(payload): Json<PhoneOtpRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    payload.validate()?;
+    state.service.send_phone_otp(&payload.phone).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Verify phone sign-in code handler
+async fn verify_phone_otp_handler(
+    State(state): State<AuthState>,
+    client: ClientInfo,
+    Json(payload): Json<VerifyPhoneOtpRequest>,
+) -> Result<Json<ApiResponse<AuthResponse>>, ApiError> {
+    payload.validate()?;
+
+    let ip_address = client.ip_address();
+    let user_agent = client.user_agent;
+
+    let response = state
+        .service
+        .verify_phone_otp(&payload.phone, &payload.code, user_agent, ip_address)
+        .await?;
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Request phone number change handler
+async fn request_phone_change_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<PhoneOtpRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    claims.forbid_impersonation()?;
+    payload.validate()?;
+
+    let user_id = claims.user_id()?;
+    state.service.request_phone_change(user_id, &payload.phone).await?;
+
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Confirm phone number change handler
+async fn verify_phone_change_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<VerifyPhoneOtpRequest>,
+) -> Result<Json<ApiResponse<UserProfile>>, ApiError> {
+    claims.forbid_impersonation()?;
+    payload.validate()?;
+
+    let user_id = claims.user_id()?;
+    let user = state
+        .service
+        .verify_phone_change(user_id, &payload.phone, &payload.code)
+        .await?;
+
+    Ok(Json(ApiResponse::success(user.into())))
+}
+
+/// List MFA factors handler
+async fn list_mfa_factors_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<MfaFactor>>>, ApiError> {
+    let user_id = claims.user_id()?;
+    let factors = state.service.list_mfa_factors(user_id).await?;
+
+    Ok(Json(ApiResponse::success(factors)))
+}
+
+/// Enroll SMS factor handler
+async fn enroll_sms_factor_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<PhoneOtpRequest>,
+) -> Result<Json<ApiResponse<MfaFactor>>, ApiError> {
+    claims.forbid_impersonation()?;
+    payload.validate()?;
+
+    let factor = state
+        .service
+        .enroll_sms_factor(&claims, &payload.phone, &audit)
+        .await?;
+
+    Ok(Json(ApiResponse::success(factor)))
+}
+
+/// Send MFA challenge handler
+async fn challenge_mfa_factor_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let user_id = claims.user_id()?;
+    state.service.challenge_sms_factor(user_id, id).await?;
+
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Verify MFA challenge handler
+async fn verify_mfa_factor_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<VerifyMfaRequest>,
+) -> Result<Json<ApiResponse<AuthResponse>>, ApiError> {
+    claims.forbid_impersonation()?;
+    payload.validate()?;
+
+    let response = state
+        .service
+        .verify_sms_factor(&claims, id, &payload.code, &audit)
+        .await?;
+
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Delete MFA factor handler
+async fn delete_mfa_factor_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    claims.forbid_impersonation()?;
+    state.service.delete_mfa_factor(&claims, id, &audit).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// List sessions handler
+async fn list_sessions_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<SessionInfo>>>, ApiError> {
+    let sessions = state.service.list_sessions(&claims).await?;
+    Ok(Json(ApiResponse::success(sessions)))
+}
+
+/// Revoke session handler
+async fn revoke_session_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let user_id = claims.user_id()?;
+    state.service.revoke_session(user_id, id, &audit).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Sign out everywhere else handler
+async fn revoke_other_sessions_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<u64>>, ApiError> {
+    let revoked = state.service.revoke_other_sessions(&claims, &audit).await?;
+    Ok(Json(ApiResponse::success(revoked)))
+}
+
+/// List organizations handler
+async fn list_organizations_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<OrganizationMembership>>>, ApiError> {
+    let user_id = claims.user_id()?;
+    let organizations = state.service.list_organizations(user_id).await?;
+    Ok(Json(ApiResponse::success(organizations)))
+}
+
+/// Create organization handler
+async fn create_organization_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<CreateOrganizationRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<Organization>>), ApiError> {
+    payload.validate()?;
+    let user_id = claims.user_id()?;
+
+    let organization = state.service.create_organization(user_id, payload).await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(organization))))
+}
+
+/// Get organization handler
+async fn get_organization_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Organization>>, ApiError> {
+    let user_id = claims.user_id()?;
+    let organization = state.service.get_organization(user_id, id).await?;
+    Ok(Json(ApiResponse::success(organization)))
+}
+
+/// Update organization handler
+async fn update_organization_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateOrganizationRequest>,
+) -> Result<Json<ApiResponse<Organization>>, ApiError> {
+    payload.validate()?;
+    let user_id = claims.user_id()?;
+
+    let organization = state
+        .service
+        .update_organization(user_id, id, payload)
+        .await?;
+    Ok(Json(ApiResponse::success(organization)))
+}
+
+/// Delete organization handler
+async fn delete_organization_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let user_id = claims.user_id()?;
+    state.service.delete_organization(user_id, id).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// List organization members handler
+async fn list_organization_members_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<OrganizationMember>>>, ApiError> {
+    let user_id = claims.user_id()?;
+    let members = state.service.list_organization_members(user_id, id).await?;
+    Ok(Json(ApiResponse::success(members)))
+}
+
+/// Update member role handler
+async fn update_member_role_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path((id, member_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateMemberRoleRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let user_id = claims.user_id()?;
+    state
+        .service
+        .update_member_role(user_id, id, member_id, payload.role)
+        .await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Remove member (or leave organization) handler
+async fn remove_organization_member_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path((id, member_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let user_id = claims.user_id()?;
+    state
+        .service
+        .remove_organization_member(user_id, id, member_id)
+        .await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// List pending invitations handler
+async fn list_organization_invitations_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<OrganizationInvitation>>>, ApiError> {
+    let user_id = claims.user_id()?;
+    let invitations = state
+        .service
+        .list_organization_invitations(user_id, id)
+        .await?;
+    Ok(Json(ApiResponse::success(invitations)))
+}
+
+/// Invite member handler
+async fn invite_organization_member_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<InviteMemberRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<OrganizationInvitation>>), ApiError> {
+    payload.validate()?;
+    let user_id = claims.user_id()?;
+
+    let invitation = state
+        .service
+        .invite_organization_member(user_id, id, payload)
+        .await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(invitation))))
+}
+
+/// Revoke invitation handler
+async fn revoke_organization_invitation_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path((id, invitation_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let user_id = claims.user_id()?;
+    state
+        .service
+        .revoke_organization_invitation(user_id, id, invitation_id)
+        .await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Accept invitation handler
+async fn accept_organization_invitation_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<AcceptInvitationRequest>,
+) -> Result<Json<ApiResponse<OrganizationMembership>>, ApiError> {
+    let user_id = claims.user_id()?;
+    let membership = state
+        .service
+        .accept_organization_invitation(user_id, &payload.token)
+        .await?;
+    Ok(Json(ApiResponse::success(membership)))
+}
+
+/// Transfer ownership handler
+async fn transfer_organization_ownership_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<TransferOwnershipRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let user_id = claims.user_id()?;
+    state
+        .service
+        .transfer_organization_ownership(user_id, id, payload.user_id)
+        .await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Create API key handler
+async fn create_api_key_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<Json<ApiResponse<ApiKeyResponse>>, ApiError> {
+    claims.forbid_impersonation()?;
+    payload.validate()?;
+
+    let api_key = state.service.create_api_key(&claims, payload, &audit).await?;
+    Ok(Json(ApiResponse::success(api_key)))
+}
+
+/// List API keys handler
+async fn list_api_keys_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<ApiKey>>>, ApiError> {
+    let user_id = claims.user_id()?;
+    let api_keys = state.service.list_api_keys(user_id).await?;
+
+    Ok(Json(ApiResponse::success(api_keys)))
+}
+
+/// Revoke API key handler
+async fn revoke_api_key_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    claims.forbid_impersonation()?;
+    let user_id = claims.user_id()?;
+    state.service.revoke_api_key(user_id, id, &audit).await?;
+
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// JSON Web Key Set handler
+async fn jwks_handler(State(state): State<AuthState>) -> Response {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(state.jwt_manager.jwks()),
+    )
+        .into_response()
+}
+
+/// OpenID Connect discovery document
+async fn openid_configuration_handler(State(state): State<AuthState>) -> Result<Response, ApiError> {
+    let document = state
+        .service
+        .oidc_provider()?
+        .discovery(state.jwt_manager.signing_algorithm());
+    Ok((
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(document),
+    )
+        .into_response())
+}
+
+/// Authorization request details for the consent page
+async fn authorization_details_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Query(params): Query<AuthorizeParams>,
+) -> Result<Json<ApiResponse<AuthorizationDetails>>, ApiError> {
+    let details = state.service.authorization_details(&claims, &params).await?;
+    Ok(Json(ApiResponse::success(details)))
+}
+
+/// Consent decision handler
+async fn authorize_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<AuthorizeDecision>,
+) -> Result<Json<ApiResponse<AuthorizationRedirect>>, ApiError> {
+    claims.forbid_impersonation()?;
+    let redirect = state.service.authorize(&claims, payload).await?;
+    Ok(Json(ApiResponse::success(redirect)))
+}
+
+/// OAuth token endpoint
+async fn oauth_token_handler(
+    State(state): State<AuthState>,
+    headers: HeaderMap,
+    Form(mut payload): Form<OAuthTokenRequest>,
+) -> Result<Response, OAuthError> {
+    let (client_id, client_secret) = client_credentials(
+        &headers,
+        payload.client_id.take(),
+        payload.client_secret.take(),
+    );
+    let tokens = state
+        .service
+        .oauth_token(payload, client_id, client_secret)
+        .await?;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(tokens)).into_response())
+}
+
+/// OpenID Connect userinfo endpoint
+async fn oauth_userinfo_handler(
+    State(state): State<AuthState>,
+    headers: HeaderMap,
+) -> Result<Json<UserInfo>, OAuthError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| crate::jwt::JwtManager::extract_token_from_header(value).ok())
+        .ok_or_else(|| OAuthError::invalid_token("Bearer token required"))?;
+
+    let user_info = state.service.oauth_userinfo(token).await?;
+    Ok(Json(user_info))
+}
+
+/// Token introspection endpoint
+async fn oauth_introspect_handler(
+    State(state): State<AuthState>,
+    headers: HeaderMap,
+    Form(mut payload): Form<OAuthTokenHintRequest>,
+) -> Result<Json<IntrospectionResponse>, OAuthError> {
+    let (client_id, client_secret) = client_credentials(
+        &headers,
+        payload.client_id.take(),
+        payload.client_secret.take(),
+    );
+    let introspection = state
+        .service
+        .oauth_introspect(payload, client_id, client_secret)
+        .await?;
+    Ok(Json(introspection))
+}
+
+/// Token revocation endpoint
+async fn oauth_revoke_handler(
+    State(state): State<AuthState>,
+    headers: HeaderMap,
+    Form(mut payload): Form<OAuthTokenHintRequest>,
+) -> Result<StatusCode, OAuthError> {
+    let (client_id, client_secret) = client_credentials(
+        &headers,
+        payload.client_id.take(),
+        payload.client_secret.take(),
+    );
+    state
+        .service
+        .oauth_revoke(payload, client_id, client_secret)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+/// List authorized apps handler
+async fn list_oauth_consents_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<OAuthConsent>>>, ApiError> {
+    let user_id = claims.user_id()?;
+    let consents = state.service.list_oauth_consents(user_id).await?;
+    Ok(Json(ApiResponse::success(consents)))
+}
+
+/// Revoke an app's access handler
+async fn revoke_oauth_consent_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(client_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    let user_id = claims.user_id()?;
+    state.service.revoke_oauth_consent(user_id, client_id).await?;
     Ok(Json(ApiResponse::success(())))
 }
 
+/// List OAuth clients handler (service role only)
+async fn list_oauth_clients_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<OAuthClient>>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let clients = state.service.oidc_provider()?.list_clients().await?;
+    Ok(Json(ApiResponse::success(clients)))
+}
+
+/// Register OAuth client handler (service role only)
+async fn register_oauth_client_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<RegisterOAuthClientRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<RegisteredOAuthClient>>), ApiError> {
+    require_service_role(&claims)?;
+    payload.validate()?;
+
+    let client = state
+        .service
+        .oidc_provider()?
+        .register_client(payload, claims.user_id().ok())
+        .await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(client))))
+}
+
+/// Delete OAuth client handler (service role only)
+async fn delete_oauth_client_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(client_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_service_role(&claims)?;
+
+    state.service.oidc_provider()?.delete_client(client_id).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// List signing keys handler (service role only)
+async fn list_signing_keys_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<SigningKey>>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let keys = state.service.list_signing_keys().await?;
+    Ok(Json(ApiResponse::success(keys)))
+}
+
+/// Stage next signing key handler (service role only)
+async fn stage_signing_key_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<SigningKey>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let key = state.service.stage_signing_key().await?;
+    Ok(Json(ApiResponse::success(key)))
+}
+
+/// Rotate signing keys handler (service role only)
+async fn rotate_signing_keys_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<SigningKey>>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let keys = state.service.rotate_signing_keys().await?;
+    Ok(Json(ApiResponse::success(keys)))
+}
+
+/// Revoke signing key handler (service role only)
+async fn revoke_signing_key_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(kid): Path<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_service_role(&claims)?;
+
+    state.service.revoke_signing_key(&kid).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Permission required to manage roles and assignments
+const MANAGE_ROLES: &str = "auth:roles";
+
+/// List roles handler
+async fn list_roles_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<Role>>>, ApiError> {
+    claims.require_permission(MANAGE_ROLES)?;
+
+    let roles = state.service.list_roles().await?;
+    Ok(Json(ApiResponse::success(roles)))
+}
+
+/// Create role handler
+async fn create_role_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<CreateRoleRequest>,
+) -> Result<Json<ApiResponse<Role>>, ApiError> {
+    claims.require_permission(MANAGE_ROLES)?;
+    payload.validate()?;
+
+    let role = state.service.create_role(payload).await?;
+    Ok(Json(ApiResponse::success(role)))
+}
+
+/// Update role handler
+async fn update_role_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> Result<Json<ApiResponse<Role>>, ApiError> {
+    claims.require_permission(MANAGE_ROLES)?;
+
+    let role = state.service.update_role(id, payload).await?;
+    Ok(Json(ApiResponse::success(role)))
+}
+
+/// Delete role handler
+async fn delete_role_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    claims.require_permission(MANAGE_ROLES)?;
+
+    state.service.delete_role(id).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// List users handler (service role only)
+async fn list_users_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<Json<ApiResponse<PaginatedResponse<User>>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let users = state.service.list_users(&query).await?;
+    Ok(Json(ApiResponse::success(users)))
+}
+
+/// The signed-in user's own audit log handler
+async fn list_own_audit_log_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Query(mut query): Query<AuditLogQuery>,
+) -> Result<Json<ApiResponse<PaginatedResponse<AuditLogEntry>>>, ApiError> {
+    query.user_id = Some(claims.user_id()?);
+
+    let entries = state.service.list_audit_log(&query).await?;
+    Ok(Json(ApiResponse::success(entries)))
+}
+
+/// List audit log handler (service role only)
+async fn list_audit_log_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<ApiResponse<PaginatedResponse<AuditLogEntry>>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let entries = state.service.list_audit_log(&query).await?;
+    Ok(Json(ApiResponse::success(entries)))
+}
+
+/// Export audit log as JSON Lines handler (service role only)
+async fn export_audit_log_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Response, ApiError> {
+    require_service_role(&claims)?;
+
+    let body = axum::body::Body::from_stream(state.service.clone().export_audit_log(query));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"auth-audit-log.jsonl\""),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Create user handler (service role only)
+async fn admin_create_user_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<AdminCreateUserRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<User>>), ApiError> {
+    require_service_role(&claims)?;
+    payload.validate()?;
+
+    let user = state.service.admin_create_user(payload, &audit).await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(user))))
+}
+
+/// Get user handler (service role only)
+async fn admin_get_user_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<User>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let user = state.service.get_user_for_admin(user_id).await?;
+    Ok(Json(ApiResponse::success(user)))
+}
+
+/// Delete user handler (service role only); `?soft=true` keeps the row
+async fn admin_delete_user_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<DeleteUserQuery>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_service_role(&claims)?;
+
+    state
+        .service
+        .admin_delete_user(user_id, query.soft, &audit)
+        .await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Ban user handler (service role only)
+async fn ban_user_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(user_id): Path<Uuid>,
+    payload: Option<Json<BanUserRequest>>,
+) -> Result<Json<ApiResponse<User>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let banned_until = payload.and_then(|Json(p)| p.banned_until);
+    let user = state.service.ban_user(user_id, banned_until, &audit).await?;
+    Ok(Json(ApiResponse::success(user)))
+}
+
+/// Unban user handler (service role only)
+async fn unban_user_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<User>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let user = state.service.unban_user(user_id, &audit).await?;
+    Ok(Json(ApiResponse::success(user)))
+}
+
+/// Force password reset handler (service role only); returns the reset token
+async fn force_password_reset_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<String>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let token = state.service.force_password_reset(user_id, &audit).await?;
+    Ok(Json(ApiResponse::success(token)))
+}
+
+/// Impersonate user handler (service role only)
+async fn impersonate_user_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<ImpersonationResponse>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let response = state
+        .service
+        .impersonate_user(&claims, user_id, &audit)
+        .await?;
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// List user roles handler
+async fn list_user_roles_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<Vec<Role>>>, ApiError> {
+    claims.require_permission(MANAGE_ROLES)?;
+
+    let roles = state.service.list_user_roles(user_id).await?;
+    Ok(Json(ApiResponse::success(roles)))
+}
+
+/// Assign role handler
+async fn assign_role_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<AssignRoleRequest>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    claims.require_permission(MANAGE_ROLES)?;
+
+    let audit = audit.with_caller(&claims);
+    state
+        .service
+        .assign_role(user_id, payload.role_id, &claims, &audit)
+        .await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Unassign role handler
+async fn unassign_role_handler(
+    State(state): State<AuthState>,
+    audit: AuditContext,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path((user_id, role_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    claims.require_permission(MANAGE_ROLES)?;
+
+    let audit = audit.with_caller(&claims);
+    state
+        .service
+        .unassign_role(user_id, role_id, &claims, &audit)
+        .await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+/// Start SSO sign-in handler
+async fn sso_sign_in_handler(
+    State(state): State<AuthState>,
+    Json(payload): Json<SsoSignInRequest>,
+) -> Result<Json<ApiResponse<SsoSignInResponse>>, ApiError> {
+    let url = state.service.saml()?.start_sign_in(payload).await?;
+    Ok(Json(ApiResponse::success(SsoSignInResponse { url })))
+}
+
+/// SAML service provider metadata handler
+async fn saml_metadata_handler(State(state): State<AuthState>) -> Result<Response, ApiError> {
+    let metadata = state.service.saml()?.metadata_xml();
+    Ok((
+        [(header::CONTENT_TYPE, "application/samlmetadata+xml")],
+        metadata,
+    )
+        .into_response())
+}
+
+/// SAML HTTP-POST binding form
+#[derive(Debug, Deserialize)]
+struct SamlAcsForm {
+    #[serde(rename = "SAMLResponse")]
+    saml_response: String,
+    #[serde(rename = "RelayState")]
+    relay_state: Option<String>,
+}
+
+/// SAML assertion consumer service handler
+async fn saml_acs_handler(
+    State(state): State<AuthState>,
+    client: ClientInfo,
+    Form(form): Form<SamlAcsForm>,
+) -> Result<Response, ApiError> {
+    let ip_address = client.ip_address();
+    let (response, redirect_to) = state
+        .service
+        .sign_in_with_saml(
+            &form.saml_response,
+            form.relay_state.as_deref(),
+            client.user_agent,
+            ip_address,
+        )
+        .await?;
+
+    match redirect_to {
+        // Tokens go in the fragment so they never reach the redirect target's server logs
+        Some(redirect_to) => Ok(Redirect::to(&format!(
+            "{}#access_token={}&refresh_token={}&expires_in={}&token_type=bearer",
+            redirect_to, response.access_token, response.refresh_token, response.expires_in
+        ))
+        .into_response()),
+        None => Ok(Json(ApiResponse::success(response)).into_response()),
+    }
+}
+
+/// Register SSO provider handler (service role only)
+async fn create_sso_provider_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Json(payload): Json<RegisterSamlProviderRequest>,
+) -> Result<Json<ApiResponse<SamlProvider>>, ApiError> {
+    require_service_role(&claims)?;
+    payload.validate()?;
+
+    let provider = state.service.saml()?.register_provider(payload).await?;
+    Ok(Json(ApiResponse::success(provider)))
+}
+
+/// List SSO providers handler (service role only)
+async fn list_sso_providers_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+) -> Result<Json<ApiResponse<Vec<SamlProvider>>>, ApiError> {
+    require_service_role(&claims)?;
+
+    let providers = state.service.saml()?.list_providers().await?;
+    Ok(Json(ApiResponse::success(providers)))
+}
+
+/// Delete SSO provider handler (service role only)
+async fn delete_sso_provider_handler(
+    State(state): State<AuthState>,
+    Extension(claims): Extension<crate::jwt::Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_service_role(&claims)?;
+
+    state.service.saml()?.delete_provider(id).await?;
+    Ok(Json(ApiResponse::success(())))
+}
+
+fn require_service_role(claims: &crate::jwt::Claims) -> Result<(), ApiError> {
+    if !claims.is_service_role() {
+        return Err(ForgeBaseError::Authorization("Service role required".to_string()).into());
+    }
+    Ok(())
+}
+
 /// API error wrapper
 pub struct ApiError(ForgeBaseError);
 
@@ -180,3 +1276,88 @@ impl IntoResponse for ApiError {
         (status, body).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{middleware::require_auth, AuthService, Claims};
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    fn app() -> (Router, Arc<AuthService>) {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/forgebase_test")
+            .unwrap();
+        let service = Arc::new(AuthService::new(pool, "test-secret".to_string(), 3600, 30));
+        let state = AuthState {
+            jwt_manager: service.jwt_manager(),
+            service: service.clone(),
+        };
+        let app = create_auth_routes()
+            .route_layer(axum::middleware::from_fn_with_state(state.clone(), require_auth))
+            .with_state(state);
+        (app, service)
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_cannot_change_the_account() {
+        let (app, service) = app();
+        let claims = Claims::new(Uuid::new_v4(), "user@example.com".to_string(), 900)
+            .with_actor(Uuid::new_v4().to_string());
+        let token = service.jwt_manager().generate_access_token(claims).unwrap();
+        let id = Uuid::new_v4();
+
+        let phone = serde_json::json!({ "phone": "+15555550100" });
+        let requests = [
+            ("POST", "/auth/user".to_string(), serde_json::json!({ "full_name": "Someone Else" })),
+            (
+                "POST",
+                "/auth/user/upgrade".to_string(),
+                serde_json::json!({ "email": "new@example.com", "password": "Correct-Horse-9" }),
+            ),
+            (
+                "POST",
+                "/auth/user/identities".to_string(),
+                serde_json::json!({ "provider": "github", "code": "code", "redirect_uri": "https://app.example.com" }),
+            ),
+            (
+                "POST",
+                "/auth/password/change".to_string(),
+                serde_json::json!({ "current_password": "old-password", "new_password": "Correct-Horse-9" }),
+            ),
+            ("POST", "/auth/otp/phone".to_string(), phone.clone()),
+            (
+                "POST",
+                "/auth/otp/phone/verify".to_string(),
+                serde_json::json!({ "phone": "+15555550100", "code": "123456" }),
+            ),
+            ("POST", "/auth/mfa/factors/sms".to_string(), phone),
+            ("POST", format!("/auth/mfa/factors/{}/verify", id), serde_json::json!({ "code": "123456" })),
+            ("DELETE", format!("/auth/mfa/factors/{}", id), serde_json::json!({})),
+            ("POST", "/auth/api-keys".to_string(), serde_json::json!({ "name": "key", "scopes": [] })),
+            ("DELETE", format!("/auth/api-keys/{}", id), serde_json::json!({})),
+            (
+                "POST",
+                "/oauth/authorize".to_string(),
+                serde_json::json!({
+                    "response_type": "code",
+                    "client_id": id,
+                    "redirect_uri": "https://app.example.com/callback",
+                    "approve": true
+                }),
+            ),
+        ];
+
+        for (method, uri, body) in requests {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(&uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+        }
+    }
+}
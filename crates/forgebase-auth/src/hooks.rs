@@ -0,0 +1,492 @@
+// Auth hooks: custom access token claims, sign-up gating and signed event webhooks
+use crate::jwt::Claims;
+use crate::models::{User, UserProfile};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use forgebase_core::{AuthHooksConfig, ForgeBaseError, FunctionInvoker, HookConfig, Result};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub const EVENT_USER_CREATED: &str = "user.created";
+pub const EVENT_USER_SIGNED_IN: &str = "user.signed_in";
+pub const EVENT_PASSWORD_CHANGED: &str = "password.changed";
+
+const EVENTS: &[&str] = &[EVENT_USER_CREATED, EVENT_USER_SIGNED_IN, EVENT_PASSWORD_CHANGED];
+
+/// Claims the custom access token hook cannot set or override
+const RESERVED_CLAIMS: &[&str] = &[
+    "sub",
+    "email",
+    "exp",
+    "iat",
+    "nbf",
+    "iss",
+    "aud",
+    "jti",
+    "role",
+    "permissions",
+    "aal",
+    "is_anonymous",
+    "session_id",
+    "act",
+    "client_id",
+    "scope",
+];
+
+/// Deliveries per webhook event before it is dropped
+const WEBHOOK_MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Clone)]
+enum HookTarget {
+    Http(String),
+    Function(Uuid),
+}
+
+#[derive(Clone)]
+struct Hook {
+    target: HookTarget,
+    secret: Option<String>,
+    timeout: Duration,
+}
+
+impl Hook {
+    fn from_config(config: &HookConfig, has_functions: bool) -> Result<Self> {
+        let target = match (&config.url, config.function_id) {
+            (Some(url), None) => {
+                url::Url::parse(url)
+                    .map_err(|e| ForgeBaseError::Config(format!("Invalid hook URL {}: {}", url, e)))?;
+                HookTarget::Http(url.clone())
+            }
+            (None, Some(function_id)) if has_functions => HookTarget::Function(function_id),
+            (None, Some(_)) => {
+                return Err(ForgeBaseError::Config(
+                    "Function hooks need the functions runtime".to_string(),
+                ))
+            }
+            _ => {
+                return Err(ForgeBaseError::Config(
+                    "A hook needs either a url or a function_id".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            target,
+            secret: config.secret.clone(),
+            timeout: Duration::from_millis(config.timeout_ms.max(1)),
+        })
+    }
+}
+
+#[derive(Clone)]
+struct Webhook {
+    hook: Hook,
+    events: Vec<String>,
+}
+
+impl Webhook {
+    fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event)
+    }
+}
+
+/// Response of the `custom_access_token` hook
+#[derive(Debug, Default, Deserialize)]
+struct CustomClaimsResponse {
+    #[serde(default)]
+    claims: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Response of the `before_user_created` hook; anything but `reject` lets the sign-up through
+#[derive(Debug, Default, Deserialize)]
+struct HookDecision {
+    decision: Option<String>,
+    message: Option<String>,
+}
+
+/// Configured auth hooks.
+///
+/// HTTP hooks receive a JSON `POST`; with a secret it carries `webhook-id`,
+/// `webhook-timestamp` and `webhook-signature` headers (see [`sign_payload`]).
+/// Function hooks receive the same JSON as their payload.
+#[derive(Clone, Default)]
+pub struct AuthHooks {
+    custom_access_token: Option<Hook>,
+    before_user_created: Option<Hook>,
+    webhooks: Vec<Webhook>,
+    http_client: reqwest::Client,
+    functions: Option<Arc<dyn FunctionInvoker>>,
+}
+
+impl AuthHooks {
+    /// Hooks from configuration; `functions` runs hooks given as a `function_id`
+    pub fn from_config(
+        config: &AuthHooksConfig,
+        functions: Option<Arc<dyn FunctionInvoker>>,
+    ) -> Result<Self> {
+        let has_functions = functions.is_some();
+        let hook = |config: &Option<HookConfig>| {
+            config
+                .as_ref()
+                .map(|config| Hook::from_config(config, has_functions))
+                .transpose()
+        };
+
+        let mut webhooks = Vec::with_capacity(config.webhooks.len());
+        for webhook in &config.webhooks {
+            if let Some(event) = webhook.events.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+                return Err(ForgeBaseError::Config(format!("Unknown webhook event: {}", event)));
+            }
+            if webhook.hook.url.is_some() && webhook.hook.secret.is_none() {
+                return Err(ForgeBaseError::Config(
+                    "HTTP webhooks need a signing secret".to_string(),
+                ));
+            }
+            webhooks.push(Webhook {
+                hook: Hook::from_config(&webhook.hook, has_functions)?,
+                events: webhook.events.clone(),
+            });
+        }
+
+        Ok(Self {
+            custom_access_token: hook(&config.custom_access_token)?,
+            before_user_created: hook(&config.before_user_created)?,
+            webhooks,
+            http_client: reqwest::Client::builder()
+                .user_agent("ForgeBase")
+                .build()
+                .unwrap_or_default(),
+            functions,
+        })
+    }
+
+    /// Extra claims for a user's access token. Reserved claims in the response are dropped;
+    /// a failing hook fails the token issuance.
+    pub async fn custom_access_token(
+        &self,
+        user: &User,
+        claims: &Claims,
+    ) -> Result<serde_json::Map<String, serde_json::Value>> {
+        let Some(hook) = &self.custom_access_token else {
+            return Ok(serde_json::Map::new());
+        };
+
+        let payload = serde_json::json!({
+            "user": UserProfile::from(user.clone()),
+            "claims": claims,
+        });
+        let response: CustomClaimsResponse = self
+            .call(hook, &new_message_id(), &payload)
+            .await
+            .and_then(parse_response)
+            .map_err(|e| {
+                tracing::error!("custom_access_token hook failed for user {}: {}", user.id, e);
+                ForgeBaseError::ExternalService("Access token hook failed".to_string())
+            })?;
+
+        Ok(response
+            .claims
+            .into_iter()
+            .filter(|(name, _)| {
+                let reserved = RESERVED_CLAIMS.contains(&name.as_str());
+                if reserved {
+                    tracing::warn!("custom_access_token hook tried to set reserved claim {}", name);
+                }
+                !reserved
+            })
+            .collect())
+    }
+
+    /// Let the hook reject a sign-up; a failing hook rejects it too
+    pub async fn before_user_created(&self, user: &User) -> Result<()> {
+        let Some(hook) = &self.before_user_created else {
+            return Ok(());
+        };
+
+        let payload = serde_json::json!({ "user": UserProfile::from(user.clone()) });
+        let decision: HookDecision = self
+            .call(hook, &new_message_id(), &payload)
+            .await
+            .and_then(parse_response)
+            .map_err(|e| {
+                tracing::error!("before_user_created hook failed: {}", e);
+                ForgeBaseError::ExternalService("Sign-up hook failed".to_string())
+            })?;
+
+        if decision.decision.as_deref() == Some("reject") {
+            return Err(ForgeBaseError::Authorization(
+                decision
+                    .message
+                    .unwrap_or_else(|| "Sign-up is not allowed".to_string()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Deliver an event to the webhooks subscribed to it in the background, retrying failures
+    pub fn dispatch(&self, event: &'static str, data: serde_json::Value) {
+        let webhooks: Vec<Webhook> = self
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.wants(event))
+            .cloned()
+            .collect();
+        if webhooks.is_empty() {
+            return;
+        }
+
+        let id = new_message_id();
+        let payload = serde_json::json!({
+            "id": id,
+            "type": event,
+            "timestamp": Utc::now().to_rfc3339(),
+            "data": data,
+        });
+        for webhook in webhooks {
+            let hooks = self.clone();
+            let (id, payload) = (id.clone(), payload.clone());
+            tokio::spawn(async move {
+                for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+                    match hooks.call(&webhook.hook, &id, &payload).await {
+                        Ok(_) => return,
+                        Err(e) if attempt < WEBHOOK_MAX_ATTEMPTS => {
+                            tracing::warn!("Webhook {} for {} failed (attempt {}): {}", id, event, attempt, e);
+                            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+                        }
+                        Err(e) => tracing::error!("Webhook {} for {} dropped: {}", id, event, e),
+                    }
+                }
+            });
+        }
+    }
+
+    /// Run a hook within its timeout
+    async fn call(&self, hook: &Hook, id: &str, payload: &serde_json::Value) -> Result<serde_json::Value> {
+        let call = async {
+            match &hook.target {
+                HookTarget::Http(url) => self.post(url, hook.secret.as_deref(), id, payload).await,
+                HookTarget::Function(function_id) => {
+                    let functions = self.functions.as_ref().ok_or_else(|| {
+                        ForgeBaseError::Config("Function hooks need the functions runtime".to_string())
+                    })?;
+                    functions.invoke_function(*function_id, payload.clone()).await
+                }
+            }
+        };
+
+        tokio::time::timeout(hook.timeout, call)
+            .await
+            .map_err(|_| ForgeBaseError::ExternalService("Hook timed out".to_string()))?
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        secret: Option<&str>,
+        id: &str,
+        payload: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let body = serde_json::to_vec(payload)
+            .map_err(|e| ForgeBaseError::Internal(format!("Failed to encode hook payload: {}", e)))?;
+
+        let mut request = self
+            .http_client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = secret {
+            let timestamp = Utc::now().timestamp();
+            request = request
+                .header("webhook-id", id)
+                .header("webhook-timestamp", timestamp.to_string())
+                .header("webhook-signature", sign_payload(secret, id, timestamp, &body));
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ForgeBaseError::ExternalService(format!("Hook request failed: {}", e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ForgeBaseError::ExternalService(format!(
+                "Hook returned status {}",
+                status
+            )));
+        }
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| ForgeBaseError::ExternalService(format!("Hook request failed: {}", e)))?;
+        if body.is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        serde_json::from_slice(&body)
+            .map_err(|e| ForgeBaseError::ExternalService(format!("Invalid hook response: {}", e)))
+    }
+}
+
+/// `webhook-signature` header value: `v1,` and the base64 HMAC-SHA256 of
+/// `{webhook-id}.{webhook-timestamp}.{body}` keyed with the hook secret
+pub fn sign_payload(secret: &str, id: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}.", id, timestamp).as_bytes());
+    mac.update(body);
+    format!("v1,{}", general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+}
+
+fn new_message_id() -> String {
+    format!("msg_{}", Uuid::new_v4().simple())
+}
+
+/// An empty response means "no changes"
+fn parse_response<T: serde::de::DeserializeOwned + Default>(value: serde_json::Value) -> Result<T> {
+    if value.is_null() {
+        return Ok(T::default());
+    }
+    serde_json::from_value(value)
+        .map_err(|e| ForgeBaseError::ExternalService(format!("Invalid hook response: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use forgebase_core::WebhookConfig;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn hook(url: String) -> HookConfig {
+        HookConfig {
+            url: Some(url),
+            function_id: None,
+            secret: Some("whsec-test".to_string()),
+            timeout_ms: 1000,
+        }
+    }
+
+    fn user() -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: Some("ada@example.com".to_string()),
+            email_verified: false,
+            phone: None,
+            phone_verified: false,
+            password_hash: None,
+            full_name: None,
+            avatar_url: None,
+            metadata: serde_json::json!({}),
+            is_anonymous: false,
+            is_active: true,
+            last_sign_in_at: None,
+            banned_until: None,
+            deleted_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        assert_eq!(
+            sign_payload("whsec-test", "msg_1", 1700000000, br#"{"a":1}"#),
+            "v1,JheLhC+XazWfKczPdtdCd7wKbDI4biXb3hLSiqCNQBU="
+        );
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let mut config = AuthHooksConfig {
+            before_user_created: Some(HookConfig {
+                function_id: Some(Uuid::new_v4()),
+                ..hook("https://hooks.example.com".to_string())
+            }),
+            ..Default::default()
+        };
+        assert!(AuthHooks::from_config(&config, None).is_err());
+
+        config.before_user_created = None;
+        config.webhooks.push(WebhookConfig {
+            hook: HookConfig {
+                secret: None,
+                ..hook("https://hooks.example.com".to_string())
+            },
+            events: Vec::new(),
+        });
+        assert!(AuthHooks::from_config(&config, None).is_err());
+
+        config.webhooks[0].hook.secret = Some("whsec-test".to_string());
+        config.webhooks[0].events = vec!["user.deleted".to_string()];
+        assert!(AuthHooks::from_config(&config, None).is_err());
+
+        config.webhooks[0].events = vec![EVENT_USER_CREATED.to_string()];
+        assert!(AuthHooks::from_config(&config, None).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_custom_access_token_drops_reserved_claims() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/claims"))
+            .and(header_exists("webhook-signature"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "claims": { "tenant": "acme", "role": "service_role" }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = AuthHooksConfig {
+            custom_access_token: Some(hook(format!("{}/claims", server.uri()))),
+            ..Default::default()
+        };
+        let hooks = AuthHooks::from_config(&config, None).unwrap();
+        let user = user();
+        let claims = Claims::new(user.id, "ada@example.com".to_string(), 3600);
+
+        let custom = hooks.custom_access_token(&user, &claims).await.unwrap();
+        assert_eq!(custom.get("tenant"), Some(&serde_json::json!("acme")));
+        assert!(!custom.contains_key("role"));
+    }
+
+    #[tokio::test]
+    async fn test_before_user_created_rejects_and_times_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/reject"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "decision": "reject", "message": "Invite only"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/slow"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&server)
+            .await;
+
+        let mut config = AuthHooksConfig {
+            before_user_created: Some(hook(format!("{}/reject", server.uri()))),
+            ..Default::default()
+        };
+        let hooks = AuthHooks::from_config(&config, None).unwrap();
+        match hooks.before_user_created(&user()).await {
+            Err(ForgeBaseError::Authorization(message)) => assert_eq!(message, "Invite only"),
+            other => panic!("expected rejection, got {:?}", other),
+        }
+
+        config.before_user_created = Some(HookConfig {
+            timeout_ms: 50,
+            ..hook(format!("{}/slow", server.uri()))
+        });
+        let hooks = AuthHooks::from_config(&config, None).unwrap();
+        assert!(matches!(
+            hooks.before_user_created(&user()).await,
+            Err(ForgeBaseError::ExternalService(_))
+        ));
+    }
+}
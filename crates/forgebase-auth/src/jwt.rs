@@ -1,9 +1,19 @@
 use chrono::{Duration, Utc};
 use forgebase_core::{ForgeBaseError, Result};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use crate::keys::{KEY_STATUS_CURRENT, KEY_STATUS_REVOKED};
+use crate::models::SigningKey;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
 use uuid::Uuid;
 
+/// Role carried by tokens that act on behalf of the platform rather than a user
+pub const SERVICE_ROLE: &str = "service_role";
+
 /// JWT claims
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -13,6 +23,26 @@ pub struct Claims {
     pub iat: i64,           // Issued at
     pub role: Option<String>, // User role
     pub permissions: Vec<String>, // User permissions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aal: Option<String>, // Authenticator assurance level (aal1, aal2)
+    #[serde(default)]
+    pub is_anonymous: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>, // Session the token belongs to; revoking it invalidates the token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>, // Who is acting as the subject (impersonation, RFC 8693)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>, // OAuth client the token was issued to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // Space-separated scopes granted to that client
+    #[serde(flatten)]
+    pub custom: serde_json::Map<String, serde_json::Value>, // Added by the custom access token hook
+}
+
+/// The party acting on behalf of a token's subject
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Actor {
+    pub sub: String,
 }
 
 impl Claims {
@@ -27,9 +57,65 @@ impl Claims {
             iat: now.timestamp(),
             role: None,
             permissions: Vec::new(),
+            aal: None,
+            is_anonymous: false,
+            session_id: None,
+            act: None,
+            client_id: None,
+            scope: None,
+            custom: serde_json::Map::new(),
+        }
+    }
+
+    /// Claims for an OAuth client acting on its own behalf (client credentials grant);
+    /// granted scopes become its permissions
+    pub fn for_client(client_id: String, scopes: Vec<String>, expiration_seconds: i64) -> Self {
+        let now = Utc::now();
+
+        Self {
+            sub: client_id.clone(),
+            email: String::new(),
+            exp: (now + Duration::seconds(expiration_seconds)).timestamp(),
+            iat: now.timestamp(),
+            role: None,
+            scope: Some(scopes.join(" ")),
+            permissions: scopes,
+            aal: None,
+            is_anonymous: false,
+            session_id: None,
+            act: None,
+            client_id: Some(client_id),
+            custom: serde_json::Map::new(),
         }
     }
 
+    /// Scope a user's token to a third-party client; it carries no permissions
+    pub fn with_client(mut self, client_id: String, scope: String) -> Self {
+        self.client_id = Some(client_id);
+        self.scope = Some(scope);
+        self.role = None;
+        self.permissions = Vec::new();
+        self
+    }
+
+    /// Whether a third-party client holds this token on behalf of a user
+    pub fn is_delegated(&self) -> bool {
+        self.client_id.is_some() && self.session_id.is_some()
+    }
+
+    /// Whether an OAuth client holds this token for itself (client credentials grant); its
+    /// subject is the client, not a user
+    pub fn is_client_credentials(&self) -> bool {
+        self.client_id.is_some() && self.session_id.is_none() && self.act.is_none()
+    }
+
+    /// Whether `scope` was granted to the client holding this token
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope
+            .as_deref()
+            .is_some_and(|granted| granted.split(' ').any(|s| s == scope))
+    }
+
     pub fn with_role(mut self, role: String) -> Self {
         self.role = Some(role);
         self
@@ -40,49 +126,227 @@ impl Claims {
         self
     }
 
+    pub fn with_session(mut self, session_id: Uuid) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+
+    /// Session the token belongs to, if it was issued for one
+    pub fn session_id(&self) -> Result<Option<Uuid>> {
+        self.session_id
+            .as_deref()
+            .map(|id| {
+                Uuid::parse_str(id)
+                    .map_err(|_| ForgeBaseError::Auth("Invalid session ID in token".to_string()))
+            })
+            .transpose()
+    }
+
+    pub fn with_actor(mut self, sub: String) -> Self {
+        self.act = Some(Actor { sub });
+        self
+    }
+
+    pub fn with_aal(mut self, aal: String) -> Self {
+        self.aal = Some(aal);
+        self
+    }
+
+    /// Whether someone else is acting as the subject (an impersonation token)
+    pub fn is_impersonated(&self) -> bool {
+        self.act.is_some()
+    }
+
+    /// Reject impersonation tokens, which may look around an account but not create
+    /// credentials for it or change it
+    pub fn forbid_impersonation(&self) -> Result<()> {
+        if self.is_impersonated() {
+            return Err(ForgeBaseError::Authorization(
+                "Not allowed while impersonating a user".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn is_service_role(&self) -> bool {
+        self.role.as_deref() == Some(SERVICE_ROLE)
+    }
+
+    /// Whether the token grants `permission` (wildcards allowed; the service role has all)
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.is_service_role() || crate::rbac::has_permission(&self.permissions, permission)
+    }
+
+    pub fn require_permission(&self, permission: &str) -> Result<()> {
+        if !self.has_permission(permission) {
+            return Err(ForgeBaseError::Authorization(format!(
+                "Missing permission: {}",
+                permission
+            )));
+        }
+        Ok(())
+    }
+
     pub fn user_id(&self) -> Result<Uuid> {
+        if self.is_client_credentials() {
+            return Err(ForgeBaseError::Auth("Token does not belong to a user".to_string()));
+        }
         Uuid::parse_str(&self.sub)
             .map_err(|_| ForgeBaseError::Auth("Invalid user ID in token".to_string()))
     }
 }
 
-/// JWT token manager
+/// JWT token manager.
+///
+/// Signs with a shared HS256 secret until asymmetric signing keys are loaded with
+/// [`JwtManager::set_signing_keys`]; after that tokens carry a `kid` and are signed by the
+/// current key, and any non-revoked key verifies. Tokens without a `kid` are then rejected,
+/// unless a grace period set with [`JwtManager::set_legacy_token_grace`] lets sessions survive
+/// the switch; it runs from the creation of the first signing key, so the secret is retired
+/// for good once it is over.
 pub struct JwtManager {
+    secret: String,
+    keys: RwLock<KeyRing>,
+}
+
+struct KeyRing {
+    signing_kid: Option<String>,
+    signing_algorithm: Algorithm,
     encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
-    validation: Validation,
+    verifying_keys: HashMap<String, (Algorithm, DecodingKey)>,
+    jwks: JwkSet,
+    legacy_grace: Duration,
+    /// Creation of the first signing key; `None` while the shared secret still signs
+    switched_at: Option<chrono::DateTime<Utc>>,
 }
 
 impl JwtManager {
     pub fn new(secret: &str) -> Self {
-        let encoding_key = EncodingKey::from_secret(secret.as_bytes());
-        let decoding_key = DecodingKey::from_secret(secret.as_bytes());
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.leeway = 60; // 60 seconds leeway for clock skew
-
         Self {
-            encoding_key,
-            decoding_key,
-            validation,
+            secret: secret.to_string(),
+            keys: RwLock::new(KeyRing {
+                signing_kid: None,
+                signing_algorithm: Algorithm::HS256,
+                encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+                verifying_keys: HashMap::new(),
+                jwks: JwkSet { keys: Vec::new() },
+                legacy_grace: Duration::zero(),
+                switched_at: None,
+            }),
+        }
+    }
+
+    /// Keep accepting tokens signed with the shared secret for `grace` after switching to
+    /// asymmetric keys
+    pub fn set_legacy_token_grace(&self, grace: Duration) {
+        self.keys.write().unwrap_or_else(|e| e.into_inner()).legacy_grace = grace;
+    }
+
+    /// Replace the asymmetric key set (after loading or rotating keys).
+    ///
+    /// `keys` must contain exactly one current key; revoked keys are ignored.
+    pub fn set_signing_keys(&self, keys: &[SigningKey]) -> Result<()> {
+        let current = keys
+            .iter()
+            .find(|k| k.status == KEY_STATUS_CURRENT)
+            .ok_or_else(|| ForgeBaseError::Config("No current signing key".to_string()))?;
+        let (signing_algorithm, encoding_key) = crate::keys::encoding_key(current)?;
+
+        let mut verifying_keys = HashMap::new();
+        let mut jwks = Vec::new();
+        for key in keys.iter().filter(|k| k.status != KEY_STATUS_REVOKED) {
+            let jwk = crate::keys::public_jwk(key)?;
+            let decoding_key = DecodingKey::from_jwk(&jwk).map_err(|e| {
+                ForgeBaseError::Internal(format!("Invalid JWK for key {}: {}", key.kid, e))
+            })?;
+            verifying_keys.insert(
+                key.kid.clone(),
+                (crate::keys::parse_algorithm(&key.algorithm)?, decoding_key),
+            );
+            jwks.push(jwk);
         }
+
+        // Revoked keys still date the switch away from the shared secret
+        let switched_at = keys.iter().map(|k| k.created_at).min();
+
+        let mut ring = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        let legacy_grace = ring.legacy_grace;
+        *ring = KeyRing {
+            signing_kid: Some(current.kid.clone()),
+            signing_algorithm,
+            encoding_key,
+            verifying_keys,
+            jwks: JwkSet { keys: jwks },
+            legacy_grace,
+            switched_at,
+        };
+
+        Ok(())
+    }
+
+    /// Public keys for `/.well-known/jwks.json`
+    pub fn jwks(&self) -> JwkSet {
+        self.keys.read().unwrap_or_else(|e| e.into_inner()).jwks.clone()
+    }
+
+    /// Algorithm new tokens are signed with
+    pub fn signing_algorithm(&self) -> Algorithm {
+        self.keys.read().unwrap_or_else(|e| e.into_inner()).signing_algorithm
     }
 
     /// Generate an access token
     pub fn generate_access_token(&self, claims: Claims) -> Result<String> {
-        encode(&Header::default(), &claims, &self.encoding_key)
+        self.sign(&claims)
+    }
+
+    /// Sign arbitrary claims (e.g. an OpenID Connect ID token) with the current key
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        let mut header = Header::new(keys.signing_algorithm);
+        header.kid = keys.signing_kid.clone();
+
+        encode(&header, claims, &keys.encoding_key)
             .map_err(|e| ForgeBaseError::Internal(format!("Failed to generate token: {}", e)))
     }
 
     /// Verify and decode a token
     pub fn verify_token(&self, token: &str) -> Result<Claims> {
-        decode::<Claims>(token, &self.decoding_key, &self.validation)
-            .map(|data| data.claims)
-            .map_err(|e| match e.kind() {
-                jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
-                    ForgeBaseError::Auth("Token has expired".to_string())
+        let header = decode_header(token)
+            .map_err(|e| ForgeBaseError::Auth(format!("Invalid token: {}", e)))?;
+
+        let result = match header.kid {
+            Some(kid) => {
+                let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+                let (algorithm, decoding_key) = keys
+                    .verifying_keys
+                    .get(&kid)
+                    .ok_or_else(|| ForgeBaseError::Auth("Unknown signing key".to_string()))?;
+                decode::<Claims>(token, decoding_key, &validation(*algorithm))
+            }
+            None => {
+                let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+                if keys
+                    .switched_at
+                    .is_some_and(|switched_at| Utc::now() >= switched_at + keys.legacy_grace)
+                {
+                    return Err(ForgeBaseError::Auth(
+                        "Token is not signed with a current key".to_string(),
+                    ));
                 }
-                _ => ForgeBaseError::Auth(format!("Invalid token: {}", e)),
-            })
+                decode::<Claims>(
+                    token,
+                    &DecodingKey::from_secret(self.secret.as_bytes()),
+                    &validation(Algorithm::HS256),
+                )
+            }
+        };
+
+        result.map(|data| data.claims).map_err(|e| match e.kind() {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                ForgeBaseError::Auth("Token has expired".to_string())
+            }
+            _ => ForgeBaseError::Auth(format!("Invalid token: {}", e)),
+        })
     }
 
     /// Extract token from Authorization header
@@ -97,6 +361,12 @@ impl JwtManager {
     }
 }
 
+fn validation(algorithm: Algorithm) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    validation.leeway = 60; // 60 seconds leeway for clock skew
+    validation
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,6 +384,131 @@ mod tests {
         assert_eq!(decoded.email, "test@example.com");
     }
 
+    #[test]
+    fn test_client_credentials_token_has_no_user() {
+        let client_id = Uuid::new_v4();
+        let claims = Claims::for_client(client_id.to_string(), vec!["read".to_string()], 3600);
+        assert!(claims.is_client_credentials());
+        assert!(matches!(claims.user_id(), Err(ForgeBaseError::Auth(_))));
+
+        // Tokens a client holds on a user's behalf still name the user
+        let user_id = Uuid::new_v4();
+        let delegated = Claims::new(user_id, "test@example.com".to_string(), 3600)
+            .with_session(Uuid::new_v4())
+            .with_client(client_id.to_string(), "openid".to_string());
+        assert!(!delegated.is_client_credentials());
+        assert_eq!(delegated.user_id().unwrap(), user_id);
+    }
+
+    #[test]
+    fn test_forbid_impersonation() {
+        let claims = Claims::new(Uuid::new_v4(), "test@example.com".to_string(), 900);
+        assert!(claims.forbid_impersonation().is_ok());
+        let impersonated = claims.with_actor(Uuid::new_v4().to_string());
+        assert!(impersonated.is_impersonated());
+        assert!(matches!(
+            impersonated.forbid_impersonation(),
+            Err(ForgeBaseError::Authorization(_))
+        ));
+    }
+
+    #[test]
+    fn test_custom_claims_round_trip() {
+        let manager = JwtManager::new("test-secret-key-123");
+        let mut claims = Claims::new(Uuid::new_v4(), "test@example.com".to_string(), 3600);
+        claims
+            .custom
+            .insert("tenant".to_string(), serde_json::json!("acme"));
+
+        let token = manager.generate_access_token(claims).unwrap();
+        let decoded = manager.verify_token(&token).unwrap();
+
+        assert_eq!(decoded.custom.get("tenant"), Some(&serde_json::json!("acme")));
+        assert!(!decoded.custom.contains_key("sub"));
+    }
+
+    #[test]
+    fn test_asymmetric_signing_and_rotation() {
+        use crate::keys::{generate_signing_key, KEY_STATUS_NEXT, KEY_STATUS_PREVIOUS};
+
+        let manager = JwtManager::new("test-secret-key-123");
+        let claims = Claims::new(Uuid::new_v4(), "test@example.com".to_string(), 3600);
+        let legacy_token = manager.generate_access_token(claims.clone()).unwrap();
+
+        let mut current = generate_signing_key(Algorithm::ES256, KEY_STATUS_CURRENT).unwrap();
+        let next = generate_signing_key(Algorithm::EdDSA, KEY_STATUS_NEXT).unwrap();
+        manager
+            .set_signing_keys(&[current.clone(), next.clone()])
+            .unwrap();
+        assert_eq!(manager.jwks().keys.len(), 2);
+
+        let es_token = manager.generate_access_token(claims.clone()).unwrap();
+        let header = decode_header(&es_token).unwrap();
+        assert_eq!(header.alg, Algorithm::ES256);
+        assert_eq!(header.kid.as_deref(), Some(current.kid.as_str()));
+
+        // Third parties verify with nothing but the published JWKS
+        let jwks = manager.jwks();
+        let jwk = jwks.find(&current.kid).unwrap();
+        let offline = decode::<Claims>(
+            &es_token,
+            &DecodingKey::from_jwk(jwk).unwrap(),
+            &Validation::new(Algorithm::ES256),
+        )
+        .unwrap();
+        assert_eq!(offline.claims.sub, claims.sub);
+
+        // Rotate: the old key keeps verifying until it is revoked
+        let mut promoted = next.clone();
+        promoted.status = KEY_STATUS_CURRENT.to_string();
+        current.status = KEY_STATUS_PREVIOUS.to_string();
+        manager
+            .set_signing_keys(&[current.clone(), promoted])
+            .unwrap();
+        let ed_token = manager.generate_access_token(claims.clone()).unwrap();
+        assert_eq!(decode_header(&ed_token).unwrap().alg, Algorithm::EdDSA);
+        assert!(manager.verify_token(&ed_token).is_ok());
+        assert!(manager.verify_token(&es_token).is_ok());
+        // Without a grace period the shared secret is retired with the switch
+        assert!(manager.verify_token(&legacy_token).is_err());
+
+        current.status = KEY_STATUS_REVOKED.to_string();
+        let mut promoted = next;
+        promoted.status = KEY_STATUS_CURRENT.to_string();
+        manager.set_signing_keys(&[current, promoted]).unwrap();
+        assert!(manager.verify_token(&es_token).is_err());
+    }
+
+    #[test]
+    fn test_legacy_token_grace() {
+        let manager = JwtManager::new("test-secret-key-123");
+        manager.set_legacy_token_grace(Duration::hours(1));
+        let claims = Claims::new(Uuid::new_v4(), "test@example.com".to_string(), 3600);
+        let legacy_token = manager.generate_access_token(claims).unwrap();
+
+        let mut key =
+            crate::keys::generate_signing_key(Algorithm::ES256, KEY_STATUS_CURRENT).unwrap();
+        manager.set_signing_keys(&[key.clone()]).unwrap();
+        assert!(manager.verify_token(&legacy_token).is_ok());
+
+        // Reloading keys does not restart the grace period
+        key.created_at = Utc::now() - Duration::hours(2);
+        manager.set_signing_keys(&[key]).unwrap();
+        assert!(manager.verify_token(&legacy_token).is_err());
+    }
+
+    #[test]
+    fn test_rs256_key_generation() {
+        let key = crate::keys::generate_signing_key(Algorithm::RS256, KEY_STATUS_CURRENT).unwrap();
+        let manager = JwtManager::new("test-secret-key-123");
+        manager.set_signing_keys(&[key]).unwrap();
+
+        let claims = Claims::new(Uuid::new_v4(), "test@example.com".to_string(), 3600);
+        let token = manager.generate_access_token(claims).unwrap();
+        assert_eq!(decode_header(&token).unwrap().alg, Algorithm::RS256);
+        assert!(manager.verify_token(&token).is_ok());
+    }
+
     #[test]
     fn test_extract_token_from_header() {
         let token = "abc123";
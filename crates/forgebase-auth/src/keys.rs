@@ -0,0 +1,302 @@
+// Asymmetric JWT signing keys and staged rotation
+use crate::models::SigningKey;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use forgebase_core::{ForgeBaseError, Result};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+    EllipticCurveKeyType, Jwk, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+    PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, EncodingKey};
+use rand::rngs::OsRng;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Published ahead of time so verifiers can cache it before it signs anything
+pub const KEY_STATUS_NEXT: &str = "next";
+/// Signs new tokens
+pub const KEY_STATUS_CURRENT: &str = "current";
+/// No longer signs, but still verifies tokens issued before the last rotation
+pub const KEY_STATUS_PREVIOUS: &str = "previous";
+/// Neither signs nor verifies
+pub const KEY_STATUS_REVOKED: &str = "revoked";
+
+const RSA_KEY_BITS: usize = 2048;
+
+/// Parse a configured asymmetric signing algorithm
+pub fn parse_algorithm(name: &str) -> Result<Algorithm> {
+    match name {
+        "RS256" => Ok(Algorithm::RS256),
+        "ES256" => Ok(Algorithm::ES256),
+        "EdDSA" => Ok(Algorithm::EdDSA),
+        other => Err(ForgeBaseError::Config(format!(
+            "Unsupported JWT signing algorithm: {}",
+            other
+        ))),
+    }
+}
+
+/// Generate a new key pair for `algorithm`
+pub fn generate_signing_key(algorithm: Algorithm, status: &str) -> Result<SigningKey> {
+    let kid = Uuid::new_v4().simple().to_string();
+    let (private_key_pem, params, key_algorithm) = match algorithm {
+        Algorithm::RS256 => {
+            use rsa::pkcs8::{EncodePrivateKey, LineEnding};
+            use rsa::traits::PublicKeyParts;
+
+            let key = rsa::RsaPrivateKey::new(&mut OsRng, RSA_KEY_BITS).map_err(key_error)?;
+            let pem = key.to_pkcs8_pem(LineEnding::LF).map_err(key_error)?;
+            let params = AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            });
+            (pem.to_string(), params, KeyAlgorithm::RS256)
+        }
+        Algorithm::ES256 => {
+            use p256::elliptic_curve::sec1::ToEncodedPoint;
+            use p256::pkcs8::{EncodePrivateKey, LineEnding};
+
+            let key = p256::SecretKey::random(&mut OsRng);
+            let pem = key.to_pkcs8_pem(LineEnding::LF).map_err(key_error)?;
+            let point = key.public_key().to_encoded_point(false);
+            let (x, y) = point
+                .x()
+                .zip(point.y())
+                .ok_or_else(|| key_error("uncompressed point expected"))?;
+            let params = AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve: EllipticCurve::P256,
+                x: URL_SAFE_NO_PAD.encode(x),
+                y: URL_SAFE_NO_PAD.encode(y),
+            });
+            (pem.to_string(), params, KeyAlgorithm::ES256)
+        }
+        Algorithm::EdDSA => {
+            use ed25519_dalek::pkcs8::{spki::der::pem::LineEnding, EncodePrivateKey};
+
+            let key = ed25519_dalek::SigningKey::generate(&mut OsRng);
+            let pem = key.to_pkcs8_pem(LineEnding::LF).map_err(key_error)?;
+            let params = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(key.verifying_key().to_bytes()),
+            });
+            (pem.to_string(), params, KeyAlgorithm::EdDSA)
+        }
+        other => {
+            return Err(ForgeBaseError::Config(format!(
+                "Unsupported JWT signing algorithm: {:?}",
+                other
+            )))
+        }
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.clone()),
+            ..Default::default()
+        },
+        algorithm: params,
+    };
+
+    Ok(SigningKey {
+        id: Uuid::new_v4(),
+        kid,
+        algorithm: format!("{:?}", algorithm),
+        status: status.to_string(),
+        private_key_pem,
+        public_jwk: serde_json::to_value(jwk)
+            .map_err(|e| ForgeBaseError::Internal(format!("Failed to encode JWK: {}", e)))?,
+        created_at: Utc::now(),
+        activated_at: (status == KEY_STATUS_CURRENT).then(Utc::now),
+        retired_at: None,
+    })
+}
+
+/// Load the private half of a stored key for signing
+pub fn encoding_key(key: &SigningKey) -> Result<(Algorithm, EncodingKey)> {
+    let algorithm = parse_algorithm(&key.algorithm)?;
+    let pem = key.private_key_pem.as_bytes();
+    let encoding_key = match algorithm {
+        Algorithm::RS256 => EncodingKey::from_rsa_pem(pem),
+        Algorithm::ES256 => EncodingKey::from_ec_pem(pem),
+        _ => EncodingKey::from_ed_pem(pem),
+    }
+    .map_err(key_error)?;
+
+    Ok((algorithm, encoding_key))
+}
+
+/// Public JWK of a stored key
+pub fn public_jwk(key: &SigningKey) -> Result<Jwk> {
+    serde_json::from_value(key.public_jwk.clone())
+        .map_err(|e| ForgeBaseError::Internal(format!("Corrupt JWK for key {}: {}", key.kid, e)))
+}
+
+fn key_error(e: impl std::fmt::Display) -> ForgeBaseError {
+    ForgeBaseError::Internal(format!("Signing key error: {}", e))
+}
+
+/// Database-backed signing key store with staged rotation
+pub struct KeyStore {
+    pool: PgPool,
+}
+
+impl KeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// All keys, newest first
+    pub async fn list(&self) -> Result<Vec<SigningKey>> {
+        sqlx::query_as::<_, SigningKey>("SELECT * FROM jwt_signing_keys ORDER BY created_at DESC")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to list signing keys: {}", e)))
+    }
+
+    /// Keys that may verify tokens (everything but revoked)
+    pub async fn active_keys(&self) -> Result<Vec<SigningKey>> {
+        sqlx::query_as::<_, SigningKey>(
+            "SELECT * FROM jwt_signing_keys WHERE status <> $1 ORDER BY created_at DESC",
+        )
+        .bind(KEY_STATUS_REVOKED)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to load signing keys: {}", e)))
+    }
+
+    /// Active keys, generating a current key on first start
+    pub async fn ensure_current(&self, algorithm: Algorithm) -> Result<Vec<SigningKey>> {
+        let keys = self.active_keys().await?;
+        if keys.iter().any(|k| k.status == KEY_STATUS_CURRENT) {
+            return Ok(keys);
+        }
+
+        let key = generate_signing_key(algorithm, KEY_STATUS_CURRENT)?;
+        self.insert(&key).await?;
+        self.active_keys().await
+    }
+
+    /// Generate the next key so it is published before it starts signing
+    pub async fn stage_next(&self, algorithm: Algorithm) -> Result<SigningKey> {
+        let keys = self.active_keys().await?;
+        if keys.iter().any(|k| k.status == KEY_STATUS_NEXT) {
+            return Err(ForgeBaseError::Conflict(
+                "A next signing key is already staged".to_string(),
+            ));
+        }
+
+        let key = generate_signing_key(algorithm, KEY_STATUS_NEXT)?;
+        self.insert(&key).await?;
+        Ok(key)
+    }
+
+    /// Rotate: previous → revoked, current → previous, next → current.
+    ///
+    /// A key is generated if none was staged. Tokens signed by the revoked key stop verifying,
+    /// so rotate at most once per access-token lifetime.
+    pub async fn rotate(&self, algorithm: Algorithm) -> Result<Vec<SigningKey>> {
+        let staged = self
+            .active_keys()
+            .await?
+            .into_iter()
+            .any(|k| k.status == KEY_STATUS_NEXT);
+        let generated = if staged {
+            None
+        } else {
+            Some(generate_signing_key(algorithm, KEY_STATUS_NEXT)?)
+        };
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        if let Some(key) = &generated {
+            insert_key(&mut *tx, key).await?;
+        }
+        for (from, to) in [
+            (KEY_STATUS_PREVIOUS, KEY_STATUS_REVOKED),
+            (KEY_STATUS_CURRENT, KEY_STATUS_PREVIOUS),
+        ] {
+            sqlx::query("UPDATE jwt_signing_keys SET status = $2, retired_at = NOW() WHERE status = $1")
+                .bind(from)
+                .bind(to)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| ForgeBaseError::Database(format!("Failed to rotate signing keys: {}", e)))?;
+        }
+        sqlx::query("UPDATE jwt_signing_keys SET status = $2, activated_at = NOW() WHERE status = $1")
+            .bind(KEY_STATUS_NEXT)
+            .bind(KEY_STATUS_CURRENT)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to rotate signing keys: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to commit transaction: {}", e)))?;
+
+        self.active_keys().await
+    }
+
+    /// Revoke a key so it no longer verifies tokens. The current key cannot be revoked.
+    pub async fn revoke(&self, kid: &str) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE jwt_signing_keys SET status = $2, retired_at = NOW() WHERE kid = $1 AND status IN ($3, $4)",
+        )
+        .bind(kid)
+        .bind(KEY_STATUS_REVOKED)
+        .bind(KEY_STATUS_NEXT)
+        .bind(KEY_STATUS_PREVIOUS)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to revoke signing key: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ForgeBaseError::Validation(
+                "Only next or previous signing keys can be revoked".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn insert(&self, key: &SigningKey) -> Result<()> {
+        insert_key(&self.pool, key).await
+    }
+}
+
+async fn insert_key<'e, E>(executor: E, key: &SigningKey) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Postgres>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO jwt_signing_keys (
+            id, kid, algorithm, status, private_key_pem, public_jwk, created_at, activated_at, retired_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(key.id)
+    .bind(&key.kid)
+    .bind(&key.algorithm)
+    .bind(&key.status)
+    .bind(&key.private_key_pem)
+    .bind(&key.public_jwk)
+    .bind(key.created_at)
+    .bind(key.activated_at)
+    .bind(key.retired_at)
+    .execute(executor)
+    .await
+    .map_err(|e| ForgeBaseError::Database(format!("Failed to store signing key: {}", e)))?;
+
+    Ok(())
+}
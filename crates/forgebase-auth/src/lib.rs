@@ -1,14 +1,29 @@
+pub mod admin;
+pub mod api_key;
+pub mod audit;
+pub mod client;
 pub mod handlers;
+pub mod hooks;
 pub mod jwt;
+pub mod lockout;
+pub mod keys;
 pub mod middleware;
 pub mod models;
 pub mod oauth;
+pub mod oidc;
+pub mod organization;
 pub mod password;
+pub mod phone;
+pub mod ratelimit;
+pub mod rbac;
 pub mod repository;
 pub mod service;
 pub mod session;
+pub mod sms;
 pub mod email;
 pub mod mfa;
+pub mod saml;
+pub mod xmldsig;
 
 pub use handlers::*;
 pub use jwt::*;
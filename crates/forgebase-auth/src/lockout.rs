@@ -0,0 +1,269 @@
+// Failed sign-in tracking, progressive delays and temporary lockouts
+use chrono::{DateTime, Duration, Utc};
+use forgebase_core::{BruteForceConfig, ForgeBaseError, Result};
+use sqlx::PgPool;
+
+/// Result of recording a failed sign-in
+#[derive(Debug, Clone)]
+pub struct FailedAttempt {
+    /// Failures for the account within the window, including this one
+    pub account_failures: u32,
+    /// How long to hold the response before answering
+    pub delay: std::time::Duration,
+    /// Set when this failure locked the account
+    pub account_locked_until: Option<DateTime<Utc>>,
+}
+
+/// Tracks failed sign-ins per account (email) and per IP address
+pub struct SignInGuard {
+    pool: PgPool,
+    config: BruteForceConfig,
+}
+
+impl SignInGuard {
+    pub fn new(pool: PgPool, config: BruteForceConfig) -> Self {
+        Self { pool, config }
+    }
+
+    pub fn config(&self) -> &BruteForceConfig {
+        &self.config
+    }
+
+    /// When the account or IP is locked, the time the lock ends
+    pub async fn locked_until(
+        &self,
+        email: &str,
+        ip_address: Option<&str>,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let keys = lockout_keys(email, ip_address);
+        let locked_until: Option<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT MAX(locked_until) FROM sign_in_lockouts WHERE key = ANY($1) AND locked_until > NOW()",
+        )
+        .bind(&keys)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to check lockout: {}", e)))?;
+
+        Ok(locked_until)
+    }
+
+    /// Record a failure, locking the account or IP once its threshold is reached
+    pub async fn record_failure(&self, email: &str, ip_address: Option<&str>) -> Result<FailedAttempt> {
+        let window_start = Utc::now() - Duration::seconds(self.config.attempt_window_seconds);
+
+        let (account_failures, ip_failures): (i64, i64) = sqlx::query_as(
+            r#"
+            WITH inserted AS (
+                INSERT INTO sign_in_failures (email, ip_address) VALUES ($1, $2)
+            )
+            SELECT
+                (SELECT COUNT(*) FROM sign_in_failures WHERE email = $1 AND created_at > $3) + 1,
+                (SELECT COUNT(*) FROM sign_in_failures WHERE $2::TEXT IS NOT NULL AND ip_address = $2 AND created_at > $3) + 1
+            "#,
+        )
+        .bind(email)
+        .bind(ip_address)
+        .bind(window_start)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to record sign-in failure: {}", e)))?;
+
+        let locked_until = Utc::now() + Duration::seconds(self.config.lockout_seconds);
+        let mut account_locked_until = None;
+        if account_failures >= i64::from(self.config.max_account_attempts) {
+            self.lock(&format!("email:{}", email), locked_until).await?;
+            account_locked_until = Some(locked_until);
+        }
+        if let Some(ip_address) = ip_address {
+            if ip_failures >= i64::from(self.config.max_ip_attempts) {
+                self.lock(&format!("ip:{}", ip_address), locked_until).await?;
+            }
+        }
+
+        let account_failures = account_failures as u32;
+        Ok(FailedAttempt {
+            account_failures,
+            delay: progressive_delay(&self.config, account_failures),
+            account_locked_until,
+        })
+    }
+
+    /// Clear an account's failures after a successful sign-in
+    pub async fn record_success(&self, email: &str) -> Result<()> {
+        sqlx::query("DELETE FROM sign_in_failures WHERE email = $1")
+            .bind(email)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to clear sign-in failures: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Remove failures and lockouts that no longer matter
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let window_start = Utc::now() - Duration::seconds(self.config.attempt_window_seconds);
+        let failures = sqlx::query("DELETE FROM sign_in_failures WHERE created_at < $1")
+            .bind(window_start)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete sign-in failures: {}", e)))?;
+        let lockouts = sqlx::query("DELETE FROM sign_in_lockouts WHERE locked_until < NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete lockouts: {}", e)))?;
+
+        Ok(failures.rows_affected() + lockouts.rows_affected())
+    }
+
+    async fn lock(&self, key: &str, locked_until: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sign_in_lockouts (key, locked_until, created_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (key) DO UPDATE SET locked_until = GREATEST(sign_in_lockouts.locked_until, $2)
+            "#,
+        )
+        .bind(key)
+        .bind(locked_until)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to lock sign-in: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+fn lockout_keys(email: &str, ip_address: Option<&str>) -> Vec<String> {
+    let mut keys = vec![format!("email:{}", email)];
+    if let Some(ip_address) = ip_address {
+        keys.push(format!("ip:{}", ip_address));
+    }
+    keys
+}
+
+/// No delay for the first failure, then `base_delay_ms` doubling up to `max_delay_ms`
+pub fn progressive_delay(config: &BruteForceConfig, failures: u32) -> std::time::Duration {
+    if failures < 2 {
+        return std::time::Duration::ZERO;
+    }
+
+    let factor = 1u64.checked_shl(failures - 2).unwrap_or(u64::MAX);
+    let delay_ms = config.base_delay_ms.saturating_mul(factor).min(config.max_delay_ms);
+    std::time::Duration::from_millis(delay_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use forgebase_db::testing::test_pool;
+    use uuid::Uuid;
+
+    fn guard(pool: PgPool, max_account_attempts: u32, max_ip_attempts: u32) -> SignInGuard {
+        SignInGuard::new(
+            pool,
+            BruteForceConfig {
+                max_account_attempts,
+                max_ip_attempts,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn unique_email() -> String {
+        format!("lockout-{}@example.com", Uuid::new_v4())
+    }
+
+    fn unique_ip() -> String {
+        format!("2001:db8::{:x}", rand::random::<u32>())
+    }
+
+    #[test]
+    fn test_lockout_keys() {
+        assert_eq!(lockout_keys("a@example.com", None), vec!["email:a@example.com"]);
+        assert_eq!(
+            lockout_keys("a@example.com", Some("203.0.113.7")),
+            vec!["email:a@example.com", "ip:203.0.113.7"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_account_locks_at_threshold() {
+        let Some(pool) = test_pool().await else { return };
+        let guard = guard(pool, 3, 100);
+        let email = unique_email();
+
+        for expected in 1..=2 {
+            let attempt = guard.record_failure(&email, Some(&unique_ip())).await.unwrap();
+            assert_eq!(attempt.account_failures, expected);
+            assert!(attempt.account_locked_until.is_none());
+        }
+        assert!(guard.locked_until(&email, None).await.unwrap().is_none());
+
+        let attempt = guard.record_failure(&email, Some(&unique_ip())).await.unwrap();
+        assert_eq!(attempt.account_failures, 3);
+        assert!(attempt.account_locked_until.is_some());
+        // The lock follows the account to any address
+        assert!(guard.locked_until(&email, Some(&unique_ip())).await.unwrap().is_some());
+        assert!(guard.locked_until(&unique_email(), None).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failures_outside_window_are_forgotten() {
+        let Some(pool) = test_pool().await else { return };
+        let guard = guard(pool.clone(), 3, 100);
+        let email = unique_email();
+
+        guard.record_failure(&email, None).await.unwrap();
+        guard.record_failure(&email, None).await.unwrap();
+        sqlx::query("UPDATE sign_in_failures SET created_at = $2 WHERE email = $1")
+            .bind(&email)
+            .bind(Utc::now() - Duration::seconds(guard.config().attempt_window_seconds + 60))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let attempt = guard.record_failure(&email, None).await.unwrap();
+        assert_eq!(attempt.account_failures, 1);
+        assert!(attempt.account_locked_until.is_none());
+        assert!(guard.locked_until(&email, None).await.unwrap().is_none());
+
+        guard.delete_expired().await.unwrap();
+        let remaining: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM sign_in_failures WHERE email = $1")
+                .bind(&email)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(remaining, 1);
+    }
+
+    #[tokio::test]
+    async fn test_ip_locks_separately_from_accounts() {
+        let Some(pool) = test_pool().await else { return };
+        let guard = guard(pool, 100, 3);
+        let ip = unique_ip();
+        let emails: Vec<String> = (0..3).map(|_| unique_email()).collect();
+
+        for email in &emails {
+            let attempt = guard.record_failure(email, Some(&ip)).await.unwrap();
+            assert_eq!(attempt.account_failures, 1);
+            assert!(attempt.account_locked_until.is_none());
+        }
+
+        // Every account is refused from the locked address, but not from elsewhere
+        assert!(guard.locked_until(&unique_email(), Some(&ip)).await.unwrap().is_some());
+        assert!(guard.locked_until(&emails[0], Some(&unique_ip())).await.unwrap().is_none());
+        assert!(guard.locked_until(&emails[0], None).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_progressive_delay() {
+        let config = BruteForceConfig::default();
+        assert_eq!(progressive_delay(&config, 1).as_millis(), 0);
+        assert_eq!(progressive_delay(&config, 2).as_millis(), 250);
+        assert_eq!(progressive_delay(&config, 3).as_millis(), 500);
+        assert_eq!(progressive_delay(&config, 4).as_millis(), 1000);
+        assert_eq!(progressive_delay(&config, 10).as_millis(), 4000);
+        assert_eq!(progressive_delay(&config, 100).as_millis(), 4000);
+    }
+}
@@ -1,8 +1,15 @@
 // Multi-factor authentication module
+use crate::jwt::Claims;
+use crate::models::MfaFactor;
+use crate::session::AAL2;
 use forgebase_core::{ForgeBaseError, Result};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use uuid::Uuid;
 
+/// Factor receiving one-time codes by SMS
+pub const FACTOR_TYPE_SMS: &str = "sms";
+
 /// TOTP (Time-based One-Time Password) configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TotpConfig {
@@ -77,6 +84,99 @@ impl MfaManager {
     }
 }
 
+/// Check that the caller may enroll or complete enrollment of a new factor.
+///
+/// Once a user has a verified factor this takes an aal2 token, so a stolen aal1 token cannot
+/// register the thief's phone and step up with it.
+pub fn ensure_can_add_factor(claims: &Claims, has_verified_factor: bool) -> Result<()> {
+    if has_verified_factor && claims.aal.as_deref() != Some(AAL2) {
+        return Err(ForgeBaseError::Authorization(
+            "Verify an existing second factor before adding another".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Enrolled second factors
+pub struct MfaFactorRepository {
+    pool: PgPool,
+}
+
+impl MfaFactorRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Enroll an SMS factor, returning the existing one if the number is already enrolled
+    pub async fn create_sms(&self, user_id: Uuid, phone: &str) -> Result<MfaFactor> {
+        sqlx::query_as::<_, MfaFactor>(
+            r#"
+            INSERT INTO mfa_factors (id, user_id, factor_type, phone, verified, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, FALSE, NOW(), NOW())
+            ON CONFLICT (user_id, factor_type, phone) DO UPDATE SET updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(FACTOR_TYPE_SMS)
+        .bind(phone)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to enroll factor: {}", e)))
+    }
+
+    pub async fn find_for_user(&self, id: Uuid, user_id: Uuid) -> Result<Option<MfaFactor>> {
+        sqlx::query_as::<_, MfaFactor>("SELECT * FROM mfa_factors WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find factor: {}", e)))
+    }
+
+    pub async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<MfaFactor>> {
+        sqlx::query_as::<_, MfaFactor>(
+            "SELECT * FROM mfa_factors WHERE user_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list factors: {}", e)))
+    }
+
+    /// Whether the user has completed enrollment of any factor
+    pub async fn has_verified(&self, user_id: Uuid) -> Result<bool> {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM mfa_factors WHERE user_id = $1 AND verified)",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to check factors: {}", e)))
+    }
+
+    pub async fn mark_verified(&self, id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE mfa_factors SET verified = TRUE, updated_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to verify factor: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM mfa_factors WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete factor: {}", e)))?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -96,6 +196,23 @@ mod tests {
         assert!(uri.contains("SECRET123"));
     }
 
+    #[test]
+    fn test_new_factor_requires_aal2_once_one_is_verified() {
+        let aal1 = Claims::new(Uuid::new_v4(), "user@example.com".to_string(), 3600)
+            .with_aal(crate::session::AAL1.to_string());
+        let aal2 = aal1.clone().with_aal(AAL2.to_string());
+
+        // First factor: an aal1 session may enroll and verify it
+        assert!(ensure_can_add_factor(&aal1, false).is_ok());
+        // With a verified factor, an aal1 token can neither enroll nor verify another, so it
+        // cannot escalate to aal2 through a phone of its choosing
+        assert!(matches!(
+            ensure_can_add_factor(&aal1, true),
+            Err(ForgeBaseError::Authorization(_))
+        ));
+        assert!(ensure_can_add_factor(&aal2, true).is_ok());
+    }
+
     #[test]
     fn test_generate_sms_code() {
         let manager = MfaManager::new();
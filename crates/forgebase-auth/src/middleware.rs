@@ -1,12 +1,13 @@
-use crate::{jwt::Claims, jwt::JwtManager, AuthService};
+use crate::{api_key::is_api_key, jwt::Claims, jwt::JwtManager, AuthService};
 use axum::{
     extract::{Request, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Extension,
 };
 use forgebase_core::{ErrorResponse, ForgeBaseError};
+use futures::future::BoxFuture;
 use std::sync::Arc;
 
 /// Authentication state shared across handlers
@@ -16,26 +17,18 @@ pub struct AuthState {
     pub jwt_manager: Arc<JwtManager>,
 }
 
-/// Middleware to require authentication
+/// Middleware to require authentication.
+///
+/// Accepts `Authorization: Bearer <jwt>`, `Authorization: Bearer fb_...` or an `apikey` header.
 pub async fn require_auth(
     State(state): State<AuthState>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, AuthError> {
-    let auth_header = request
-        .headers()
-        .get("authorization")
-        .and_then(|h| h.to_str().ok())
+    let claims = authenticate(&state, request.headers())
+        .await?
         .ok_or(AuthError::MissingToken)?;
 
-    let token = JwtManager::extract_token_from_header(auth_header)
-        .map_err(|_| AuthError::InvalidToken)?;
-
-    let claims = state
-        .jwt_manager
-        .verify_token(token)
-        .map_err(|_| AuthError::InvalidToken)?;
-
     // Add claims to request extensions
     request.extensions_mut().insert(claims);
 
@@ -48,19 +41,90 @@ pub async fn optional_auth(
     mut request: Request,
     next: Next,
 ) -> Response {
-    if let Some(auth_header) = request.headers().get("authorization") {
-        if let Ok(auth_str) = auth_header.to_str() {
-            if let Ok(token) = JwtManager::extract_token_from_header(auth_str) {
-                if let Ok(claims) = state.jwt_manager.verify_token(token) {
-                    request.extensions_mut().insert(claims);
-                }
-            }
-        }
+    if let Ok(Some(claims)) = authenticate(&state, request.headers()).await {
+        request.extensions_mut().insert(claims);
     }
 
     next.run(request).await
 }
 
+/// Resolve the request's credentials to claims, if any were presented
+async fn authenticate(state: &AuthState, headers: &HeaderMap) -> Result<Option<Claims>, AuthError> {
+    let credential = match headers.get("authorization") {
+        Some(auth_header) => {
+            let auth_header = auth_header.to_str().map_err(|_| AuthError::InvalidToken)?;
+            JwtManager::extract_token_from_header(auth_header).map_err(|_| AuthError::InvalidToken)?
+        }
+        None => match headers.get("apikey") {
+            Some(api_key) => api_key.to_str().map_err(|_| AuthError::InvalidToken)?,
+            None => return Ok(None),
+        },
+    };
+
+    let claims = if is_api_key(credential) {
+        state
+            .service
+            .authenticate_api_key(credential)
+            .await
+            .map_err(|e| match e {
+                ForgeBaseError::Database(msg) => AuthError::Internal(msg),
+                _ => AuthError::InvalidToken,
+            })?
+    } else {
+        let claims = state
+            .jwt_manager
+            .verify_token(credential)
+            .map_err(|_| AuthError::InvalidToken)?;
+
+        // Tokens held by third-party apps are only good for the OAuth endpoints
+        if claims.is_delegated() {
+            return Err(AuthError::InvalidToken);
+        }
+
+        // Revoked sessions take their access tokens with them
+        if let Some(session_id) = claims.session_id().map_err(|_| AuthError::InvalidToken)? {
+            let active = state
+                .service
+                .is_session_active(session_id)
+                .await
+                .map_err(|e| AuthError::Internal(e.to_string()))?;
+            if !active {
+                return Err(AuthError::InvalidToken);
+            }
+        }
+
+        claims
+    };
+
+    Ok(Some(claims))
+}
+
+/// Middleware requiring a permission, for use after [`require_auth`]:
+///
+/// `.route_layer(axum::middleware::from_fn(require_permission("storage:write")))`
+pub fn require_permission(
+    permission: &'static str,
+) -> impl Fn(Request, Next) -> BoxFuture<'static, Result<Response, AuthError>> + Clone + Send + Sync + 'static
+{
+    move |request: Request, next: Next| {
+        Box::pin(async move {
+            let claims = request
+                .extensions()
+                .get::<Claims>()
+                .ok_or(AuthError::MissingToken)?;
+
+            if !claims.has_permission(permission) {
+                return Err(AuthError::Forbidden(format!(
+                    "Missing permission: {}",
+                    permission
+                )));
+            }
+
+            Ok(next.run(request).await)
+        })
+    }
+}
+
 /// Extract claims from request
 pub fn extract_claims(request: &Request) -> Option<Claims> {
     request.extensions().get::<Claims>().cloned()
@@ -71,6 +135,7 @@ pub fn extract_claims(request: &Request) -> Option<Claims> {
 pub enum AuthError {
     MissingToken,
     InvalidToken,
+    Forbidden(String),
     Internal(String),
 }
 
@@ -85,6 +150,7 @@ impl IntoResponse for AuthError {
                 StatusCode::UNAUTHORIZED,
                 ForgeBaseError::Auth("Invalid or expired token".to_string()),
             ),
+            AuthError::Forbidden(msg) => (StatusCode::FORBIDDEN, ForgeBaseError::Authorization(msg)),
             AuthError::Internal(msg) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ForgeBaseError::Internal(msg),
@@ -98,9 +164,4 @@ impl IntoResponse for AuthError {
     }
 }
 
-/// Rate limiting middleware (simplified version)
-pub async fn rate_limit(request: Request, next: Next) -> Response {
-    // TODO: Implement proper rate limiting with Redis or in-memory store
-    // For now, just pass through
-    next.run(request).await
-}
+pub use crate::ratelimit::rate_limit;
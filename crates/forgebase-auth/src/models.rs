@@ -1,3 +1,4 @@
+use crate::organization::OrganizationRole;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -7,7 +8,7 @@ use validator::Validate;
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: Uuid,
-    pub email: String,
+    pub email: Option<String>,
     pub email_verified: bool,
     pub phone: Option<String>,
     pub phone_verified: bool,
@@ -19,6 +20,8 @@ pub struct User {
     pub is_anonymous: bool,
     pub is_active: bool,
     pub last_sign_in_at: Option<DateTime<Utc>>,
+    pub banned_until: Option<DateTime<Utc>>, // Ban lifts automatically at this time; None bans indefinitely
+    pub deleted_at: Option<DateTime<Utc>>,   // Set by soft deletion
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -27,13 +30,14 @@ pub struct User {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserProfile {
     pub id: Uuid,
-    pub email: String,
+    pub email: Option<String>,
     pub email_verified: bool,
     pub phone: Option<String>,
     pub phone_verified: bool,
     pub full_name: Option<String>,
     pub avatar_url: Option<String>,
     pub metadata: serde_json::Value,
+    pub is_anonymous: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -48,6 +52,7 @@ impl From<User> for UserProfile {
             full_name: user.full_name,
             avatar_url: user.avatar_url,
             metadata: user.metadata,
+            is_anonymous: user.is_anonymous,
             created_at: user.created_at,
         }
     }
@@ -64,6 +69,21 @@ pub struct SignUpRequest {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// Anonymous sign up request
+#[derive(Debug, Default, Deserialize)]
+pub struct AnonymousSignUpRequest {
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Turn an anonymous user into a permanent one with an email and password
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpgradeAnonymousRequest {
+    #[validate(email)]
+    pub email: String,
+    #[validate(length(min = 8))]
+    pub password: String,
+}
+
 /// Sign in request
 #[derive(Debug, Deserialize, Validate)]
 pub struct SignInRequest {
@@ -88,6 +108,22 @@ pub struct OAuthSignInRequest {
     pub redirect_uri: String,
 }
 
+/// Request a one-time code by SMS
+#[derive(Debug, Deserialize, Validate)]
+pub struct PhoneOtpRequest {
+    #[validate(length(min = 1, max = 32))]
+    pub phone: String,
+}
+
+/// Verify an SMS one-time code
+#[derive(Debug, Deserialize, Validate)]
+pub struct VerifyPhoneOtpRequest {
+    #[validate(length(min = 1, max = 32))]
+    pub phone: String,
+    #[validate(length(min = 1, max = 10))]
+    pub code: String,
+}
+
 /// Password reset request
 #[derive(Debug, Deserialize, Validate)]
 pub struct PasswordResetRequest {
@@ -126,6 +162,10 @@ pub struct AuthResponse {
     pub access_token: String,
     pub refresh_token: String,
     pub expires_in: i64,
+    /// The user has a verified second factor but this session is only aal1; verify a factor
+    /// to reach aal2
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub mfa_required: bool,
 }
 
 /// Refresh token request
@@ -139,11 +179,51 @@ pub struct RefreshTokenRequest {
 pub struct Session {
     pub id: Uuid,
     pub user_id: Uuid,
-    pub refresh_token: String,
     pub user_agent: Option<String>,
     pub ip_address: Option<String>,
+    pub aal: String, // aal1, or aal2 once a second factor is verified
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub last_active_at: DateTime<Utc>,
+    pub client_id: Option<Uuid>, // OAuth client the session was granted to
+    pub scope: Option<String>,   // Scopes granted to that client
+}
+
+/// Device details parsed from a session's user agent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device_type: String, // desktop, mobile, tablet, bot, unknown
+}
+
+/// Active session as shown to its user
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub device: DeviceInfo,
+    pub aal: String,
+    pub current: bool, // The session making the request
+    pub client_id: Option<Uuid>, // Third-party app holding the session, if any
+    pub created_at: DateTime<Utc>,
+    pub last_active_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
+}
+
+/// Refresh token model (only the SHA-256 hash of the token is stored)
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub parent: Option<String>, // Hash of the token this one replaced
+    pub revoked: bool,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 /// OAuth account model
@@ -189,6 +269,29 @@ pub struct UserRole {
     pub created_at: DateTime<Utc>,
 }
 
+/// Role creation request
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateRoleRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+/// Role update request (permissions replace the existing set)
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub description: Option<String>,
+    pub permissions: Option<Vec<String>>,
+}
+
+/// Role assignment request
+#[derive(Debug, Deserialize)]
+pub struct AssignRoleRequest {
+    pub role_id: Uuid,
+}
+
 /// API Key model
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct ApiKey {
@@ -224,3 +327,433 @@ pub struct ApiKeyResponse {
     pub expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
+
+/// SAML identity provider
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SamlProvider {
+    pub id: Uuid,
+    pub entity_id: String,
+    pub sso_url: String,
+    pub certificates: Vec<String>, // Base64 DER signing certificates
+    #[serde(skip_serializing)]
+    pub metadata_xml: String,
+    pub metadata_url: Option<String>,
+    pub domains: Vec<String>,
+    pub attribute_mapping: serde_json::Value,
+    pub jit_provisioning: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Pending SAML authentication request
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SamlRequest {
+    pub id: String,
+    pub provider_id: Uuid,
+    pub redirect_to: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Register SAML identity provider request (metadata XML or URL)
+#[derive(Debug, Deserialize, Validate)]
+pub struct RegisterSamlProviderRequest {
+    pub metadata_xml: Option<String>,
+    #[validate(url)]
+    pub metadata_url: Option<String>,
+    #[validate(length(min = 1))]
+    pub domains: Vec<String>,
+    pub attribute_mapping: Option<serde_json::Value>,
+    pub jit_provisioning: Option<bool>,
+}
+
+/// Single sign-on request; the identity provider is picked by id, domain or email
+#[derive(Debug, Deserialize)]
+pub struct SsoSignInRequest {
+    pub provider_id: Option<Uuid>,
+    pub domain: Option<String>,
+    pub email: Option<String>,
+    pub redirect_to: Option<String>,
+}
+
+/// Single sign-on response
+#[derive(Debug, Serialize)]
+pub struct SsoSignInResponse {
+    pub url: String,
+}
+
+/// JWT signing key (status: next, current, previous or revoked)
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SigningKey {
+    pub id: Uuid,
+    pub kid: String,
+    pub algorithm: String,
+    pub status: String,
+    #[serde(skip_serializing)]
+    pub private_key_pem: String,
+    pub public_jwk: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub activated_at: Option<DateTime<Utc>>,
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+/// Rendered email waiting in the outbox (status: pending, sent or failed)
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    pub to_email: String,
+    pub to_name: Option<String>,
+    pub template: String,
+    pub locale: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+/// Enrolled second factor
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MfaFactor {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub factor_type: String, // sms
+    pub phone: Option<String>,
+    pub verified: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Verify an MFA challenge
+#[derive(Debug, Deserialize, Validate)]
+pub struct VerifyMfaRequest {
+    #[validate(length(min = 1, max = 10))]
+    pub code: String,
+}
+
+/// Admin user listing filters
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ListUsersQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub search: Option<String>, // Matches email, phone or name
+    pub is_anonymous: Option<bool>,
+    pub email_verified: Option<bool>,
+    pub banned: Option<bool>,
+    pub include_deleted: bool,
+}
+
+/// Admin user creation; the email is treated as verified unless `email_verified` is false
+#[derive(Debug, Deserialize, Validate)]
+pub struct AdminCreateUserRequest {
+    #[validate(email)]
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub password: Option<String>,
+    pub email_verified: Option<bool>,
+    pub full_name: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Ban a user until a time, or indefinitely
+#[derive(Debug, Default, Deserialize)]
+pub struct BanUserRequest {
+    pub banned_until: Option<DateTime<Utc>>,
+}
+
+/// Admin user deletion options
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DeleteUserQuery {
+    pub soft: bool,
+}
+
+/// Short-lived access token for acting as a user
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    pub user: UserProfile,
+    pub access_token: String,
+    pub expires_in: i64,
+}
+
+/// Organization that owns sites, buckets and functions on behalf of its members
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Organization the current user belongs to, with their role in it
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OrganizationMembership {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub organization: Organization,
+    pub role: OrganizationRole,
+}
+
+/// Member of an organization
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OrganizationMember {
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub role: OrganizationRole,
+    pub email: Option<String>,
+    pub full_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Pending or accepted invitation to join an organization
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OrganizationInvitation {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub email: String,
+    pub role: OrganizationRole,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub invited_by: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Create organization request; the slug is derived from the name when omitted
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateOrganizationRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    pub slug: Option<String>,
+}
+
+/// Update organization request
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateOrganizationRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: Option<String>,
+    pub slug: Option<String>,
+}
+
+/// Invite someone to an organization by email
+#[derive(Debug, Deserialize, Validate)]
+pub struct InviteMemberRequest {
+    #[validate(email)]
+    pub email: String,
+    pub role: OrganizationRole,
+}
+
+/// Change a member's role
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRoleRequest {
+    pub role: OrganizationRole,
+}
+
+/// Hand ownership of an organization to another member
+#[derive(Debug, Deserialize)]
+pub struct TransferOwnershipRequest {
+    pub user_id: Uuid,
+}
+
+/// Accept an invitation with the token from its email
+#[derive(Debug, Deserialize)]
+pub struct AcceptInvitationRequest {
+    pub token: String,
+}
+
+/// Third-party app registered to sign users in with ForgeBase
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OAuthClient {
+    pub client_id: Uuid,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: Option<String>, // None for public clients (PKCE only)
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<String>,
+    pub scopes: Vec<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Register OAuth client request
+#[derive(Debug, Deserialize, Validate)]
+pub struct RegisterOAuthClientRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Option<Vec<String>>, // Defaults to authorization_code and refresh_token
+    pub scopes: Option<Vec<String>>,      // Defaults to openid, profile and email
+    #[serde(default)]
+    pub public: bool, // Browser and mobile apps that cannot keep a secret
+}
+
+/// Newly registered client; the secret is only ever shown here
+#[derive(Debug, Serialize)]
+pub struct RegisteredOAuthClient {
+    #[serde(flatten)]
+    pub client: OAuthClient,
+    pub client_secret: Option<String>,
+}
+
+/// Authorization code awaiting exchange (only its hash is stored)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OAuthAuthorizationCode {
+    pub code_hash: String,
+    pub client_id: Uuid,
+    pub user_id: Uuid,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub aal: String,
+    pub auth_time: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Scopes a user has allowed a client
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OAuthConsent {
+    pub user_id: Uuid,
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Authorization request parameters, as sent by the client to the consent page
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthorizeParams {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// What the consent page shows the user
+#[derive(Debug, Serialize)]
+pub struct AuthorizationDetails {
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub consent_required: bool, // False when the user already allowed these scopes
+}
+
+/// The user's answer on the consent page
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeDecision {
+    #[serde(flatten)]
+    pub params: AuthorizeParams,
+    pub approve: bool,
+}
+
+/// Where to send the browser next: the client's redirect URI with a code or an error
+#[derive(Debug, Serialize)]
+pub struct AuthorizationRedirect {
+    pub redirect_to: String,
+}
+
+/// Token endpoint request (form encoded)
+#[derive(Debug, Default, Deserialize)]
+pub struct OAuthTokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Token endpoint response
+#[derive(Debug, Serialize)]
+pub struct OAuthTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+    pub scope: String,
+}
+
+/// Introspection or revocation request (form encoded)
+#[derive(Debug, Default, Deserialize)]
+pub struct OAuthTokenHintRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Token introspection response (RFC 7662)
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+}
+
+/// Security event recorded in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub action: String,
+    pub outcome: String, // success or failure
+    pub user_id: Option<Uuid>,
+    pub actor_id: Option<String>, // Admin or impersonator acting on the user's behalf
+    pub email: Option<String>,    // Identifier given, also for unknown accounts
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub factor: Option<String>, // password, sso, phone_otp, sms, refresh_token, ...
+    pub session_id: Option<Uuid>,
+    pub reason: Option<String>, // Why a failure failed
+    pub metadata: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Audit log filters; `since` is inclusive and `until` exclusive
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuditLogQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub user_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub outcome: Option<String>,
+    pub ip_address: Option<String>,
+    pub actor_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,866 @@
+// OpenID Connect provider: third-party apps signing users in with ForgeBase
+use crate::models::{
+    AuthorizeParams, OAuthAuthorizationCode, OAuthClient, OAuthConsent,
+    RegisterOAuthClientRequest, RegisteredOAuthClient, User,
+};
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use forgebase_core::{ForgeBaseError, OidcProviderConfig, Result};
+use jsonwebtoken::Algorithm;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use url::Url;
+use uuid::Uuid;
+
+pub const GRANT_AUTHORIZATION_CODE: &str = "authorization_code";
+pub const GRANT_REFRESH_TOKEN: &str = "refresh_token";
+pub const GRANT_CLIENT_CREDENTIALS: &str = "client_credentials";
+
+pub const SCOPE_OPENID: &str = "openid";
+pub const SCOPE_PROFILE: &str = "profile";
+pub const SCOPE_EMAIL: &str = "email";
+pub const SCOPE_PHONE: &str = "phone";
+/// Also issue a refresh token
+pub const SCOPE_OFFLINE_ACCESS: &str = "offline_access";
+
+/// Scopes a user can grant to a client; other scopes only apply to client credentials
+pub const USER_SCOPES: &[&str] = &[
+    SCOPE_OPENID,
+    SCOPE_PROFILE,
+    SCOPE_EMAIL,
+    SCOPE_PHONE,
+    SCOPE_OFFLINE_ACCESS,
+];
+
+const GRANT_TYPES: &[&str] = &[
+    GRANT_AUTHORIZATION_CODE,
+    GRANT_REFRESH_TOKEN,
+    GRANT_CLIENT_CREDENTIALS,
+];
+
+/// Error response of the token, introspection, revocation and userinfo endpoints (RFC 6749 5.2)
+#[derive(Debug)]
+pub struct OAuthError {
+    pub error: &'static str,
+    pub description: String,
+}
+
+impl OAuthError {
+    fn new(error: &'static str, description: impl Into<String>) -> Self {
+        Self {
+            error,
+            description: description.into(),
+        }
+    }
+
+    pub fn invalid_request(description: impl Into<String>) -> Self {
+        Self::new("invalid_request", description)
+    }
+
+    pub fn invalid_client(description: impl Into<String>) -> Self {
+        Self::new("invalid_client", description)
+    }
+
+    pub fn invalid_grant(description: impl Into<String>) -> Self {
+        Self::new("invalid_grant", description)
+    }
+
+    pub fn unauthorized_client(description: impl Into<String>) -> Self {
+        Self::new("unauthorized_client", description)
+    }
+
+    pub fn unsupported_grant_type(description: impl Into<String>) -> Self {
+        Self::new("unsupported_grant_type", description)
+    }
+
+    pub fn invalid_scope(description: impl Into<String>) -> Self {
+        Self::new("invalid_scope", description)
+    }
+
+    pub fn access_denied(description: impl Into<String>) -> Self {
+        Self::new("access_denied", description)
+    }
+
+    pub fn invalid_token(description: impl Into<String>) -> Self {
+        Self::new("invalid_token", description)
+    }
+
+    pub fn insufficient_scope(description: impl Into<String>) -> Self {
+        Self::new("insufficient_scope", description)
+    }
+
+    fn status(&self) -> StatusCode {
+        match self.error {
+            "invalid_client" | "invalid_token" => StatusCode::UNAUTHORIZED,
+            "insufficient_scope" => StatusCode::FORBIDDEN,
+            "server_error" => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl From<ForgeBaseError> for OAuthError {
+    fn from(error: ForgeBaseError) -> Self {
+        match error {
+            ForgeBaseError::Validation(msg) | ForgeBaseError::InvalidInput(msg) => {
+                Self::invalid_request(msg)
+            }
+            other => {
+                tracing::error!("OAuth endpoint failed: {}", other);
+                Self::new("server_error", "Internal server error")
+            }
+        }
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let mut response = (
+            status,
+            [(header::CACHE_CONTROL, "no-store")],
+            Json(serde_json::json!({
+                "error": self.error,
+                "error_description": self.description,
+            })),
+        )
+            .into_response();
+
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            let challenge = format!(r#"Bearer error="{}""#, self.error);
+            if let Ok(value) = HeaderValue::from_str(&challenge) {
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, value);
+            }
+        }
+        response
+    }
+}
+
+/// Standard claims about the user, released according to the granted scopes
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UserInfo {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number_verified: Option<bool>,
+}
+
+/// ID token claims
+#[derive(Debug, Serialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    pub auth_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub user: UserInfo,
+}
+
+/// OpenID Connect provider: registered clients, authorization codes and consents
+pub struct OidcProvider {
+    config: OidcProviderConfig,
+    repo: OidcRepository,
+}
+
+impl OidcProvider {
+    pub fn new(pool: PgPool, config: OidcProviderConfig) -> Self {
+        Self {
+            config,
+            repo: OidcRepository::new(pool),
+        }
+    }
+
+    pub fn issuer(&self) -> &str {
+        self.config.issuer.trim_end_matches('/')
+    }
+
+    /// `/.well-known/openid-configuration` document
+    pub fn discovery(&self, signing_algorithm: Algorithm) -> serde_json::Value {
+        let issuer = self.issuer();
+        let authorization_endpoint = self
+            .config
+            .authorization_endpoint
+            .clone()
+            .unwrap_or_else(|| format!("{}/oauth/authorize", issuer));
+
+        serde_json::json!({
+            "issuer": issuer,
+            "authorization_endpoint": authorization_endpoint,
+            "token_endpoint": format!("{}/oauth/token", issuer),
+            "userinfo_endpoint": format!("{}/oauth/userinfo", issuer),
+            "jwks_uri": format!("{}/.well-known/jwks.json", issuer),
+            "introspection_endpoint": format!("{}/oauth/introspect", issuer),
+            "revocation_endpoint": format!("{}/oauth/revoke", issuer),
+            "response_types_supported": ["code"],
+            "grant_types_supported": GRANT_TYPES,
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": [format!("{:?}", signing_algorithm)],
+            "scopes_supported": USER_SCOPES,
+            "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+            "code_challenge_methods_supported": ["S256"],
+            "claims_supported": [
+                "sub", "iss", "aud", "exp", "iat", "auth_time", "nonce", "email", "email_verified",
+                "name", "picture", "updated_at", "phone_number", "phone_number_verified",
+            ],
+        })
+    }
+
+    /// Register a client; confidential clients get a secret that is only shown once
+    pub async fn register_client(
+        &self,
+        request: RegisterOAuthClientRequest,
+        created_by: Option<Uuid>,
+    ) -> Result<RegisteredOAuthClient> {
+        let grant_types = request.grant_types.unwrap_or_else(|| {
+            vec![
+                GRANT_AUTHORIZATION_CODE.to_string(),
+                GRANT_REFRESH_TOKEN.to_string(),
+            ]
+        });
+        if let Some(grant_type) = grant_types.iter().find(|g| !GRANT_TYPES.contains(&g.as_str())) {
+            return Err(ForgeBaseError::Validation(format!(
+                "Unsupported grant type: {}",
+                grant_type
+            )));
+        }
+        if request.public && grant_types.iter().any(|g| g == GRANT_CLIENT_CREDENTIALS) {
+            return Err(ForgeBaseError::Validation(
+                "Public clients cannot use the client credentials grant".to_string(),
+            ));
+        }
+        if grant_types.iter().any(|g| g == GRANT_AUTHORIZATION_CODE) && request.redirect_uris.is_empty() {
+            return Err(ForgeBaseError::Validation(
+                "The authorization code grant needs at least one redirect URI".to_string(),
+            ));
+        }
+        for uri in &request.redirect_uris {
+            validate_redirect_uri(uri)?;
+        }
+
+        let scopes = request.scopes.unwrap_or_else(|| {
+            vec![
+                SCOPE_OPENID.to_string(),
+                SCOPE_PROFILE.to_string(),
+                SCOPE_EMAIL.to_string(),
+            ]
+        });
+        if let Some(scope) = scopes.iter().find(|s| !is_valid_scope(s)) {
+            return Err(ForgeBaseError::Validation(format!("Invalid scope: {}", scope)));
+        }
+
+        let client_secret = (!request.public).then(generate_client_secret);
+        let now = Utc::now();
+        let client = self
+            .repo
+            .create_client(&OAuthClient {
+                client_id: Uuid::new_v4(),
+                client_secret_hash: client_secret.as_deref().map(hash_client_secret),
+                name: request.name,
+                redirect_uris: request.redirect_uris,
+                grant_types,
+                scopes,
+                created_by,
+                created_at: now,
+                updated_at: now,
+            })
+            .await?;
+
+        Ok(RegisteredOAuthClient {
+            client,
+            client_secret,
+        })
+    }
+
+    pub async fn list_clients(&self) -> Result<Vec<OAuthClient>> {
+        self.repo.list_clients().await
+    }
+
+    /// Delete a client, ending every session granted to it
+    pub async fn delete_client(&self, client_id: Uuid) -> Result<()> {
+        if !self.repo.delete_client(client_id).await? {
+            return Err(ForgeBaseError::NotFound("OAuth client not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Look up a client by the `client_id` it presents
+    pub async fn find_client(&self, client_id: &str) -> Result<Option<OAuthClient>> {
+        match Uuid::parse_str(client_id) {
+            Ok(id) => self.repo.find_client(id).await,
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Authenticate a client at the token, introspection or revocation endpoint.
+    ///
+    /// Public clients only identify themselves; confidential clients must present their secret.
+    pub async fn authenticate_client(
+        &self,
+        client_id: Option<String>,
+        client_secret: Option<String>,
+    ) -> std::result::Result<OAuthClient, OAuthError> {
+        let client_id =
+            client_id.ok_or_else(|| OAuthError::invalid_client("Client authentication required"))?;
+        let client = self
+            .find_client(&client_id)
+            .await?
+            .ok_or_else(|| OAuthError::invalid_client("Unknown client"))?;
+
+        match (&client.client_secret_hash, client_secret) {
+            (None, None) => Ok(client),
+            (Some(expected), Some(secret))
+                if constant_time_eq::constant_time_eq(
+                    expected.as_bytes(),
+                    hash_client_secret(&secret).as_bytes(),
+                ) =>
+            {
+                Ok(client)
+            }
+            _ => Err(OAuthError::invalid_client("Client authentication failed")),
+        }
+    }
+
+    /// Check the client and redirect URI of an authorization request. Errors here must be
+    /// shown to the user rather than redirected, since the redirect URI is not trusted yet.
+    pub async fn validate_client_redirect(&self, params: &AuthorizeParams) -> Result<OAuthClient> {
+        let client = self
+            .find_client(&params.client_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::InvalidInput("Unknown client".to_string()))?;
+
+        if !client.redirect_uris.contains(&params.redirect_uri) {
+            return Err(ForgeBaseError::InvalidInput(
+                "Redirect URI is not registered for this client".to_string(),
+            ));
+        }
+        Ok(client)
+    }
+
+    /// Check the rest of an authorization request, returning the requested scopes
+    pub fn validate_authorization_request(
+        &self,
+        client: &OAuthClient,
+        params: &AuthorizeParams,
+    ) -> std::result::Result<Vec<String>, OAuthError> {
+        if params.response_type != "code" {
+            return Err(OAuthError::new(
+                "unsupported_response_type",
+                "Only the code response type is supported",
+            ));
+        }
+        if !client.grant_types.iter().any(|g| g == GRANT_AUTHORIZATION_CODE) {
+            return Err(OAuthError::unauthorized_client(
+                "Client may not use the authorization code grant",
+            ));
+        }
+
+        let scopes = parse_scope(params.scope.as_deref().unwrap_or(SCOPE_OPENID));
+        if let Some(scope) = scopes
+            .iter()
+            .find(|s| !USER_SCOPES.contains(&s.as_str()) || !client.scopes.contains(s))
+        {
+            return Err(OAuthError::invalid_scope(format!("Scope not allowed: {}", scope)));
+        }
+
+        match (params.code_challenge.as_deref(), params.code_challenge_method.as_deref()) {
+            (Some(_), Some("S256")) => {}
+            (Some(_), _) => {
+                return Err(OAuthError::invalid_request(
+                    "code_challenge_method must be S256",
+                ))
+            }
+            (None, _) if client.client_secret_hash.is_none() => {
+                return Err(OAuthError::invalid_request("Public clients must use PKCE"))
+            }
+            (None, _) => {}
+        }
+
+        Ok(scopes)
+    }
+
+    /// Whether the user already allowed the client every one of `scopes`
+    pub async fn has_consent(&self, user_id: Uuid, client_id: Uuid, scopes: &[String]) -> Result<bool> {
+        let granted = self.repo.consented_scopes(user_id, client_id).await?;
+        Ok(scopes.iter().all(|scope| granted.contains(scope)))
+    }
+
+    /// Remember that the user allowed the client these scopes
+    pub async fn grant_consent(&self, user_id: Uuid, client_id: Uuid, scopes: &[String]) -> Result<()> {
+        self.repo.upsert_consent(user_id, client_id, scopes).await
+    }
+
+    pub async fn list_consents(&self, user_id: Uuid) -> Result<Vec<OAuthConsent>> {
+        self.repo.list_consents(user_id).await
+    }
+
+    /// Forget a consent; returns false if there was none
+    pub async fn revoke_consent(&self, user_id: Uuid, client_id: Uuid) -> Result<bool> {
+        self.repo.delete_consent(user_id, client_id).await
+    }
+
+    /// Issue a single-use authorization code for the approved request
+    pub async fn create_authorization_code(
+        &self,
+        client: &OAuthClient,
+        user_id: Uuid,
+        params: &AuthorizeParams,
+        scopes: &[String],
+        aal: &str,
+        auth_time: DateTime<Utc>,
+    ) -> Result<String> {
+        let code = generate_client_secret();
+        let now = Utc::now();
+
+        self.repo.delete_expired_codes().await?;
+        self.repo
+            .create_code(&OAuthAuthorizationCode {
+                code_hash: hash_client_secret(&code),
+                client_id: client.client_id,
+                user_id,
+                redirect_uri: params.redirect_uri.clone(),
+                scope: scopes.join(" "),
+                nonce: params.nonce.clone(),
+                code_challenge: params.code_challenge.clone(),
+                aal: aal.to_string(),
+                auth_time,
+                expires_at: now + Duration::seconds(self.config.authorization_code_expiry_seconds),
+                created_at: now,
+            })
+            .await?;
+
+        Ok(code)
+    }
+
+    /// Redeem an authorization code; it cannot be used again
+    pub async fn take_authorization_code(&self, code: &str) -> Result<Option<OAuthAuthorizationCode>> {
+        self.repo.take_code(&hash_client_secret(code)).await
+    }
+
+    /// ID token claims for a user signed in to `client_id`
+    pub fn id_token_claims(
+        &self,
+        user: &User,
+        client_id: Uuid,
+        scopes: &[String],
+        nonce: Option<String>,
+        auth_time: DateTime<Utc>,
+    ) -> IdTokenClaims {
+        let now = Utc::now();
+        IdTokenClaims {
+            iss: self.issuer().to_string(),
+            aud: client_id.to_string(),
+            exp: (now + Duration::seconds(self.config.id_token_expiration)).timestamp(),
+            iat: now.timestamp(),
+            auth_time: auth_time.timestamp(),
+            nonce,
+            user: user_info(user, scopes),
+        }
+    }
+}
+
+/// The claims about `user` that `scopes` release
+pub fn user_info(user: &User, scopes: &[String]) -> UserInfo {
+    let has = |scope: &str| scopes.iter().any(|s| s == scope);
+    let profile = has(SCOPE_PROFILE);
+    let email = has(SCOPE_EMAIL) && user.email.is_some();
+    let phone = has(SCOPE_PHONE) && user.phone.is_some();
+
+    UserInfo {
+        sub: user.id.to_string(),
+        email: user.email.clone().filter(|_| email),
+        email_verified: email.then_some(user.email_verified),
+        name: user.full_name.clone().filter(|_| profile),
+        picture: user.avatar_url.clone().filter(|_| profile),
+        updated_at: profile.then(|| user.updated_at.timestamp()),
+        phone_number: user.phone.clone().filter(|_| phone),
+        phone_number_verified: phone.then_some(user.phone_verified),
+    }
+}
+
+/// Split a space-separated scope string, dropping duplicates
+pub fn parse_scope(scope: &str) -> Vec<String> {
+    let mut scopes: Vec<String> = Vec::new();
+    for scope in scope.split_whitespace() {
+        if !scopes.iter().any(|s| s == scope) {
+            scopes.push(scope.to_string());
+        }
+    }
+    scopes
+}
+
+fn is_valid_scope(scope: &str) -> bool {
+    !scope.is_empty()
+        && scope
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '_' | '.' | '*' | '-'))
+}
+
+/// PKCE check (RFC 7636): BASE64URL(SHA256(verifier)) must equal the challenge
+pub fn verify_pkce(code_verifier: &str, code_challenge: &str) -> bool {
+    let computed = general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+    constant_time_eq::constant_time_eq(computed.as_bytes(), code_challenge.as_bytes())
+}
+
+/// Redirect URIs must be exact absolute URLs: https, http on loopback (development and
+/// native apps) or a private-use scheme such as `com.example.app:/callback`
+pub fn validate_redirect_uri(uri: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        ForgeBaseError::Validation(format!("Invalid redirect URI {}: {}", uri, reason))
+    };
+    let url = Url::parse(uri).map_err(|_| invalid("not an absolute URL"))?;
+
+    if url.fragment().is_some() {
+        return Err(invalid("must not contain a fragment"));
+    }
+    match url.scheme() {
+        "https" => Ok(()),
+        "http" if matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")) => Ok(()),
+        "http" => Err(invalid("http is only allowed for loopback addresses")),
+        scheme if scheme.contains('.') => Ok(()),
+        _ => Err(invalid("unsupported scheme")),
+    }
+}
+
+/// The client's redirect URI with `params` appended to its query
+pub fn redirect_with(redirect_uri: &str, params: &[(&str, &str)]) -> Result<String> {
+    let mut url = Url::parse(redirect_uri)
+        .map_err(|_| ForgeBaseError::InvalidInput("Invalid redirect URI".to_string()))?;
+    {
+        let mut query = url.query_pairs_mut();
+        for (key, value) in params {
+            query.append_pair(key, value);
+        }
+    }
+    Ok(url.to_string())
+}
+
+/// Client ID and secret from `Authorization: Basic` (client_secret_basic), falling back to
+/// the form body (client_secret_post)
+pub fn client_credentials(
+    headers: &HeaderMap,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+) -> (Option<String>, Option<String>) {
+    let basic = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| general_purpose::STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .and_then(|decoded| {
+            let (id, secret) = decoded.split_once(':')?;
+            Some((form_decode(id), form_decode(secret)))
+        });
+
+    match basic {
+        Some((id, secret)) => (Some(id), Some(secret)),
+        None => (client_id, client_secret),
+    }
+}
+
+fn form_decode(value: &str) -> String {
+    url::form_urlencoded::parse(value.as_bytes())
+        .next()
+        .map(|(decoded, _)| decoded.into_owned())
+        .unwrap_or_default()
+}
+
+fn generate_client_secret() -> String {
+    use rand::Rng;
+
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect()
+}
+
+/// Client secrets and authorization codes are random, so a plain hash suffices
+fn hash_client_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+/// OAuth client, authorization code and consent repository
+pub struct OidcRepository {
+    pool: PgPool,
+}
+
+impl OidcRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_client(&self, client: &OAuthClient) -> Result<OAuthClient> {
+        let client = sqlx::query_as::<_, OAuthClient>(
+            r#"
+            INSERT INTO oauth_clients (
+                client_id, client_secret_hash, name, redirect_uris, grant_types, scopes,
+                created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+        )
+        .bind(client.client_id)
+        .bind(&client.client_secret_hash)
+        .bind(&client.name)
+        .bind(&client.redirect_uris)
+        .bind(&client.grant_types)
+        .bind(&client.scopes)
+        .bind(client.created_by)
+        .bind(client.created_at)
+        .bind(client.updated_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create OAuth client: {}", e)))?;
+
+        Ok(client)
+    }
+
+    pub async fn list_clients(&self) -> Result<Vec<OAuthClient>> {
+        let clients = sqlx::query_as::<_, OAuthClient>("SELECT * FROM oauth_clients ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to list OAuth clients: {}", e)))?;
+
+        Ok(clients)
+    }
+
+    pub async fn find_client(&self, client_id: Uuid) -> Result<Option<OAuthClient>> {
+        let client = sqlx::query_as::<_, OAuthClient>("SELECT * FROM oauth_clients WHERE client_id = $1")
+            .bind(client_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find OAuth client: {}", e)))?;
+
+        Ok(client)
+    }
+
+    pub async fn delete_client(&self, client_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM oauth_clients WHERE client_id = $1")
+            .bind(client_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete OAuth client: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_code(&self, code: &OAuthAuthorizationCode) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_authorization_codes (
+                code_hash, client_id, user_id, redirect_uri, scope, nonce, code_challenge,
+                aal, auth_time, expires_at, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "#,
+        )
+        .bind(&code.code_hash)
+        .bind(code.client_id)
+        .bind(code.user_id)
+        .bind(&code.redirect_uri)
+        .bind(&code.scope)
+        .bind(&code.nonce)
+        .bind(&code.code_challenge)
+        .bind(&code.aal)
+        .bind(code.auth_time)
+        .bind(code.expires_at)
+        .bind(code.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create authorization code: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Delete and return an unexpired code, so concurrent exchanges cannot both succeed
+    pub async fn take_code(&self, code_hash: &str) -> Result<Option<OAuthAuthorizationCode>> {
+        let code = sqlx::query_as::<_, OAuthAuthorizationCode>(
+            "DELETE FROM oauth_authorization_codes WHERE code_hash = $1 AND expires_at > NOW() RETURNING *",
+        )
+        .bind(code_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to redeem authorization code: {}", e)))?;
+
+        Ok(code)
+    }
+
+    pub async fn delete_expired_codes(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM oauth_authorization_codes WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ForgeBaseError::Database(format!("Failed to delete expired authorization codes: {}", e))
+            })?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn consented_scopes(&self, user_id: Uuid, client_id: Uuid) -> Result<Vec<String>> {
+        let scopes: Option<Vec<String>> = sqlx::query_scalar(
+            "SELECT scopes FROM oauth_consents WHERE user_id = $1 AND client_id = $2",
+        )
+        .bind(user_id)
+        .bind(client_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to find consent: {}", e)))?;
+
+        Ok(scopes.unwrap_or_default())
+    }
+
+    /// Record consent, adding to any scopes granted before
+    pub async fn upsert_consent(&self, user_id: Uuid, client_id: Uuid, scopes: &[String]) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_consents (user_id, client_id, scopes, created_at, updated_at)
+            VALUES ($1, $2, $3, NOW(), NOW())
+            ON CONFLICT (user_id, client_id) DO UPDATE SET
+                scopes = ARRAY(SELECT DISTINCT unnest(oauth_consents.scopes || EXCLUDED.scopes)),
+                updated_at = NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(client_id)
+        .bind(scopes)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to record consent: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub async fn list_consents(&self, user_id: Uuid) -> Result<Vec<OAuthConsent>> {
+        let consents = sqlx::query_as::<_, OAuthConsent>(
+            r#"
+            SELECT c.user_id, c.client_id, o.name AS client_name, c.scopes, c.created_at, c.updated_at
+            FROM oauth_consents c
+            JOIN oauth_clients o ON o.client_id = c.client_id
+            WHERE c.user_id = $1
+            ORDER BY c.updated_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list consents: {}", e)))?;
+
+        Ok(consents)
+    }
+
+    pub async fn delete_consent(&self, user_id: Uuid, client_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM oauth_consents WHERE user_id = $1 AND client_id = $2")
+            .bind(user_id)
+            .bind(client_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete consent: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user() -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: Some("ada@example.com".to_string()),
+            email_verified: true,
+            phone: Some("+15550001111".to_string()),
+            phone_verified: false,
+            password_hash: None,
+            full_name: Some("Ada".to_string()),
+            avatar_url: None,
+            metadata: serde_json::json!({}),
+            is_anonymous: false,
+            is_active: true,
+            last_sign_in_at: None,
+            banned_until: None,
+            deleted_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_pkce_rfc7636_example() {
+        assert!(verify_pkce(
+            "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk",
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        ));
+        assert!(!verify_pkce("wrong-verifier", "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"));
+    }
+
+    #[test]
+    fn test_redirect_uris() {
+        assert!(validate_redirect_uri("https://app.example.com/callback").is_ok());
+        assert!(validate_redirect_uri("http://localhost:8080/cb").is_ok());
+        assert!(validate_redirect_uri("com.example.app:/oauth").is_ok());
+        assert!(validate_redirect_uri("http://app.example.com/cb").is_err());
+        assert!(validate_redirect_uri("https://app.example.com/cb#frag").is_err());
+        assert!(validate_redirect_uri("/relative").is_err());
+
+        let url = redirect_with("https://app.example.com/cb?x=1", &[("code", "abc"), ("state", "a b")]).unwrap();
+        assert_eq!(url, "https://app.example.com/cb?x=1&code=abc&state=a+b");
+    }
+
+    #[test]
+    fn test_client_credentials_prefer_basic_auth() {
+        let mut headers = HeaderMap::new();
+        let encoded = general_purpose::STANDARD.encode("my%20client:s3cr%3At");
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Basic {}", encoded)).unwrap(),
+        );
+
+        let (id, secret) = client_credentials(&headers, Some("form".to_string()), None);
+        assert_eq!(id.as_deref(), Some("my client"));
+        assert_eq!(secret.as_deref(), Some("s3cr:t"));
+
+        let (id, secret) = client_credentials(&HeaderMap::new(), Some("form".to_string()), None);
+        assert_eq!(id.as_deref(), Some("form"));
+        assert_eq!(secret, None);
+    }
+
+    #[test]
+    fn test_user_info_follows_scopes() {
+        let user = user();
+        let info = user_info(&user, &parse_scope("openid openid"));
+        assert_eq!(info.sub, user.id.to_string());
+        assert_eq!(info.email, None);
+        assert_eq!(info.name, None);
+
+        let info = user_info(&user, &parse_scope("openid email profile phone"));
+        assert_eq!(info.email.as_deref(), Some("ada@example.com"));
+        assert_eq!(info.email_verified, Some(true));
+        assert_eq!(info.name.as_deref(), Some("Ada"));
+        assert_eq!(info.phone_number_verified, Some(false));
+    }
+}
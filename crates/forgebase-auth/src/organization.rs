@@ -0,0 +1,476 @@
+// Organizations, their members and invitations
+use crate::models::{
+    Organization, OrganizationInvitation, OrganizationMember, OrganizationMembership,
+};
+use forgebase_core::{ForgeBaseError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// How long an invitation can be accepted
+pub const INVITATION_TTL_DAYS: i64 = 7;
+
+const MAX_SLUG_LENGTH: usize = 63;
+
+/// Role of a member within an organization, ordered from least to most privileged
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum OrganizationRole {
+    Viewer,    // Read-only access to the organization's resources
+    Developer, // Deploys sites and functions, manages buckets
+    Admin,     // Also manages members, invitations and settings
+    Owner,     // Also deletes the organization and appoints owners
+}
+
+impl OrganizationRole {
+    /// Whether this role may invite, remove and re-role members
+    pub fn can_manage_members(self) -> bool {
+        self >= Self::Admin
+    }
+
+    /// Whether this role may create and change the organization's resources
+    pub fn can_deploy(self) -> bool {
+        self >= Self::Developer
+    }
+
+    /// Whether a member with this role may hand out (or take away) `role`
+    pub fn can_grant(self, role: OrganizationRole) -> bool {
+        self.can_manage_members() && role <= self
+    }
+}
+
+/// URL-safe slug derived from an organization name, e.g. "Acme, Inc." -> "acme-inc"
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(MAX_SLUG_LENGTH);
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Validate a user-chosen slug: lowercase letters, digits and inner hyphens
+pub fn validate_slug(slug: &str) -> Result<()> {
+    let valid = !slug.is_empty()
+        && slug.len() <= MAX_SLUG_LENGTH
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ForgeBaseError::Validation(format!(
+            "Invalid organization slug: {} (use lowercase letters, digits and hyphens)",
+            slug
+        )))
+    }
+}
+
+/// Generate an invitation token; only its hash is stored
+pub fn generate_invitation_token() -> String {
+    use rand::Rng;
+
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect()
+}
+
+/// Hash an invitation token for storage and lookup
+pub fn hash_invitation_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Organization repository
+pub struct OrganizationRepository {
+    pool: PgPool,
+}
+
+impl OrganizationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create an organization with `owner_id` as its first owner
+    pub async fn create(&self, organization: &Organization, owner_id: Uuid) -> Result<Organization> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        let organization = sqlx::query_as::<_, Organization>(
+            r#"
+            INSERT INTO organizations (id, name, slug, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(organization.id)
+        .bind(&organization.name)
+        .bind(&organization.slug)
+        .bind(organization.created_by)
+        .bind(organization.created_at)
+        .bind(organization.updated_at)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create organization: {}", e)))?;
+
+        sqlx::query(
+            "INSERT INTO organization_members (organization_id, user_id, role, created_at, updated_at) VALUES ($1, $2, $3, NOW(), NOW())",
+        )
+        .bind(organization.id)
+        .bind(owner_id)
+        .bind(OrganizationRole::Owner)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to add organization owner: {}", e)))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(organization)
+    }
+
+    /// Find an organization by ID
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Organization>> {
+        let organization = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find organization: {}", e)))?;
+
+        Ok(organization)
+    }
+
+    /// Find an organization by slug
+    pub async fn find_by_slug(&self, slug: &str) -> Result<Option<Organization>> {
+        let organization = sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = $1")
+            .bind(slug)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find organization: {}", e)))?;
+
+        Ok(organization)
+    }
+
+    /// Organizations a user belongs to, with their role in each
+    pub async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<OrganizationMembership>> {
+        let memberships = sqlx::query_as::<_, OrganizationMembership>(
+            r#"
+            SELECT o.*, m.role FROM organizations o
+            JOIN organization_members m ON m.organization_id = o.id
+            WHERE m.user_id = $1
+            ORDER BY o.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list organizations: {}", e)))?;
+
+        Ok(memberships)
+    }
+
+    /// Update an organization's name and slug
+    pub async fn update(&self, organization: &Organization) -> Result<Organization> {
+        let organization = sqlx::query_as::<_, Organization>(
+            "UPDATE organizations SET name = $2, slug = $3, updated_at = NOW() WHERE id = $1 RETURNING *",
+        )
+        .bind(organization.id)
+        .bind(&organization.name)
+        .bind(&organization.slug)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to update organization: {}", e)))?;
+
+        Ok(organization)
+    }
+
+    /// Delete an organization along with everything it owns
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM organizations WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete organization: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// A user's role in an organization, if they are a member
+    pub async fn member_role(&self, organization_id: Uuid, user_id: Uuid) -> Result<Option<OrganizationRole>> {
+        let role = sqlx::query_scalar(
+            "SELECT role FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to find organization member: {}", e)))?;
+
+        Ok(role)
+    }
+
+    /// Members of an organization, most privileged first
+    pub async fn list_members(&self, organization_id: Uuid) -> Result<Vec<OrganizationMember>> {
+        let members = sqlx::query_as::<_, OrganizationMember>(
+            r#"
+            SELECT m.organization_id, m.user_id, m.role, u.email, u.full_name, m.created_at, m.updated_at
+            FROM organization_members m
+            JOIN users u ON u.id = m.user_id
+            WHERE m.organization_id = $1
+            ORDER BY CASE m.role WHEN 'owner' THEN 0 WHEN 'admin' THEN 1 WHEN 'developer' THEN 2 ELSE 3 END, m.created_at
+            "#,
+        )
+        .bind(organization_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list organization members: {}", e)))?;
+
+        Ok(members)
+    }
+
+    /// Add a member
+    pub async fn add_member(&self, organization_id: Uuid, user_id: Uuid, role: OrganizationRole) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO organization_members (organization_id, user_id, role, created_at, updated_at) VALUES ($1, $2, $3, NOW(), NOW())",
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to add organization member: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Change a member's role; returns false if they are not a member
+    pub async fn set_member_role(&self, organization_id: Uuid, user_id: Uuid, role: OrganizationRole) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE organization_members SET role = $3, updated_at = NOW() WHERE organization_id = $1 AND user_id = $2",
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to update organization member: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Remove a member; returns false if they were not a member
+    pub async fn remove_member(&self, organization_id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM organization_members WHERE organization_id = $1 AND user_id = $2")
+            .bind(organization_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to remove organization member: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Number of owners of an organization
+    pub async fn count_owners(&self, organization_id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM organization_members WHERE organization_id = $1 AND role = 'owner'",
+        )
+        .bind(organization_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to count organization owners: {}", e)))?;
+
+        Ok(count)
+    }
+
+    /// Organizations the user is the only owner of, with their total member count
+    pub async fn solely_owned_by(&self, user_id: Uuid) -> Result<Vec<(Organization, i64)>> {
+        let rows: Vec<(Uuid, i64)> = sqlx::query_as(
+            r#"
+            SELECT m.organization_id,
+                   (SELECT COUNT(*) FROM organization_members a WHERE a.organization_id = m.organization_id)
+            FROM organization_members m
+            WHERE m.user_id = $1 AND m.role = 'owner'
+              AND NOT EXISTS (
+                  SELECT 1 FROM organization_members o
+                  WHERE o.organization_id = m.organization_id AND o.role = 'owner' AND o.user_id <> $1
+              )
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list owned organizations: {}", e)))?;
+
+        let mut organizations = Vec::with_capacity(rows.len());
+        for (id, members) in rows {
+            if let Some(organization) = self.find_by_id(id).await? {
+                organizations.push((organization, members));
+            }
+        }
+        Ok(organizations)
+    }
+
+    /// Make `new_owner` an owner and demote `previous_owner` to admin, atomically
+    pub async fn transfer_ownership(&self, organization_id: Uuid, previous_owner: Uuid, new_owner: Uuid) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        for (user_id, role) in [
+            (new_owner, OrganizationRole::Owner),
+            (previous_owner, OrganizationRole::Admin),
+        ] {
+            sqlx::query(
+                "UPDATE organization_members SET role = $3, updated_at = NOW() WHERE organization_id = $1 AND user_id = $2",
+            )
+            .bind(organization_id)
+            .bind(user_id)
+            .bind(role)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to transfer ownership: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to commit transaction: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Create an invitation
+    pub async fn create_invitation(&self, invitation: &OrganizationInvitation) -> Result<OrganizationInvitation> {
+        let invitation = sqlx::query_as::<_, OrganizationInvitation>(
+            r#"
+            INSERT INTO organization_invitations (
+                id, organization_id, email, role, token_hash, invited_by, expires_at, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(invitation.id)
+        .bind(invitation.organization_id)
+        .bind(&invitation.email)
+        .bind(invitation.role)
+        .bind(&invitation.token_hash)
+        .bind(invitation.invited_by)
+        .bind(invitation.expires_at)
+        .bind(invitation.created_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create invitation: {}", e)))?;
+
+        Ok(invitation)
+    }
+
+    /// Invitations of an organization that have not been accepted yet
+    pub async fn list_pending_invitations(&self, organization_id: Uuid) -> Result<Vec<OrganizationInvitation>> {
+        let invitations = sqlx::query_as::<_, OrganizationInvitation>(
+            "SELECT * FROM organization_invitations WHERE organization_id = $1 AND accepted_at IS NULL ORDER BY created_at DESC",
+        )
+        .bind(organization_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list invitations: {}", e)))?;
+
+        Ok(invitations)
+    }
+
+    /// Find an unexpired, unaccepted invitation by token hash
+    pub async fn find_valid_invitation(&self, token_hash: &str) -> Result<Option<OrganizationInvitation>> {
+        let invitation = sqlx::query_as::<_, OrganizationInvitation>(
+            "SELECT * FROM organization_invitations WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > NOW()",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to find invitation: {}", e)))?;
+
+        Ok(invitation)
+    }
+
+    /// Mark an invitation accepted so it cannot be used again
+    pub async fn mark_invitation_accepted(&self, id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE organization_invitations SET accepted_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to accept invitation: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Delete an invitation; returns false if it does not exist
+    pub async fn delete_invitation(&self, organization_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM organization_invitations WHERE id = $1 AND organization_id = $2")
+            .bind(id)
+            .bind(organization_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete invitation: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_ordering_and_permissions() {
+        assert!(OrganizationRole::Owner > OrganizationRole::Admin);
+        assert!(OrganizationRole::Developer > OrganizationRole::Viewer);
+
+        assert!(OrganizationRole::Admin.can_manage_members());
+        assert!(!OrganizationRole::Developer.can_manage_members());
+        assert!(OrganizationRole::Developer.can_deploy());
+        assert!(!OrganizationRole::Viewer.can_deploy());
+
+        assert!(OrganizationRole::Admin.can_grant(OrganizationRole::Admin));
+        assert!(!OrganizationRole::Admin.can_grant(OrganizationRole::Owner));
+        assert!(OrganizationRole::Owner.can_grant(OrganizationRole::Owner));
+        assert!(!OrganizationRole::Developer.can_grant(OrganizationRole::Viewer));
+
+        let role: OrganizationRole = serde_json::from_str("\"developer\"").unwrap();
+        assert_eq!(role, OrganizationRole::Developer);
+    }
+
+    #[test]
+    fn test_slugs() {
+        assert_eq!(slugify("  Acme, Inc.  "), "acme-inc");
+        assert_eq!(slugify("Ünïcode -- Team 42"), "n-code-team-42");
+        assert!(validate_slug("acme-inc").is_ok());
+        assert!(validate_slug("-acme").is_err());
+        assert!(validate_slug("Acme").is_err());
+        assert!(validate_slug("").is_err());
+    }
+
+    #[test]
+    fn test_invitation_tokens_are_hashed() {
+        let token = generate_invitation_token();
+        assert_eq!(token.len(), 40);
+        assert_eq!(hash_invitation_token(&token).len(), 64);
+        assert_ne!(hash_invitation_token(&token), token);
+    }
+}
@@ -1,8 +1,11 @@
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    Algorithm, Argon2, Params, Version,
 };
-use forgebase_core::ForgeBaseError;
+use forgebase_core::{ForgeBaseError, PasswordPolicyConfig};
+use sha1::{Digest, Sha1};
+use std::path::Path;
+use std::sync::OnceLock;
 
 /// Hash a password using Argon2id
 pub fn hash_password(password: &str) -> Result<String, ForgeBaseError> {
@@ -25,25 +28,309 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, ForgeBaseErro
         .is_ok())
 }
 
-/// Validate password strength
+/// Spend the same time as a real verification when there is no hash to check against,
+/// so response timing does not reveal whether an account exists
+pub fn verify_dummy_password(password: &str) {
+    static DUMMY_HASH: OnceLock<Option<String>> = OnceLock::new();
+
+    if let Some(hash) = DUMMY_HASH.get_or_init(|| hash_password("forgebase-dummy-password").ok()) {
+        let _ = verify_password(password, hash);
+    }
+}
+
+/// Validate password strength against the default policy (no breach or history checks)
 pub fn validate_password_strength(password: &str) -> Result<(), ForgeBaseError> {
-    if password.len() < 8 {
-        return Err(ForgeBaseError::Validation(
-            "Password must be at least 8 characters long".to_string(),
-        ));
+    PasswordPolicy::default().check(password, None)
+}
+
+/// Password rules and hashing parameters from [`PasswordPolicyConfig`]
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    config: PasswordPolicyConfig,
+    argon2_params: Params,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            config: PasswordPolicyConfig::default(),
+            argon2_params: Params::default(),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    pub fn from_config(min_length: usize, config: &PasswordPolicyConfig) -> Result<Self, ForgeBaseError> {
+        let argon2_params = Params::new(
+            config.argon2_memory_kib,
+            config.argon2_iterations,
+            config.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| ForgeBaseError::Config(format!("Invalid Argon2 parameters: {}", e)))?;
+
+        Ok(Self {
+            min_length,
+            config: config.clone(),
+            argon2_params,
+        })
+    }
+
+    /// Previous passwords that may not be reused
+    pub fn history_size(&self) -> usize {
+        self.config.history_size
+    }
+
+    /// Check length, character classes, strength score and email reuse
+    pub fn check(&self, password: &str, email: Option<&str>) -> Result<(), ForgeBaseError> {
+        let invalid = |message: String| Err(ForgeBaseError::Validation(message));
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            return invalid(format!(
+                "Password must be at least {} characters long",
+                self.min_length
+            ));
+        }
+        if length > self.config.max_length {
+            return invalid(format!(
+                "Password must be at most {} characters long",
+                self.config.max_length
+            ));
+        }
+
+        let classes = [
+            (self.config.require_uppercase, password.chars().any(char::is_uppercase), "an uppercase letter"),
+            (self.config.require_lowercase, password.chars().any(char::is_lowercase), "a lowercase letter"),
+            (self.config.require_digit, password.chars().any(|c| c.is_ascii_digit()), "a digit"),
+            (self.config.require_symbol, password.chars().any(|c| !c.is_alphanumeric()), "a symbol"),
+        ];
+        if let Some((_, _, class)) = classes.iter().find(|(required, present, _)| *required && !present) {
+            return invalid(format!("Password must contain {}", class));
+        }
+
+        if self.config.reject_email && email.is_some_and(|email| contains_email(password, email)) {
+            return invalid("Password must not contain your email address".to_string());
+        }
+
+        if password_score(password) < self.config.min_score {
+            return invalid("Password is too easy to guess".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// [`PasswordPolicy::check`] plus the breached password list
+    pub async fn validate(&self, password: &str, email: Option<&str>) -> Result<(), ForgeBaseError> {
+        self.check(password, email)?;
+
+        if self.breach_count(password).await? >= self.config.breach_threshold.max(1) {
+            return Err(ForgeBaseError::Validation(
+                "Password has appeared in a data breach, please choose another".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Times the password appears in the local breach list (0 when none is configured)
+    pub async fn breach_count(&self, password: &str) -> Result<u64, ForgeBaseError> {
+        match &self.config.breached_passwords_dir {
+            Some(dir) => breach_count(Path::new(dir), password).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Hash with the configured Argon2id parameters
+    pub fn hash(&self, password: &str) -> Result<String, ForgeBaseError> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| ForgeBaseError::Internal(format!("Failed to hash password: {}", e)))
+    }
+
+    /// Whether a stored hash was made with other parameters and should be replaced
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return false;
+        };
+        if parsed.algorithm != Algorithm::Argon2id.ident() {
+            return true;
+        }
+
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                params.m_cost() != self.argon2_params.m_cost()
+                    || params.t_cost() != self.argon2_params.t_cost()
+                    || params.p_cost() != self.argon2_params.p_cost()
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.argon2_params.clone())
+    }
+}
+
+fn contains_email(password: &str, email: &str) -> bool {
+    let password = password.to_lowercase();
+    let email = email.trim().to_lowercase();
+    let local = email
+        .split('@')
+        .next()
+        .unwrap_or_default()
+        .split('+')
+        .next()
+        .unwrap_or_default();
+
+    password.contains(&email) || (local.chars().count() >= 3 && password.contains(local))
+}
+
+/// Look up a password in HIBP range files by the first five characters of its SHA-1 hash,
+/// so only that bucket is read (the same k-anonymity split the online API uses)
+async fn breach_count(dir: &Path, password: &str) -> Result<u64, ForgeBaseError> {
+    let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+    let (prefix, suffix) = hash.split_at(5);
+
+    let path = dir.join(format!("{}.txt", prefix));
+    let contents = match tokio::fs::read_to_string(&path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            tracing::warn!("Breached password range file {} is missing", path.display());
+            return Ok(0);
+        }
+        Err(e) => {
+            return Err(ForgeBaseError::Internal(format!(
+                "Failed to read breached password list: {}",
+                e
+            )))
+        }
+    };
+
+    Ok(contents
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .unwrap_or(0))
+}
+
+/// Passwords (after lowercasing and undoing common substitutions) guessed first by attackers
+const COMMON_PASSWORDS: &[&str] = &[
+    "password", "123456", "12345678", "123456789", "1234567890", "qwerty", "qwertyuiop",
+    "abc123", "letmein", "welcome", "admin", "iloveyou", "monkey", "dragon", "football",
+    "baseball", "master", "sunshine", "princess", "shadow", "superman", "trustnoi", "starwars",
+    "whatever", "changeme", "passwordi", "asdfghjkl", "iiiiii", "oooooo",
+];
+
+/// Words that make up most human-chosen passwords; each counts as one dictionary guess
+const COMMON_WORDS: &[&str] = &[
+    "password", "pass", "word", "test", "admin", "user", "login", "welcome", "hello", "love",
+    "secret", "qwerty", "letmein", "monkey", "dragon", "master", "summer", "winter", "spring",
+    "autumn", "football", "baseball", "soccer", "sunshine", "princess", "shadow", "iloveyou",
+    "forgebase", "default", "changeme", "company", "money", "freedom", "computer", "michael",
+    "jordan", "charlie", "george", "hunter", "killer", "flower", "orange", "purple", "cookie",
+    "chocolate", "banana", "apple", "secure", "access", "superman", "batman", "starwars",
+];
+
+const KEYBOARD_ROWS: &[&str] = &["qwertyuiop", "asdfghjkl", "zxcvbnm", "1234567890"];
+
+/// Bits credited for a dictionary word (roughly a 1,000-word guessing list)
+const WORD_BITS: f64 = 10.0;
+
+/// Undo the substitutions people use to "strengthen" dictionary words
+fn unleet(c: char) -> char {
+    match c {
+        '@' | '4' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' => 't',
+        _ => c,
     }
+}
+
+fn is_keyboard_neighbour(a: char, b: char) -> bool {
+    KEYBOARD_ROWS.iter().any(|row| match (row.find(a), row.find(b)) {
+        (Some(i), Some(j)) => i.abs_diff(j) == 1,
+        _ => false,
+    })
+}
 
-    let has_uppercase = password.chars().any(|c| c.is_uppercase());
-    let has_lowercase = password.chars().any(|c| c.is_lowercase());
-    let has_digit = password.chars().any(|c| c.is_ascii_digit());
+/// Strength score from 0 (guessable in under a thousand tries) to 4 (over ten billion).
+///
+/// A zxcvbn-style estimate: common passwords score 0, dictionary words (also with leet
+/// substitutions) count as one guess from a word list, and repeats, sequences and keyboard
+/// walks add almost nothing; the rest is brute force over the character classes present.
+pub fn password_score(password: &str) -> u8 {
+    let lower: Vec<char> = password.to_lowercase().chars().collect();
+    let words: String = lower.iter().map(|&c| unleet(c)).collect();
 
-    if !has_uppercase || !has_lowercase || !has_digit {
-        return Err(ForgeBaseError::Validation(
-            "Password must contain uppercase, lowercase, and digit characters".to_string(),
-        ));
+    // Also with the digits and symbols people tack on the end
+    let suffix = lower.iter().rev().take_while(|c| !c.is_alphabetic()).count();
+    let stripped: String = words.chars().take(lower.len() - suffix).collect();
+    if COMMON_PASSWORDS.contains(&words.as_str()) || COMMON_PASSWORDS.contains(&stripped.as_str()) {
+        return 0;
     }
 
-    Ok(())
+    // Cover dictionary words, longest first
+    let word_chars: Vec<char> = words.chars().collect();
+    let mut covered = vec![false; word_chars.len()];
+    let mut bits = 0.0;
+    let mut dictionary = COMMON_WORDS.to_vec();
+    dictionary.sort_by_key(|word| std::cmp::Reverse(word.len()));
+    for word in dictionary {
+        let word: Vec<char> = word.chars().collect();
+        let mut start = 0;
+        while start + word.len() <= word_chars.len() {
+            let end = start + word.len();
+            if word_chars[start..end] == word[..] && !covered[start..end].iter().any(|&c| c) {
+                covered[start..end].iter_mut().for_each(|c| *c = true);
+                bits += WORD_BITS;
+                start = end;
+            } else {
+                start += 1;
+            }
+        }
+    }
+
+    let pool: f64 = [
+        (password.chars().any(|c| c.is_ascii_lowercase()), 26.0),
+        (password.chars().any(|c| c.is_ascii_uppercase()), 26.0),
+        (password.chars().any(|c| c.is_ascii_digit()), 10.0),
+        (password.chars().any(|c| c.is_ascii_punctuation() || c == ' '), 33.0),
+        (!password.is_ascii(), 100.0),
+    ]
+    .iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, size)| size)
+    .sum();
+    let char_bits = pool.max(2.0).log2();
+
+    for (i, &c) in lower.iter().enumerate() {
+        if covered[i] {
+            continue;
+        }
+        let predictable = i > 0 && {
+            let prev = lower[i - 1];
+            prev == c || (c as i64 - prev as i64).abs() == 1 || is_keyboard_neighbour(prev, c)
+        };
+        bits += if predictable { 1.0 } else { char_bits };
+    }
+
+    match bits {
+        b if b < 10.0 => 0,
+        b if b < 20.0 => 1,
+        b if b < 27.0 => 2,
+        b if b < 34.0 => 3,
+        _ => 4,
+    }
 }
 
 #[cfg(test)]
@@ -66,4 +353,83 @@ mod tests {
         assert!(validate_password_strength("NOLOWERCASE123").is_err());
         assert!(validate_password_strength("NoDigitsHere").is_err());
     }
+
+    #[test]
+    fn test_password_score() {
+        assert_eq!(password_score("password"), 0);
+        assert_eq!(password_score("P@ssw0rd1!"), 0);
+        assert!(password_score("aaaaaaaaaaaa") <= 1);
+        assert!(password_score("qwertyuiop12") <= 1);
+        assert!(password_score("TestPass123") >= 2);
+        assert_eq!(password_score("vT8#qL2m!xR9wZ"), 4);
+    }
+
+    #[test]
+    fn test_policy_rejects_email_and_honours_config() {
+        let policy = PasswordPolicy::from_config(
+            12,
+            &PasswordPolicyConfig {
+                require_symbol: true,
+                ..PasswordPolicyConfig::default()
+            },
+        )
+        .unwrap();
+
+        assert!(policy.check("Xk9mQ2vL7wRt", None).is_err()); // no symbol
+        assert!(policy.check("Xk9#Q2vL7", None).is_err()); // too short
+        assert!(policy.check("Xk9#mQ2vL7wRt", None).is_ok());
+        assert!(policy
+            .check("Alice.Smith#42x", Some("alice.smith+work@example.com"))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_breach_count_reads_range_file() {
+        let dir = std::env::temp_dir().join(format!("forgebase-hibp-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        // Write the password's range file with a decoy entry before it
+        let hash = format!("{:X}", Sha1::digest(b"Tr0ub4dor&3"));
+        let (prefix, suffix) = hash.split_at(5);
+        tokio::fs::write(
+            dir.join(format!("{}.txt", prefix)),
+            format!("0000000000000000000000000000000000A:3\r\n{}:42\r\n", suffix),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(breach_count(&dir, "Tr0ub4dor&3").await.unwrap(), 42);
+        assert_eq!(breach_count(&dir, "correct horse battery staple").await.unwrap(), 0);
+
+        let policy = PasswordPolicy::from_config(
+            8,
+            &PasswordPolicyConfig {
+                breached_passwords_dir: Some(dir.to_string_lossy().into_owned()),
+                min_score: 0,
+                ..PasswordPolicyConfig::default()
+            },
+        )
+        .unwrap();
+        assert!(policy.validate("Tr0ub4dor&3", None).await.is_err());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_needs_rehash_when_params_change() {
+        let weak = PasswordPolicy::from_config(
+            8,
+            &PasswordPolicyConfig {
+                argon2_memory_kib: 8192,
+                argon2_iterations: 1,
+                ..PasswordPolicyConfig::default()
+            },
+        )
+        .unwrap();
+        let hash = weak.hash("TestPassword123").unwrap();
+
+        assert!(!weak.needs_rehash(&hash));
+        assert!(PasswordPolicy::default().needs_rehash(&hash));
+        assert!(!PasswordPolicy::default().needs_rehash(&hash_password("TestPassword123").unwrap()));
+        assert!(verify_password("TestPassword123", &hash).unwrap());
+    }
 }
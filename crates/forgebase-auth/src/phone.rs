@@ -0,0 +1,220 @@
+// Phone numbers and SMS one-time codes
+use crate::mfa::MfaManager;
+use crate::sms::SmsProvider;
+use chrono::{DateTime, Duration, Utc};
+use forgebase_core::{ForgeBaseError, Result, SmsConfig};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Code for signing in (or up) with a phone number
+pub const OTP_PURPOSE_SIGN_IN: &str = "sign_in";
+/// Code confirming a signed-in user's new phone number
+pub const OTP_PURPOSE_PHONE_CHANGE: &str = "phone_change";
+/// Code for an SMS second factor
+pub const OTP_PURPOSE_MFA: &str = "mfa";
+
+/// Normalize a phone number to E.164 (`+<country code><number>`).
+///
+/// Spaces and punctuation are ignored, `00` is accepted as the international prefix, and
+/// numbers without a country code get `default_country_code` (dropping a trunk `0`).
+pub fn normalize_phone(input: &str, default_country_code: Option<&str>) -> Result<String> {
+    let trimmed = input.trim();
+    let invalid = || ForgeBaseError::Validation(format!("Invalid phone number: {}", input));
+
+    if trimmed
+        .chars()
+        .any(|c| !(c.is_ascii_digit() || matches!(c, '+' | ' ' | '-' | '.' | '(' | ')')))
+        || trimmed.rfind('+').is_some_and(|i| i > 0)
+    {
+        return Err(invalid());
+    }
+
+    let digits: String = trimmed.chars().filter(char::is_ascii_digit).collect();
+    let international = if trimmed.starts_with('+') {
+        digits
+    } else if let Some(rest) = digits.strip_prefix("00") {
+        rest.to_string()
+    } else {
+        let country_code = default_country_code
+            .map(|code| code.trim_start_matches('+'))
+            .ok_or_else(|| {
+                ForgeBaseError::Validation(
+                    "Phone number must include a country code, e.g. +14155550123".to_string(),
+                )
+            })?;
+        format!("{}{}", country_code, digits.trim_start_matches('0'))
+    };
+
+    // E.164: up to 15 digits, country codes never start with 0
+    if !(8..=15).contains(&international.len()) || international.starts_with('0') {
+        return Err(invalid());
+    }
+
+    Ok(format!("+{}", international))
+}
+
+fn hash_code(phone: &str, code: &str) -> String {
+    format!("{:x}", Sha256::digest(format!("{}:{}", phone, code).as_bytes()))
+}
+
+/// Sends SMS codes and verifies them, with per-number send limits and expiry
+pub struct PhoneAuth {
+    pool: PgPool,
+    provider: Arc<dyn SmsProvider>,
+    config: SmsConfig,
+}
+
+impl PhoneAuth {
+    pub fn new(pool: PgPool, config: SmsConfig, provider: Arc<dyn SmsProvider>) -> Self {
+        Self {
+            pool,
+            provider,
+            config,
+        }
+    }
+
+    /// Normalize a number using the configured default country code
+    pub fn normalize(&self, phone: &str) -> Result<String> {
+        normalize_phone(phone, self.config.default_country_code.as_deref())
+    }
+
+    /// Send a new code to an E.164 number
+    pub async fn send_code(&self, phone: &str, purpose: &str, user_id: Option<Uuid>) -> Result<()> {
+        let (sent_last_hour, last_sent): (i64, Option<DateTime<Utc>>) = sqlx::query_as(
+            "SELECT COUNT(*), MAX(created_at) FROM phone_otps WHERE phone = $1 AND created_at > $2",
+        )
+        .bind(phone)
+        .bind(Utc::now() - Duration::hours(1))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to count codes: {}", e)))?;
+
+        let too_soon = last_sent
+            .is_some_and(|at| at > Utc::now() - Duration::seconds(self.config.resend_interval_seconds));
+        if sent_last_hour >= i64::from(self.config.max_sends_per_hour) || too_soon {
+            return Err(ForgeBaseError::RateLimit);
+        }
+
+        let code = MfaManager::new().generate_sms_code();
+        sqlx::query(
+            r#"
+            INSERT INTO phone_otps (id, phone, purpose, user_id, code_hash, attempts, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, 0, $6, NOW())
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(phone)
+        .bind(purpose)
+        .bind(user_id)
+        .bind(hash_code(phone, &code))
+        .bind(Utc::now() + Duration::seconds(self.config.otp_expiry_seconds))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to store code: {}", e)))?;
+
+        self.provider
+            .send(
+                phone,
+                &format!("Your ForgeBase code is {}. It expires in {} minutes.", code, self.config.otp_expiry_seconds / 60),
+            )
+            .await
+    }
+
+    /// Check the latest unexpired code for the number, purpose and user.
+    ///
+    /// Each guess counts against the code; it is discarded after too many wrong guesses.
+    pub async fn verify_code(
+        &self,
+        phone: &str,
+        purpose: &str,
+        user_id: Option<Uuid>,
+        code: &str,
+    ) -> Result<()> {
+        let invalid = || ForgeBaseError::Auth("Invalid or expired code".to_string());
+
+        let otp: Option<(Uuid,)> = sqlx::query_as(
+            r#"
+            SELECT id FROM phone_otps
+            WHERE phone = $1 AND purpose = $2 AND user_id IS NOT DISTINCT FROM $3
+              AND consumed_at IS NULL AND expires_at > NOW()
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(phone)
+        .bind(purpose)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to find code: {}", e)))?;
+        let (id,) = otp.ok_or_else(invalid)?;
+
+        // Count the guess before comparing so concurrent guesses cannot exceed the limit
+        let code_hash: Option<String> = sqlx::query_scalar(
+            "UPDATE phone_otps SET attempts = attempts + 1 WHERE id = $1 AND attempts < $2 RETURNING code_hash",
+        )
+        .bind(id)
+        .bind(self.config.max_verify_attempts)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to check code: {}", e)))?;
+        let code_hash = code_hash.ok_or_else(invalid)?;
+
+        if !constant_time_eq::constant_time_eq(
+            code_hash.as_bytes(),
+            hash_code(phone, code.trim()).as_bytes(),
+        ) {
+            return Err(invalid());
+        }
+
+        // Older codes for the same purpose die with this one
+        sqlx::query(
+            "UPDATE phone_otps SET consumed_at = NOW() WHERE phone = $1 AND purpose = $2 AND consumed_at IS NULL",
+        )
+        .bind(phone)
+        .bind(purpose)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to consume code: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Remove codes older than the send-limit window
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM phone_otps WHERE created_at < $1")
+            .bind(Utc::now() - Duration::hours(1))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete codes: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(normalize_phone("+1 (415) 555-0123", None).unwrap(), "+14155550123");
+        assert_eq!(normalize_phone("0044 7700 900123", None).unwrap(), "+447700900123");
+        assert_eq!(normalize_phone("07700 900123", Some("44")).unwrap(), "+447700900123");
+        assert_eq!(normalize_phone("415.555.0123", Some("+1")).unwrap(), "+14155550123");
+
+        assert!(normalize_phone("4155550123", None).is_err());
+        assert!(normalize_phone("+1 415 555 0123 ext 4", None).is_err());
+        assert!(normalize_phone("1+4155550123", None).is_err());
+        assert!(normalize_phone("+1234", None).is_err());
+        assert!(normalize_phone("+1234567890123456", None).is_err());
+    }
+
+    #[test]
+    fn test_hash_code_is_bound_to_phone() {
+        assert_eq!(hash_code("+14155550123", "123456"), hash_code("+14155550123", "123456"));
+        assert_ne!(hash_code("+14155550123", "123456"), hash_code("+14155550124", "123456"));
+    }
+}
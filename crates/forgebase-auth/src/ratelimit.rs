@@ -0,0 +1,517 @@
+// Request rate limiting: per route group, keyed by IP, user and API key
+use crate::api_key::{hash_api_key, is_api_key};
+use crate::client::TrustedProxies;
+use crate::jwt::Claims;
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use forgebase_core::{
+    ErrorResponse, ForgeBaseError, RateLimitConfig, RateLimitQuota, RateLimitRule,
+    RateLimitStoreKind, Result,
+};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Outcome of counting one request against a quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the quota is fully available again
+    pub reset_seconds: u64,
+    /// Seconds until a denied request may be retried
+    pub retry_after_seconds: u64,
+}
+
+/// Counter storage shared by all rules
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Count one request for `key` and decide whether it fits in `quota`
+    async fn hit(&self, key: &str, quota: RateLimitQuota) -> Result<RateLimitDecision>;
+
+    /// Drop counters that can no longer affect a decision, run periodically by the limiter
+    async fn delete_expired(&self, _max_window_seconds: u64) -> Result<u64> {
+        Ok(0)
+    }
+}
+
+/// Per-instance token bucket store
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// When the bucket is full again under its own quota, and no different from a new one
+    full_at: Instant,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn hit_at(&self, key: &str, quota: RateLimitQuota, now: Instant) -> RateLimitDecision {
+        let capacity = f64::from(quota.requests);
+        let refill_per_second = capacity / quota.window_seconds.max(1) as f64;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+            full_at: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_second).min(capacity);
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        bucket.full_at =
+            now + Duration::from_secs_f64((capacity - bucket.tokens) / refill_per_second);
+
+        RateLimitDecision {
+            allowed,
+            limit: quota.requests,
+            remaining: bucket.tokens.floor() as u32,
+            reset_seconds: ((capacity - bucket.tokens) / refill_per_second).ceil() as u64,
+            retry_after_seconds: if allowed {
+                0
+            } else {
+                ((1.0 - bucket.tokens) / refill_per_second).ceil() as u64
+            },
+        }
+    }
+
+    /// Drop buckets that have fully refilled by `now`
+    fn delete_expired_at(&self, now: Instant) -> u64 {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let before = buckets.len();
+        buckets.retain(|_, bucket| bucket.full_at > now);
+        (before - buckets.len()) as u64
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn hit(&self, key: &str, quota: RateLimitQuota) -> Result<RateLimitDecision> {
+        Ok(self.hit_at(key, quota, Instant::now()))
+    }
+
+    async fn delete_expired(&self, _max_window_seconds: u64) -> Result<u64> {
+        Ok(self.delete_expired_at(Instant::now()))
+    }
+}
+
+/// Postgres sliding-window store, shared by all instances
+pub struct PostgresRateLimitStore {
+    pool: PgPool,
+}
+
+impl PostgresRateLimitStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for PostgresRateLimitStore {
+    async fn hit(&self, key: &str, quota: RateLimitQuota) -> Result<RateLimitDecision> {
+        let window = quota.window_seconds.max(1) as i64;
+        let now = Utc::now();
+        let window_start = now.timestamp() - now.timestamp().rem_euclid(window);
+        let current_start = DateTime::from_timestamp(window_start, 0).unwrap_or(now);
+        let previous_start = DateTime::from_timestamp(window_start - window, 0).unwrap_or(now);
+
+        let (current, previous): (i32, i32) = sqlx::query_as(
+            r#"
+            WITH current AS (
+                INSERT INTO rate_limits (key, window_start, count)
+                VALUES ($1, $2, 1)
+                ON CONFLICT (key, window_start) DO UPDATE SET count = rate_limits.count + 1
+                RETURNING count
+            )
+            SELECT
+                (SELECT count FROM current),
+                COALESCE((SELECT count FROM rate_limits WHERE key = $1 AND window_start = $3), 0)
+            "#,
+        )
+        .bind(key)
+        .bind(current_start)
+        .bind(previous_start)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to update rate limit: {}", e)))?;
+
+        Ok(sliding_window_decision(
+            quota,
+            current as u32,
+            previous as u32,
+            (now.timestamp() - window_start) as u64,
+        ))
+    }
+
+    /// Drop windows too old to affect any decision
+    async fn delete_expired(&self, max_window_seconds: u64) -> Result<u64> {
+        let cutoff = Utc::now() - chrono::Duration::seconds(2 * max_window_seconds as i64);
+        let result = sqlx::query("DELETE FROM rate_limits WHERE window_start < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ForgeBaseError::Database(format!("Failed to delete expired rate limits: {}", e))
+            })?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Weight the previous window by how much of it still overlaps the sliding window
+fn sliding_window_decision(
+    quota: RateLimitQuota,
+    current: u32,
+    previous: u32,
+    elapsed_seconds: u64,
+) -> RateLimitDecision {
+    let window = quota.window_seconds.max(1);
+    let overlap = 1.0 - elapsed_seconds.min(window) as f64 / window as f64;
+    let estimate = (f64::from(previous) * overlap + f64::from(current)).ceil() as u32;
+    let allowed = estimate <= quota.requests;
+    let reset_seconds = window - elapsed_seconds.min(window);
+
+    RateLimitDecision {
+        allowed,
+        limit: quota.requests,
+        remaining: quota.requests.saturating_sub(estimate),
+        reset_seconds,
+        retry_after_seconds: if allowed { 0 } else { reset_seconds.max(1) },
+    }
+}
+
+/// Who a request counts against
+#[derive(Debug, Clone, Default)]
+pub struct ClientIdentity {
+    pub ip: Option<IpAddr>,
+    pub user_id: Option<String>,
+    /// Hash of the presented API key, so keys never sit in memory or the database
+    pub api_key: Option<String>,
+}
+
+impl ClientIdentity {
+    /// Identify a request from its peer address, auth claims (if authenticated) and API key
+    ///
+    /// A presented API key only gets its own budget once the auth middleware has resolved it;
+    /// unknown keys count against the IP like any anonymous request.
+    pub fn from_request(request: &Request) -> Self {
+        let ip = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let claims = request.extensions().get::<Claims>();
+
+        Self {
+            ip,
+            user_id: claims.map(|c| c.sub.clone()),
+            api_key: claims
+                .and(presented_api_key(request.headers()))
+                .map(hash_api_key),
+        }
+    }
+}
+
+fn presented_api_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    let header = headers.get("apikey").and_then(|h| h.to_str().ok());
+
+    bearer.or(header).filter(|key| is_api_key(key))
+}
+
+/// Applies configured rules to requests
+pub struct RateLimiter {
+    enabled: bool,
+    rules: Vec<RateLimitRule>,
+    store: Arc<dyn RateLimitStore>,
+    trusted_proxies: TrustedProxies,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig, store: Arc<dyn RateLimitStore>) -> Self {
+        let mut rules = config.rules.clone();
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.path_prefix.len()));
+
+        Self {
+            enabled: config.enabled,
+            rules,
+            store,
+            trusted_proxies: TrustedProxies::default(),
+        }
+    }
+
+    /// Count requests from these proxies against the forwarded client address
+    pub fn with_trusted_proxies(mut self, trusted_proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// Build a limiter with the store named in the config
+    pub fn from_config(config: &RateLimitConfig, pool: PgPool) -> Self {
+        let store: Arc<dyn RateLimitStore> = match config.store {
+            RateLimitStoreKind::Memory => Arc::new(MemoryRateLimitStore::new()),
+            RateLimitStoreKind::Postgres => Arc::new(PostgresRateLimitStore::new(pool)),
+        };
+        Self::new(config, store)
+    }
+
+    /// Longest window of any configured quota
+    fn max_window_seconds(&self) -> u64 {
+        self.rules
+            .iter()
+            .flat_map(|rule| [rule.per_ip, rule.per_user, rule.per_api_key])
+            .flatten()
+            .map(|quota| quota.window_seconds)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Periodically drop expired counters from the store, once per longest window
+    pub fn spawn_cleanup(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let max_window_seconds = self.max_window_seconds();
+        let interval = std::time::Duration::from_secs(max_window_seconds.max(60));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.store.delete_expired(max_window_seconds).await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("Deleted {} expired rate limit counters", deleted),
+                    Err(e) => tracing::error!("Rate limit cleanup failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Most specific rule for a path
+    pub fn rule_for(&self, path: &str) -> Option<&RateLimitRule> {
+        self.rules.iter().find(|rule| {
+            let prefix = rule.path_prefix.as_str();
+            path.starts_with(prefix)
+                && (prefix.ends_with('/')
+                    || path.len() == prefix.len()
+                    || path.as_bytes()[prefix.len()] == b'/')
+        })
+    }
+
+    /// Count a request against every quota that applies to it.
+    ///
+    /// Returns the denying decision if any quota is exhausted, otherwise the tightest one.
+    pub async fn check(
+        &self,
+        path: &str,
+        identity: &ClientIdentity,
+    ) -> Result<Option<RateLimitDecision>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let Some(rule) = self.rule_for(path) else {
+            return Ok(None);
+        };
+
+        let mut checks: Vec<(String, RateLimitQuota)> = Vec::new();
+        if let (Some(quota), Some(ip)) = (rule.per_ip, identity.ip) {
+            checks.push((format!("{}|ip|{}", rule.path_prefix, ip), quota));
+        }
+        match (&identity.api_key, &identity.user_id) {
+            (Some(api_key), _) => {
+                if let Some(quota) = rule.per_api_key {
+                    checks.push((format!("{}|key|{}", rule.path_prefix, api_key), quota));
+                }
+            }
+            (None, Some(user_id)) => {
+                if let Some(quota) = rule.per_user {
+                    checks.push((format!("{}|user|{}", rule.path_prefix, user_id), quota));
+                }
+            }
+            (None, None) => {}
+        }
+
+        let mut tightest: Option<RateLimitDecision> = None;
+        for (key, quota) in checks {
+            let decision = self.store.hit(&key, quota).await?;
+            if !decision.allowed {
+                return Ok(Some(decision));
+            }
+            if tightest.is_none_or(|t| decision.remaining < t.remaining) {
+                tightest = Some(decision);
+            }
+        }
+
+        Ok(tightest)
+    }
+}
+
+/// Rate limiting middleware.
+///
+/// Use with `axum::middleware::from_fn_with_state(limiter, rate_limit)`. Layer it inside
+/// `require_auth`/`optional_auth` for per-user limits, and serve with
+/// `into_make_service_with_connect_info::<SocketAddr>()` for per-IP limits.
+/// With the Postgres store, also run [`RateLimiter::spawn_cleanup`].
+pub async fn rate_limit(
+    axum::extract::State(limiter): axum::extract::State<Arc<RateLimiter>>,
+    request: Request,
+    next: axum::middleware::Next,
+) -> Response {
+    let mut identity = ClientIdentity::from_request(&request);
+    identity.ip = limiter
+        .trusted_proxies
+        .client_ip(identity.ip, request.headers());
+    let decision = match limiter.check(request.uri().path(), &identity).await {
+        Ok(decision) => decision,
+        Err(e) => {
+            // Fail open: a counter outage should not take the API down
+            tracing::warn!("Rate limit check failed: {}", e);
+            None
+        }
+    };
+
+    let Some(decision) = decision else {
+        return next.run(request).await;
+    };
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        let body = serde_json::to_string(&ErrorResponse::from_error(&ForgeBaseError::RateLimit))
+            .unwrap_or_else(|_| "{}".to_string());
+        (StatusCode::TOO_MANY_REQUESTS, body).into_response()
+    };
+
+    let headers = response.headers_mut();
+    headers.insert("RateLimit-Limit", HeaderValue::from(decision.limit));
+    headers.insert("RateLimit-Remaining", HeaderValue::from(decision.remaining));
+    headers.insert("RateLimit-Reset", HeaderValue::from(decision.reset_seconds));
+    if !decision.allowed {
+        headers.insert("Retry-After", HeaderValue::from(decision.retry_after_seconds));
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let store = MemoryRateLimitStore::new();
+        let quota = RateLimitQuota::new(2, 10);
+        let start = Instant::now();
+
+        assert!(store.hit_at("k", quota, start).allowed);
+        let second = store.hit_at("k", quota, start);
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+
+        let denied = store.hit_at("k", quota, start);
+        assert!(!denied.allowed);
+        assert_eq!(denied.retry_after_seconds, 5);
+
+        assert!(store.hit_at("k", quota, start + Duration::from_secs(5)).allowed);
+        assert!(store.hit_at("other", quota, start).allowed);
+    }
+
+    #[test]
+    fn test_memory_store_drops_buckets_once_refilled() {
+        let store = MemoryRateLimitStore::new();
+        let start = Instant::now();
+        // A long window refills slowly; its bucket must outlive the short one's
+        store.hit_at("short", RateLimitQuota::new(10, 10), start);
+        store.hit_at("long", RateLimitQuota::new(10, 3600), start);
+
+        assert_eq!(store.delete_expired_at(start), 0);
+        assert_eq!(store.delete_expired_at(start + Duration::from_secs(2)), 1);
+        let buckets = store.buckets.lock().unwrap();
+        assert!(buckets.contains_key("long"));
+        assert!(!buckets.contains_key("short"));
+        drop(buckets);
+
+        assert_eq!(store.delete_expired_at(start + Duration::from_secs(361)), 1);
+        assert!(store.buckets.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sliding_window_weights_previous_window() {
+        let quota = RateLimitQuota::new(10, 60);
+        // Halfway through the window, half of the previous 10 requests still count
+        let decision = sliding_window_decision(quota, 5, 10, 30);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.reset_seconds, 30);
+
+        let decision = sliding_window_decision(quota, 6, 10, 30);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_seconds, 30);
+    }
+
+    #[tokio::test]
+    async fn test_limiter_applies_most_specific_rule() {
+        let limiter = RateLimiter::new(
+            &RateLimitConfig::default(),
+            Arc::new(MemoryRateLimitStore::new()),
+        );
+        assert_eq!(limiter.rule_for("/auth/signin").unwrap().path_prefix, "/auth/signin");
+        assert_eq!(limiter.rule_for("/auth/signup").unwrap().path_prefix, "/");
+        assert_eq!(limiter.rule_for("/auth/signinx").unwrap().path_prefix, "/");
+
+        let identity = ClientIdentity {
+            ip: Some("203.0.113.7".parse().unwrap()),
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            let decision = limiter.check("/auth/signin", &identity).await.unwrap().unwrap();
+            assert!(decision.allowed);
+        }
+        let decision = limiter.check("/auth/signin", &identity).await.unwrap().unwrap();
+        assert!(!decision.allowed);
+
+        // Other route groups have their own budget
+        let decision = limiter.check("/auth/user", &identity).await.unwrap().unwrap();
+        assert!(decision.allowed);
+    }
+
+    #[test]
+    fn test_unresolved_api_key_counts_against_ip() {
+        let request = || {
+            Request::builder()
+                .header("apikey", "fb_junk")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        assert!(ClientIdentity::from_request(&request()).api_key.is_none());
+
+        let mut resolved = request();
+        resolved.extensions_mut().insert(Claims::new(
+            uuid::Uuid::new_v4(),
+            "user@example.com".to_string(),
+            3600,
+        ));
+        assert_eq!(
+            ClientIdentity::from_request(&resolved).api_key,
+            Some(hash_api_key("fb_junk"))
+        );
+    }
+}
@@ -0,0 +1,79 @@
+// Permission strings and wildcard matching
+use forgebase_core::{ForgeBaseError, Result};
+
+/// Whether a granted permission covers a required one.
+///
+/// Permissions are `:`-separated segments such as `storage:write`. A `*` segment matches any
+/// single segment, and a trailing `*` matches any remainder, so `storage:*` covers
+/// `storage:write` and `storage:objects:delete`, and `*` covers everything.
+pub fn permission_matches(granted: &str, required: &str) -> bool {
+    let mut granted = granted.split(':');
+    let mut required = required.split(':');
+
+    loop {
+        match (granted.next(), required.next()) {
+            (Some("*"), Some(_)) => {
+                if granted.clone().next().is_none() {
+                    return true;
+                }
+            }
+            (Some(g), Some(r)) if g == r => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Whether any of `granted` covers `required`
+pub fn has_permission(granted: &[String], required: &str) -> bool {
+    granted.iter().any(|g| permission_matches(g, required))
+}
+
+/// Validate a permission string before storing it on a role
+pub fn validate_permission(permission: &str) -> Result<()> {
+    let valid = !permission.is_empty()
+        && permission.split(':').all(|segment| {
+            segment == "*"
+                || (!segment.is_empty()
+                    && segment
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+        });
+
+    if !valid {
+        return Err(ForgeBaseError::Validation(format!(
+            "Invalid permission: {}",
+            permission
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_matches() {
+        assert!(permission_matches("storage:write", "storage:write"));
+        assert!(!permission_matches("storage:read", "storage:write"));
+        assert!(permission_matches("storage:*", "storage:write"));
+        assert!(permission_matches("storage:*", "storage:objects:delete"));
+        assert!(permission_matches("*", "functions:invoke"));
+        assert!(permission_matches("*:read", "storage:read"));
+        assert!(!permission_matches("*:read", "storage:write"));
+        assert!(!permission_matches("storage", "storage:write"));
+        assert!(!permission_matches("storage:write", "storage"));
+        assert!(!permission_matches("storage:*", "sites:write"));
+    }
+
+    #[test]
+    fn test_validate_permission() {
+        assert!(validate_permission("storage:write").is_ok());
+        assert!(validate_permission("storage:*").is_ok());
+        assert!(validate_permission("sites.deploy").is_ok());
+        assert!(validate_permission("").is_err());
+        assert!(validate_permission("storage::write").is_err());
+        assert!(validate_permission("storage:wr*te").is_err());
+    }
+}
@@ -1,9 +1,20 @@
+use crate::admin::like_pattern;
 use crate::models::*;
 use chrono::{DateTime, Utc};
 use forgebase_core::{ForgeBaseError, Result};
 use sqlx::PgPool;
 use uuid::Uuid;
 
+/// Admin listing filters; binds search pattern, is_anonymous, email_verified, banned and
+/// include_deleted as $1..$5 (NULL skips a filter)
+const USER_FILTER: &str = r#"
+    ($1::text IS NULL OR email ILIKE $1 OR phone ILIKE $1 OR full_name ILIKE $1)
+    AND ($2::bool IS NULL OR is_anonymous = $2)
+    AND ($3::bool IS NULL OR email_verified = $3)
+    AND ($4::bool IS NULL OR (NOT is_active AND deleted_at IS NULL) = $4)
+    AND ($5 OR deleted_at IS NULL)
+"#;
+
 /// User repository for database operations
 pub struct UserRepository {
     pool: PgPool,
@@ -70,6 +81,38 @@ impl UserRepository {
         Ok(user)
     }
 
+    /// Find user by E.164 phone number
+    pub async fn find_by_phone(&self, phone: &str) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE phone = $1")
+            .bind(phone)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find user: {}", e)))?;
+
+        Ok(user)
+    }
+
+    /// Delete anonymous users created before `cutoff` with no refresh since then
+    pub async fn delete_inactive_anonymous(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM users u
+            WHERE u.is_anonymous AND u.created_at < $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM refresh_tokens rt WHERE rt.user_id = u.id AND rt.created_at >= $1
+              )
+            "#,
+        )
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            ForgeBaseError::Database(format!("Failed to delete anonymous users: {}", e))
+        })?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Update user
     pub async fn update(&self, user: &User) -> Result<User> {
         let user = sqlx::query_as::<_, User>(
@@ -83,9 +126,10 @@ impl UserRepository {
                 full_name = $7,
                 avatar_url = $8,
                 metadata = $9,
-                is_active = $10,
-                last_sign_in_at = $11,
-                updated_at = $12
+                is_anonymous = $10,
+                is_active = $11,
+                last_sign_in_at = $12,
+                updated_at = $13
             WHERE id = $1
             RETURNING *
             "#,
@@ -99,6 +143,7 @@ impl UserRepository {
         .bind(&user.full_name)
         .bind(&user.avatar_url)
         .bind(&user.metadata)
+        .bind(user.is_anonymous)
         .bind(user.is_active)
         .bind(user.last_sign_in_at)
         .bind(user.updated_at)
@@ -120,6 +165,97 @@ impl UserRepository {
         Ok(())
     }
 
+    /// Users matching admin listing filters, newest first
+    pub async fn list(&self, query: &ListUsersQuery, limit: i64, offset: i64) -> Result<Vec<User>> {
+        let users = sqlx::query_as::<_, User>(&format!(
+            "SELECT * FROM users WHERE {} ORDER BY created_at DESC LIMIT $6 OFFSET $7",
+            USER_FILTER
+        ))
+        .bind(query.search.as_deref().map(like_pattern))
+        .bind(query.is_anonymous)
+        .bind(query.email_verified)
+        .bind(query.banned)
+        .bind(query.include_deleted)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list users: {}", e)))?;
+
+        Ok(users)
+    }
+
+    /// Number of users matching admin listing filters
+    pub async fn count(&self, query: &ListUsersQuery) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM users WHERE {}", USER_FILTER))
+            .bind(query.search.as_deref().map(like_pattern))
+            .bind(query.is_anonymous)
+            .bind(query.email_verified)
+            .bind(query.banned)
+            .bind(query.include_deleted)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to count users: {}", e)))?;
+
+        Ok(count)
+    }
+
+    /// Disable a user until `banned_until` (indefinitely when `None`)
+    pub async fn ban(&self, id: Uuid, banned_until: Option<DateTime<Utc>>) -> Result<User> {
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET is_active = FALSE, banned_until = $2, updated_at = NOW() WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .bind(banned_until)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to ban user: {}", e)))?;
+
+        Ok(user)
+    }
+
+    /// Re-enable a banned user (deleted users stay disabled)
+    pub async fn unban(&self, id: Uuid) -> Result<Option<User>> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users SET is_active = TRUE, banned_until = NULL, updated_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to unban user: {}", e)))?;
+
+        Ok(user)
+    }
+
+    /// Disable a user and mark them deleted, keeping the row
+    pub async fn soft_delete(&self, id: Uuid) -> Result<()> {
+        sqlx::query(
+            "UPDATE users SET is_active = FALSE, banned_until = NULL, deleted_at = NOW(), updated_at = NOW() WHERE id = $1",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to delete user: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Replace a user's password hash (e.g. after upgrading the hashing parameters)
+    pub async fn update_password_hash(&self, id: Uuid, password_hash: &str) -> Result<()> {
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+            .bind(password_hash)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to update password: {}", e)))?;
+
+        Ok(())
+    }
+
     /// Update last sign in time
     pub async fn update_last_sign_in(&self, id: Uuid) -> Result<()> {
         sqlx::query("UPDATE users SET last_sign_in_at = $1 WHERE id = $2")
@@ -148,19 +284,23 @@ impl SessionRepository {
         let session = sqlx::query_as::<_, Session>(
             r#"
             INSERT INTO sessions (
-                id, user_id, refresh_token, user_agent, ip_address, expires_at, created_at
+                id, user_id, user_agent, ip_address, aal, expires_at, created_at, last_active_at,
+                client_id, scope
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             RETURNING *
             "#,
         )
         .bind(session.id)
         .bind(session.user_id)
-        .bind(&session.refresh_token)
         .bind(&session.user_agent)
         .bind(&session.ip_address)
+        .bind(&session.aal)
         .bind(session.expires_at)
         .bind(session.created_at)
+        .bind(session.last_active_at)
+        .bind(session.client_id)
+        .bind(&session.scope)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| ForgeBaseError::Database(format!("Failed to create session: {}", e)))?;
@@ -168,12 +308,12 @@ impl SessionRepository {
         Ok(session)
     }
 
-    /// Find session by refresh token
-    pub async fn find_by_refresh_token(&self, refresh_token: &str) -> Result<Option<Session>> {
+    /// Find an unexpired session by ID
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Session>> {
         let session = sqlx::query_as::<_, Session>(
-            "SELECT * FROM sessions WHERE refresh_token = $1 AND expires_at > NOW()",
+            "SELECT * FROM sessions WHERE id = $1 AND expires_at > NOW()",
         )
-        .bind(refresh_token)
+        .bind(id)
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| ForgeBaseError::Database(format!("Failed to find session: {}", e)))?;
@@ -181,6 +321,115 @@ impl SessionRepository {
         Ok(session)
     }
 
+    /// Unexpired sessions of a user, most recently active first
+    pub async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            "SELECT * FROM sessions WHERE user_id = $1 AND expires_at > NOW() ORDER BY last_active_at DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list sessions: {}", e)))?;
+
+        Ok(sessions)
+    }
+
+    /// Whether an unexpired session exists
+    pub async fn is_active(&self, id: Uuid) -> Result<bool> {
+        let active: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sessions WHERE id = $1 AND expires_at > NOW())",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to check session: {}", e)))?;
+
+        Ok(active)
+    }
+
+    /// Set the assurance level of one of a user's unexpired sessions
+    pub async fn set_aal(&self, id: Uuid, user_id: Uuid, aal: &str) -> Result<Option<Session>> {
+        let session = sqlx::query_as::<_, Session>(
+            "UPDATE sessions SET aal = $3 WHERE id = $1 AND user_id = $2 AND expires_at > NOW() RETURNING *",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(aal)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to update session: {}", e)))?;
+
+        Ok(session)
+    }
+
+    /// Record activity on a session
+    pub async fn touch(&self, id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE sessions SET last_active_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to update session: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Delete one of a user's sessions, returning whether it existed
+    pub async fn delete_for_user(&self, id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM sessions WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete session: {}", e)))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete all of a user's sessions except `keep`
+    pub async fn delete_others(&self, user_id: Uuid, keep: Uuid) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND id <> $2")
+            .bind(user_id)
+            .bind(keep)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete sessions: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Delete the sessions a user granted to an OAuth client
+    pub async fn delete_for_client(&self, user_id: Uuid, client_id: Uuid) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND client_id = $2")
+            .bind(user_id)
+            .bind(client_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete sessions: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Keep a user's `max` most recently active sign-ins and delete the rest; sessions
+    /// granted to OAuth clients do not count
+    pub async fn delete_beyond_limit(&self, user_id: Uuid, max: usize) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM sessions WHERE id IN (
+                SELECT id FROM sessions WHERE user_id = $1 AND client_id IS NULL
+                ORDER BY last_active_at DESC, created_at DESC
+                OFFSET $2
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(max as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to enforce session limit: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Delete session
     pub async fn delete(&self, id: Uuid) -> Result<()> {
         sqlx::query("DELETE FROM sessions WHERE id = $1")
@@ -218,6 +467,69 @@ impl SessionRepository {
     }
 }
 
+/// Refresh token repository
+pub struct RefreshTokenRepository {
+    pool: PgPool,
+}
+
+impl RefreshTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a refresh token
+    pub async fn create(&self, token: &RefreshToken) -> Result<RefreshToken> {
+        let token = sqlx::query_as::<_, RefreshToken>(
+            r#"
+            INSERT INTO refresh_tokens (
+                id, session_id, user_id, token_hash, parent, revoked, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(token.id)
+        .bind(token.session_id)
+        .bind(token.user_id)
+        .bind(&token.token_hash)
+        .bind(&token.parent)
+        .bind(token.revoked)
+        .bind(token.created_at)
+        .bind(token.updated_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create refresh token: {}", e)))?;
+
+        Ok(token)
+    }
+
+    /// Find a token by hash, revoked or not
+    pub async fn find_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>> {
+        let token = sqlx::query_as::<_, RefreshToken>(
+            "SELECT * FROM refresh_tokens WHERE token_hash = $1",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to find refresh token: {}", e)))?;
+
+        Ok(token)
+    }
+
+    /// Mark a token as rotated. Returns false if another request already rotated it.
+    pub async fn revoke(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE refresh_tokens SET revoked = TRUE, updated_at = NOW() WHERE id = $1 AND revoked = FALSE",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to revoke refresh token: {}", e)))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
+
 /// Verification token repository
 pub struct VerificationTokenRepository {
     pool: PgPool,
@@ -295,3 +607,366 @@ impl VerificationTokenRepository {
         Ok(())
     }
 }
+
+/// OAuth / SSO identity repository
+pub struct OAuthAccountRepository {
+    pool: PgPool,
+}
+
+impl OAuthAccountRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Link an external identity to a user
+    pub async fn create(&self, account: &OAuthAccount) -> Result<OAuthAccount> {
+        let account = sqlx::query_as::<_, OAuthAccount>(
+            r#"
+            INSERT INTO oauth_accounts (
+                id, user_id, provider, provider_user_id, access_token, refresh_token,
+                expires_at, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+        )
+        .bind(account.id)
+        .bind(account.user_id)
+        .bind(&account.provider)
+        .bind(&account.provider_user_id)
+        .bind(&account.access_token)
+        .bind(&account.refresh_token)
+        .bind(account.expires_at)
+        .bind(account.created_at)
+        .bind(account.updated_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to link identity: {}", e)))?;
+
+        Ok(account)
+    }
+
+    /// Find an identity by provider and the provider's user ID
+    pub async fn find_by_provider(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<Option<OAuthAccount>> {
+        let account = sqlx::query_as::<_, OAuthAccount>(
+            "SELECT * FROM oauth_accounts WHERE provider = $1 AND provider_user_id = $2",
+        )
+        .bind(provider)
+        .bind(provider_user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to find identity: {}", e)))?;
+
+        Ok(account)
+    }
+
+    /// List identities linked to a user
+    pub async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<OAuthAccount>> {
+        let accounts = sqlx::query_as::<_, OAuthAccount>(
+            "SELECT * FROM oauth_accounts WHERE user_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list identities: {}", e)))?;
+
+        Ok(accounts)
+    }
+
+    /// Unlink an identity
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM oauth_accounts WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to unlink identity: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Role repository
+pub struct RoleRepository {
+    pool: PgPool,
+}
+
+impl RoleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create a role
+    pub async fn create(&self, role: &Role) -> Result<Role> {
+        let role = sqlx::query_as::<_, Role>(
+            r#"
+            INSERT INTO roles (id, name, description, permissions, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(role.id)
+        .bind(&role.name)
+        .bind(&role.description)
+        .bind(&role.permissions)
+        .bind(role.created_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create role: {}", e)))?;
+
+        Ok(role)
+    }
+
+    /// Find role by ID
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Role>> {
+        let role = sqlx::query_as::<_, Role>("SELECT * FROM roles WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find role: {}", e)))?;
+
+        Ok(role)
+    }
+
+    /// Find role by name
+    pub async fn find_by_name(&self, name: &str) -> Result<Option<Role>> {
+        let role = sqlx::query_as::<_, Role>("SELECT * FROM roles WHERE name = $1")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find role: {}", e)))?;
+
+        Ok(role)
+    }
+
+    /// List all roles
+    pub async fn list(&self) -> Result<Vec<Role>> {
+        let roles = sqlx::query_as::<_, Role>("SELECT * FROM roles ORDER BY name")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to list roles: {}", e)))?;
+
+        Ok(roles)
+    }
+
+    /// Update a role's description and permissions
+    pub async fn update(&self, role: &Role) -> Result<Role> {
+        let role = sqlx::query_as::<_, Role>(
+            r#"
+            UPDATE roles SET description = $2, permissions = $3
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(role.id)
+        .bind(&role.description)
+        .bind(&role.permissions)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to update role: {}", e)))?;
+
+        Ok(role)
+    }
+
+    /// Delete a role (assignments cascade)
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM roles WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete role: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Assign a role to a user (no-op if already assigned)
+    pub async fn assign(&self, user_id: Uuid, role_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_roles (user_id, role_id, created_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (user_id, role_id) DO NOTHING
+            "#,
+        )
+        .bind(user_id)
+        .bind(role_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to assign role: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Remove a role from a user
+    pub async fn unassign(&self, user_id: Uuid, role_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM user_roles WHERE user_id = $1 AND role_id = $2")
+            .bind(user_id)
+            .bind(role_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to unassign role: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Roles assigned to a user, in assignment order
+    pub async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<Role>> {
+        let roles = sqlx::query_as::<_, Role>(
+            r#"
+            SELECT r.* FROM roles r
+            JOIN user_roles ur ON ur.role_id = r.id
+            WHERE ur.user_id = $1
+            ORDER BY ur.created_at, r.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list user roles: {}", e)))?;
+
+        Ok(roles)
+    }
+}
+
+/// API key repository
+pub struct ApiKeyRepository {
+    pool: PgPool,
+}
+
+impl ApiKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Create an API key
+    pub async fn create(&self, api_key: &ApiKey) -> Result<ApiKey> {
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            r#"
+            INSERT INTO api_keys (
+                id, user_id, name, key_hash, prefix, scopes, last_used_at, expires_at, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+        )
+        .bind(api_key.id)
+        .bind(api_key.user_id)
+        .bind(&api_key.name)
+        .bind(&api_key.key_hash)
+        .bind(&api_key.prefix)
+        .bind(&api_key.scopes)
+        .bind(api_key.last_used_at)
+        .bind(api_key.expires_at)
+        .bind(api_key.created_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create API key: {}", e)))?;
+
+        Ok(api_key)
+    }
+
+    /// Find an API key by its public prefix
+    pub async fn find_by_prefix(&self, prefix: &str) -> Result<Option<ApiKey>> {
+        let api_key = sqlx::query_as::<_, ApiKey>("SELECT * FROM api_keys WHERE prefix = $1")
+            .bind(prefix)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find API key: {}", e)))?;
+
+        Ok(api_key)
+    }
+
+    /// List a user's API keys
+    pub async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<ApiKey>> {
+        let api_keys = sqlx::query_as::<_, ApiKey>(
+            "SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to list API keys: {}", e)))?;
+
+        Ok(api_keys)
+    }
+
+    /// Delete one of a user's API keys, returning whether it existed
+    pub async fn delete_for_user(&self, id: Uuid, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM api_keys WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete API key: {}", e)))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Record that a key was used
+    pub async fn touch_last_used(&self, id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE api_keys SET last_used_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to update API key: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Password history repository
+pub struct PasswordHistoryRepository {
+    pool: PgPool,
+}
+
+impl PasswordHistoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Hashes of a user's most recent passwords, newest first
+    pub async fn recent(&self, user_id: Uuid, limit: usize) -> Result<Vec<String>> {
+        let hashes = sqlx::query_scalar(
+            "SELECT password_hash FROM password_history WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2",
+        )
+        .bind(user_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to load password history: {}", e)))?;
+
+        Ok(hashes)
+    }
+
+    /// Record a new password and forget all but the `keep` most recent
+    pub async fn add(&self, user_id: Uuid, password_hash: &str, keep: usize) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO password_history (id, user_id, password_hash, created_at) VALUES ($1, $2, $3, NOW())",
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(password_hash)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to record password: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM password_history WHERE id IN (
+                SELECT id FROM password_history WHERE user_id = $1
+                ORDER BY created_at DESC
+                OFFSET $2
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(keep as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to prune password history: {}", e)))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,933 @@
+// SAML 2.0 service provider (SP-initiated, HTTP-Redirect request / HTTP-POST response)
+use crate::models::{RegisterSamlProviderRequest, SamlProvider, SamlRequest, SsoSignInRequest};
+use crate::xmldsig::{self, child};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Duration, Utc};
+use forgebase_core::{ForgeBaseError, Result, SamlConfig};
+use roxmltree::{Document, Node};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::io::Write;
+use uuid::Uuid;
+
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const METADATA_NS: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+const STATUS_SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+const BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const BINDING_REDIRECT: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect";
+const BINDING_POST: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+const NAMEID_EMAIL: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+
+/// How long an AuthnRequest may stay unanswered
+pub(crate) const REQUEST_TTL_MINUTES: i64 = 10;
+
+/// Attribute names commonly used for the user's email and display name
+const EMAIL_ATTRIBUTES: &[&str] = &[
+    "email",
+    "mail",
+    "emailaddress",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/emailaddress",
+    "urn:oid:0.9.2342.19200300.100.1.3",
+];
+const NAME_ATTRIBUTES: &[&str] = &[
+    "displayName",
+    "name",
+    "http://schemas.microsoft.com/identity/claims/displayname",
+    "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/name",
+    "urn:oid:2.16.840.1.113730.3.1.241",
+];
+
+/// Parsed identity provider metadata
+#[derive(Debug, Clone)]
+pub struct IdpMetadata {
+    pub entity_id: String,
+    pub sso_url: String,
+    pub certificates: Vec<String>,
+}
+
+/// A validated SAML assertion
+#[derive(Debug, Clone)]
+pub struct SamlAssertion {
+    pub name_id: String,
+    pub name_id_format: Option<String>,
+    pub session_index: Option<String>,
+    pub attributes: HashMap<String, Vec<String>>,
+}
+
+/// User details mapped from an assertion
+#[derive(Debug, Clone)]
+pub struct SamlUser {
+    pub email: String,
+    pub full_name: Option<String>,
+    pub metadata: serde_json::Value,
+}
+
+/// Parse IdP metadata XML (an `EntityDescriptor` with an `IDPSSODescriptor`)
+pub fn parse_idp_metadata(xml: &str) -> Result<IdpMetadata> {
+    let doc = Document::parse(xml)
+        .map_err(|e| ForgeBaseError::InvalidInput(format!("Invalid SAML metadata: {}", e)))?;
+
+    let entity = doc
+        .descendants()
+        .find(|n| is(n, METADATA_NS, "EntityDescriptor"))
+        .ok_or_else(|| ForgeBaseError::InvalidInput("Metadata has no EntityDescriptor".to_string()))?;
+    let entity_id = entity
+        .attribute("entityID")
+        .ok_or_else(|| ForgeBaseError::InvalidInput("Metadata has no entityID".to_string()))?;
+    let idp = child(entity, METADATA_NS, "IDPSSODescriptor").ok_or_else(|| {
+        ForgeBaseError::InvalidInput("Metadata has no IDPSSODescriptor".to_string())
+    })?;
+
+    // We send requests with the redirect binding; fall back to POST-only IdPs' location
+    let sso_services: Vec<Node> = idp
+        .children()
+        .filter(|n| is(n, METADATA_NS, "SingleSignOnService"))
+        .collect();
+    let sso_url = sso_services
+        .iter()
+        .find(|n| n.attribute("Binding") == Some(BINDING_REDIRECT))
+        .or_else(|| sso_services.iter().find(|n| n.attribute("Binding") == Some(BINDING_POST)))
+        .and_then(|n| n.attribute("Location"))
+        .ok_or_else(|| ForgeBaseError::InvalidInput("Metadata has no SingleSignOnService".to_string()))?;
+
+    let certificates: Vec<String> = idp
+        .children()
+        .filter(|n| is(n, METADATA_NS, "KeyDescriptor"))
+        .filter(|n| matches!(n.attribute("use"), None | Some("signing")))
+        .flat_map(|n| n.descendants().filter(|d| d.tag_name().name() == "X509Certificate"))
+        .filter_map(|n| n.text())
+        .map(|text| text.chars().filter(|c| !c.is_whitespace()).collect())
+        .collect();
+    if certificates.is_empty() {
+        return Err(ForgeBaseError::InvalidInput(
+            "Metadata has no signing certificate".to_string(),
+        ));
+    }
+
+    Ok(IdpMetadata {
+        entity_id: entity_id.to_string(),
+        sso_url: sso_url.to_string(),
+        certificates,
+    })
+}
+
+/// SAML service provider
+pub struct SamlServiceProvider {
+    config: SamlConfig,
+    repo: SamlRepository,
+    http_client: reqwest::Client,
+}
+
+impl SamlServiceProvider {
+    pub fn new(pool: PgPool, config: SamlConfig) -> Self {
+        Self {
+            config,
+            repo: SamlRepository::new(pool),
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Service provider metadata for IdP administrators
+    pub fn metadata_xml(&self) -> String {
+        format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<md:EntityDescriptor xmlns:md="{}" entityID="{}">"#,
+                r#"<md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="{}">"#,
+                r#"<md:NameIDFormat>{}</md:NameIDFormat>"#,
+                r#"<md:AssertionConsumerService Binding="{}" Location="{}" index="0" isDefault="true"/>"#,
+                r#"</md:SPSSODescriptor></md:EntityDescriptor>"#
+            ),
+            METADATA_NS,
+            escape_xml(&self.config.entity_id),
+            PROTOCOL_NS,
+            NAMEID_EMAIL,
+            BINDING_POST,
+            escape_xml(&self.config.acs_url),
+        )
+    }
+
+    /// Register an identity provider from metadata XML or a metadata URL
+    pub async fn register_provider(&self, request: RegisterSamlProviderRequest) -> Result<SamlProvider> {
+        let metadata_xml = match (&request.metadata_xml, &request.metadata_url) {
+            (Some(xml), _) => xml.clone(),
+            (None, Some(url)) => self.fetch_metadata(url).await?,
+            (None, None) => {
+                return Err(ForgeBaseError::Validation(
+                    "Either metadata_xml or metadata_url is required".to_string(),
+                ))
+            }
+        };
+        let metadata = parse_idp_metadata(&metadata_xml)?;
+
+        let domains: Vec<String> = request
+            .domains
+            .iter()
+            .map(|d| d.trim().trim_start_matches('@').to_lowercase())
+            .collect();
+        for domain in &domains {
+            if self.repo.find_provider_by_domain(domain).await?.is_some() {
+                return Err(ForgeBaseError::Conflict(format!(
+                    "Domain {} is already assigned to an identity provider",
+                    domain
+                )));
+            }
+        }
+
+        let provider = SamlProvider {
+            id: Uuid::new_v4(),
+            entity_id: metadata.entity_id,
+            sso_url: metadata.sso_url,
+            certificates: metadata.certificates,
+            metadata_xml,
+            metadata_url: request.metadata_url,
+            domains,
+            attribute_mapping: request.attribute_mapping.unwrap_or(serde_json::json!({})),
+            jit_provisioning: request.jit_provisioning.unwrap_or(true),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        self.repo.create_provider(&provider).await
+    }
+
+    /// Re-fetch metadata for a provider registered by URL (picks up certificate rollovers)
+    pub async fn refresh_provider_metadata(&self, provider_id: Uuid) -> Result<SamlProvider> {
+        let mut provider = self.get_provider(provider_id).await?;
+        let url = provider.metadata_url.clone().ok_or_else(|| {
+            ForgeBaseError::Validation("Provider was not registered with a metadata URL".to_string())
+        })?;
+
+        let metadata_xml = self.fetch_metadata(&url).await?;
+        let metadata = parse_idp_metadata(&metadata_xml)?;
+        provider.entity_id = metadata.entity_id;
+        provider.sso_url = metadata.sso_url;
+        provider.certificates = metadata.certificates;
+        provider.metadata_xml = metadata_xml;
+        provider.updated_at = Utc::now();
+
+        self.repo.update_provider(&provider).await
+    }
+
+    pub async fn get_provider(&self, provider_id: Uuid) -> Result<SamlProvider> {
+        self.repo
+            .find_provider(provider_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("SSO provider not found".to_string()))
+    }
+
+    pub async fn list_providers(&self) -> Result<Vec<SamlProvider>> {
+        self.repo.list_providers().await
+    }
+
+    pub async fn delete_provider(&self, provider_id: Uuid) -> Result<()> {
+        self.repo.delete_provider(provider_id).await
+    }
+
+    /// Start SP-initiated sign-in and return the IdP redirect URL
+    pub async fn start_sign_in(&self, request: SsoSignInRequest) -> Result<String> {
+        let provider = match (request.provider_id, &request.domain, &request.email) {
+            (Some(id), _, _) => self.get_provider(id).await?,
+            (None, domain, email) => {
+                let domain = domain
+                    .clone()
+                    .or_else(|| email.as_deref().and_then(email_domain))
+                    .ok_or_else(|| {
+                        ForgeBaseError::Validation(
+                            "One of provider_id, domain or email is required".to_string(),
+                        )
+                    })?;
+                self.repo
+                    .find_provider_by_domain(&domain.to_lowercase())
+                    .await?
+                    .ok_or_else(|| {
+                        ForgeBaseError::NotFound("No SSO provider for this domain".to_string())
+                    })?
+            }
+        };
+
+        if let Some(redirect_to) = &request.redirect_to {
+            if !self.is_allowed_redirect(redirect_to) {
+                return Err(ForgeBaseError::Validation(
+                    "redirect_to is not an allowed URL".to_string(),
+                ));
+            }
+        }
+
+        let saml_request = SamlRequest {
+            id: format!("_{}", Uuid::new_v4().simple()),
+            provider_id: provider.id,
+            redirect_to: request.redirect_to,
+            expires_at: Utc::now() + Duration::minutes(REQUEST_TTL_MINUTES),
+            created_at: Utc::now(),
+        };
+        self.repo.create_request(&saml_request).await?;
+
+        self.authn_request_url(&provider, &saml_request.id, Utc::now())
+    }
+
+    /// Build the HTTP-Redirect binding URL carrying an AuthnRequest
+    pub fn authn_request_url(
+        &self,
+        provider: &SamlProvider,
+        request_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<String> {
+        let authn_request = format!(
+            concat!(
+                r#"<samlp:AuthnRequest xmlns:samlp="{}" xmlns:saml="{}" ID="{}" Version="2.0" IssueInstant="{}" "#,
+                r#"Destination="{}" AssertionConsumerServiceURL="{}" ProtocolBinding="{}">"#,
+                r#"<saml:Issuer>{}</saml:Issuer>"#,
+                r#"<samlp:NameIDPolicy Format="{}" AllowCreate="true"/>"#,
+                r#"</samlp:AuthnRequest>"#
+            ),
+            PROTOCOL_NS,
+            ASSERTION_NS,
+            request_id,
+            now.format("%Y-%m-%dT%H:%M:%SZ"),
+            escape_xml(&provider.sso_url),
+            escape_xml(&self.config.acs_url),
+            BINDING_POST,
+            escape_xml(&self.config.entity_id),
+            NAMEID_EMAIL,
+        );
+
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder
+            .write_all(authn_request.as_bytes())
+            .map_err(|e| ForgeBaseError::Internal(format!("Failed to encode AuthnRequest: {}", e)))?;
+        let deflated = encoder
+            .finish()
+            .map_err(|e| ForgeBaseError::Internal(format!("Failed to encode AuthnRequest: {}", e)))?;
+
+        let mut url = url::Url::parse(&provider.sso_url)
+            .map_err(|e| ForgeBaseError::Internal(format!("Invalid SSO URL: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("SAMLRequest", &BASE64.encode(deflated))
+            .append_pair("RelayState", request_id);
+
+        Ok(url.to_string())
+    }
+
+    /// Validate a base64 `SAMLResponse` posted to the ACS endpoint, consuming its pending request
+    pub async fn consume_response(
+        &self,
+        saml_response: &str,
+        relay_state: Option<&str>,
+    ) -> Result<(SamlProvider, SamlAssertion, Option<String>)> {
+        let xml = decode_saml_response(saml_response)?;
+
+        // InResponseTo is only trusted after the signature check below; here it just finds the request
+        let request_id = {
+            let doc = parse_response_document(&xml)?;
+            doc.root_element()
+                .attribute("InResponseTo")
+                .map(String::from)
+                .ok_or_else(|| {
+                    ForgeBaseError::Auth("Unsolicited SAML responses are not accepted".to_string())
+                })?
+        };
+        if let Some(relay_state) = relay_state {
+            if relay_state != request_id {
+                return Err(ForgeBaseError::Auth("RelayState does not match the SAML request".to_string()));
+            }
+        }
+
+        let pending = self
+            .repo
+            .take_request(&request_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::Auth("Unknown or expired SAML request".to_string()))?;
+        let provider = self.get_provider(pending.provider_id).await?;
+
+        let assertion = self.validate_response(&xml, &provider, &request_id, Utc::now())?;
+        Ok((provider, assertion, pending.redirect_to))
+    }
+
+    /// Validate a SAML response document against a provider.
+    ///
+    /// Checks status, issuer, destination, the XML signature over the assertion (or the whole
+    /// response), audience, the validity window and that it answers `expected_request_id`.
+    pub fn validate_response(
+        &self,
+        xml: &str,
+        provider: &SamlProvider,
+        expected_request_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<SamlAssertion> {
+        let doc = parse_response_document(xml)?;
+        let response = doc.root_element();
+        let skew = Duration::seconds(self.config.clock_skew_seconds);
+
+        let status = child(response, PROTOCOL_NS, "Status")
+            .and_then(|s| child(s, PROTOCOL_NS, "StatusCode"))
+            .and_then(|s| s.attribute("Value"));
+        if status != Some(STATUS_SUCCESS) {
+            return Err(ForgeBaseError::Auth(format!(
+                "Identity provider returned status {}",
+                status.unwrap_or("(none)")
+            )));
+        }
+
+        if response.attribute("InResponseTo") != Some(expected_request_id) {
+            return Err(ForgeBaseError::Auth("SAML response InResponseTo mismatch".to_string()));
+        }
+        if let Some(destination) = response.attribute("Destination") {
+            if destination != self.config.acs_url {
+                return Err(ForgeBaseError::Auth("SAML response Destination mismatch".to_string()));
+            }
+        }
+        if let Some(issuer) = child(response, ASSERTION_NS, "Issuer").and_then(|i| i.text()) {
+            if issuer.trim() != provider.entity_id {
+                return Err(ForgeBaseError::Auth("SAML response Issuer mismatch".to_string()));
+            }
+        }
+
+        if doc.descendants().any(|n| is(&n, ASSERTION_NS, "EncryptedAssertion")) {
+            return Err(ForgeBaseError::Auth("Encrypted assertions are not supported".to_string()));
+        }
+        let assertions: Vec<Node> = doc
+            .descendants()
+            .filter(|n| is(n, ASSERTION_NS, "Assertion"))
+            .collect();
+        if assertions.len() != 1 || assertions[0].parent() != Some(response) {
+            return Err(ForgeBaseError::Auth("Expected exactly one assertion".to_string()));
+        }
+        let assertion = assertions[0];
+
+        // The assertion we read must itself be covered by a verified signature
+        let certificates = provider
+            .certificates
+            .iter()
+            .map(|c| BASE64.decode(c))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| ForgeBaseError::Internal(format!("Corrupt stored certificate: {}", e)))?;
+        let response_signed = xmldsig::has_signature(response);
+        if response_signed {
+            xmldsig::verify_enveloped_signature(&doc, response, &certificates)?;
+        }
+        if xmldsig::has_signature(assertion) {
+            xmldsig::verify_enveloped_signature(&doc, assertion, &certificates)?;
+        } else if !response_signed {
+            return Err(ForgeBaseError::Auth("SAML assertion is not signed".to_string()));
+        }
+
+        let issuer = child(assertion, ASSERTION_NS, "Issuer").and_then(|i| i.text());
+        if issuer.map(str::trim) != Some(provider.entity_id.as_str()) {
+            return Err(ForgeBaseError::Auth("SAML assertion Issuer mismatch".to_string()));
+        }
+
+        // Subject and its bearer confirmation
+        let subject = child(assertion, ASSERTION_NS, "Subject")
+            .ok_or_else(|| ForgeBaseError::Auth("SAML assertion has no Subject".to_string()))?;
+        let name_id = child(subject, ASSERTION_NS, "NameID")
+            .ok_or_else(|| ForgeBaseError::Auth("SAML assertion has no NameID".to_string()))?;
+        let confirmed = subject
+            .children()
+            .filter(|n| is(n, ASSERTION_NS, "SubjectConfirmation"))
+            .filter(|n| n.attribute("Method") == Some(BEARER))
+            .filter_map(|n| child(n, ASSERTION_NS, "SubjectConfirmationData"))
+            .any(|data| {
+                let in_time = data
+                    .attribute("NotOnOrAfter")
+                    .and_then(parse_time)
+                    .map(|t| now < t + skew)
+                    .unwrap_or(false);
+                let recipient_ok = data
+                    .attribute("Recipient")
+                    .map(|r| r == self.config.acs_url)
+                    .unwrap_or(true);
+                let request_ok = data
+                    .attribute("InResponseTo")
+                    .map(|r| r == expected_request_id)
+                    .unwrap_or(true);
+                in_time && recipient_ok && request_ok
+            });
+        if !confirmed {
+            return Err(ForgeBaseError::Auth(
+                "SAML assertion has no valid bearer confirmation".to_string(),
+            ));
+        }
+
+        // Conditions: validity window and audience
+        let conditions = child(assertion, ASSERTION_NS, "Conditions")
+            .ok_or_else(|| ForgeBaseError::Auth("SAML assertion has no Conditions".to_string()))?;
+        if let Some(not_before) = conditions.attribute("NotBefore").and_then(parse_time) {
+            if now + skew < not_before {
+                return Err(ForgeBaseError::Auth("SAML assertion is not yet valid".to_string()));
+            }
+        }
+        if let Some(not_on_or_after) = conditions.attribute("NotOnOrAfter").and_then(parse_time) {
+            if now >= not_on_or_after + skew {
+                return Err(ForgeBaseError::Auth("SAML assertion has expired".to_string()));
+            }
+        }
+        let audience_ok = conditions
+            .children()
+            .filter(|n| is(n, ASSERTION_NS, "AudienceRestriction"))
+            .all(|restriction| {
+                restriction
+                    .children()
+                    .filter(|n| is(n, ASSERTION_NS, "Audience"))
+                    .any(|a| a.text().map(str::trim) == Some(self.config.entity_id.as_str()))
+            });
+        let has_audience = conditions
+            .children()
+            .any(|n| is(&n, ASSERTION_NS, "AudienceRestriction"));
+        if !has_audience || !audience_ok {
+            return Err(ForgeBaseError::Auth("SAML assertion audience mismatch".to_string()));
+        }
+
+        let session_index = child(assertion, ASSERTION_NS, "AuthnStatement")
+            .and_then(|s| s.attribute("SessionIndex"))
+            .map(String::from);
+
+        let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
+        for statement in assertion
+            .children()
+            .filter(|n| is(n, ASSERTION_NS, "AttributeStatement"))
+        {
+            for attribute in statement.children().filter(|n| is(n, ASSERTION_NS, "Attribute")) {
+                let Some(name) = attribute.attribute("Name") else { continue };
+                let values: Vec<String> = attribute
+                    .children()
+                    .filter(|n| is(n, ASSERTION_NS, "AttributeValue"))
+                    .filter_map(|n| n.text())
+                    .map(|t| t.trim().to_string())
+                    .collect();
+                if let Some(friendly_name) = attribute.attribute("FriendlyName") {
+                    attributes
+                        .entry(friendly_name.to_string())
+                        .or_default()
+                        .extend(values.clone());
+                }
+                attributes.entry(name.to_string()).or_default().extend(values);
+            }
+        }
+
+        Ok(SamlAssertion {
+            name_id: name_id.text().unwrap_or_default().trim().to_string(),
+            name_id_format: name_id.attribute("Format").map(String::from),
+            session_index,
+            attributes,
+        })
+    }
+
+    /// Map assertion attributes to user fields using the provider's attribute mapping.
+    ///
+    /// `attribute_mapping` maps `email`, `full_name` and any metadata key to an attribute name;
+    /// unmapped email and name fall back to well-known attribute names.
+    pub fn map_user(&self, provider: &SamlProvider, assertion: &SamlAssertion) -> Result<SamlUser> {
+        let mapping = provider.attribute_mapping.as_object();
+        let lookup = |name: &str| {
+            assertion
+                .attributes
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .and_then(|(_, v)| v.first().cloned())
+        };
+        let mapped = |field: &str| {
+            mapping
+                .and_then(|m| m.get(field))
+                .and_then(|v| v.as_str())
+                .and_then(lookup)
+        };
+
+        let email = mapped("email")
+            .or_else(|| EMAIL_ATTRIBUTES.iter().find_map(|name| lookup(name)))
+            .or_else(|| {
+                assertion
+                    .name_id
+                    .contains('@')
+                    .then(|| assertion.name_id.clone())
+            })
+            .ok_or_else(|| ForgeBaseError::Auth("SAML assertion carries no email".to_string()))?
+            .to_lowercase();
+
+        let full_name = mapped("full_name").or_else(|| NAME_ATTRIBUTES.iter().find_map(|name| lookup(name)));
+
+        let mut metadata = serde_json::Map::new();
+        if let Some(mapping) = mapping {
+            for (field, attribute) in mapping {
+                if field == "email" || field == "full_name" {
+                    continue;
+                }
+                if let Some(values) = attribute.as_str().and_then(|a| {
+                    assertion
+                        .attributes
+                        .iter()
+                        .find(|(k, _)| k.eq_ignore_ascii_case(a))
+                        .map(|(_, v)| v)
+                }) {
+                    let value = match values.as_slice() {
+                        [single] => serde_json::Value::String(single.clone()),
+                        many => serde_json::json!(many),
+                    };
+                    metadata.insert(field.clone(), value);
+                }
+            }
+        }
+
+        Ok(SamlUser {
+            email,
+            full_name,
+            metadata: serde_json::Value::Object(metadata),
+        })
+    }
+
+    /// Remove requests that were never answered
+    pub async fn delete_expired_requests(&self) -> Result<u64> {
+        self.repo.delete_expired_requests().await
+    }
+
+    fn is_allowed_redirect(&self, redirect_to: &str) -> bool {
+        self.config
+            .allowed_redirect_urls
+            .iter()
+            .any(|allowed| redirect_matches(allowed, redirect_to))
+    }
+
+    async fn fetch_metadata(&self, url: &str) -> Result<String> {
+        self.http_client
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ForgeBaseError::ExternalService(format!("Failed to fetch SAML metadata: {}", e)))?
+            .text()
+            .await
+            .map_err(|e| ForgeBaseError::ExternalService(format!("Failed to read SAML metadata: {}", e)))
+    }
+}
+
+/// Decode the base64 `SAMLResponse` form field
+pub fn decode_saml_response(saml_response: &str) -> Result<String> {
+    let compact: String = saml_response.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = BASE64
+        .decode(compact)
+        .map_err(|_| ForgeBaseError::InvalidInput("SAMLResponse is not valid base64".to_string()))?;
+
+    String::from_utf8(bytes)
+        .map_err(|_| ForgeBaseError::InvalidInput("SAMLResponse is not valid UTF-8".to_string()))
+}
+
+fn parse_response_document(xml: &str) -> Result<Document<'_>> {
+    let doc = Document::parse(xml)
+        .map_err(|e| ForgeBaseError::InvalidInput(format!("Invalid SAML response: {}", e)))?;
+    if !is(&doc.root_element(), PROTOCOL_NS, "Response") {
+        return Err(ForgeBaseError::InvalidInput(
+            "Document is not a SAML Response".to_string(),
+        ));
+    }
+    Ok(doc)
+}
+
+fn is(node: &Node, ns: &str, name: &str) -> bool {
+    node.is_element() && node.tag_name().namespace() == Some(ns) && node.tag_name().name() == name
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn email_domain(email: &str) -> Option<String> {
+    email.rsplit_once('@').map(|(_, domain)| domain.to_string())
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// SAML provider and request repository
+pub struct SamlRepository {
+    pool: PgPool,
+}
+
+impl SamlRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_provider(&self, provider: &SamlProvider) -> Result<SamlProvider> {
+        let provider = sqlx::query_as::<_, SamlProvider>(
+            r#"
+            INSERT INTO saml_providers (
+                id, entity_id, sso_url, certificates, metadata_xml, metadata_url, domains,
+                attribute_mapping, jit_provisioning, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING *
+            "#,
+        )
+        .bind(provider.id)
+        .bind(&provider.entity_id)
+        .bind(&provider.sso_url)
+        .bind(&provider.certificates)
+        .bind(&provider.metadata_xml)
+        .bind(&provider.metadata_url)
+        .bind(&provider.domains)
+        .bind(&provider.attribute_mapping)
+        .bind(provider.jit_provisioning)
+        .bind(provider.created_at)
+        .bind(provider.updated_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create SSO provider: {}", e)))?;
+
+        Ok(provider)
+    }
+
+    pub async fn update_provider(&self, provider: &SamlProvider) -> Result<SamlProvider> {
+        let provider = sqlx::query_as::<_, SamlProvider>(
+            r#"
+            UPDATE saml_providers SET
+                entity_id = $2,
+                sso_url = $3,
+                certificates = $4,
+                metadata_xml = $5,
+                domains = $6,
+                attribute_mapping = $7,
+                jit_provisioning = $8,
+                updated_at = $9
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(provider.id)
+        .bind(&provider.entity_id)
+        .bind(&provider.sso_url)
+        .bind(&provider.certificates)
+        .bind(&provider.metadata_xml)
+        .bind(&provider.domains)
+        .bind(&provider.attribute_mapping)
+        .bind(provider.jit_provisioning)
+        .bind(provider.updated_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to update SSO provider: {}", e)))?;
+
+        Ok(provider)
+    }
+
+    pub async fn find_provider(&self, id: Uuid) -> Result<Option<SamlProvider>> {
+        sqlx::query_as::<_, SamlProvider>("SELECT * FROM saml_providers WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find SSO provider: {}", e)))
+    }
+
+    pub async fn find_provider_by_domain(&self, domain: &str) -> Result<Option<SamlProvider>> {
+        sqlx::query_as::<_, SamlProvider>("SELECT * FROM saml_providers WHERE $1 = ANY(domains)")
+            .bind(domain)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to find SSO provider: {}", e)))
+    }
+
+    pub async fn list_providers(&self) -> Result<Vec<SamlProvider>> {
+        sqlx::query_as::<_, SamlProvider>("SELECT * FROM saml_providers ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to list SSO providers: {}", e)))
+    }
+
+    pub async fn delete_provider(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM saml_providers WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete SSO provider: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub async fn create_request(&self, request: &SamlRequest) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO saml_requests (id, provider_id, redirect_to, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&request.id)
+        .bind(request.provider_id)
+        .bind(&request.redirect_to)
+        .bind(request.expires_at)
+        .bind(request.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to create SAML request: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Fetch and delete a pending request in one step, so each response is accepted once
+    pub async fn take_request(&self, id: &str) -> Result<Option<SamlRequest>> {
+        sqlx::query_as::<_, SamlRequest>(
+            "DELETE FROM saml_requests WHERE id = $1 AND expires_at > NOW() RETURNING *",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to find SAML request: {}", e)))
+    }
+
+    pub async fn delete_expired_requests(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM saml_requests WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                ForgeBaseError::Database(format!("Failed to delete expired SAML requests: {}", e))
+            })?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Whether `redirect_to` has the allowed URL's scheme, host and port, and lies under its path
+/// on a segment boundary
+fn redirect_matches(allowed: &str, redirect_to: &str) -> bool {
+    let (Ok(allowed), Ok(redirect)) = (url::Url::parse(allowed), url::Url::parse(redirect_to))
+    else {
+        return false;
+    };
+    if allowed.scheme() != redirect.scheme()
+        || allowed.host_str().is_none()
+        || allowed.host_str() != redirect.host_str()
+        || allowed.port_or_known_default() != redirect.port_or_known_default()
+    {
+        return false;
+    }
+
+    let (prefix, path) = (allowed.path(), redirect.path());
+    path == prefix
+        || (path.starts_with(prefix)
+            && (prefix.ends_with('/') || path.as_bytes().get(prefix.len()) == Some(&b'/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = include_str!("../tests/fixtures/saml/signed_response.xml");
+    const METADATA: &str = include_str!("../tests/fixtures/saml/idp_metadata.xml");
+
+    fn service_provider(entity_id: &str) -> SamlServiceProvider {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/forgebase_test")
+            .unwrap();
+        SamlServiceProvider::new(
+            pool,
+            SamlConfig {
+                entity_id: entity_id.to_string(),
+                acs_url: "https://sp.example.com/auth/sso/saml/acs".to_string(),
+                clock_skew_seconds: 60,
+                allowed_redirect_urls: vec!["https://app.example.com/".to_string()],
+            },
+        )
+    }
+
+    fn provider() -> SamlProvider {
+        let metadata = parse_idp_metadata(METADATA).unwrap();
+        SamlProvider {
+            id: Uuid::new_v4(),
+            entity_id: metadata.entity_id,
+            sso_url: metadata.sso_url,
+            certificates: metadata.certificates,
+            metadata_xml: METADATA.to_string(),
+            metadata_url: None,
+            domains: vec!["corp.example.com".to_string()],
+            attribute_mapping: serde_json::json!({ "department": "department" }),
+            jit_provisioning: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        parse_time(time).unwrap()
+    }
+
+    #[test]
+    fn test_redirect_matches() {
+        assert!(redirect_matches("https://app.example.com", "https://app.example.com/home"));
+        assert!(redirect_matches("https://app.example.com/", "https://APP.example.com:443/"));
+        assert!(redirect_matches("https://app.example.com/app", "https://app.example.com/app/x"));
+        assert!(redirect_matches("https://app.example.com/app", "https://app.example.com/app"));
+
+        assert!(!redirect_matches("https://app.example.com", "https://app.example.com.evil.net/"));
+        assert!(!redirect_matches("https://app.example.com", "https://app.example.com@evil.net/"));
+        assert!(!redirect_matches("https://app.example.com", "http://app.example.com/"));
+        assert!(!redirect_matches("https://app.example.com", "https://app.example.com:8443/"));
+        assert!(!redirect_matches("https://app.example.com/app", "https://app.example.com/apple"));
+        assert!(!redirect_matches("https://app.example.com", "/relative"));
+    }
+
+    #[test]
+    fn test_parse_idp_metadata() {
+        let metadata = parse_idp_metadata(METADATA).unwrap();
+        assert_eq!(metadata.entity_id, "https://idp.example.com");
+        assert_eq!(metadata.sso_url, "https://idp.example.com/sso/redirect");
+        assert_eq!(metadata.certificates.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_validate_signed_response_and_map_attributes() {
+        let sp = service_provider("https://sp.example.com");
+        let provider = provider();
+
+        let assertion = sp
+            .validate_response(RESPONSE, &provider, "_req1", at("2024-01-01T00:01:00Z"))
+            .unwrap();
+        assert_eq!(assertion.name_id, "jane@corp.example.com");
+        assert_eq!(assertion.session_index.as_deref(), Some("_session1"));
+
+        let user = sp.map_user(&provider, &assertion).unwrap();
+        assert_eq!(user.email, "jane@corp.example.com");
+        assert_eq!(user.full_name.as_deref(), Some("Jane Doe"));
+        assert_eq!(user.metadata["department"], "Engineering");
+    }
+
+    #[tokio::test]
+    async fn test_reject_tampered_expired_and_misdirected_responses() {
+        let sp = service_provider("https://sp.example.com");
+        let provider = provider();
+        let now = at("2024-01-01T00:01:00Z");
+
+        let tampered = RESPONSE.replace("Engineering", "Finance");
+        assert!(sp.validate_response(&tampered, &provider, "_req1", now).is_err());
+
+        assert!(sp
+            .validate_response(RESPONSE, &provider, "_req1", at("2024-01-01T00:10:00Z"))
+            .is_err());
+        assert!(sp.validate_response(RESPONSE, &provider, "_other", now).is_err());
+        assert!(service_provider("https://other-sp.example.com")
+            .validate_response(RESPONSE, &provider, "_req1", now)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_authn_request_url() {
+        let sp = service_provider("https://sp.example.com");
+        let url = sp
+            .authn_request_url(&provider(), "_req1", at("2024-01-01T00:00:00Z"))
+            .unwrap();
+        let parsed = url::Url::parse(&url).unwrap();
+        let params: HashMap<_, _> = parsed.query_pairs().into_owned().collect();
+        assert_eq!(params["RelayState"], "_req1");
+
+        let deflated = BASE64.decode(&params["SAMLRequest"]).unwrap();
+        let mut xml = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::DeflateDecoder::new(&deflated[..]), &mut xml)
+            .unwrap();
+        assert!(xml.contains(r#"ID="_req1""#));
+        assert!(xml.contains("<saml:Issuer>https://sp.example.com</saml:Issuer>"));
+    }
+}
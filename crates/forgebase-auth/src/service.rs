@@ -1,23 +1,77 @@
 use crate::{
-    jwt::{Claims, JwtManager},
+    admin::{page_params, pagination, IMPERSONATION_TOKEN_SECONDS},
+    api_key::{generate_api_key, hash_api_key, parse_prefix},
+    audit::*,
+    client::{parse_user_agent, TrustedProxies},
+    jwt::{Claims, JwtManager, SERVICE_ROLE},
+    keys::KeyStore,
     models::*,
-    password::{hash_password, verify_password, validate_password_strength},
-    repository::{SessionRepository, UserRepository, VerificationTokenRepository},
-    session::SessionManager,
+    email::EmailService,
+    hooks::{AuthHooks, EVENT_PASSWORD_CHANGED, EVENT_USER_CREATED, EVENT_USER_SIGNED_IN},
+    lockout::SignInGuard,
+    mfa::{ensure_can_add_factor, MfaFactorRepository, FACTOR_TYPE_SMS},
+    oauth::{OAuthManager, OAuthUserInfo},
+    oidc::{
+        parse_scope, redirect_with, user_info, verify_pkce, OAuthError, OidcProvider, UserInfo,
+        GRANT_AUTHORIZATION_CODE, GRANT_CLIENT_CREDENTIALS, GRANT_REFRESH_TOKEN,
+        SCOPE_OFFLINE_ACCESS, SCOPE_OPENID, USER_SCOPES,
+    },
+    organization::{
+        generate_invitation_token, hash_invitation_token, slugify, validate_slug,
+        OrganizationRepository, OrganizationRole, INVITATION_TTL_DAYS,
+    },
+    password::{verify_dummy_password, verify_password, PasswordPolicy},
+    repository::{
+        ApiKeyRepository, OAuthAccountRepository, PasswordHistoryRepository, RefreshTokenRepository,
+        RoleRepository, SessionRepository, UserRepository, VerificationTokenRepository,
+    },
+    rbac::validate_permission,
+    phone::{normalize_phone, PhoneAuth, OTP_PURPOSE_MFA, OTP_PURPOSE_PHONE_CHANGE, OTP_PURPOSE_SIGN_IN},
+    saml::SamlServiceProvider,
+    session::{hash_refresh_token, SessionManager, AAL2},
+    sms::SmsProvider,
 };
-use chrono::Utc;
-use forgebase_core::{ForgeBaseError, Result};
+use chrono::{DateTime, Utc};
+use forgebase_core::{
+    AnonymousUsersConfig, AuditLogConfig, BruteForceConfig, ForgeBaseError, OidcProviderConfig, PaginatedResponse,
+    Result, SamlConfig, SmsConfig, UserDataCleanup,
+};
+use jsonwebtoken::Algorithm;
 use sqlx::PgPool;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Authentication service
 pub struct AuthService {
+    pool: PgPool,
     user_repo: UserRepository,
     session_repo: SessionRepository,
+    refresh_token_repo: RefreshTokenRepository,
     token_repo: VerificationTokenRepository,
-    jwt_manager: JwtManager,
+    oauth_account_repo: OAuthAccountRepository,
+    role_repo: RoleRepository,
+    api_key_repo: ApiKeyRepository,
+    mfa_factor_repo: MfaFactorRepository,
+    password_history_repo: PasswordHistoryRepository,
+    organization_repo: OrganizationRepository,
+    password_policy: PasswordPolicy,
+    sign_in_guard: SignInGuard,
+    email_service: Option<Arc<EmailService>>,
+    jwt_manager: Arc<JwtManager>,
+    key_store: KeyStore,
+    signing_algorithm: Option<Algorithm>,
     session_manager: SessionManager,
     jwt_expiration: i64,
+    saml: Option<SamlServiceProvider>,
+    oidc: Option<OidcProvider>,
+    phone_auth: Option<PhoneAuth>,
+    oauth: Option<OAuthManager>,
+    anonymous: AnonymousUsersConfig,
+    trusted_proxies: TrustedProxies,
+    max_sessions_per_user: Option<usize>,
+    user_data_cleanups: Vec<Arc<dyn UserDataCleanup>>,
+    hooks: AuthHooks,
+    audit_log: AuditLog,
 }
 
 impl AuthService {
@@ -30,13 +84,175 @@ impl AuthService {
         Self {
             user_repo: UserRepository::new(pool.clone()),
             session_repo: SessionRepository::new(pool.clone()),
-            token_repo: VerificationTokenRepository::new(pool),
-            jwt_manager: JwtManager::new(&jwt_secret),
+            refresh_token_repo: RefreshTokenRepository::new(pool.clone()),
+            token_repo: VerificationTokenRepository::new(pool.clone()),
+            oauth_account_repo: OAuthAccountRepository::new(pool.clone()),
+            role_repo: RoleRepository::new(pool.clone()),
+            api_key_repo: ApiKeyRepository::new(pool.clone()),
+            mfa_factor_repo: MfaFactorRepository::new(pool.clone()),
+            password_history_repo: PasswordHistoryRepository::new(pool.clone()),
+            organization_repo: OrganizationRepository::new(pool.clone()),
+            audit_log: AuditLog::new(pool.clone(), AuditLogConfig::default()),
+            password_policy: PasswordPolicy::default(),
+            key_store: KeyStore::new(pool.clone()),
+            sign_in_guard: SignInGuard::new(pool.clone(), BruteForceConfig::default()),
+            email_service: None,
+            pool,
+            jwt_manager: Arc::new(JwtManager::new(&jwt_secret)),
+            signing_algorithm: None,
             session_manager: SessionManager::new(refresh_token_expiration_days),
             jwt_expiration,
+            saml: None,
+            oidc: None,
+            phone_auth: None,
+            oauth: None,
+            anonymous: AnonymousUsersConfig::default(),
+            trusted_proxies: TrustedProxies::default(),
+            max_sessions_per_user: None,
+            user_data_cleanups: Vec::new(),
+            hooks: AuthHooks::default(),
         }
     }
 
+    /// Sign tokens with rotating asymmetric keys (RS256, ES256 or EdDSA) instead of the secret.
+    ///
+    /// Keys are loaded by [`AuthService::load_signing_keys`].
+    pub fn with_signing_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.signing_algorithm = Some(algorithm);
+        self
+    }
+
+    /// Keep accepting tokens signed with the shared secret for this long after switching to
+    /// asymmetric keys; by default they stop verifying at once
+    pub fn with_legacy_token_grace(self, seconds: i64) -> Self {
+        self.jwt_manager
+            .set_legacy_token_grace(chrono::Duration::seconds(seconds));
+        self
+    }
+
+    /// Token manager shared with the auth middleware, so key rotation is seen everywhere
+    pub fn jwt_manager(&self) -> Arc<JwtManager> {
+        self.jwt_manager.clone()
+    }
+
+    /// Configure failed sign-in thresholds and lockouts
+    pub fn with_brute_force_protection(mut self, config: BruteForceConfig) -> Self {
+        self.sign_in_guard = SignInGuard::new(self.pool.clone(), config);
+        self
+    }
+
+    /// Apply password rules, breach checks, history and hashing cost from configuration
+    pub fn with_password_policy(mut self, policy: PasswordPolicy) -> Self {
+        self.password_policy = policy;
+        self
+    }
+
+    /// Send verification, password reset and account emails through this email service.
+    ///
+    /// Emails are queued; run [`EmailService::spawn_outbox_worker`] to deliver them.
+    pub fn with_email(mut self, email_service: Arc<EmailService>) -> Self {
+        self.email_service = Some(email_service);
+        self
+    }
+
+    /// Set how long a rotated refresh token is still accepted
+    pub fn with_refresh_token_reuse_interval(mut self, seconds: i64) -> Self {
+        self.session_manager = self.session_manager.with_reuse_interval(seconds);
+        self
+    }
+
+    /// Enable SAML single sign-on
+    pub fn with_saml(mut self, config: SamlConfig) -> Self {
+        self.saml = Some(SamlServiceProvider::new(self.pool.clone(), config));
+        self
+    }
+
+    /// SAML service provider, if SSO is configured
+    pub fn saml(&self) -> Result<&SamlServiceProvider> {
+        self.saml
+            .as_ref()
+            .ok_or_else(|| ForgeBaseError::Config("SAML SSO is not configured".to_string()))
+    }
+
+    /// Act as an OpenID Connect provider for third-party apps
+    pub fn with_oidc_provider(mut self, config: OidcProviderConfig) -> Self {
+        self.oidc = Some(OidcProvider::new(self.pool.clone(), config));
+        self
+    }
+
+    /// OpenID Connect provider, if enabled
+    pub fn oidc_provider(&self) -> Result<&OidcProvider> {
+        self.oidc.as_ref().ok_or_else(|| {
+            ForgeBaseError::Config("OpenID Connect provider is not configured".to_string())
+        })
+    }
+
+    /// Enable phone sign-in and SMS second factors
+    pub fn with_sms(mut self, config: SmsConfig, provider: Arc<dyn SmsProvider>) -> Self {
+        self.phone_auth = Some(PhoneAuth::new(self.pool.clone(), config, provider));
+        self
+    }
+
+    /// SMS one-time codes, if an SMS provider is configured
+    pub fn phone_auth(&self) -> Result<&PhoneAuth> {
+        self.phone_auth
+            .as_ref()
+            .ok_or_else(|| ForgeBaseError::Config("SMS is not configured".to_string()))
+    }
+
+    /// Enable OAuth providers (used to link identities to existing users)
+    pub fn with_oauth(mut self, oauth: OAuthManager) -> Self {
+        self.oauth = Some(oauth);
+        self
+    }
+
+    /// OAuth manager, if providers are configured
+    pub fn oauth(&self) -> Result<&OAuthManager> {
+        self.oauth
+            .as_ref()
+            .ok_or_else(|| ForgeBaseError::Config("OAuth is not configured".to_string()))
+    }
+
+    /// Allow anonymous sign-ins and set how long abandoned anonymous users are kept
+    pub fn with_anonymous_users(mut self, config: AnonymousUsersConfig) -> Self {
+        self.anonymous = config;
+        self
+    }
+
+    /// Believe forwarded client addresses from these proxies
+    pub fn with_trusted_proxies(mut self, trusted_proxies: TrustedProxies) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    pub fn trusted_proxies(&self) -> &TrustedProxies {
+        &self.trusted_proxies
+    }
+
+    /// Limit concurrent sessions per user; the least recently active are revoked first
+    pub fn with_max_sessions_per_user(mut self, max: usize) -> Self {
+        self.max_sessions_per_user = Some(max.max(1));
+        self
+    }
+
+    /// Clean up a service's user data (storage, sites) when users are deleted
+    pub fn with_user_data_cleanup(mut self, cleanup: Arc<dyn UserDataCleanup>) -> Self {
+        self.user_data_cleanups.push(cleanup);
+        self
+    }
+
+    /// Call custom claims, sign-up and webhook hooks
+    pub fn with_hooks(mut self, hooks: AuthHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Configure the audit log (enabled by default, 90 days retention)
+    pub fn with_audit_log(mut self, config: AuditLogConfig) -> Self {
+        self.audit_log = AuditLog::new(self.pool.clone(), config);
+        self
+    }
+
     /// Sign up a new user
     pub async fn sign_up(
         &self,
@@ -44,9 +260,6 @@ impl AuthService {
         user_agent: Option<String>,
         ip_address: Option<String>,
     ) -> Result<AuthResponse> {
-        // Validate password strength
-        validate_password_strength(&request.password)?;
-
         // Check if user already exists
         if let Some(_) = self.user_repo.find_by_email(&request.email).await? {
             return Err(ForgeBaseError::Conflict(
@@ -54,13 +267,15 @@ impl AuthService {
             ));
         }
 
-        // Hash password
-        let password_hash = hash_password(&request.password)?;
+        // Validate and hash password
+        let password_hash = self
+            .hash_new_password(&request.password, Some(&request.email), None)
+            .await?;
 
         // Create user
         let user = User {
             id: Uuid::new_v4(),
-            email: request.email.clone(),
+            email: Some(request.email.clone()),
             email_verified: false,
             phone: None,
             phone_verified: false,
@@ -71,27 +286,172 @@ impl AuthService {
             is_anonymous: false,
             is_active: true,
             last_sign_in_at: Some(Utc::now()),
+            banned_until: None,
+            deleted_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
-        let created_user = self.user_repo.create(&user).await?;
+        let created_user = self.create_user(&user).await?;
+        self.remember_password(&created_user).await?;
+        self.send_verification_email(&created_user).await;
 
-        // Create session
-        let session = self
-            .session_manager
-            .create_session(created_user.id, user_agent, ip_address);
-        let created_session = self.session_repo.create(&session).await?;
+        let audit = AuditEntry::success(ACTION_SIGN_UP).factor(FACTOR_PASSWORD);
+        self.create_auth_response(created_user, user_agent, ip_address, audit)
+            .await
+    }
 
-        // Generate tokens
-        let claims = Claims::new(created_user.id, created_user.email.clone(), self.jwt_expiration);
-        let access_token = self.jwt_manager.generate_access_token(claims)?;
+    /// Create an anonymous user and sign it in
+    pub async fn sign_up_anonymous(
+        &self,
+        request: AnonymousSignUpRequest,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> Result<AuthResponse> {
+        if !self.anonymous.enabled {
+            return Err(ForgeBaseError::Authorization(
+                "Anonymous sign-ins are disabled".to_string(),
+            ));
+        }
 
-        Ok(AuthResponse {
-            user: created_user.into(),
-            access_token,
-            refresh_token: created_session.refresh_token,
-            expires_in: self.jwt_expiration,
+        let user = anonymous_user(request.metadata);
+        let created_user = self.create_user(&user).await?;
+
+        let audit = AuditEntry::success(ACTION_SIGN_UP).factor(FACTOR_ANONYMOUS);
+        self.create_auth_response(created_user, user_agent, ip_address, audit)
+            .await
+    }
+
+    /// Give an anonymous user an email and password, keeping its id and data
+    pub async fn upgrade_anonymous_user(
+        &self,
+        user_id: Uuid,
+        request: UpgradeAnonymousRequest,
+        audit: &AuditContext,
+    ) -> Result<User> {
+        let mut user = self.find_anonymous_user(user_id).await?;
+
+        ensure_email_available(self.user_repo.find_by_email(&request.email).await?.as_ref())?;
+        let password_hash = self
+            .hash_new_password(&request.password, Some(&request.email), Some(&user))
+            .await?;
+        upgrade_with_password(&mut user, request.email, password_hash);
+
+        let user = self.user_repo.update(&user).await?;
+        self.remember_password(&user).await?;
+        self.send_verification_email(&user).await;
+
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_USER_UPGRADE)
+                    .user(user.id)
+                    .email(user.email.as_deref())
+                    .factor(FACTOR_PASSWORD)
+                    .context(audit),
+            )
+            .await;
+        Ok(user)
+    }
+
+    /// Link an OAuth identity to the user; an anonymous user becomes permanent
+    pub async fn link_oauth_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        code: &str,
+        audit: &AuditContext,
+    ) -> Result<User> {
+        let oauth = self.oauth()?;
+        let tokens = oauth.exchange_code(provider, code).await?;
+        let info = oauth.get_user_info(provider, &tokens).await?;
+
+        if let Some(account) = self
+            .oauth_account_repo
+            .find_by_provider(provider, &info.provider_user_id)
+            .await?
+        {
+            if account.user_id != user_id {
+                return Err(ForgeBaseError::Conflict(
+                    "Identity is already linked to another user".to_string(),
+                ));
+            }
+        } else {
+            let account = OAuthAccount {
+                id: Uuid::new_v4(),
+                user_id,
+                provider: provider.to_string(),
+                provider_user_id: info.provider_user_id.clone(),
+                access_token: Some(tokens.access_token.clone()),
+                refresh_token: tokens.refresh_token.clone(),
+                expires_at: tokens
+                    .expires_in
+                    .map(|seconds| Utc::now() + chrono::Duration::seconds(seconds)),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            };
+            self.oauth_account_repo.create(&account).await?;
+            self.audit_log
+                .record(
+                    AuditEntry::success(ACTION_IDENTITY_LINK)
+                        .user(user_id)
+                        .factor(FACTOR_OAUTH)
+                        .context(audit)
+                        .metadata(serde_json::json!({ "provider": provider })),
+                )
+                .await;
+        }
+
+        let mut user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
+        if !user.is_anonymous {
+            return Ok(user);
+        }
+
+        // Take the provider's email unless another account already has it
+        let email_available =
+            user.email.is_none() && self.user_repo.find_by_email(&info.email).await?.is_none();
+        upgrade_with_identity(&mut user, info, email_available);
+
+        self.user_repo.update(&user).await
+    }
+
+    async fn find_anonymous_user(&self, user_id: Uuid) -> Result<User> {
+        let user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
+        if !user.is_anonymous {
+            return Err(ForgeBaseError::Validation(
+                "User is not anonymous".to_string(),
+            ));
+        }
+
+        Ok(user)
+    }
+
+    /// Delete anonymous users with no session activity within the configured period
+    pub async fn delete_inactive_anonymous_users(&self) -> Result<u64> {
+        let cutoff = anonymous_cleanup_cutoff(Utc::now(), self.anonymous.inactive_days);
+        self.user_repo.delete_inactive_anonymous(cutoff).await
+    }
+
+    /// Run [`AuthService::delete_inactive_anonymous_users`] on the configured schedule
+    pub fn spawn_anonymous_user_cleanup(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let interval = std::time::Duration::from_secs(self.anonymous.cleanup_interval_seconds.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.delete_inactive_anonymous_users().await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("Deleted {} inactive anonymous users", deleted),
+                    Err(e) => tracing::error!("Anonymous user cleanup failed: {}", e),
+                }
+            }
         })
     }
 
@@ -102,132 +462,1176 @@ impl AuthService {
         user_agent: Option<String>,
         ip_address: Option<String>,
     ) -> Result<AuthResponse> {
-        // Find user
-        let user = self
-            .user_repo
-            .find_by_email(&request.email)
+        let tracking_email = request.email.trim().to_lowercase();
+        let context = AuditContext::client(user_agent.clone(), ip_address.clone());
+        let failure = |reason: &str| {
+            AuditEntry::failure(ACTION_SIGN_IN, reason)
+                .email(Some(&tracking_email))
+                .factor(FACTOR_PASSWORD)
+                .context(&context)
+        };
+
+        // Locked accounts and IPs are rejected before the password is looked at
+        if self
+            .sign_in_guard
+            .locked_until(&tracking_email, ip_address.as_deref())
             .await?
-            .ok_or_else(|| ForgeBaseError::Auth("Invalid credentials".to_string()))?;
+            .is_some()
+        {
+            self.audit_log.record(failure("locked")).await;
+            return Err(ForgeBaseError::RateLimit);
+        }
 
-        // Verify user is active
-        if !user.is_active {
-            return Err(ForgeBaseError::Auth("Account is disabled".to_string()));
+        // Every failure path looks the same to the caller, including for unknown emails
+        let user = self.user_repo.find_by_email(&request.email).await?;
+        let verified = match user.as_ref().and_then(|u| u.password_hash.as_ref()) {
+            Some(password_hash) => verify_password(&request.password, password_hash)?,
+            None => {
+                verify_dummy_password(&request.password);
+                false
+            }
+        };
+
+        let mut user = match user {
+            Some(user) if verified => user,
+            user => {
+                let attempt = self
+                    .sign_in_guard
+                    .record_failure(&tracking_email, ip_address.as_deref())
+                    .await?;
+                let mut entry = failure("invalid_credentials");
+                if let Some(user) = &user {
+                    entry = entry.user(user.id);
+                }
+                self.audit_log.record(entry).await;
+                if let (Some(user), Some(locked_until)) = (&user, attempt.account_locked_until) {
+                    self.audit_log
+                        .record(
+                            AuditEntry::success(ACTION_ACCOUNT_LOCKED)
+                                .user(user.id)
+                                .email(Some(&tracking_email))
+                                .context(&context)
+                                .metadata(serde_json::json!({ "locked_until": locked_until })),
+                        )
+                        .await;
+                    self.notify_account_locked(user, locked_until).await;
+                }
+                tokio::time::sleep(attempt.delay).await;
+
+                return Err(ForgeBaseError::Auth("Invalid credentials".to_string()));
+            }
+        };
+
+        // Verify user is active, lifting an expired ban
+        if let Err(e) = self.ensure_active(&mut user).await {
+            self.audit_log.record(failure("account_disabled").user(user.id)).await;
+            return Err(e);
         }
 
-        // Verify password
-        let password_hash = user
-            .password_hash
-            .as_ref()
-            .ok_or_else(|| ForgeBaseError::Auth("Password not set".to_string()))?;
+        self.sign_in_guard.record_success(&tracking_email).await?;
 
-        if !verify_password(&request.password, password_hash)? {
-            return Err(ForgeBaseError::Auth("Invalid credentials".to_string()));
+        // Upgrade hashes made with older Argon2 parameters while the password is at hand
+        if user
+            .password_hash
+            .as_deref()
+            .is_some_and(|hash| self.password_policy.needs_rehash(hash))
+        {
+            let password_hash = self.password_policy.hash(&request.password)?;
+            self.user_repo.update_password_hash(user.id, &password_hash).await?;
+            user.password_hash = Some(password_hash);
         }
 
         // Update last sign in
         self.user_repo.update_last_sign_in(user.id).await?;
 
-        // Create session
+        let audit = AuditEntry::success(ACTION_SIGN_IN).factor(FACTOR_PASSWORD);
+        self.create_auth_response(user, user_agent, ip_address, audit)
+            .await
+    }
+
+    /// Complete SAML sign-in from a response posted to the ACS endpoint.
+    ///
+    /// Returns the session and the `redirect_to` URL recorded when sign-in started.
+    pub async fn sign_in_with_saml(
+        &self,
+        saml_response: &str,
+        relay_state: Option<&str>,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> Result<(AuthResponse, Option<String>)> {
+        let saml = self.saml()?;
+        let context = AuditContext::client(user_agent.clone(), ip_address.clone());
+        let failure = |reason: String| {
+            AuditEntry::failure(ACTION_SIGN_IN, reason)
+                .factor(FACTOR_SSO)
+                .context(&context)
+        };
+
+        let (provider, assertion, redirect_to) =
+            match saml.consume_response(saml_response, relay_state).await {
+                Ok(response) => response,
+                Err(e) => {
+                    self.audit_log.record(failure(e.to_string())).await;
+                    return Err(e);
+                }
+            };
+        let saml_user = saml.map_user(&provider, &assertion)?;
+
+        let provider_key = format!("sso:{}", provider.id);
+        let mut user = match self
+            .oauth_account_repo
+            .find_by_provider(&provider_key, &assertion.name_id)
+            .await?
+        {
+            Some(account) => self
+                .user_repo
+                .find_by_id(account.user_id)
+                .await?
+                .ok_or_else(|| ForgeBaseError::Auth("User not found".to_string()))?,
+            None => {
+                let user = self.find_or_provision_sso_user(&provider, &saml_user).await?;
+                let account = OAuthAccount {
+                    id: Uuid::new_v4(),
+                    user_id: user.id,
+                    provider: provider_key,
+                    provider_user_id: assertion.name_id.clone(),
+                    access_token: None,
+                    refresh_token: None,
+                    expires_at: None,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                };
+                self.oauth_account_repo.create(&account).await?;
+                user
+            }
+        };
+
+        if let Err(e) = self.ensure_active(&mut user).await {
+            self.audit_log
+                .record(failure("account_disabled".to_string()).user(user.id))
+                .await;
+            return Err(e);
+        }
+        self.user_repo.update_last_sign_in(user.id).await?;
+
+        let audit = AuditEntry::success(ACTION_SIGN_IN)
+            .factor(FACTOR_SSO)
+            .metadata(serde_json::json!({ "provider_id": provider.id }));
+        let response = self
+            .create_auth_response(user, user_agent, ip_address, audit)
+            .await?;
+        Ok((response, redirect_to))
+    }
+
+    /// Link an SSO identity to an existing account in one of the provider's domains, or create one
+    async fn find_or_provision_sso_user(
+        &self,
+        provider: &SamlProvider,
+        saml_user: &crate::saml::SamlUser,
+    ) -> Result<User> {
+        let domain = saml_user
+            .email
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .unwrap_or_default();
+        if !provider.domains.iter().any(|d| d == domain) {
+            return Err(ForgeBaseError::Authorization(
+                "Email domain is not managed by this identity provider".to_string(),
+            ));
+        }
+
+        if let Some(user) = self.user_repo.find_by_email(&saml_user.email).await? {
+            return Ok(user);
+        }
+
+        if !provider.jit_provisioning {
+            return Err(ForgeBaseError::Authorization(
+                "No account exists for this user".to_string(),
+            ));
+        }
+
+        let user = User {
+            id: Uuid::new_v4(),
+            email: Some(saml_user.email.clone()),
+            email_verified: true,
+            phone: None,
+            phone_verified: false,
+            password_hash: None,
+            full_name: saml_user.full_name.clone(),
+            avatar_url: None,
+            metadata: saml_user.metadata.clone(),
+            is_anonymous: false,
+            is_active: true,
+            last_sign_in_at: Some(Utc::now()),
+            banned_until: None,
+            deleted_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let user = self.create_user(&user).await?;
+        self.remember_password(&user).await?;
+
+        Ok(user)
+    }
+
+    /// Create a user the `before_user_created` hook lets through, announcing it to webhooks
+    async fn create_user(&self, user: &User) -> Result<User> {
+        self.hooks.before_user_created(user).await?;
+        let user = self.user_repo.create(user).await?;
+        self.hooks.dispatch(
+            EVENT_USER_CREATED,
+            serde_json::json!({ "user": UserProfile::from(user.clone()) }),
+        );
+
+        Ok(user)
+    }
+
+    /// Tell the account owner about a lockout; failures are logged, never surfaced
+    async fn notify_account_locked(&self, user: &User, locked_until: chrono::DateTime<Utc>) {
+        let (Some(email_service), Some(email)) = (&self.email_service, &user.email) else {
+            return;
+        };
+
+        if let Err(e) = email_service
+            .send_account_locked_email(
+                email,
+                display_name(user),
+                user_locale(user),
+                &locked_until.to_rfc3339(),
+            )
+            .await
+        {
+            tracing::warn!("Failed to send lockout email to user {}: {}", user.id, e);
+        }
+    }
+
+    /// Email a verification link to a new user; failures are logged, never surfaced
+    async fn send_verification_email(&self, user: &User) {
+        let (Some(email_service), Some(email)) = (&self.email_service, &user.email) else {
+            return;
+        };
+
+        let result = match self.create_email_verification_token(user.id).await {
+            Ok(token) => {
+                email_service
+                    .send_verification_email(
+                        email,
+                        display_name(user),
+                        user_locale(user),
+                        &email_service.link("/verify-email", &token),
+                    )
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to send verification email to user {}: {}", user.id, e);
+        }
+    }
+
+    /// Access token claims, with the user's roles and permissions.
+    ///
+    /// `role` is the service role when the user holds it, else the earliest assigned role;
+    /// `permissions` is the union across all roles. Role changes take effect on the next
+    /// token refresh.
+    async fn build_claims(&self, user: &User) -> Result<Claims> {
+        let roles = self.role_repo.list_for_user(user.id).await?;
+
+        let mut permissions: Vec<String> = roles
+            .iter()
+            .flat_map(|role| role.permissions.iter().cloned())
+            .collect();
+        permissions.sort();
+        permissions.dedup();
+
+        let mut claims = Claims::new(
+            user.id,
+            user.email.clone().unwrap_or_default(),
+            self.jwt_expiration,
+        )
+            .with_permissions(permissions);
+        if let Some(role) = claims_role(&roles) {
+            claims = claims.with_role(role.to_string());
+        }
+        claims.is_anonymous = user.is_anonymous;
+
+        Ok(claims)
+    }
+
+    /// Sign an access token for the user, adding claims from the `custom_access_token` hook
+    async fn sign_access_token(&self, user: &User, mut claims: Claims) -> Result<String> {
+        claims.custom = self.hooks.custom_access_token(user, &claims).await?;
+        self.jwt_manager.generate_access_token(claims)
+    }
+
+    /// Create a session for the user and issue tokens
+    async fn create_auth_response(
+        &self,
+        user: User,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+        audit: AuditEntry,
+    ) -> Result<AuthResponse> {
         let session = self
             .session_manager
             .create_session(user.id, user_agent, ip_address);
+        self.issue_session_tokens(user, session, audit).await
+    }
+
+    /// Store a new session and issue its tokens, recording `audit` for it
+    async fn issue_session_tokens(
+        &self,
+        user: User,
+        session: Session,
+        audit: AuditEntry,
+    ) -> Result<AuthResponse> {
         let created_session = self.session_repo.create(&session).await?;
+        if let Some(max) = self.max_sessions_per_user {
+            self.session_repo.delete_beyond_limit(user.id, max).await?;
+        }
+
+        self.issue_tokens(user, created_session, audit).await
+    }
+
+    /// Issue tokens for a stored session, recording `audit` for it
+    async fn issue_tokens(
+        &self,
+        user: User,
+        created_session: Session,
+        audit: AuditEntry,
+    ) -> Result<AuthResponse> {
+        let (token, refresh_token) = self
+            .session_manager
+            .issue_refresh_token(&created_session, None);
+        self.refresh_token_repo.create(&token).await?;
 
         // Generate tokens
-        let claims = Claims::new(user.id, user.email.clone(), self.jwt_expiration);
-        let access_token = self.jwt_manager.generate_access_token(claims)?;
+        let claims = self
+            .build_claims(&user)
+            .await?
+            .with_aal(created_session.aal.clone())
+            .with_session(created_session.id);
+        let access_token = self.sign_access_token(&user, claims).await?;
+
+        let context = AuditContext::client(
+            created_session.user_agent.clone(),
+            created_session.ip_address.clone(),
+        );
+        self.audit_log
+            .record(
+                audit
+                    .user(user.id)
+                    .email(user.email.as_deref())
+                    .session(created_session.id)
+                    .context(&context),
+            )
+            .await;
+        self.hooks.dispatch(
+            EVENT_USER_SIGNED_IN,
+            serde_json::json!({
+                "user_id": user.id,
+                "session_id": created_session.id,
+                "aal": created_session.aal,
+                "ip_address": created_session.ip_address,
+                "user_agent": created_session.user_agent,
+            }),
+        );
+
+        let mfa_required = self.mfa_required(&user, &created_session).await?;
+        Ok(AuthResponse {
+            user: user.into(),
+            access_token,
+            refresh_token,
+            expires_in: self.jwt_expiration,
+            mfa_required,
+        })
+    }
 
+    /// Whether the user has a second factor this session has not been verified with
+    async fn mfa_required(&self, user: &User, session: &Session) -> Result<bool> {
+        if session.aal == AAL2 {
+            return Ok(false);
+        }
+        self.mfa_factor_repo.has_verified(user.id).await
+    }
+
+    /// Refresh access token, rotating the refresh token.
+    ///
+    /// Presenting a token that was already rotated revokes the whole session, unless it
+    /// happens within the reuse interval (concurrent refreshes from the same client).
+    pub async fn refresh_token(
+        &self,
+        refresh_token: &str,
+        audit: &AuditContext,
+    ) -> Result<AuthResponse> {
+        let (session, user, refresh_token) = self
+            .rotate_refresh_token(refresh_token, None, audit)
+            .await?;
+
+        // Generate new access token
+        let claims = self
+            .build_claims(&user)
+            .await?
+            .with_aal(session.aal.clone())
+            .with_session(session.id);
+        let access_token = self.sign_access_token(&user, claims).await?;
+
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_TOKEN_REFRESH)
+                    .user(user.id)
+                    .factor(FACTOR_REFRESH_TOKEN)
+                    .session(session.id)
+                    .context(audit),
+            )
+            .await;
+        let mfa_required = self.mfa_required(&user, &session).await?;
         Ok(AuthResponse {
             user: user.into(),
             access_token,
-            refresh_token: created_session.refresh_token,
+            refresh_token,
             expires_in: self.jwt_expiration,
+            mfa_required,
         })
     }
 
-    /// Refresh access token
-    pub async fn refresh_token(&self, refresh_token: &str) -> Result<AuthResponse> {
+    /// Exchange a refresh token for its successor. Tokens only work for the party they were
+    /// issued to: ForgeBase itself (`client_id` None) or one OAuth client.
+    async fn rotate_refresh_token(
+        &self,
+        refresh_token: &str,
+        client_id: Option<Uuid>,
+        audit: &AuditContext,
+    ) -> Result<(Session, User, String)> {
+        let token = self
+            .refresh_token_repo
+            .find_by_hash(&hash_refresh_token(refresh_token))
+            .await?
+            .ok_or_else(|| ForgeBaseError::Auth("Invalid refresh token".to_string()))?;
+
         // Find session
         let session = self
             .session_repo
-            .find_by_refresh_token(refresh_token)
+            .find_by_id(token.session_id)
             .await?
-            .ok_or_else(|| ForgeBaseError::Auth("Invalid refresh token".to_string()))?;
+            .ok_or_else(|| ForgeBaseError::Auth("Session expired".to_string()))?;
 
         // Validate session
         if !self.session_manager.is_session_valid(&session) {
             return Err(ForgeBaseError::Auth("Session expired".to_string()));
         }
+        if session.client_id != client_id {
+            return Err(ForgeBaseError::Auth("Invalid refresh token".to_string()));
+        }
+
+        if token.revoked {
+            if !self.session_manager.is_within_reuse_interval(&token) {
+                // A rotated token came back: assume it leaked and end the whole family
+                tracing::warn!(
+                    "Refresh token reuse detected for session {}, revoking session",
+                    session.id
+                );
+                self.audit_log
+                    .record(
+                        AuditEntry::failure(ACTION_TOKEN_REFRESH, "reuse_detected")
+                            .user(session.user_id)
+                            .factor(FACTOR_REFRESH_TOKEN)
+                            .session(session.id)
+                            .context(audit),
+                    )
+                    .await;
+                self.session_repo.delete(session.id).await?;
+                return Err(ForgeBaseError::Auth("Invalid refresh token".to_string()));
+            }
+        } else {
+            // Losing this race means a concurrent request rotated it just now, which is allowed
+            self.refresh_token_repo.revoke(token.id).await?;
+        }
 
         // Find user
-        let user = self
+        let mut user = self
             .user_repo
             .find_by_id(session.user_id)
             .await?
             .ok_or_else(|| ForgeBaseError::Auth("User not found".to_string()))?;
 
-        // Verify user is active
-        if !user.is_active {
-            return Err(ForgeBaseError::Auth("Account is disabled".to_string()));
-        }
+        // Verify user is active, lifting an expired ban
+        self.ensure_active(&mut user).await?;
 
-        // Generate new access token
-        let claims = Claims::new(user.id, user.email.clone(), self.jwt_expiration);
-        let access_token = self.jwt_manager.generate_access_token(claims)?;
+        let (next_token, refresh_token) = self
+            .session_manager
+            .issue_refresh_token(&session, Some(token.token_hash));
+        self.refresh_token_repo.create(&next_token).await?;
+        self.session_repo.touch(session.id).await?;
 
-        Ok(AuthResponse {
-            user: user.into(),
-            access_token,
-            refresh_token: session.refresh_token,
-            expires_in: self.jwt_expiration,
-        })
+        Ok((session, user, refresh_token))
     }
 
     /// Sign out (invalidate session)
-    pub async fn sign_out(&self, refresh_token: &str) -> Result<()> {
-        if let Some(session) = self
-            .session_repo
-            .find_by_refresh_token(refresh_token)
+    pub async fn sign_out(&self, refresh_token: &str, audit: &AuditContext) -> Result<()> {
+        if let Some(token) = self
+            .refresh_token_repo
+            .find_by_hash(&hash_refresh_token(refresh_token))
             .await?
         {
-            self.session_repo.delete(session.id).await?;
+            if let Some(session) = self.session_repo.find_by_id(token.session_id).await? {
+                self.audit_log
+                    .record(
+                        AuditEntry::success(ACTION_SIGN_OUT)
+                            .user(session.user_id)
+                            .session(session.id)
+                            .context(audit),
+                    )
+                    .await;
+            }
+            self.session_repo.delete(token.session_id).await?;
         }
         Ok(())
     }
 
-    /// Verify access token and get claims
-    pub fn verify_access_token(&self, token: &str) -> Result<Claims> {
-        self.jwt_manager.verify_token(token)
+    /// Whether the session behind an access token still exists
+    pub async fn is_session_active(&self, session_id: Uuid) -> Result<bool> {
+        self.session_repo.is_active(session_id).await
     }
 
-    /// Get user by ID
-    pub async fn get_user(&self, user_id: Uuid) -> Result<UserProfile> {
-        let user = self
-            .user_repo
-            .find_by_id(user_id)
-            .await?
-            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
-
-        Ok(user.into())
+    /// The signed-in user's active sessions, marking the one making the request
+    pub async fn list_sessions(&self, claims: &Claims) -> Result<Vec<SessionInfo>> {
+        let user_id = claims.user_id()?;
+        let current = claims.session_id()?;
+        let sessions = self.session_repo.list_for_user(user_id).await?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|session| SessionInfo {
+                device: parse_user_agent(session.user_agent.as_deref().unwrap_or_default()),
+                current: Some(session.id) == current,
+                client_id: session.client_id,
+                id: session.id,
+                user_agent: session.user_agent,
+                ip_address: session.ip_address,
+                aal: session.aal,
+                created_at: session.created_at,
+                last_active_at: session.last_active_at,
+                expires_at: session.expires_at,
+            })
+            .collect())
     }
 
-    /// Update user profile
-    pub async fn update_profile(
+    /// Revoke one of the user's sessions
+    pub async fn revoke_session(
         &self,
         user_id: Uuid,
-        request: UpdateProfileRequest,
-    ) -> Result<UserProfile> {
-        let mut user = self
-            .user_repo
-            .find_by_id(user_id)
-            .await?
-            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
-
-        if let Some(full_name) = request.full_name {
-            user.full_name = Some(full_name);
+        session_id: Uuid,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        if !self.session_repo.delete_for_user(session_id, user_id).await? {
+            return Err(ForgeBaseError::NotFound("Session not found".to_string()));
         }
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_SESSION_REVOKE)
+                    .user(user_id)
+                    .session(session_id)
+                    .context(audit),
+            )
+            .await;
+        Ok(())
+    }
 
-        if let Some(avatar_url) = request.avatar_url {
+    /// Revoke every session of the user except the one making the request
+    pub async fn revoke_other_sessions(&self, claims: &Claims, audit: &AuditContext) -> Result<u64> {
+        let user_id = claims.user_id()?;
+        let current = claims.session_id()?.ok_or_else(|| {
+            ForgeBaseError::Validation("Token is not bound to a session".to_string())
+        })?;
+        let revoked = self.session_repo.delete_others(user_id, current).await?;
+
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_SESSION_REVOKE)
+                    .user(user_id)
+                    .context(audit)
+                    .metadata(serde_json::json!({ "kept_session_id": current, "revoked": revoked })),
+            )
+            .await;
+        Ok(revoked)
+    }
+
+    /// Text a sign-in code to a phone number
+    pub async fn send_phone_otp(&self, phone: &str) -> Result<()> {
+        let phone_auth = self.phone_auth()?;
+        let phone = phone_auth.normalize(phone)?;
+        phone_auth.send_code(&phone, OTP_PURPOSE_SIGN_IN, None).await
+    }
+
+    /// Sign in with a texted code, creating a phone-only account for new numbers
+    pub async fn verify_phone_otp(
+        &self,
+        phone: &str,
+        code: &str,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> Result<AuthResponse> {
+        let phone_auth = self.phone_auth()?;
+        let phone = phone_auth.normalize(phone)?;
+        if let Err(e) = phone_auth
+            .verify_code(&phone, OTP_PURPOSE_SIGN_IN, None, code)
+            .await
+        {
+            let context = AuditContext::client(user_agent, ip_address);
+            self.audit_log
+                .record(
+                    AuditEntry::failure(ACTION_SIGN_IN, e.to_string())
+                        .factor(FACTOR_PHONE_OTP)
+                        .context(&context)
+                        .metadata(serde_json::json!({ "phone": phone })),
+                )
+                .await;
+            return Err(e);
+        }
+
+        let user = match self.user_repo.find_by_phone(&phone).await? {
+            Some(mut user) => {
+                self.ensure_active(&mut user).await?;
+                user.phone_verified = true;
+                user.last_sign_in_at = Some(Utc::now());
+                user.updated_at = Utc::now();
+                self.user_repo.update(&user).await?
+            }
+            None => {
+                let user = User {
+                    id: Uuid::new_v4(),
+                    email: None,
+                    email_verified: false,
+                    phone: Some(phone),
+                    phone_verified: true,
+                    password_hash: None,
+                    full_name: None,
+                    avatar_url: None,
+                    metadata: serde_json::json!({}),
+                    is_anonymous: false,
+                    is_active: true,
+                    last_sign_in_at: Some(Utc::now()),
+                    banned_until: None,
+                    deleted_at: None,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                };
+                self.create_user(&user).await?
+            }
+        };
+
+        let audit = AuditEntry::success(ACTION_SIGN_IN).factor(FACTOR_PHONE_OTP);
+        self.create_auth_response(user, user_agent, ip_address, audit)
+            .await
+    }
+
+    /// Text a code confirming a new phone number for a signed-in user
+    pub async fn request_phone_change(&self, user_id: Uuid, phone: &str) -> Result<()> {
+        let phone_auth = self.phone_auth()?;
+        let phone = phone_auth.normalize(phone)?;
+        self.ensure_phone_available(&phone, user_id).await?;
+        phone_auth
+            .send_code(&phone, OTP_PURPOSE_PHONE_CHANGE, Some(user_id))
+            .await
+    }
+
+    /// Set the user's phone number once the texted code is confirmed
+    pub async fn verify_phone_change(&self, user_id: Uuid, phone: &str, code: &str) -> Result<User> {
+        let phone_auth = self.phone_auth()?;
+        let phone = phone_auth.normalize(phone)?;
+        phone_auth
+            .verify_code(&phone, OTP_PURPOSE_PHONE_CHANGE, Some(user_id), code)
+            .await?;
+        self.ensure_phone_available(&phone, user_id).await?;
+
+        let mut user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
+        user.phone = Some(phone);
+        user.phone_verified = true;
+        user.is_anonymous = false;
+        user.updated_at = Utc::now();
+
+        self.user_repo.update(&user).await
+    }
+
+    async fn ensure_phone_available(&self, phone: &str, user_id: Uuid) -> Result<()> {
+        match self.user_repo.find_by_phone(phone).await? {
+            Some(owner) if owner.id != user_id => Err(ForgeBaseError::Conflict(
+                "Phone number is already in use".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Enroll a phone number as an SMS second factor and text it a code.
+    ///
+    /// Users who already have a verified factor must be at aal2.
+    pub async fn enroll_sms_factor(
+        &self,
+        claims: &Claims,
+        phone: &str,
+        audit: &AuditContext,
+    ) -> Result<MfaFactor> {
+        let user_id = claims.user_id()?;
+        ensure_can_add_factor(claims, self.mfa_factor_repo.has_verified(user_id).await?)?;
+        let phone_auth = self.phone_auth()?;
+        let phone = phone_auth.normalize(phone)?;
+        let factor = self.mfa_factor_repo.create_sms(user_id, &phone).await?;
+        phone_auth
+            .send_code(&phone, OTP_PURPOSE_MFA, Some(user_id))
+            .await?;
+
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_MFA_ENROLL)
+                    .user(user_id)
+                    .factor(FACTOR_SMS)
+                    .context(audit)
+                    .metadata(serde_json::json!({ "factor_id": factor.id })),
+            )
+            .await;
+
+        Ok(factor)
+    }
+
+    /// Text a code for an enrolled SMS factor
+    pub async fn challenge_sms_factor(&self, user_id: Uuid, factor_id: Uuid) -> Result<()> {
+        let phone = self.sms_factor_phone(user_id, factor_id).await?.1;
+        self.phone_auth()?
+            .send_code(&phone, OTP_PURPOSE_MFA, Some(user_id))
+            .await
+    }
+
+    /// Verify an SMS factor code, raising the caller's session to aal2.
+    ///
+    /// The first successful verification also completes enrollment, which like enrolling takes
+    /// an aal2 session once the user has another verified factor.
+    pub async fn verify_sms_factor(
+        &self,
+        claims: &Claims,
+        factor_id: Uuid,
+        code: &str,
+        audit: &AuditContext,
+    ) -> Result<AuthResponse> {
+        let user_id = claims.user_id()?;
+        let session_id = claims
+            .session_id()?
+            .ok_or_else(|| ForgeBaseError::Auth("Token is not tied to a session".to_string()))?;
+        let (factor, phone) = self.sms_factor_phone(user_id, factor_id).await?;
+        if !factor.verified {
+            ensure_can_add_factor(claims, self.mfa_factor_repo.has_verified(user_id).await?)?;
+        }
+        if let Err(e) = self
+            .phone_auth()?
+            .verify_code(&phone, OTP_PURPOSE_MFA, Some(user_id), code)
+            .await
+        {
+            self.audit_log
+                .record(
+                    AuditEntry::failure(ACTION_MFA_VERIFY, e.to_string())
+                        .user(user_id)
+                        .factor(FACTOR_SMS)
+                        .context(audit)
+                        .metadata(serde_json::json!({ "factor_id": factor.id })),
+                )
+                .await;
+            return Err(e);
+        }
+        if !factor.verified {
+            self.mfa_factor_repo.mark_verified(factor.id).await?;
+        }
+
+        let mut user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::Auth("User not found".to_string()))?;
+        self.ensure_active(&mut user).await?;
+
+        let session = self
+            .session_repo
+            .set_aal(session_id, user.id, AAL2)
+            .await?
+            .ok_or_else(|| ForgeBaseError::Auth("Session expired".to_string()))?;
+        let audit = AuditEntry::success(ACTION_MFA_VERIFY)
+            .factor(FACTOR_SMS)
+            .metadata(serde_json::json!({ "factor_id": factor.id }));
+        self.issue_tokens(user, session, audit).await
+    }
+
+    /// List the user's second factors
+    pub async fn list_mfa_factors(&self, user_id: Uuid) -> Result<Vec<MfaFactor>> {
+        self.mfa_factor_repo.list_for_user(user_id).await
+    }
+
+    /// Remove a second factor. Verified factors can only be removed from an aal2 session.
+    pub async fn delete_mfa_factor(
+        &self,
+        claims: &Claims,
+        factor_id: Uuid,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        let user_id = claims.user_id()?;
+        let factor = self
+            .mfa_factor_repo
+            .find_for_user(factor_id, user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("Factor not found".to_string()))?;
+        if factor.verified && claims.aal.as_deref() != Some(AAL2) {
+            return Err(ForgeBaseError::Authorization(
+                "Verify a second factor before removing one".to_string(),
+            ));
+        }
+
+        self.mfa_factor_repo.delete(factor.id).await?;
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_MFA_FACTOR_DELETE)
+                    .user(user_id)
+                    .factor(FACTOR_SMS)
+                    .context(audit)
+                    .metadata(serde_json::json!({ "factor_id": factor.id })),
+            )
+            .await;
+        Ok(())
+    }
+
+    async fn sms_factor_phone(&self, user_id: Uuid, factor_id: Uuid) -> Result<(MfaFactor, String)> {
+        let factor = self
+            .mfa_factor_repo
+            .find_for_user(factor_id, user_id)
+            .await?
+            .filter(|factor| factor.factor_type == FACTOR_TYPE_SMS)
+            .ok_or_else(|| ForgeBaseError::NotFound("Factor not found".to_string()))?;
+        let phone = factor
+            .phone
+            .clone()
+            .ok_or_else(|| ForgeBaseError::Internal("SMS factor has no phone number".to_string()))?;
+
+        Ok((factor, phone))
+    }
+
+    /// Load signing keys from the key store, creating the first key if needed.
+    ///
+    /// Also call this periodically (or after rotating on another instance) to pick up changes.
+    pub async fn load_signing_keys(&self) -> Result<()> {
+        let Some(algorithm) = self.signing_algorithm else {
+            return Ok(());
+        };
+
+        let keys = self.key_store.ensure_current(algorithm).await?;
+        self.jwt_manager.set_signing_keys(&keys)
+    }
+
+    /// List signing keys
+    pub async fn list_signing_keys(&self) -> Result<Vec<SigningKey>> {
+        self.key_store.list().await
+    }
+
+    /// Stage the next signing key so it is published in the JWKS before it signs tokens
+    pub async fn stage_signing_key(&self) -> Result<SigningKey> {
+        let key = self.key_store.stage_next(self.require_signing_algorithm()?).await?;
+        self.load_signing_keys().await?;
+        Ok(key)
+    }
+
+    /// Promote the next signing key to current
+    pub async fn rotate_signing_keys(&self) -> Result<Vec<SigningKey>> {
+        let keys = self.key_store.rotate(self.require_signing_algorithm()?).await?;
+        self.jwt_manager.set_signing_keys(&keys)?;
+        Ok(keys)
+    }
+
+    /// Revoke a next or previous signing key
+    pub async fn revoke_signing_key(&self, kid: &str) -> Result<()> {
+        self.key_store.revoke(kid).await?;
+        self.load_signing_keys().await
+    }
+
+    fn require_signing_algorithm(&self) -> Result<Algorithm> {
+        self.signing_algorithm.ok_or_else(|| {
+            ForgeBaseError::Config("Asymmetric JWT signing is not configured".to_string())
+        })
+    }
+
+    /// List roles
+    pub async fn list_roles(&self) -> Result<Vec<Role>> {
+        self.role_repo.list().await
+    }
+
+    /// Create a role
+    pub async fn create_role(&self, request: CreateRoleRequest) -> Result<Role> {
+        for permission in &request.permissions {
+            validate_permission(permission)?;
+        }
+        ensure_not_reserved(&request.name)?;
+        if self.role_repo.find_by_name(&request.name).await?.is_some() {
+            return Err(ForgeBaseError::Conflict(
+                "Role with this name already exists".to_string(),
+            ));
+        }
+
+        let role = Role {
+            id: Uuid::new_v4(),
+            name: request.name,
+            description: request.description,
+            permissions: request.permissions,
+            created_at: Utc::now(),
+        };
+
+        self.role_repo.create(&role).await
+    }
+
+    /// Update a role's description or permissions
+    pub async fn update_role(&self, role_id: Uuid, request: UpdateRoleRequest) -> Result<Role> {
+        let mut role = self
+            .role_repo
+            .find_by_id(role_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("Role not found".to_string()))?;
+        ensure_not_reserved(&role.name)?;
+
+        if let Some(description) = request.description {
+            role.description = Some(description);
+        }
+        if let Some(permissions) = request.permissions {
+            for permission in &permissions {
+                validate_permission(permission)?;
+            }
+            role.permissions = permissions;
+        }
+
+        self.role_repo.update(&role).await
+    }
+
+    /// Delete a role
+    pub async fn delete_role(&self, role_id: Uuid) -> Result<()> {
+        if let Some(role) = self.role_repo.find_by_id(role_id).await? {
+            ensure_not_reserved(&role.name)?;
+        }
+        self.role_repo.delete(role_id).await
+    }
+
+    /// Roles assigned to a user
+    pub async fn list_user_roles(&self, user_id: Uuid) -> Result<Vec<Role>> {
+        self.role_repo.list_for_user(user_id).await
+    }
+
+    /// Assign a role to a user; only a service-role `caller` may hand out the service role
+    pub async fn assign_role(
+        &self,
+        user_id: Uuid,
+        role_id: Uuid,
+        caller: &Claims,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        self.user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
+        let role = self
+            .role_repo
+            .find_by_id(role_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("Role not found".to_string()))?;
+        ensure_can_grant(&role, caller)?;
+
+        self.role_repo.assign(user_id, role_id).await?;
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_ROLE_ASSIGN)
+                    .user(user_id)
+                    .context(audit)
+                    .metadata(serde_json::json!({ "role_id": role_id })),
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Remove a role from a user; only a service-role `caller` may take away the service role
+    pub async fn unassign_role(
+        &self,
+        user_id: Uuid,
+        role_id: Uuid,
+        caller: &Claims,
+        audit: &AuditContext,
+    ) -> Result<()> {
+        if let Some(role) = self.role_repo.find_by_id(role_id).await? {
+            ensure_can_grant(&role, caller)?;
+        }
+        self.role_repo.unassign(user_id, role_id).await?;
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_ROLE_UNASSIGN)
+                    .user(user_id)
+                    .context(audit)
+                    .metadata(serde_json::json!({ "role_id": role_id })),
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Create an API key for the caller. Scopes may not exceed the caller's own permissions.
+    pub async fn create_api_key(
+        &self,
+        claims: &Claims,
+        request: CreateApiKeyRequest,
+        audit: &AuditContext,
+    ) -> Result<ApiKeyResponse> {
+        let user_id = claims.user_id()?;
+        for scope in &request.scopes {
+            validate_permission(scope)?;
+            claims.require_permission(scope)?;
+        }
+
+        let expires_at = match request.expires_in_days {
+            Some(days) if days <= 0 => {
+                return Err(ForgeBaseError::Validation(
+                    "expires_in_days must be positive".to_string(),
+                ))
+            }
+            Some(days) => Some(Utc::now() + chrono::Duration::days(days)),
+            None => None,
+        };
+
+        let generated = generate_api_key();
+        let api_key = ApiKey {
+            id: Uuid::new_v4(),
+            user_id,
+            name: request.name,
+            key_hash: generated.key_hash,
+            prefix: generated.prefix,
+            scopes: request.scopes,
+            last_used_at: None,
+            expires_at,
+            created_at: Utc::now(),
+        };
+        let created = self.api_key_repo.create(&api_key).await?;
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_API_KEY_CREATE)
+                    .user(user_id)
+                    .context(audit)
+                    .metadata(serde_json::json!({
+                        "api_key_id": created.id,
+                        "prefix": created.prefix,
+                        "scopes": created.scopes,
+                    })),
+            )
+            .await;
+
+        Ok(ApiKeyResponse {
+            id: created.id,
+            name: created.name,
+            prefix: created.prefix,
+            key: generated.key,
+            scopes: created.scopes,
+            expires_at: created.expires_at,
+            created_at: created.created_at,
+        })
+    }
+
+    /// List a user's API keys (without secrets)
+    pub async fn list_api_keys(&self, user_id: Uuid) -> Result<Vec<ApiKey>> {
+        self.api_key_repo.list_for_user(user_id).await
+    }
+
+    /// Revoke one of a user's API keys
+    pub async fn revoke_api_key(&self, user_id: Uuid, key_id: Uuid, audit: &AuditContext) -> Result<()> {
+        if !self.api_key_repo.delete_for_user(key_id, user_id).await? {
+            return Err(ForgeBaseError::NotFound("API key not found".to_string()));
+        }
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_API_KEY_REVOKE)
+                    .user(user_id)
+                    .context(audit)
+                    .metadata(serde_json::json!({ "api_key_id": key_id })),
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Authenticate an `fb_` API key, returning claims limited to the key's scopes
+    pub async fn authenticate_api_key(&self, key: &str) -> Result<Claims> {
+        let invalid = || ForgeBaseError::Auth("Invalid API key".to_string());
+
+        let prefix = parse_prefix(key).ok_or_else(invalid)?;
+        let api_key = self
+            .api_key_repo
+            .find_by_prefix(prefix)
+            .await?
+            .ok_or_else(invalid)?;
+
+        if !constant_time_eq::constant_time_eq(
+            hash_api_key(key).as_bytes(),
+            api_key.key_hash.as_bytes(),
+        ) {
+            return Err(invalid());
+        }
+        if api_key.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(ForgeBaseError::Auth("API key has expired".to_string()));
+        }
+
+        let mut user = self
+            .user_repo
+            .find_by_id(api_key.user_id)
+            .await?
+            .ok_or_else(invalid)?;
+        self.ensure_active(&mut user).await?;
+
+        // Usage tracking must not slow down or fail the request
+        let repo = ApiKeyRepository::new(self.pool.clone());
+        tokio::spawn(async move {
+            if let Err(e) = repo.touch_last_used(api_key.id).await {
+                tracing::warn!("Failed to record API key usage: {}", e);
+            }
+        });
+
+        Ok(
+            Claims::new(user.id, user.email.unwrap_or_default(), self.jwt_expiration)
+                .with_permissions(api_key.scopes),
+        )
+    }
+
+    /// Verify access token and get claims
+    pub fn verify_access_token(&self, token: &str) -> Result<Claims> {
+        self.jwt_manager.verify_token(token)
+    }
+
+    /// Get user by ID
+    pub async fn get_user(&self, user_id: Uuid) -> Result<UserProfile> {
+        let user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
+
+        Ok(user.into())
+    }
+
+    /// Update user profile
+    pub async fn update_profile(
+        &self,
+        user_id: Uuid,
+        request: UpdateProfileRequest,
+    ) -> Result<UserProfile> {
+        let mut user = self
+            .user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
+
+        if let Some(full_name) = request.full_name {
+            user.full_name = Some(full_name);
+        }
+
+        if let Some(avatar_url) = request.avatar_url {
             user.avatar_url = Some(avatar_url);
         }
 
@@ -246,6 +1650,7 @@ impl AuthService {
         &self,
         user_id: Uuid,
         request: ChangePasswordRequest,
+        audit: &AuditContext,
     ) -> Result<()> {
         let mut user = self
             .user_repo
@@ -260,22 +1665,42 @@ impl AuthService {
             .ok_or_else(|| ForgeBaseError::Auth("Password not set".to_string()))?;
 
         if !verify_password(&request.current_password, current_hash)? {
+            self.audit_log
+                .record(
+                    AuditEntry::failure(ACTION_PASSWORD_CHANGE, "invalid_current_password")
+                        .user(user_id)
+                        .factor(FACTOR_PASSWORD)
+                        .context(audit),
+                )
+                .await;
             return Err(ForgeBaseError::Auth("Invalid current password".to_string()));
         }
 
-        // Validate new password
-        validate_password_strength(&request.new_password)?;
-
-        // Hash new password
-        let new_hash = hash_password(&request.new_password)?;
+        // Validate and hash new password
+        let new_hash = self
+            .hash_new_password(&request.new_password, user.email.as_deref(), Some(&user))
+            .await?;
         user.password_hash = Some(new_hash);
         user.updated_at = Utc::now();
 
         self.user_repo.update(&user).await?;
+        self.remember_password(&user).await?;
 
         // Invalidate all sessions
         self.session_repo.delete_all_for_user(user_id).await?;
 
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_PASSWORD_CHANGE)
+                    .user(user_id)
+                    .factor(FACTOR_PASSWORD)
+                    .context(audit),
+            )
+            .await;
+        self.hooks.dispatch(
+            EVENT_PASSWORD_CHANGED,
+            serde_json::json!({ "user_id": user.id, "method": "change" }),
+        );
         Ok(())
     }
 
@@ -305,12 +1730,17 @@ impl AuthService {
     }
 
     /// Verify email with token
-    pub async fn verify_email(&self, token: &str) -> Result<()> {
-        let verification_token = self
+    pub async fn verify_email(&self, token: &str, audit: &AuditContext) -> Result<()> {
+        let Some(verification_token) = self
             .token_repo
             .find_valid_token(token, "email_verification")
             .await?
-            .ok_or_else(|| ForgeBaseError::Auth("Invalid or expired token".to_string()))?;
+        else {
+            self.audit_log
+                .record(AuditEntry::failure(ACTION_EMAIL_VERIFY, "invalid_token").context(audit))
+                .await;
+            return Err(ForgeBaseError::Auth("Invalid or expired token".to_string()));
+        };
 
         let mut user = self
             .user_repo
@@ -324,19 +1754,32 @@ impl AuthService {
         self.user_repo.update(&user).await?;
         self.token_repo.delete(verification_token.id).await?;
 
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_EMAIL_VERIFY)
+                    .user(user.id)
+                    .email(user.email.as_deref())
+                    .context(audit),
+            )
+            .await;
         Ok(())
     }
 
     /// Request password reset
-    pub async fn request_password_reset(&self, email: &str) -> Result<String> {
+    pub async fn request_password_reset(&self, email: &str, audit: &AuditContext) -> Result<String> {
         use chrono::Duration;
         use rand::Rng;
 
-        let user = self
-            .user_repo
-            .find_by_email(email)
-            .await?
-            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
+        let Some(user) = self.user_repo.find_by_email(email).await? else {
+            self.audit_log
+                .record(
+                    AuditEntry::failure(ACTION_PASSWORD_RESET_REQUEST, "unknown_email")
+                        .email(Some(email))
+                        .context(audit),
+                )
+                .await;
+            return Err(ForgeBaseError::NotFound("User not found".to_string()));
+        };
 
         let token: String = rand::thread_rng()
             .sample_iter(&rand::distributions::Alphanumeric)
@@ -354,17 +1797,41 @@ impl AuthService {
         };
 
         self.token_repo.create(&verification_token).await?;
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_PASSWORD_RESET_REQUEST)
+                    .user(user.id)
+                    .email(Some(email))
+                    .context(audit),
+            )
+            .await;
+
+        if let Some(email_service) = &self.email_service {
+            email_service
+                .send_password_reset_email(
+                    email,
+                    display_name(&user),
+                    user_locale(&user),
+                    &email_service.link("/reset-password", &token),
+                )
+                .await?;
+        }
 
         Ok(token)
     }
 
     /// Reset password with token
-    pub async fn reset_password(&self, request: PasswordUpdateRequest) -> Result<()> {
-        let verification_token = self
+    pub async fn reset_password(&self, request: PasswordUpdateRequest, audit: &AuditContext) -> Result<()> {
+        let Some(verification_token) = self
             .token_repo
             .find_valid_token(&request.token, "password_reset")
             .await?
-            .ok_or_else(|| ForgeBaseError::Auth("Invalid or expired token".to_string()))?;
+        else {
+            self.audit_log
+                .record(AuditEntry::failure(ACTION_PASSWORD_RESET, "invalid_token").context(audit))
+                .await;
+            return Err(ForgeBaseError::Auth("Invalid or expired token".to_string()));
+        };
 
         let mut user = self
             .user_repo
@@ -372,20 +1839,1772 @@ impl AuthService {
             .await?
             .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
 
-        // Validate new password
-        validate_password_strength(&request.new_password)?;
-
-        // Hash new password
-        let new_hash = hash_password(&request.new_password)?;
+        // Validate and hash new password
+        let new_hash = self
+            .hash_new_password(&request.new_password, user.email.as_deref(), Some(&user))
+            .await?;
         user.password_hash = Some(new_hash);
         user.updated_at = Utc::now();
 
         self.user_repo.update(&user).await?;
+        self.remember_password(&user).await?;
         self.token_repo.delete(verification_token.id).await?;
 
         // Invalidate all sessions
         self.session_repo.delete_all_for_user(user.id).await?;
 
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_PASSWORD_RESET)
+                    .user(user.id)
+                    .email(user.email.as_deref())
+                    .context(audit),
+            )
+            .await;
+        self.hooks.dispatch(
+            EVENT_PASSWORD_CHANGED,
+            serde_json::json!({ "user_id": user.id, "method": "reset" }),
+        );
+        Ok(())
+    }
+
+    /// Check a new password against the policy and the user's recent passwords, and hash it
+    async fn hash_new_password(
+        &self,
+        password: &str,
+        email: Option<&str>,
+        user: Option<&User>,
+    ) -> Result<String> {
+        self.password_policy.validate(password, email).await?;
+
+        let history_size = self.password_policy.history_size();
+        if let Some(user) = user.filter(|_| history_size > 0) {
+            let mut previous = self.password_history_repo.recent(user.id, history_size).await?;
+            previous.extend(user.password_hash.clone());
+            for hash in previous {
+                if verify_password(password, &hash)? {
+                    return Err(ForgeBaseError::Validation(format!(
+                        "Password must differ from your last {} passwords",
+                        history_size
+                    )));
+                }
+            }
+        }
+
+        self.password_policy.hash(password)
+    }
+
+    /// Add the user's current password to their history
+    async fn remember_password(&self, user: &User) -> Result<()> {
+        let history_size = self.password_policy.history_size();
+        match &user.password_hash {
+            Some(hash) if history_size > 0 => {
+                self.password_history_repo.add(user.id, hash, history_size).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject disabled and deleted accounts, lifting bans that have run out
+    async fn ensure_active(&self, user: &mut User) -> Result<()> {
+        if user.is_active {
+            return Ok(());
+        }
+
+        let ban_expired = user.deleted_at.is_none()
+            && user.banned_until.is_some_and(|until| until <= Utc::now());
+        if !ban_expired {
+            return Err(ForgeBaseError::Auth("Account is disabled".to_string()));
+        }
+
+        *user = self
+            .user_repo
+            .unban(user.id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::Auth("Account is disabled".to_string()))?;
         Ok(())
     }
+
+    async fn find_user_for_admin(&self, user_id: Uuid) -> Result<User> {
+        self.user_repo
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))
+    }
+
+    /// List users with search, filters and pagination
+    pub async fn list_users(&self, query: &ListUsersQuery) -> Result<PaginatedResponse<User>> {
+        let params = pagination(query);
+        let total = self.user_repo.count(query).await?;
+        let users = self
+            .user_repo
+            .list(query, i64::from(params.limit()), i64::from(params.offset()))
+            .await?;
+
+        Ok(PaginatedResponse::new(
+            users,
+            params.page,
+            params.per_page,
+            total as u64,
+        ))
+    }
+
+    /// Get any user, including banned and soft-deleted ones
+    pub async fn get_user_for_admin(&self, user_id: Uuid) -> Result<User> {
+        self.find_user_for_admin(user_id).await
+    }
+
+    /// Create a user on their behalf; the email counts as verified unless told otherwise
+    pub async fn admin_create_user(
+        &self,
+        request: AdminCreateUserRequest,
+        audit: &AuditContext,
+    ) -> Result<User> {
+        let phone = match request.phone.as_deref() {
+            Some(phone) => Some(match &self.phone_auth {
+                Some(phone_auth) => phone_auth.normalize(phone)?,
+                None => normalize_phone(phone, None)?,
+            }),
+            None => None,
+        };
+        if request.email.is_none() && phone.is_none() {
+            return Err(ForgeBaseError::Validation(
+                "An email address or phone number is required".to_string(),
+            ));
+        }
+
+        if let Some(email) = &request.email {
+            if self.user_repo.find_by_email(email).await?.is_some() {
+                return Err(ForgeBaseError::Conflict(
+                    "User with this email already exists".to_string(),
+                ));
+            }
+        }
+        let id = Uuid::new_v4();
+        if let Some(phone) = &phone {
+            self.ensure_phone_available(phone, id).await?;
+        }
+
+        let password_hash = match &request.password {
+            Some(password) => Some(
+                self.hash_new_password(password, request.email.as_deref(), None)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        let user = User {
+            id,
+            email_verified: request.email.is_some() && request.email_verified.unwrap_or(true),
+            email: request.email,
+            phone_verified: phone.is_some(),
+            phone,
+            password_hash,
+            full_name: request.full_name,
+            avatar_url: None,
+            metadata: request.metadata.unwrap_or(serde_json::json!({})),
+            is_anonymous: false,
+            is_active: true,
+            last_sign_in_at: None,
+            banned_until: None,
+            deleted_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let user = self.user_repo.create(&user).await?;
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_USER_CREATE)
+                    .user(user.id)
+                    .email(user.email.as_deref())
+                    .context(audit),
+            )
+            .await;
+        self.hooks.dispatch(
+            EVENT_USER_CREATED,
+            serde_json::json!({ "user": UserProfile::from(user.clone()) }),
+        );
+
+        Ok(user)
+    }
+
+    /// Ban a user until a time (indefinitely when `None`), signing them out everywhere
+    pub async fn ban_user(
+        &self,
+        user_id: Uuid,
+        banned_until: Option<DateTime<Utc>>,
+        audit: &AuditContext,
+    ) -> Result<User> {
+        if banned_until.is_some_and(|until| until <= Utc::now()) {
+            return Err(ForgeBaseError::Validation(
+                "Ban end must be in the future".to_string(),
+            ));
+        }
+
+        let user = self.find_user_for_admin(user_id).await?;
+        if user.deleted_at.is_some() {
+            return Err(ForgeBaseError::NotFound("User not found".to_string()));
+        }
+
+        let user = self.user_repo.ban(user.id, banned_until).await?;
+        self.session_repo.delete_all_for_user(user.id).await?;
+        tracing::info!("Banned user {} until {:?}", user.id, banned_until);
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_USER_BAN)
+                    .user(user.id)
+                    .context(audit)
+                    .metadata(serde_json::json!({ "banned_until": banned_until })),
+            )
+            .await;
+
+        Ok(user)
+    }
+
+    /// Lift a ban
+    pub async fn unban_user(&self, user_id: Uuid, audit: &AuditContext) -> Result<User> {
+        let user = self
+            .user_repo
+            .unban(user_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("User not found".to_string()))?;
+
+        self.audit_log
+            .record(AuditEntry::success(ACTION_USER_UNBAN).user(user.id).context(audit))
+            .await;
+        Ok(user)
+    }
+
+    /// Invalidate the user's password and sessions and issue a reset token (also emailed)
+    pub async fn force_password_reset(&self, user_id: Uuid, audit: &AuditContext) -> Result<String> {
+        let mut user = self.find_user_for_admin(user_id).await?;
+        let email = user.email.clone().ok_or_else(|| {
+            ForgeBaseError::Validation("User has no email address".to_string())
+        })?;
+
+        user.password_hash = None;
+        user.updated_at = Utc::now();
+        self.user_repo.update(&user).await?;
+        self.session_repo.delete_all_for_user(user.id).await?;
+
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_FORCE_PASSWORD_RESET)
+                    .user(user.id)
+                    .email(Some(&email))
+                    .context(audit),
+            )
+            .await;
+        self.request_password_reset(&email, audit).await
+    }
+
+    /// Issue a short-lived access token for acting as a user.
+    ///
+    /// The token names the caller in its `act` claim and has no session or refresh token.
+    pub async fn impersonate_user(
+        &self,
+        actor: &Claims,
+        user_id: Uuid,
+        audit: &AuditContext,
+    ) -> Result<ImpersonationResponse> {
+        let user = self.find_user_for_admin(user_id).await?;
+        if user.deleted_at.is_some() {
+            return Err(ForgeBaseError::NotFound("User not found".to_string()));
+        }
+
+        let expires_in = IMPERSONATION_TOKEN_SECONDS.min(self.jwt_expiration);
+        let mut claims = self.build_claims(&user).await?.with_actor(actor.sub.clone());
+        claims.exp = claims.iat + expires_in;
+        let access_token = self.sign_access_token(&user, claims).await?;
+
+        tracing::warn!(
+            "Impersonation token issued for user {} by {} (expires in {}s)",
+            user.id,
+            actor.sub,
+            expires_in
+        );
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_USER_IMPERSONATE)
+                    .user(user.id)
+                    .context(audit)
+                    .metadata(serde_json::json!({ "expires_in": expires_in })),
+            )
+            .await;
+
+        Ok(ImpersonationResponse {
+            user: user.into(),
+            access_token,
+            expires_in,
+        })
+    }
+
+    /// Delete a user and clean up their data in other services.
+    ///
+    /// Soft deletion keeps the row (disabled, with `deleted_at` set) and only takes their
+    /// data offline; hard deletion removes everything. Fails while the user is the only owner
+    /// of an organization that has other members; organizations with no other members are
+    /// deleted with them.
+    pub async fn admin_delete_user(&self, user_id: Uuid, soft: bool, audit: &AuditContext) -> Result<()> {
+        let user = self.find_user_for_admin(user_id).await?;
+
+        let owned = self.organization_repo.solely_owned_by(user.id).await?;
+        if let Some((organization, _)) = owned.iter().find(|(_, members)| *members > 1) {
+            return Err(ForgeBaseError::Conflict(format!(
+                "User is the only owner of organization {}; transfer ownership first",
+                organization.slug
+            )));
+        }
+
+        for cleanup in &self.user_data_cleanups {
+            cleanup.cleanup_user_data(user.id, !soft).await?;
+        }
+
+        if soft {
+            self.user_repo.soft_delete(user.id).await?;
+            self.session_repo.delete_all_for_user(user.id).await?;
+        } else {
+            for (organization, _) in &owned {
+                self.organization_repo.delete(organization.id).await?;
+            }
+            self.user_repo.delete(user.id).await?;
+        }
+        tracing::info!("Deleted user {} (soft: {})", user.id, soft);
+        self.audit_log
+            .record(
+                AuditEntry::success(ACTION_USER_DELETE)
+                    .user(user.id)
+                    .email(user.email.as_deref())
+                    .context(audit)
+                    .metadata(serde_json::json!({ "soft": soft })),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    /// Audit log entries matching the filters, newest first
+    pub async fn list_audit_log(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<PaginatedResponse<AuditLogEntry>> {
+        let params = page_params(query.page, query.per_page);
+        let total = self.audit_log.count(query).await?;
+        let entries = self
+            .audit_log
+            .list(query, i64::from(params.limit()), i64::from(params.offset()))
+            .await?;
+
+        Ok(PaginatedResponse::new(
+            entries,
+            params.page,
+            params.per_page,
+            total as u64,
+        ))
+    }
+
+    /// Audit log entries matching the filters as JSON Lines, oldest first, read in batches
+    pub fn export_audit_log(
+        self: Arc<Self>,
+        query: AuditLogQuery,
+    ) -> impl futures::Stream<Item = Result<String>> + Send {
+        futures::stream::try_unfold(
+            (self, query, None, false),
+            |(service, query, after, done)| async move {
+                if done {
+                    return Ok(None);
+                }
+
+                let batch = service
+                    .audit_log
+                    .export_batch(&query, after, EXPORT_BATCH_SIZE)
+                    .await?;
+                let done = (batch.len() as i64) < EXPORT_BATCH_SIZE;
+                let after = batch.last().map(|entry| (entry.created_at, entry.id)).or(after);
+
+                let mut chunk = String::new();
+                for entry in &batch {
+                    chunk.push_str(&json_line(entry)?);
+                }
+                Ok(Some((chunk, (service, query, after, done))))
+            },
+        )
+    }
+
+    /// Delete audit log entries older than the retention period
+    pub async fn delete_expired_audit_entries(&self) -> Result<u64> {
+        self.audit_log.delete_expired().await
+    }
+
+    /// Run [`AuthService::delete_expired_audit_entries`] on the configured schedule
+    pub fn spawn_audit_log_cleanup(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let interval =
+            std::time::Duration::from_secs(self.audit_log.config().cleanup_interval_seconds.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.delete_expired_audit_entries().await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("Deleted {} expired audit log entries", deleted),
+                    Err(e) => tracing::error!("Audit log cleanup failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Delete sign-in failures outside the attempt window and lockouts that have ended
+    pub async fn delete_expired_sign_in_failures(&self) -> Result<u64> {
+        self.sign_in_guard.delete_expired().await
+    }
+
+    /// Run [`AuthService::delete_expired_sign_in_failures`] once per attempt window
+    pub fn spawn_sign_in_failure_cleanup(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let interval = std::time::Duration::from_secs(
+            self.sign_in_guard.config().attempt_window_seconds.max(60) as u64,
+        );
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.delete_expired_sign_in_failures().await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("Deleted {} expired sign-in failures and lockouts", deleted),
+                    Err(e) => tracing::error!("Sign-in failure cleanup failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Delete SMS codes that can no longer be used or count towards send limits
+    pub async fn delete_expired_phone_codes(&self) -> Result<u64> {
+        match &self.phone_auth {
+            Some(phone_auth) => phone_auth.delete_expired().await,
+            None => Ok(0),
+        }
+    }
+
+    /// Run [`AuthService::delete_expired_phone_codes`] every hour
+    pub fn spawn_phone_code_cleanup(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                ticker.tick().await;
+                match self.delete_expired_phone_codes().await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("Deleted {} expired SMS codes", deleted),
+                    Err(e) => tracing::error!("SMS code cleanup failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Delete SAML requests whose identity provider never answered
+    pub async fn delete_expired_saml_requests(&self) -> Result<u64> {
+        match &self.saml {
+            Some(saml) => saml.delete_expired_requests().await,
+            None => Ok(0),
+        }
+    }
+
+    /// Run [`AuthService::delete_expired_saml_requests`] once per request lifetime
+    pub fn spawn_saml_request_cleanup(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let interval = std::time::Duration::from_secs(crate::saml::REQUEST_TTL_MINUTES as u64 * 60);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.delete_expired_saml_requests().await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("Deleted {} expired SAML requests", deleted),
+                    Err(e) => tracing::error!("SAML request cleanup failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Start every periodic cleanup task the service needs
+    pub fn spawn_maintenance_tasks(self: Arc<Self>) -> Vec<tokio::task::JoinHandle<()>> {
+        let mut tasks = vec![
+            self.clone().spawn_anonymous_user_cleanup(),
+            self.clone().spawn_audit_log_cleanup(),
+            self.clone().spawn_sign_in_failure_cleanup(),
+        ];
+        if self.phone_auth.is_some() {
+            tasks.push(self.clone().spawn_phone_code_cleanup());
+        }
+        if self.saml.is_some() {
+            tasks.push(self.spawn_saml_request_cleanup());
+        }
+        tasks
+    }
+
+    /// Create an organization owned by `user_id`
+    pub async fn create_organization(
+        &self,
+        user_id: Uuid,
+        request: CreateOrganizationRequest,
+    ) -> Result<Organization> {
+        let slug = match request.slug {
+            Some(slug) => slug,
+            None => slugify(&request.name),
+        };
+        validate_slug(&slug)?;
+        if self.organization_repo.find_by_slug(&slug).await?.is_some() {
+            return Err(ForgeBaseError::Conflict(format!(
+                "Organization slug already taken: {}",
+                slug
+            )));
+        }
+
+        let now = Utc::now();
+        let organization = Organization {
+            id: Uuid::new_v4(),
+            name: request.name.trim().to_string(),
+            slug,
+            created_by: Some(user_id),
+            created_at: now,
+            updated_at: now,
+        };
+
+        self.organization_repo.create(&organization, user_id).await
+    }
+
+    /// Organizations the user belongs to
+    pub async fn list_organizations(&self, user_id: Uuid) -> Result<Vec<OrganizationMembership>> {
+        self.organization_repo.list_for_user(user_id).await
+    }
+
+    /// Get an organization the user belongs to
+    pub async fn get_organization(&self, user_id: Uuid, organization_id: Uuid) -> Result<Organization> {
+        self.require_organization_role(user_id, organization_id, OrganizationRole::Viewer)
+            .await?;
+        self.find_organization(organization_id).await
+    }
+
+    /// Rename an organization or change its slug (admins and owners)
+    pub async fn update_organization(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+        request: UpdateOrganizationRequest,
+    ) -> Result<Organization> {
+        self.require_organization_role(user_id, organization_id, OrganizationRole::Admin)
+            .await?;
+        let mut organization = self.find_organization(organization_id).await?;
+
+        if let Some(name) = request.name {
+            organization.name = name.trim().to_string();
+        }
+        if let Some(slug) = request.slug.filter(|slug| *slug != organization.slug) {
+            validate_slug(&slug)?;
+            if self.organization_repo.find_by_slug(&slug).await?.is_some() {
+                return Err(ForgeBaseError::Conflict(format!(
+                    "Organization slug already taken: {}",
+                    slug
+                )));
+            }
+            organization.slug = slug;
+        }
+
+        self.organization_repo.update(&organization).await
+    }
+
+    /// Delete an organization and everything it owns (owners only)
+    pub async fn delete_organization(&self, user_id: Uuid, organization_id: Uuid) -> Result<()> {
+        self.require_organization_role(user_id, organization_id, OrganizationRole::Owner)
+            .await?;
+        self.organization_repo.delete(organization_id).await?;
+        tracing::info!("Organization {} deleted by {}", organization_id, user_id);
+
+        Ok(())
+    }
+
+    /// Members of an organization
+    pub async fn list_organization_members(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+    ) -> Result<Vec<OrganizationMember>> {
+        self.require_organization_role(user_id, organization_id, OrganizationRole::Viewer)
+            .await?;
+        self.organization_repo.list_members(organization_id).await
+    }
+
+    /// Change a member's role. Members can only grant roles up to their own, and the
+    /// last owner cannot be demoted.
+    pub async fn update_member_role(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+        member_id: Uuid,
+        role: OrganizationRole,
+    ) -> Result<()> {
+        let caller_role = self
+            .require_organization_role(user_id, organization_id, OrganizationRole::Admin)
+            .await?;
+        let current_role = self
+            .organization_repo
+            .member_role(organization_id, member_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("Member not found".to_string()))?;
+
+        if !caller_role.can_grant(current_role) || !caller_role.can_grant(role) {
+            return Err(ForgeBaseError::Authorization(
+                "Insufficient organization role".to_string(),
+            ));
+        }
+        if current_role == OrganizationRole::Owner && role != OrganizationRole::Owner {
+            self.ensure_not_last_owner(organization_id).await?;
+        }
+
+        self.organization_repo
+            .set_member_role(organization_id, member_id, role)
+            .await?;
+        Ok(())
+    }
+
+    /// Remove a member (admins and owners), or leave an organization (anyone)
+    pub async fn remove_organization_member(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+        member_id: Uuid,
+    ) -> Result<()> {
+        let member_role = self
+            .organization_repo
+            .member_role(organization_id, member_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("Member not found".to_string()))?;
+
+        if member_id != user_id {
+            let caller_role = self
+                .require_organization_role(user_id, organization_id, OrganizationRole::Admin)
+                .await?;
+            if !caller_role.can_grant(member_role) {
+                return Err(ForgeBaseError::Authorization(
+                    "Insufficient organization role".to_string(),
+                ));
+            }
+        }
+        if member_role == OrganizationRole::Owner {
+            self.ensure_not_last_owner(organization_id).await?;
+        }
+
+        self.organization_repo
+            .remove_member(organization_id, member_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Invite someone by email; the invitation expires after a week
+    pub async fn invite_organization_member(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+        request: InviteMemberRequest,
+    ) -> Result<OrganizationInvitation> {
+        use chrono::Duration;
+
+        let caller_role = self
+            .require_organization_role(user_id, organization_id, OrganizationRole::Admin)
+            .await?;
+        if !caller_role.can_grant(request.role) {
+            return Err(ForgeBaseError::Authorization(
+                "Insufficient organization role".to_string(),
+            ));
+        }
+        let organization = self.find_organization(organization_id).await?;
+
+        let email = request.email.trim().to_lowercase();
+        if let Some(existing) = self.user_repo.find_by_email(&email).await? {
+            if self
+                .organization_repo
+                .member_role(organization_id, existing.id)
+                .await?
+                .is_some()
+            {
+                return Err(ForgeBaseError::Conflict(
+                    "User is already a member".to_string(),
+                ));
+            }
+        }
+
+        let token = generate_invitation_token();
+        let now = Utc::now();
+        let invitation = self
+            .organization_repo
+            .create_invitation(&OrganizationInvitation {
+                id: Uuid::new_v4(),
+                organization_id,
+                email: email.clone(),
+                role: request.role,
+                token_hash: hash_invitation_token(&token),
+                invited_by: Some(user_id),
+                expires_at: now + Duration::days(INVITATION_TTL_DAYS),
+                accepted_at: None,
+                created_at: now,
+            })
+            .await?;
+
+        if let Some(email_service) = &self.email_service {
+            let inviter = self.user_repo.find_by_id(user_id).await?;
+            email_service
+                .send_invite_email(
+                    &email,
+                    &email,
+                    inviter.as_ref().and_then(user_locale),
+                    inviter.as_ref().map(display_name).unwrap_or_default(),
+                    &organization.name,
+                    &email_service.link("/accept-invite", &token),
+                )
+                .await?;
+        }
+
+        Ok(invitation)
+    }
+
+    /// Invitations that have not been accepted yet
+    pub async fn list_organization_invitations(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+    ) -> Result<Vec<OrganizationInvitation>> {
+        self.require_organization_role(user_id, organization_id, OrganizationRole::Admin)
+            .await?;
+        self.organization_repo
+            .list_pending_invitations(organization_id)
+            .await
+    }
+
+    /// Withdraw an invitation
+    pub async fn revoke_organization_invitation(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+        invitation_id: Uuid,
+    ) -> Result<()> {
+        self.require_organization_role(user_id, organization_id, OrganizationRole::Admin)
+            .await?;
+        if !self
+            .organization_repo
+            .delete_invitation(organization_id, invitation_id)
+            .await?
+        {
+            return Err(ForgeBaseError::NotFound("Invitation not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Join an organization with an invitation sent to the user's email address
+    pub async fn accept_organization_invitation(
+        &self,
+        user_id: Uuid,
+        token: &str,
+    ) -> Result<OrganizationMembership> {
+        let invitation = self
+            .organization_repo
+            .find_valid_invitation(&hash_invitation_token(token))
+            .await?
+            .ok_or_else(|| ForgeBaseError::Auth("Invalid or expired invitation".to_string()))?;
+
+        let user = self.find_user_for_admin(user_id).await?;
+        let email_matches = user
+            .email
+            .as_deref()
+            .is_some_and(|email| email.eq_ignore_ascii_case(&invitation.email));
+        if !email_matches || !user.email_verified {
+            return Err(ForgeBaseError::Authorization(
+                "Invitation was sent to a different email address".to_string(),
+            ));
+        }
+
+        if self
+            .organization_repo
+            .member_role(invitation.organization_id, user_id)
+            .await?
+            .is_none()
+        {
+            self.organization_repo
+                .add_member(invitation.organization_id, user_id, invitation.role)
+                .await?;
+        }
+        self.organization_repo
+            .mark_invitation_accepted(invitation.id)
+            .await?;
+
+        let role = self
+            .organization_repo
+            .member_role(invitation.organization_id, user_id)
+            .await?
+            .unwrap_or(invitation.role);
+        Ok(OrganizationMembership {
+            organization: self.find_organization(invitation.organization_id).await?,
+            role,
+        })
+    }
+
+    /// Make another member the owner; the caller stays on as an admin
+    pub async fn transfer_organization_ownership(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+        new_owner_id: Uuid,
+    ) -> Result<()> {
+        self.require_organization_role(user_id, organization_id, OrganizationRole::Owner)
+            .await?;
+        if new_owner_id == user_id {
+            return Err(ForgeBaseError::InvalidInput(
+                "Ownership must go to another member".to_string(),
+            ));
+        }
+        if self
+            .organization_repo
+            .member_role(organization_id, new_owner_id)
+            .await?
+            .is_none()
+        {
+            return Err(ForgeBaseError::NotFound("Member not found".to_string()));
+        }
+
+        self.organization_repo
+            .transfer_ownership(organization_id, user_id, new_owner_id)
+            .await?;
+        tracing::info!(
+            "Ownership of organization {} transferred from {} to {}",
+            organization_id,
+            user_id,
+            new_owner_id
+        );
+
+        Ok(())
+    }
+
+    /// A user's role in an organization, if they are a member
+    pub async fn organization_role(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+    ) -> Result<Option<OrganizationRole>> {
+        self.organization_repo
+            .member_role(organization_id, user_id)
+            .await
+    }
+
+    /// Require at least `minimum` in an organization; non-members get NotFound so
+    /// organization IDs are not disclosed
+    pub async fn require_organization_role(
+        &self,
+        user_id: Uuid,
+        organization_id: Uuid,
+        minimum: OrganizationRole,
+    ) -> Result<OrganizationRole> {
+        match self.organization_role(user_id, organization_id).await? {
+            Some(role) if role >= minimum => Ok(role),
+            Some(_) => Err(ForgeBaseError::Authorization(
+                "Insufficient organization role".to_string(),
+            )),
+            None => Err(ForgeBaseError::NotFound("Organization not found".to_string())),
+        }
+    }
+
+    async fn find_organization(&self, organization_id: Uuid) -> Result<Organization> {
+        self.organization_repo
+            .find_by_id(organization_id)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound("Organization not found".to_string()))
+    }
+
+    async fn ensure_not_last_owner(&self, organization_id: Uuid) -> Result<()> {
+        if self.organization_repo.count_owners(organization_id).await? <= 1 {
+            return Err(ForgeBaseError::Conflict(
+                "An organization needs at least one owner; transfer ownership first".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Describe an authorization request for the consent page
+    pub async fn authorization_details(
+        &self,
+        claims: &Claims,
+        params: &AuthorizeParams,
+    ) -> Result<AuthorizationDetails> {
+        let user_id = claims.user_id()?;
+        let provider = self.oidc_provider()?;
+        let client = provider.validate_client_redirect(params).await?;
+        let scopes = provider
+            .validate_authorization_request(&client, params)
+            .map_err(|e| ForgeBaseError::InvalidInput(e.description))?;
+
+        Ok(AuthorizationDetails {
+            consent_required: !provider
+                .has_consent(user_id, client.client_id, &scopes)
+                .await?,
+            client_id: client.client_id,
+            client_name: client.name,
+            redirect_uri: params.redirect_uri.clone(),
+            scopes,
+        })
+    }
+
+    /// Record the signed-in user's decision and send them back to the client with an
+    /// authorization code, or an error
+    pub async fn authorize(
+        &self,
+        claims: &Claims,
+        decision: AuthorizeDecision,
+    ) -> Result<AuthorizationRedirect> {
+        let provider = self.oidc_provider()?;
+        let params = &decision.params;
+        let client = provider.validate_client_redirect(params).await?;
+
+        let redirect_error = |error: OAuthError| -> Result<AuthorizationRedirect> {
+            let mut query = vec![
+                ("error", error.error),
+                ("error_description", error.description.as_str()),
+            ];
+            if let Some(state) = &params.state {
+                query.push(("state", state));
+            }
+            Ok(AuthorizationRedirect {
+                redirect_to: redirect_with(&params.redirect_uri, &query)?,
+            })
+        };
+
+        let scopes = match provider.validate_authorization_request(&client, params) {
+            Ok(scopes) => scopes,
+            Err(error) => return redirect_error(error),
+        };
+        if !decision.approve {
+            return redirect_error(OAuthError::access_denied("The user denied the request"));
+        }
+
+        let mut user = self.find_user_for_admin(claims.user_id()?).await?;
+        self.ensure_active(&mut user).await?;
+
+        provider
+            .grant_consent(user.id, client.client_id, &scopes)
+            .await?;
+        // auth_time is when the user signed in, not when their current access token was issued
+        let session = match claims.session_id()? {
+            Some(session_id) => self.session_repo.find_by_id(session_id).await?,
+            None => None,
+        }
+        .filter(|session| session.user_id == user.id)
+        .ok_or_else(|| ForgeBaseError::Auth("Token is not tied to a session".to_string()))?;
+        let code = provider
+            .create_authorization_code(
+                &client,
+                user.id,
+                params,
+                &scopes,
+                &session.aal,
+                session.created_at,
+            )
+            .await?;
+
+        let mut query = vec![("code", code.as_str())];
+        if let Some(state) = &params.state {
+            query.push(("state", state));
+        }
+        Ok(AuthorizationRedirect {
+            redirect_to: redirect_with(&params.redirect_uri, &query)?,
+        })
+    }
+
+    /// Token endpoint: authorization code, refresh token and client credentials grants
+    pub async fn oauth_token(
+        &self,
+        request: OAuthTokenRequest,
+        client_id: Option<String>,
+        client_secret: Option<String>,
+    ) -> std::result::Result<OAuthTokenResponse, OAuthError> {
+        let provider = self.oidc_provider()?;
+        let client = provider
+            .authenticate_client(client_id, client_secret)
+            .await?;
+
+        let grant_type = request.grant_type.as_str();
+        if ![GRANT_AUTHORIZATION_CODE, GRANT_REFRESH_TOKEN, GRANT_CLIENT_CREDENTIALS]
+            .contains(&grant_type)
+        {
+            return Err(OAuthError::unsupported_grant_type(format!(
+                "Unsupported grant type: {}",
+                grant_type
+            )));
+        }
+        if !client.grant_types.iter().any(|g| g == grant_type) {
+            return Err(OAuthError::unauthorized_client(format!(
+                "Client may not use the {} grant",
+                grant_type
+            )));
+        }
+
+        match grant_type {
+            GRANT_AUTHORIZATION_CODE => self.exchange_authorization_code(provider, &client, request).await,
+            GRANT_REFRESH_TOKEN => self.refresh_oauth_token(provider, &client, request).await,
+            _ => self.client_credentials_token(&client, request),
+        }
+    }
+
+    async fn exchange_authorization_code(
+        &self,
+        provider: &OidcProvider,
+        client: &OAuthClient,
+        request: OAuthTokenRequest,
+    ) -> std::result::Result<OAuthTokenResponse, OAuthError> {
+        let code = request
+            .code
+            .ok_or_else(|| OAuthError::invalid_request("code is required"))?;
+        let code = provider
+            .take_authorization_code(&code)
+            .await?
+            .filter(|code| code.client_id == client.client_id)
+            .ok_or_else(|| OAuthError::invalid_grant("Invalid or expired authorization code"))?;
+
+        if request.redirect_uri.as_deref() != Some(code.redirect_uri.as_str()) {
+            return Err(OAuthError::invalid_grant("redirect_uri does not match"));
+        }
+        if let Some(challenge) = &code.code_challenge {
+            let verifier = request
+                .code_verifier
+                .ok_or_else(|| OAuthError::invalid_grant("code_verifier is required"))?;
+            if !verify_pkce(&verifier, challenge) {
+                return Err(OAuthError::invalid_grant("PKCE verification failed"));
+            }
+        }
+
+        let mut user = self
+            .user_repo
+            .find_by_id(code.user_id)
+            .await?
+            .ok_or_else(|| OAuthError::invalid_grant("User not found"))?;
+        self.ensure_active(&mut user)
+            .await
+            .map_err(|_| OAuthError::invalid_grant("Account is disabled"))?;
+
+        let mut session = self.session_manager.create_session(user.id, None, None);
+        session.aal = code.aal.clone();
+        session.client_id = Some(client.client_id);
+        session.scope = Some(code.scope.clone());
+        let session = self.session_repo.create(&session).await?;
+
+        let scopes = parse_scope(&code.scope);
+        let refresh_token = if scopes.iter().any(|s| s == SCOPE_OFFLINE_ACCESS) {
+            let (token, refresh_token) = self.session_manager.issue_refresh_token(&session, None);
+            self.refresh_token_repo.create(&token).await?;
+            Some(refresh_token)
+        } else {
+            None
+        };
+
+        self.oauth_token_response(provider, client, &user, &session, refresh_token, code.nonce, code.auth_time)
+            .await
+    }
+
+    async fn refresh_oauth_token(
+        &self,
+        provider: &OidcProvider,
+        client: &OAuthClient,
+        request: OAuthTokenRequest,
+    ) -> std::result::Result<OAuthTokenResponse, OAuthError> {
+        let refresh_token = request
+            .refresh_token
+            .ok_or_else(|| OAuthError::invalid_request("refresh_token is required"))?;
+        let (session, user, refresh_token) = self
+            .rotate_refresh_token(&refresh_token, Some(client.client_id), &AuditContext::default())
+            .await
+            .map_err(|e| match e {
+                ForgeBaseError::Auth(msg) => OAuthError::invalid_grant(msg),
+                other => other.into(),
+            })?;
+
+        self.oauth_token_response(
+            provider,
+            client,
+            &user,
+            &session,
+            Some(refresh_token),
+            None,
+            session.created_at,
+        )
+        .await
+    }
+
+    /// Token for a client acting for itself. There is no user to pass the custom access token
+    /// hook, so these tokens never carry custom claims.
+    fn client_credentials_token(
+        &self,
+        client: &OAuthClient,
+        request: OAuthTokenRequest,
+    ) -> std::result::Result<OAuthTokenResponse, OAuthError> {
+        if client.client_secret_hash.is_none() {
+            return Err(OAuthError::unauthorized_client(
+                "Public clients cannot use the client credentials grant",
+            ));
+        }
+
+        let scopes = match request.scope.as_deref() {
+            Some(scope) => parse_scope(scope),
+            None => client
+                .scopes
+                .iter()
+                .filter(|s| !USER_SCOPES.contains(&s.as_str()))
+                .cloned()
+                .collect(),
+        };
+        if let Some(scope) = scopes
+            .iter()
+            .find(|s| USER_SCOPES.contains(&s.as_str()) || !client.scopes.contains(s))
+        {
+            return Err(OAuthError::invalid_scope(format!("Scope not allowed: {}", scope)));
+        }
+
+        let scope = scopes.join(" ");
+        let claims = Claims::for_client(client.client_id.to_string(), scopes, self.jwt_expiration);
+        Ok(OAuthTokenResponse {
+            access_token: self.jwt_manager.generate_access_token(claims)?,
+            token_type: "Bearer".to_string(),
+            expires_in: self.jwt_expiration,
+            refresh_token: None,
+            id_token: None,
+            scope,
+        })
+    }
+
+    /// Access token (and ID token when `openid` was granted) for a client's session
+    #[allow(clippy::too_many_arguments)]
+    async fn oauth_token_response(
+        &self,
+        provider: &OidcProvider,
+        client: &OAuthClient,
+        user: &User,
+        session: &Session,
+        refresh_token: Option<String>,
+        nonce: Option<String>,
+        auth_time: DateTime<Utc>,
+    ) -> std::result::Result<OAuthTokenResponse, OAuthError> {
+        let scope = session.scope.clone().unwrap_or_default();
+        let scopes = parse_scope(&scope);
+
+        let mut claims = Claims::new(
+            user.id,
+            user_info(user, &scopes).email.unwrap_or_default(),
+            self.jwt_expiration,
+        )
+        .with_aal(session.aal.clone())
+        .with_session(session.id)
+        .with_client(client.client_id.to_string(), scope.clone());
+        claims.is_anonymous = user.is_anonymous;
+        let access_token = self.sign_access_token(user, claims).await?;
+
+        let id_token = if scopes.iter().any(|s| s == SCOPE_OPENID) {
+            if self.jwt_manager.signing_algorithm() == Algorithm::HS256 {
+                return Err(ForgeBaseError::Config(
+                    "ID tokens need asymmetric JWT signing keys".to_string(),
+                )
+                .into());
+            }
+            let id_claims = provider.id_token_claims(user, client.client_id, &scopes, nonce, auth_time);
+            Some(self.jwt_manager.sign(&id_claims)?)
+        } else {
+            None
+        };
+
+        Ok(OAuthTokenResponse {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.jwt_expiration,
+            refresh_token,
+            id_token,
+            scope,
+        })
+    }
+
+    /// Verify an access token issued to an OAuth client on behalf of a user
+    async fn verify_delegated_token(&self, token: &str) -> std::result::Result<Claims, OAuthError> {
+        let claims = self
+            .jwt_manager
+            .verify_token(token)
+            .map_err(|_| OAuthError::invalid_token("Invalid or expired access token"))?;
+        let session_id = match claims.session_id() {
+            Ok(Some(session_id)) if claims.client_id.is_some() => session_id,
+            _ => return Err(OAuthError::invalid_token("Not an OAuth access token")),
+        };
+        if !self.session_repo.is_active(session_id).await? {
+            return Err(OAuthError::invalid_token("Access token was revoked"));
+        }
+        Ok(claims)
+    }
+
+    /// Claims about the user behind an access token, as allowed by its scopes
+    pub async fn oauth_userinfo(&self, access_token: &str) -> std::result::Result<UserInfo, OAuthError> {
+        let claims = self.verify_delegated_token(access_token).await?;
+        if !claims.has_scope(SCOPE_OPENID) {
+            return Err(OAuthError::insufficient_scope("The openid scope is required"));
+        }
+
+        let user = self
+            .user_repo
+            .find_by_id(claims.user_id()?)
+            .await?
+            .filter(|user| user.is_active)
+            .ok_or_else(|| OAuthError::invalid_token("User not found"))?;
+        let scopes = parse_scope(claims.scope.as_deref().unwrap_or_default());
+        Ok(user_info(&user, &scopes))
+    }
+
+    /// Token introspection (RFC 7662) for access and refresh tokens issued to OAuth clients;
+    /// refresh tokens are only disclosed to the client holding them
+    pub async fn oauth_introspect(
+        &self,
+        request: OAuthTokenHintRequest,
+        client_id: Option<String>,
+        client_secret: Option<String>,
+    ) -> std::result::Result<IntrospectionResponse, OAuthError> {
+        let provider = self.oidc_provider()?;
+        let client = provider
+            .authenticate_client(client_id, client_secret)
+            .await?;
+
+        if let Ok(claims) = self.jwt_manager.verify_token(&request.token) {
+            if claims.client_id.is_none() {
+                return Ok(IntrospectionResponse::default());
+            }
+            if let Some(session_id) = claims.session_id().ok().flatten() {
+                if !self.session_repo.is_active(session_id).await? {
+                    return Ok(IntrospectionResponse::default());
+                }
+            }
+            return Ok(IntrospectionResponse {
+                active: true,
+                scope: claims.scope,
+                client_id: claims.client_id,
+                sub: Some(claims.sub),
+                token_type: Some("Bearer".to_string()),
+                exp: Some(claims.exp),
+                iat: Some(claims.iat),
+                iss: Some(provider.issuer().to_string()),
+            });
+        }
+
+        let token = self
+            .refresh_token_repo
+            .find_by_hash(&hash_refresh_token(&request.token))
+            .await?
+            .filter(|token| !token.revoked);
+        let session = match token {
+            Some(token) => self.session_repo.find_by_id(token.session_id).await?,
+            None => None,
+        };
+        match session.filter(|session| session.client_id == Some(client.client_id)) {
+            Some(session) => Ok(IntrospectionResponse {
+                active: true,
+                scope: session.scope,
+                client_id: Some(client.client_id.to_string()),
+                sub: Some(session.user_id.to_string()),
+                token_type: Some("refresh_token".to_string()),
+                exp: Some(session.expires_at.timestamp()),
+                iat: Some(session.created_at.timestamp()),
+                iss: Some(provider.issuer().to_string()),
+            }),
+            None => Ok(IntrospectionResponse::default()),
+        }
+    }
+
+    /// Token revocation (RFC 7009): revoking a client's access or refresh token ends the
+    /// session behind it. Client credentials tokens are stateless and simply expire.
+    pub async fn oauth_revoke(
+        &self,
+        request: OAuthTokenHintRequest,
+        client_id: Option<String>,
+        client_secret: Option<String>,
+    ) -> std::result::Result<(), OAuthError> {
+        let client = self
+            .oidc_provider()?
+            .authenticate_client(client_id, client_secret)
+            .await?;
+
+        let session_id = match self.jwt_manager.verify_token(&request.token) {
+            Ok(claims) if claims.client_id == Some(client.client_id.to_string()) => {
+                claims.session_id().ok().flatten()
+            }
+            Ok(_) => None,
+            Err(_) => self
+                .refresh_token_repo
+                .find_by_hash(&hash_refresh_token(&request.token))
+                .await?
+                .map(|token| token.session_id),
+        };
+
+        // Unknown tokens and other clients' tokens are ignored, as the RFC requires
+        if let Some(session) = match session_id {
+            Some(id) => self.session_repo.find_by_id(id).await?,
+            None => None,
+        } {
+            if session.client_id == Some(client.client_id) {
+                self.session_repo.delete(session.id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apps the user has allowed to sign them in
+    pub async fn list_oauth_consents(&self, user_id: Uuid) -> Result<Vec<OAuthConsent>> {
+        self.oidc_provider()?.list_consents(user_id).await
+    }
+
+    /// Withdraw an app's access, signing the user out of it
+    pub async fn revoke_oauth_consent(&self, user_id: Uuid, client_id: Uuid) -> Result<()> {
+        if !self.oidc_provider()?.revoke_consent(user_id, client_id).await? {
+            return Err(ForgeBaseError::NotFound("Authorization not found".to_string()));
+        }
+        self.session_repo.delete_for_client(user_id, client_id).await?;
+        Ok(())
+    }
+}
+
+/// Name used to greet the user in emails
+fn display_name(user: &User) -> &str {
+    user.full_name
+        .as_deref()
+        .or(user.email.as_deref())
+        .or(user.phone.as_deref())
+        .unwrap_or_default()
+}
+
+/// Preferred locale from the user's metadata (`{"locale": "pt-BR"}`)
+fn user_locale(user: &User) -> Option<&str> {
+    user.metadata.get("locale").and_then(|locale| locale.as_str())
+}
+
+/// A new anonymous user, signed in now
+fn anonymous_user(metadata: Option<serde_json::Value>) -> User {
+    User {
+        id: Uuid::new_v4(),
+        email: None,
+        email_verified: false,
+        phone: None,
+        phone_verified: false,
+        password_hash: None,
+        full_name: None,
+        avatar_url: None,
+        metadata: metadata.unwrap_or(serde_json::json!({})),
+        is_anonymous: true,
+        is_active: true,
+        last_sign_in_at: Some(Utc::now()),
+        banned_until: None,
+        deleted_at: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+/// Reject an email that already belongs to a user
+fn ensure_email_available(existing: Option<&User>) -> Result<()> {
+    if existing.is_some() {
+        return Err(ForgeBaseError::Conflict(
+            "User with this email already exists".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Make an anonymous user permanent with an (unverified) email and password
+fn upgrade_with_password(user: &mut User, email: String, password_hash: String) {
+    user.email = Some(email);
+    user.email_verified = false;
+    user.password_hash = Some(password_hash);
+    user.is_anonymous = false;
+    user.updated_at = Utc::now();
+}
+
+/// Make an anonymous user permanent from a linked identity, taking its email when available
+fn upgrade_with_identity(user: &mut User, info: OAuthUserInfo, email_available: bool) {
+    if email_available {
+        user.email = Some(info.email);
+        user.email_verified = info.email_verified;
+    }
+    user.full_name = user.full_name.take().or(info.full_name);
+    user.avatar_url = user.avatar_url.take().or(info.avatar_url);
+    user.is_anonymous = false;
+    user.updated_at = Utc::now();
+}
+
+/// The role carried in access tokens: the service role if assigned, else the earliest role
+fn claims_role(roles: &[Role]) -> Option<&str> {
+    roles
+        .iter()
+        .find(|role| role.name == SERVICE_ROLE)
+        .or_else(|| roles.first())
+        .map(|role| role.name.as_str())
+}
+
+/// Reject the reserved service role name; that role is never created or edited through the API
+fn ensure_not_reserved(name: &str) -> Result<()> {
+    if name == SERVICE_ROLE {
+        return Err(ForgeBaseError::Validation(format!(
+            "{} is a reserved role name",
+            SERVICE_ROLE
+        )));
+    }
+    Ok(())
+}
+
+/// Only a service-role caller may grant or take away the service role
+fn ensure_can_grant(role: &Role, caller: &Claims) -> Result<()> {
+    if role.name == SERVICE_ROLE && !caller.is_service_role() {
+        return Err(ForgeBaseError::Authorization(format!(
+            "Only a {} caller may grant or remove {}",
+            SERVICE_ROLE, SERVICE_ROLE
+        )));
+    }
+    Ok(())
+}
+
+/// Anonymous users last active before this are deleted
+fn anonymous_cleanup_cutoff(now: DateTime<Utc>, inactive_days: i64) -> DateTime<Utc> {
+    now - chrono::Duration::days(inactive_days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth::{OAuthConfig, OAuthEndpoints};
+    use forgebase_db::testing::test_pool;
+    use std::collections::HashMap;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn lazy_service(anonymous: AnonymousUsersConfig) -> AuthService {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/forgebase_test")
+            .unwrap();
+        AuthService::new(pool, "test-secret".to_string(), 3600, 30).with_anonymous_users(anonymous)
+    }
+
+    fn anonymous_enabled() -> AnonymousUsersConfig {
+        AnonymousUsersConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    fn unique_email(name: &str) -> String {
+        format!("{}-{}@example.com", name, Uuid::new_v4())
+    }
+
+    /// A permanent user holding `email`
+    async fn user_with_email(service: &AuthService, email: &str) -> User {
+        let mut user = anonymous_user(None);
+        user.email = Some(email.to_string());
+        user.is_anonymous = false;
+        service.user_repo.create(&user).await.unwrap()
+    }
+
+    /// An OAuth manager whose "github" provider signs in `email` as provider user `id`
+    async fn github_provider(server: &MockServer, id: u32, email: &str) -> OAuthManager {
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({ "access_token": "access-abc", "token_type": "Bearer" }),
+            ))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/user"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({ "id": id, "email": email, "name": "Octo Cat" }),
+            ))
+            .mount(server)
+            .await;
+
+        let config = OAuthConfig {
+            client_id: "client-123".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_uri: "https://app.example.com/callback".to_string(),
+            scopes: vec!["user:email".to_string()],
+            issuer: None,
+            endpoints: Some(OAuthEndpoints {
+                authorization_url: format!("{}/authorize", server.uri()),
+                token_url: format!("{}/token", server.uri()),
+                user_info_url: Some(format!("{}/user", server.uri())),
+                jwks_url: None,
+                issuer: None,
+            }),
+        };
+        OAuthManager::new(HashMap::from([("github".to_string(), config)]))
+    }
+
+    #[tokio::test]
+    async fn test_sign_up_anonymous_requires_enabled() {
+        let result = lazy_service(AnonymousUsersConfig::default())
+            .sign_up_anonymous(AnonymousSignUpRequest { metadata: None }, None, None)
+            .await;
+        assert!(matches!(result, Err(ForgeBaseError::Authorization(_))));
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_user_upgrade() {
+        let Some(pool) = test_pool().await else { return };
+        let service = AuthService::new(pool, "test-secret".to_string(), 3600, 30)
+            .with_anonymous_users(anonymous_enabled());
+
+        let signed_up = service
+            .sign_up_anonymous(AnonymousSignUpRequest { metadata: None }, None, None)
+            .await
+            .unwrap();
+        assert!(signed_up.user.is_anonymous);
+        let user_id = signed_up.user.id;
+        let upgrade = |email: &str, password: &str| UpgradeAnonymousRequest {
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+
+        let taken = unique_email("taken");
+        user_with_email(&service, &taken).await;
+        let result = service
+            .upgrade_anonymous_user(user_id, upgrade(&taken, "Correct-Horse-Battery-9"), &AuditContext::default())
+            .await;
+        assert!(matches!(result, Err(ForgeBaseError::Conflict(_))));
+
+        let email = unique_email("upgraded");
+        let result = service
+            .upgrade_anonymous_user(user_id, upgrade(&email, "short"), &AuditContext::default())
+            .await;
+        assert!(matches!(result, Err(ForgeBaseError::Validation(_))));
+
+        let user = service
+            .upgrade_anonymous_user(user_id, upgrade(&email, "Correct-Horse-Battery-9"), &AuditContext::default())
+            .await
+            .unwrap();
+        assert_eq!(user.id, user_id);
+        assert!(!user.is_anonymous);
+        assert_eq!(user.email.as_deref(), Some(email.as_str()));
+
+        // Only anonymous users can be upgraded
+        let result = service
+            .upgrade_anonymous_user(user_id, upgrade(&unique_email("again"), "Correct-Horse-Battery-9"), &AuditContext::default())
+            .await;
+        assert!(matches!(result, Err(ForgeBaseError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_link_oauth_identity_keeps_taken_email() {
+        let Some(pool) = test_pool().await else { return };
+        let server = MockServer::start().await;
+        let taken = unique_email("taken");
+        let provider_user_id = rand::random::<u32>();
+        let service = AuthService::new(pool, "test-secret".to_string(), 3600, 30)
+            .with_anonymous_users(anonymous_enabled())
+            .with_oauth(github_provider(&server, provider_user_id, &taken).await);
+        user_with_email(&service, &taken).await;
+
+        let anonymous = service
+            .sign_up_anonymous(AnonymousSignUpRequest { metadata: None }, None, None)
+            .await
+            .unwrap();
+        let user = service
+            .link_oauth_identity(anonymous.user.id, "github", "code", &AuditContext::default())
+            .await
+            .unwrap();
+        assert_eq!(user.id, anonymous.user.id);
+        assert!(!user.is_anonymous);
+        // Another account has the provider's email, so it is not taken over
+        assert!(user.email.is_none());
+        assert_eq!(user.full_name.as_deref(), Some("Octo Cat"));
+
+        // The identity now belongs to that user
+        let other = service
+            .sign_up_anonymous(AnonymousSignUpRequest { metadata: None }, None, None)
+            .await
+            .unwrap();
+        let result = service
+            .link_oauth_identity(other.user.id, "github", "code", &AuditContext::default())
+            .await;
+        assert!(matches!(result, Err(ForgeBaseError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_delete_inactive_anonymous_users_uses_cutoff() {
+        let Some(pool) = test_pool().await else { return };
+        let service = AuthService::new(pool, "test-secret".to_string(), 3600, 30)
+            .with_anonymous_users(AnonymousUsersConfig {
+                inactive_days: 30,
+                ..anonymous_enabled()
+            });
+        let create = |days_ago: i64, is_anonymous: bool| {
+            let mut user = anonymous_user(None);
+            user.created_at = Utc::now() - chrono::Duration::days(days_ago);
+            user.is_anonymous = is_anonymous;
+            user
+        };
+
+        let inactive = service.user_repo.create(&create(31, true)).await.unwrap();
+        let recent = service.user_repo.create(&create(29, true)).await.unwrap();
+        let permanent = service.user_repo.create(&create(31, false)).await.unwrap();
+        // Created long ago, but refreshed within the period
+        let active = service.user_repo.create(&create(31, true)).await.unwrap();
+        let session = service
+            .session_repo
+            .create(&service.session_manager.create_session(active.id, None, None))
+            .await
+            .unwrap();
+        let (token, _) = service.session_manager.issue_refresh_token(&session, None);
+        service.refresh_token_repo.create(&token).await.unwrap();
+
+        assert!(service.delete_inactive_anonymous_users().await.unwrap() >= 1);
+        let exists = |id| service.user_repo.find_by_id(id);
+        assert!(exists(inactive.id).await.unwrap().is_none());
+        assert!(exists(recent.id).await.unwrap().is_some());
+        assert!(exists(permanent.id).await.unwrap().is_some());
+        assert!(exists(active.id).await.unwrap().is_some());
+    }
+
+    /// The reserved service role, inserted directly as `create_role` refuses the name
+    async fn service_role(service: &AuthService) -> Role {
+        sqlx::query(
+            "INSERT INTO roles (id, name) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING",
+        )
+        .bind(Uuid::new_v4())
+        .bind(SERVICE_ROLE)
+        .execute(&service.pool)
+        .await
+        .unwrap();
+        service.role_repo.find_by_name(SERVICE_ROLE).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_service_role_is_reserved() {
+        let Some(pool) = test_pool().await else { return };
+        let service = AuthService::new(pool, "test-secret".to_string(), 3600, 30);
+        let role = service_role(&service).await;
+        let user = user_with_email(&service, &unique_email("role")).await;
+        let audit = AuditContext::default();
+        let admin = Claims::new(Uuid::new_v4(), "admin@example.com".to_string(), 900)
+            .with_permissions(vec!["auth:roles".to_string()]);
+        let service_caller = Claims::new(Uuid::new_v4(), String::new(), 900)
+            .with_role(SERVICE_ROLE.to_string());
+
+        let update = UpdateRoleRequest {
+            description: Some("Everything".to_string()),
+            permissions: None,
+        };
+        assert!(matches!(
+            service.update_role(role.id, update).await,
+            Err(ForgeBaseError::Validation(_))
+        ));
+        assert!(matches!(
+            service.delete_role(role.id).await,
+            Err(ForgeBaseError::Validation(_))
+        ));
+
+        assert!(matches!(
+            service.assign_role(user.id, role.id, &admin, &audit).await,
+            Err(ForgeBaseError::Authorization(_))
+        ));
+        assert!(service.list_user_roles(user.id).await.unwrap().is_empty());
+
+        service
+            .assign_role(user.id, role.id, &service_caller, &audit)
+            .await
+            .unwrap();
+        assert!(matches!(
+            service.unassign_role(user.id, role.id, &admin, &audit).await,
+            Err(ForgeBaseError::Authorization(_))
+        ));
+        service
+            .unassign_role(user.id, role.id, &service_caller, &audit)
+            .await
+            .unwrap();
+        assert!(service.list_user_roles(user.id).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_claims_role_prefers_service_role() {
+        let role = |name: &str| Role {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            permissions: vec![],
+            created_at: Utc::now(),
+        };
+
+        assert_eq!(claims_role(&[]), None);
+        assert_eq!(claims_role(&[role("editor"), role("viewer")]), Some("editor"));
+        assert_eq!(
+            claims_role(&[role("editor"), role(SERVICE_ROLE)]),
+            Some(SERVICE_ROLE)
+        );
+    }
+
+    #[test]
+    fn test_anonymous_user() {
+        let user = anonymous_user(Some(serde_json::json!({ "theme": "dark" })));
+        assert!(user.is_anonymous);
+        assert!(user.is_active);
+        assert!(user.email.is_none() && user.password_hash.is_none());
+        assert_eq!(user.metadata["theme"], "dark");
+        assert_eq!(anonymous_user(None).metadata, serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_upgrade_rejects_taken_email() {
+        assert!(ensure_email_available(None).is_ok());
+        let existing = anonymous_user(None);
+        assert!(matches!(
+            ensure_email_available(Some(&existing)),
+            Err(ForgeBaseError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_upgrade_with_password() {
+        let mut user = anonymous_user(None);
+        let id = user.id;
+        upgrade_with_password(&mut user, "user@example.com".to_string(), "hash".to_string());
+        assert_eq!(user.id, id);
+        assert!(!user.is_anonymous);
+        assert_eq!(user.email.as_deref(), Some("user@example.com"));
+        assert!(!user.email_verified);
+        assert_eq!(user.password_hash.as_deref(), Some("hash"));
+    }
+
+    #[test]
+    fn test_anonymous_cleanup_cutoff() {
+        let now = Utc::now();
+        assert_eq!(
+            anonymous_cleanup_cutoff(now, 30),
+            now - chrono::Duration::days(30)
+        );
+        assert_eq!(anonymous_cleanup_cutoff(now, 0), now);
+    }
+
+    #[test]
+    fn test_upgrade_with_identity() {
+        let mut user = anonymous_user(None);
+        upgrade_with_identity(&mut user, identity("user@example.com"), true);
+        assert!(!user.is_anonymous);
+        assert_eq!(user.email.as_deref(), Some("user@example.com"));
+        assert!(user.email_verified);
+
+        let mut user = anonymous_user(None);
+        user.full_name = Some("Chosen Name".to_string());
+        upgrade_with_identity(&mut user, identity("taken@example.com"), false);
+        assert!(user.email.is_none());
+        assert_eq!(user.full_name.as_deref(), Some("Chosen Name"));
+    }
+
+    fn identity(email: &str) -> OAuthUserInfo {
+        OAuthUserInfo {
+            provider_user_id: "12345".to_string(),
+            email: email.to_string(),
+            email_verified: true,
+            full_name: Some("Provider Name".to_string()),
+            avatar_url: None,
+            metadata: serde_json::json!({}),
+        }
+    }
 }
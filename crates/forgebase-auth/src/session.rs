@@ -1,20 +1,41 @@
-use crate::{models::Session, User};
+use crate::models::{RefreshToken, Session};
 use chrono::{DateTime, Duration, Utc};
-use forgebase_core::{ForgeBaseError, Result};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+/// Session authenticated with a single factor
+pub const AAL1: &str = "aal1";
+/// Session that also passed a second factor
+pub const AAL2: &str = "aal2";
+
+/// Default window in which a rotated refresh token is still accepted
+const DEFAULT_REUSE_INTERVAL_SECONDS: i64 = 10;
+
+/// Hash a refresh token for storage and lookup
+pub fn hash_refresh_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
 /// Session manager for refresh tokens
 pub struct SessionManager {
     refresh_token_expiration_days: i64,
+    reuse_interval: Duration,
 }
 
 impl SessionManager {
     pub fn new(refresh_token_expiration_days: i64) -> Self {
         Self {
             refresh_token_expiration_days,
+            reuse_interval: Duration::seconds(DEFAULT_REUSE_INTERVAL_SECONDS),
         }
     }
 
+    /// Set how long a rotated refresh token may still be presented
+    pub fn with_reuse_interval(mut self, seconds: i64) -> Self {
+        self.reuse_interval = Duration::seconds(seconds);
+        self
+    }
+
     /// Generate a refresh token
     pub fn generate_refresh_token(&self) -> String {
         use rand::Rng;
@@ -44,14 +65,45 @@ impl SessionManager {
         Session {
             id: Uuid::new_v4(),
             user_id,
-            refresh_token: self.generate_refresh_token(),
             user_agent,
             ip_address,
+            aal: AAL1.to_string(),
             expires_at: self.calculate_expiration(),
             created_at: Utc::now(),
+            last_active_at: Utc::now(),
+            client_id: None,
+            scope: None,
         }
     }
 
+    /// Issue a refresh token for a session, returning the record and the plaintext token
+    pub fn issue_refresh_token(
+        &self,
+        session: &Session,
+        parent: Option<String>,
+    ) -> (RefreshToken, String) {
+        let token = self.generate_refresh_token();
+        let record = RefreshToken {
+            id: Uuid::new_v4(),
+            session_id: session.id,
+            user_id: session.user_id,
+            token_hash: hash_refresh_token(&token),
+            parent,
+            revoked: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        (record, token)
+    }
+
+    /// Whether a rotated token is still inside the reuse grace interval.
+    ///
+    /// `updated_at` on a revoked token records when it was rotated.
+    pub fn is_within_reuse_interval(&self, token: &RefreshToken) -> bool {
+        token.revoked && Utc::now() < token.updated_at + self.reuse_interval
+    }
+
     /// Validate if a session is still valid
     pub fn is_session_valid(&self, session: &Session) -> bool {
         Utc::now() < session.expires_at
@@ -80,4 +132,30 @@ mod tests {
 
         assert!(manager.is_session_valid(&session));
     }
+
+    #[test]
+    fn test_issue_refresh_token_stores_hash() {
+        let manager = SessionManager::new(30);
+        let session = manager.create_session(Uuid::new_v4(), None, None);
+        let (record, token) = manager.issue_refresh_token(&session, Some("parent".to_string()));
+
+        assert_eq!(record.session_id, session.id);
+        assert_eq!(record.token_hash, hash_refresh_token(&token));
+        assert_ne!(record.token_hash, token);
+        assert_eq!(record.token_hash.len(), 64);
+    }
+
+    #[test]
+    fn test_reuse_interval() {
+        let manager = SessionManager::new(30).with_reuse_interval(10);
+        let session = manager.create_session(Uuid::new_v4(), None, None);
+        let (mut record, _) = manager.issue_refresh_token(&session, None);
+        assert!(!manager.is_within_reuse_interval(&record));
+
+        record.revoked = true;
+        assert!(manager.is_within_reuse_interval(&record));
+
+        record.updated_at = Utc::now() - Duration::seconds(11);
+        assert!(!manager.is_within_reuse_interval(&record));
+    }
 }
@@ -0,0 +1,239 @@
+// SMS delivery through pluggable providers
+use async_trait::async_trait;
+use forgebase_core::{ForgeBaseError, Result, SmsConfig, SmsProviderKind};
+use std::sync::{Arc, Mutex};
+
+const TWILIO_API_BASE_URL: &str = "https://api.twilio.com";
+const VONAGE_API_BASE_URL: &str = "https://rest.nexmo.com";
+
+/// Sends a text message to an E.164 phone number
+#[async_trait]
+pub trait SmsProvider: Send + Sync {
+    async fn send(&self, to: &str, body: &str) -> Result<()>;
+}
+
+/// Provider selected by configuration
+pub fn provider_from_config(config: &SmsConfig) -> Arc<dyn SmsProvider> {
+    match config.provider {
+        SmsProviderKind::Twilio => Arc::new(TwilioSmsProvider::new(config)),
+        SmsProviderKind::Vonage => Arc::new(VonageSmsProvider::new(config)),
+        SmsProviderKind::Mock => Arc::new(MockSmsProvider::default()),
+    }
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent("ForgeBase")
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .unwrap_or_default()
+}
+
+fn sms_error(provider: &str, e: impl std::fmt::Display) -> ForgeBaseError {
+    ForgeBaseError::ExternalService(format!("{} SMS error: {}", provider, e))
+}
+
+/// Twilio Programmable Messaging
+pub struct TwilioSmsProvider {
+    http_client: reqwest::Client,
+    base_url: String,
+    account_sid: String,
+    auth_token: String,
+    from: String,
+}
+
+impl TwilioSmsProvider {
+    pub fn new(config: &SmsConfig) -> Self {
+        Self {
+            http_client: http_client(),
+            base_url: config
+                .api_base_url
+                .clone()
+                .unwrap_or_else(|| TWILIO_API_BASE_URL.to_string()),
+            account_sid: config.api_key.clone(),
+            auth_token: config.api_secret.clone(),
+            from: config.from.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl SmsProvider for TwilioSmsProvider {
+    async fn send(&self, to: &str, body: &str) -> Result<()> {
+        let url = format!(
+            "{}/2010-04-01/Accounts/{}/Messages.json",
+            self.base_url.trim_end_matches('/'),
+            self.account_sid
+        );
+        let response = self
+            .http_client
+            .post(&url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("To", to), ("From", self.from.as_str()), ("Body", body)])
+            .send()
+            .await
+            .map_err(|e| sms_error("Twilio", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let data: serde_json::Value = response.json().await.unwrap_or_default();
+            return Err(sms_error(
+                "Twilio",
+                format!("{} {}", status, data["message"].as_str().unwrap_or_default()),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Vonage (Nexmo) SMS API
+pub struct VonageSmsProvider {
+    http_client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    api_secret: String,
+    from: String,
+}
+
+impl VonageSmsProvider {
+    pub fn new(config: &SmsConfig) -> Self {
+        Self {
+            http_client: http_client(),
+            base_url: config
+                .api_base_url
+                .clone()
+                .unwrap_or_else(|| VONAGE_API_BASE_URL.to_string()),
+            api_key: config.api_key.clone(),
+            api_secret: config.api_secret.clone(),
+            from: config.from.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl SmsProvider for VonageSmsProvider {
+    async fn send(&self, to: &str, body: &str) -> Result<()> {
+        let url = format!("{}/sms/json", self.base_url.trim_end_matches('/'));
+        let response = self
+            .http_client
+            .post(&url)
+            .form(&[
+                ("api_key", self.api_key.as_str()),
+                ("api_secret", self.api_secret.as_str()),
+                ("from", self.from.as_str()),
+                // Vonage expects international format without the leading +
+                ("to", to.trim_start_matches('+')),
+                ("text", body),
+            ])
+            .send()
+            .await
+            .map_err(|e| sms_error("Vonage", e))?;
+
+        if !response.status().is_success() {
+            return Err(sms_error("Vonage", response.status()));
+        }
+
+        // Vonage answers 200 and reports failures per message
+        let data: serde_json::Value = response.json().await.map_err(|e| sms_error("Vonage", e))?;
+        let message = &data["messages"][0];
+        if message["status"].as_str() != Some("0") {
+            return Err(sms_error(
+                "Vonage",
+                message["error-text"].as_str().unwrap_or("message rejected"),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A message captured by [`MockSmsProvider`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentSms {
+    pub to: String,
+    pub body: String,
+}
+
+/// Logs messages and keeps them in memory instead of sending them
+#[derive(Default)]
+pub struct MockSmsProvider {
+    sent: Mutex<Vec<SentSms>>,
+}
+
+impl MockSmsProvider {
+    /// Messages sent so far, oldest first
+    pub fn sent(&self) -> Vec<SentSms> {
+        self.sent.lock().map(|sent| sent.clone()).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl SmsProvider for MockSmsProvider {
+    async fn send(&self, to: &str, body: &str) -> Result<()> {
+        tracing::info!("SMS to {}: {}", to, body);
+        if let Ok(mut sent) = self.sent.lock() {
+            sent.push(SentSms {
+                to: to.to_string(),
+                body: body.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config(provider: SmsProviderKind, server: &MockServer) -> SmsConfig {
+        SmsConfig {
+            provider,
+            api_key: "AC123".to_string(),
+            api_secret: "secret".to_string(),
+            from: "+15550001111".to_string(),
+            api_base_url: Some(server.uri()),
+            default_country_code: None,
+            otp_expiry_seconds: 300,
+            max_sends_per_hour: 5,
+            resend_interval_seconds: 60,
+            max_verify_attempts: 5,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_twilio_sends_form_with_basic_auth() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/2010-04-01/Accounts/AC123/Messages.json"))
+            .and(header("authorization", "Basic QUMxMjM6c2VjcmV0"))
+            .and(body_string_contains("To=%2B447700900123"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({ "sid": "SM1" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = TwilioSmsProvider::new(&config(SmsProviderKind::Twilio, &server));
+        provider.send("+447700900123", "Your code is 123456").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_vonage_reports_rejected_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/sms/json"))
+            .and(body_string_contains("to=447700900123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "message-count": "1",
+                "messages": [{ "status": "4", "error-text": "Bad Credentials" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = VonageSmsProvider::new(&config(SmsProviderKind::Vonage, &server));
+        let err = provider.send("+447700900123", "Your code is 123456").await.unwrap_err();
+        assert!(err.to_string().contains("Bad Credentials"));
+    }
+}
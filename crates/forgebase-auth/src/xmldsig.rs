@@ -0,0 +1,444 @@
+// XML digital signature verification (enveloped signatures, exclusive C14N)
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use forgebase_core::{ForgeBaseError, Result};
+use roxmltree::{Document, Node, NodeType};
+use rsa::{pkcs1v15::Pkcs1v15Sign, pkcs8::DecodePublicKey, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::{BTreeMap, HashMap};
+use x509_cert::der::{Decode, Encode};
+
+pub const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const EXC_C14N_WITH_COMMENTS: &str = "http://www.w3.org/2001/10/xml-exc-c14n#WithComments";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+
+/// Digest algorithms accepted in signature references
+#[derive(Debug, Clone, Copy)]
+enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    fn from_digest_uri(uri: &str) -> Result<Self> {
+        match uri {
+            "http://www.w3.org/2000/09/xmldsig#sha1" => Ok(Self::Sha1),
+            "http://www.w3.org/2001/04/xmlenc#sha256" => Ok(Self::Sha256),
+            "http://www.w3.org/2001/04/xmlenc#sha512" => Ok(Self::Sha512),
+            _ => Err(signature_error(format!("unsupported digest method {}", uri))),
+        }
+    }
+
+    fn from_signature_uri(uri: &str) -> Result<Self> {
+        match uri {
+            "http://www.w3.org/2000/09/xmldsig#rsa-sha1" => Ok(Self::Sha1),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => Ok(Self::Sha256),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => Ok(Self::Sha512),
+            _ => Err(signature_error(format!("unsupported signature method {}", uri))),
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha1 => Sha1::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    fn pkcs1v15(&self) -> Pkcs1v15Sign {
+        match self {
+            Self::Sha1 => Pkcs1v15Sign::new::<Sha1>(),
+            Self::Sha256 => Pkcs1v15Sign::new::<Sha256>(),
+            Self::Sha512 => Pkcs1v15Sign::new::<Sha512>(),
+        }
+    }
+}
+
+/// Find a direct child element by namespace and local name
+pub fn child<'a, 'input>(node: Node<'a, 'input>, ns: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|c| c.is_element() && c.tag_name().namespace() == Some(ns) && c.tag_name().name() == name)
+}
+
+/// Does `element` carry an enveloped `ds:Signature` as a direct child?
+pub fn has_signature(element: Node) -> bool {
+    child(element, DSIG_NS, "Signature").is_some()
+}
+
+/// Verify the enveloped signature that is a direct child of `element`.
+///
+/// The signature must reference `element` itself by its `ID` attribute, so a valid signature
+/// elsewhere in the document can never vouch for this element (signature wrapping).
+pub fn verify_enveloped_signature(
+    doc: &Document,
+    element: Node,
+    certificates: &[Vec<u8>],
+) -> Result<()> {
+    let signature = child(element, DSIG_NS, "Signature")
+        .ok_or_else(|| signature_error("element is not signed".to_string()))?;
+    let signed_info = child(signature, DSIG_NS, "SignedInfo")
+        .ok_or_else(|| signature_error("missing SignedInfo".to_string()))?;
+
+    // Canonicalization of SignedInfo itself
+    let c14n_node = child(signed_info, DSIG_NS, "CanonicalizationMethod")
+        .ok_or_else(|| signature_error("missing CanonicalizationMethod".to_string()))?;
+    let c14n_method = c14n_node
+        .attribute("Algorithm")
+        .ok_or_else(|| signature_error("missing CanonicalizationMethod".to_string()))?;
+    let signed_info_c14n = canonicalize_node(
+        doc,
+        signed_info,
+        c14n_method,
+        inclusive_prefixes(Some(c14n_node)),
+        None,
+    )?;
+
+    let signature_method = DigestAlgorithm::from_signature_uri(
+        child(signed_info, DSIG_NS, "SignatureMethod")
+            .and_then(|n| n.attribute("Algorithm"))
+            .ok_or_else(|| signature_error("missing SignatureMethod".to_string()))?,
+    )?;
+
+    // Exactly one reference, and it must point at `element`
+    let references: Vec<Node> = signed_info
+        .children()
+        .filter(|c| {
+            c.is_element()
+                && c.tag_name().namespace() == Some(DSIG_NS)
+                && c.tag_name().name() == "Reference"
+        })
+        .collect();
+    if references.len() != 1 {
+        return Err(signature_error("expected exactly one Reference".to_string()));
+    }
+    let reference = references[0];
+
+    let element_id = element
+        .attribute("ID")
+        .ok_or_else(|| signature_error("signed element has no ID".to_string()))?;
+    if reference.attribute("URI") != Some(&format!("#{}", element_id)) {
+        return Err(signature_error("Reference does not point at the signed element".to_string()));
+    }
+    let id_count = doc
+        .descendants()
+        .filter(|n| n.attribute("ID") == Some(element_id))
+        .count();
+    if id_count != 1 {
+        return Err(signature_error("duplicate element IDs".to_string()));
+    }
+
+    // Transforms: enveloped-signature followed by exclusive C14N
+    let mut enveloped = false;
+    let mut reference_c14n = EXC_C14N.to_string();
+    let mut reference_prefixes = Vec::new();
+    if let Some(transforms) = child(reference, DSIG_NS, "Transforms") {
+        for transform in transforms.children().filter(|c| c.is_element()) {
+            match transform.attribute("Algorithm") {
+                Some(ENVELOPED_SIGNATURE) => enveloped = true,
+                Some(alg @ (EXC_C14N | EXC_C14N_WITH_COMMENTS)) => {
+                    reference_c14n = alg.to_string();
+                    reference_prefixes = inclusive_prefixes(Some(transform));
+                }
+                Some(other) => {
+                    return Err(signature_error(format!("unsupported transform {}", other)))
+                }
+                None => return Err(signature_error("transform without Algorithm".to_string())),
+            }
+        }
+    }
+    if !enveloped {
+        return Err(signature_error("signature is not enveloped".to_string()));
+    }
+
+    let digest_method = DigestAlgorithm::from_digest_uri(
+        child(reference, DSIG_NS, "DigestMethod")
+            .and_then(|n| n.attribute("Algorithm"))
+            .ok_or_else(|| signature_error("missing DigestMethod".to_string()))?,
+    )?;
+    let expected_digest = decode_base64_text(
+        child(reference, DSIG_NS, "DigestValue")
+            .ok_or_else(|| signature_error("missing DigestValue".to_string()))?,
+    )?;
+
+    let element_c14n =
+        canonicalize_node(doc, element, &reference_c14n, reference_prefixes, Some(signature))?;
+    let actual_digest = digest_method.digest(element_c14n.as_bytes());
+    if !constant_time_eq::constant_time_eq(&actual_digest, &expected_digest) {
+        return Err(signature_error("digest mismatch".to_string()));
+    }
+
+    let signature_value = decode_base64_text(
+        child(signature, DSIG_NS, "SignatureValue")
+            .ok_or_else(|| signature_error("missing SignatureValue".to_string()))?,
+    )?;
+    let hashed = signature_method.digest(signed_info_c14n.as_bytes());
+
+    for certificate in certificates {
+        let public_key = match rsa_public_key(certificate) {
+            Ok(key) => key,
+            Err(e) => {
+                tracing::warn!("Skipping unusable IdP certificate: {}", e);
+                continue;
+            }
+        };
+        if public_key
+            .verify(signature_method.pkcs1v15(), &hashed, &signature_value)
+            .is_ok()
+        {
+            return Ok(());
+        }
+    }
+
+    Err(signature_error("signature does not match any trusted certificate".to_string()))
+}
+
+/// Extract the RSA public key from a DER-encoded X.509 certificate
+fn rsa_public_key(certificate_der: &[u8]) -> Result<RsaPublicKey> {
+    let certificate = x509_cert::Certificate::from_der(certificate_der)
+        .map_err(|e| ForgeBaseError::InvalidInput(format!("Invalid X.509 certificate: {}", e)))?;
+    let spki = certificate
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(|e| ForgeBaseError::InvalidInput(format!("Invalid certificate key: {}", e)))?;
+
+    RsaPublicKey::from_public_key_der(&spki)
+        .map_err(|e| ForgeBaseError::InvalidInput(format!("Certificate key is not RSA: {}", e)))
+}
+
+/// Decode base64 element text, ignoring the line breaks IdPs like to insert
+pub fn decode_base64_text(node: Node) -> Result<Vec<u8>> {
+    let text: String = node
+        .text()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    BASE64
+        .decode(text)
+        .map_err(|e| signature_error(format!("invalid base64: {}", e)))
+}
+
+/// PrefixList of an `ec:InclusiveNamespaces` child, if any
+fn inclusive_prefixes(transform: Option<Node>) -> Vec<String> {
+    transform
+        .and_then(|t| {
+            t.children()
+                .find(|c| c.is_element() && c.tag_name().name() == "InclusiveNamespaces")
+        })
+        .and_then(|n| n.attribute("PrefixList"))
+        .map(|list| list.split_whitespace().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+fn canonicalize_node(
+    doc: &Document,
+    node: Node,
+    algorithm: &str,
+    inclusive_prefixes: Vec<String>,
+    exclude: Option<Node>,
+) -> Result<String> {
+    let with_comments = match algorithm {
+        EXC_C14N => false,
+        EXC_C14N_WITH_COMMENTS => true,
+        other => return Err(signature_error(format!("unsupported canonicalization {}", other))),
+    };
+
+    let mut canonicalizer = ExclusiveCanonicalizer {
+        input: doc.input_text(),
+        with_comments,
+        inclusive_prefixes,
+        exclude: exclude.map(|n| n.id()),
+        output: String::new(),
+    };
+    canonicalizer.element(node, &HashMap::new());
+    Ok(canonicalizer.output)
+}
+
+/// Exclusive XML Canonicalization 1.0 of a document subtree
+struct ExclusiveCanonicalizer<'t> {
+    input: &'t str,
+    with_comments: bool,
+    inclusive_prefixes: Vec<String>,
+    exclude: Option<roxmltree::NodeId>,
+    output: String,
+}
+
+impl ExclusiveCanonicalizer<'_> {
+    fn element(&mut self, node: Node, rendered: &HashMap<String, String>) {
+        let qname = self.element_qname(node);
+        let prefix = qname.split_once(':').map(|(p, _)| p).unwrap_or("");
+
+        // Prefixes visibly utilized by this element and its attributes
+        let mut utilized: Vec<String> = vec![prefix.to_string()];
+        let mut attributes: Vec<(&str, &str, String, &str)> = Vec::new();
+        for attribute in node.attributes() {
+            let attr_qname = &self.input[attribute.range_qname()];
+            if let Some((attr_prefix, _)) = attr_qname.split_once(':') {
+                if attr_prefix != "xml" {
+                    utilized.push(attr_prefix.to_string());
+                }
+            }
+            attributes.push((
+                attribute.namespace().unwrap_or(""),
+                attribute.name(),
+                attr_qname.to_string(),
+                attribute.value(),
+            ));
+        }
+        for inclusive in &self.inclusive_prefixes {
+            let inclusive = if inclusive == "#default" { "" } else { inclusive.as_str() };
+            utilized.push(inclusive.to_string());
+        }
+
+        // Namespace declarations not already rendered by an output ancestor
+        let mut declarations: BTreeMap<String, String> = BTreeMap::new();
+        for utilized_prefix in utilized {
+            let uri = if utilized_prefix.is_empty() {
+                node.lookup_namespace_uri(None).unwrap_or("").to_string()
+            } else {
+                match node.lookup_namespace_uri(Some(&utilized_prefix)) {
+                    Some(uri) => uri.to_string(),
+                    None => continue,
+                }
+            };
+            let already = rendered
+                .get(&utilized_prefix)
+                .map(String::as_str)
+                .unwrap_or("");
+            if already != uri {
+                declarations.insert(utilized_prefix, uri);
+            }
+        }
+
+        let mut scope = rendered.clone();
+        self.output.push('<');
+        self.output.push_str(&qname);
+        for (ns_prefix, uri) in &declarations {
+            if ns_prefix.is_empty() {
+                self.output.push_str(" xmlns=\"");
+            } else {
+                self.output.push_str(" xmlns:");
+                self.output.push_str(ns_prefix);
+                self.output.push_str("=\"");
+            }
+            escape_attribute(uri, &mut self.output);
+            self.output.push('"');
+            scope.insert(ns_prefix.clone(), uri.clone());
+        }
+
+        attributes.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        for (_, _, attr_qname, value) in attributes {
+            self.output.push(' ');
+            self.output.push_str(&attr_qname);
+            self.output.push_str("=\"");
+            escape_attribute(value, &mut self.output);
+            self.output.push('"');
+        }
+        self.output.push('>');
+
+        for child in node.children() {
+            if Some(child.id()) == self.exclude {
+                continue;
+            }
+            match child.node_type() {
+                NodeType::Element => self.element(child, &scope),
+                NodeType::Text => escape_text(child.text().unwrap_or_default(), &mut self.output),
+                NodeType::Comment if self.with_comments => {
+                    self.output.push_str("<!--");
+                    self.output.push_str(child.text().unwrap_or_default());
+                    self.output.push_str("-->");
+                }
+                NodeType::PI => {
+                    if let Some(pi) = child.pi() {
+                        self.output.push_str("<?");
+                        self.output.push_str(pi.target);
+                        if let Some(value) = pi.value {
+                            self.output.push(' ');
+                            self.output.push_str(value);
+                        }
+                        self.output.push_str("?>");
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.output.push_str("</");
+        self.output.push_str(&qname);
+        self.output.push('>');
+    }
+
+    /// The element's qualified name exactly as written in the source
+    fn element_qname(&self, node: Node) -> String {
+        let start = node.range().start + 1;
+        self.input[start..]
+            .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+fn escape_text(text: &str, output: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '\r' => output.push_str("&#xD;"),
+            _ => output.push(c),
+        }
+    }
+}
+
+fn escape_attribute(value: &str, output: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '"' => output.push_str("&quot;"),
+            '\t' => output.push_str("&#x9;"),
+            '\n' => output.push_str("&#xA;"),
+            '\r' => output.push_str("&#xD;"),
+            _ => output.push(c),
+        }
+    }
+}
+
+fn signature_error(message: String) -> ForgeBaseError {
+    ForgeBaseError::Auth(format!("Invalid XML signature: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c14n(xml: &str, prefixes: Vec<String>) -> String {
+        let doc = Document::parse(xml).unwrap();
+        canonicalize_node(&doc, doc.root_element(), EXC_C14N, prefixes, None).unwrap()
+    }
+
+    #[test]
+    fn test_exclusive_c14n_renders_only_utilized_namespaces() {
+        let xml = r#"<a:Root xmlns:a="urn:a" xmlns:b="urn:b" xmlns:unused="urn:u"><b:Child z="1" a:y='2&amp;"'>text &lt; more</b:Child><a:Empty/></a:Root>"#;
+        assert_eq!(
+            c14n(xml, vec![]),
+            r#"<a:Root xmlns:a="urn:a"><b:Child xmlns:b="urn:b" z="1" a:y="2&amp;&quot;">text &lt; more</b:Child><a:Empty></a:Empty></a:Root>"#
+        );
+    }
+
+    #[test]
+    fn test_exclusive_c14n_inclusive_prefix_list() {
+        let xml = r#"<Root xmlns="urn:default" xmlns:x="urn:x"><Child/></Root>"#;
+        assert_eq!(
+            c14n(xml, vec!["x".to_string()]),
+            r#"<Root xmlns="urn:default" xmlns:x="urn:x"><Child></Child></Root>"#
+        );
+    }
+}
@@ -0,0 +1,12 @@
+//! Cleanup of user-owned data held by other services
+
+use crate::Result;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Releases data a user owns in a service (storage, sites) when the user is deleted
+#[async_trait]
+pub trait UserDataCleanup: Send + Sync {
+    /// Hard deletion removes the data; soft deletion only takes it offline
+    async fn cleanup_user_data(&self, user_id: Uuid, hard: bool) -> Result<()>;
+}
@@ -1,5 +1,6 @@
 use serde::Deserialize;
 use std::collections::HashMap;
+use uuid::Uuid;
 
 /// Global configuration
 #[derive(Debug, Clone, Deserialize)]
@@ -42,11 +43,42 @@ pub struct DatabaseConfig {
 pub struct AuthConfig {
     pub jwt_secret: String,
     pub jwt_expiration: i64,
+    /// HS256 (shared secret) or RS256, ES256, EdDSA (rotating key pairs published as JWKS)
+    #[serde(default = "default_jwt_algorithm")]
+    pub jwt_algorithm: String,
+    /// Seconds tokens signed with `jwt_secret` still verify after switching to key pairs
+    #[serde(default)]
+    pub legacy_token_grace_seconds: i64,
     pub refresh_token_expiration: i64,
+    /// Seconds a rotated refresh token may still be presented (concurrent refreshes)
+    #[serde(default = "default_refresh_token_reuse_interval")]
+    pub refresh_token_reuse_interval: i64,
     pub password_min_length: usize,
+    #[serde(default)]
+    pub password_policy: PasswordPolicyConfig,
     pub enable_email_verification: bool,
     pub enable_magic_links: bool,
     pub oauth_providers: HashMap<String, OAuthProviderConfig>,
+    pub saml: Option<SamlConfig>,
+    /// Act as an OpenID Connect provider for third-party apps
+    pub oidc_provider: Option<OidcProviderConfig>,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub brute_force: BruteForceConfig,
+    /// Phone sign-in and SMS multi-factor authentication
+    pub sms: Option<SmsConfig>,
+    #[serde(default)]
+    pub anonymous: AnonymousUsersConfig,
+    /// Concurrent sessions per user; the least recently active are ended beyond this
+    pub max_sessions_per_user: Option<usize>,
+    /// Proxy addresses or CIDR ranges whose `X-Forwarded-For` headers are trusted
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    #[serde(default)]
+    pub hooks: AuthHooksConfig,
+    #[serde(default)]
+    pub audit_log: AuditLogConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -55,6 +87,347 @@ pub struct OAuthProviderConfig {
     pub client_secret: String,
     pub redirect_uri: String,
     pub scopes: Vec<String>,
+    /// Issuer URL for generic OpenID Connect providers
+    #[serde(default)]
+    pub issuer: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SamlConfig {
+    /// Entity ID this service provider presents to identity providers
+    pub entity_id: String,
+    /// Assertion consumer service URL (the public URL of `/auth/sso/saml/acs`)
+    pub acs_url: String,
+    #[serde(default = "default_saml_clock_skew")]
+    pub clock_skew_seconds: i64,
+    /// URL prefixes users may be sent back to after single sign-on
+    #[serde(default)]
+    pub allowed_redirect_urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProviderConfig {
+    /// Issuer identifier: the public base URL the `/oauth/*` endpoints are served under
+    pub issuer: String,
+    /// Consent page that forwards its query to `/oauth/authorize`; defaults to
+    /// `{issuer}/oauth/authorize`
+    pub authorization_endpoint: Option<String>,
+    #[serde(default = "default_authorization_code_expiry")]
+    pub authorization_code_expiry_seconds: i64,
+    #[serde(default = "default_id_token_expiration")]
+    pub id_token_expiration: i64,
+}
+
+/// Custom logic called by the auth service: token claims, sign-up gating and event webhooks
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuthHooksConfig {
+    /// Returns extra claims merged into every access token issued for a user (client
+    /// credentials tokens have no user and are left alone)
+    pub custom_access_token: Option<HookConfig>,
+    /// May reject a sign-up before the user is created
+    pub before_user_created: Option<HookConfig>,
+    /// Receive `user.created`, `user.signed_in` and `password.changed` events
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+/// An HTTP endpoint (`url`) or a deployed function (`function_id`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookConfig {
+    pub url: Option<String>,
+    pub function_id: Option<Uuid>,
+    /// Key for the HMAC-SHA256 `webhook-signature` header on HTTP requests
+    pub secret: Option<String>,
+    #[serde(default = "default_hook_timeout")]
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(flatten)]
+    pub hook: HookConfig,
+    /// Events to deliver; all of them when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmsConfig {
+    pub provider: SmsProviderKind,
+    /// Twilio account SID or Vonage API key
+    #[serde(default)]
+    pub api_key: String,
+    /// Twilio auth token or Vonage API secret
+    #[serde(default)]
+    pub api_secret: String,
+    /// Sender number or alphanumeric sender ID
+    #[serde(default)]
+    pub from: String,
+    /// Override the provider's API base URL
+    pub api_base_url: Option<String>,
+    /// Country calling code assumed for numbers without one, e.g. "1"
+    pub default_country_code: Option<String>,
+    #[serde(default = "default_otp_expiry")]
+    pub otp_expiry_seconds: i64,
+    /// Codes sent to one number per hour
+    #[serde(default = "default_otp_max_sends")]
+    pub max_sends_per_hour: u32,
+    /// Minimum seconds between codes sent to one number
+    #[serde(default = "default_otp_resend_interval")]
+    pub resend_interval_seconds: i64,
+    /// Wrong guesses before a code is discarded
+    #[serde(default = "default_otp_max_attempts")]
+    pub max_verify_attempts: i32,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmsProviderKind {
+    Twilio,
+    Vonage,
+    /// Logs codes instead of sending them (development and tests only)
+    Mock,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Where counters live: `memory` (per instance) or `postgres` (shared)
+    #[serde(default)]
+    pub store: RateLimitStoreKind,
+    /// Rules by route group; the longest matching `path_prefix` applies
+    #[serde(default = "default_rate_limit_rules")]
+    pub rules: Vec<RateLimitRule>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitStoreKind {
+    #[default]
+    Memory,
+    Postgres,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitRule {
+    pub path_prefix: String,
+    #[serde(default)]
+    pub per_ip: Option<RateLimitQuota>,
+    #[serde(default)]
+    pub per_user: Option<RateLimitQuota>,
+    #[serde(default)]
+    pub per_api_key: Option<RateLimitQuota>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct RateLimitQuota {
+    pub requests: u32,
+    pub window_seconds: u64,
+}
+
+impl RateLimitQuota {
+    pub const fn new(requests: u32, window_seconds: u64) -> Self {
+        Self {
+            requests,
+            window_seconds,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            store: RateLimitStoreKind::Memory,
+            rules: default_rate_limit_rules(),
+        }
+    }
+}
+
+/// Failed sign-in tracking and lockout
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BruteForceConfig {
+    /// Failures per account within the window before it is locked
+    pub max_account_attempts: u32,
+    /// Failures per IP address within the window before it is locked
+    pub max_ip_attempts: u32,
+    pub attempt_window_seconds: i64,
+    pub lockout_seconds: i64,
+    /// Delay added to the second failure, doubling with each further failure
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for BruteForceConfig {
+    fn default() -> Self {
+        Self {
+            max_account_attempts: 5,
+            max_ip_attempts: 20,
+            attempt_window_seconds: 900,
+            lockout_seconds: 900,
+            base_delay_ms: 250,
+            max_delay_ms: 4000,
+        }
+    }
+}
+
+/// Anonymous sign-ins and cleanup of abandoned anonymous users
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnonymousUsersConfig {
+    pub enabled: bool,
+    /// Anonymous users with no session activity for this long are deleted
+    pub inactive_days: i64,
+    /// How often the cleanup runs
+    pub cleanup_interval_seconds: u64,
+}
+
+impl Default for AnonymousUsersConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            inactive_days: 30,
+            cleanup_interval_seconds: 3600,
+        }
+    }
+}
+
+/// Audit log of security events (sign-ins, password changes, admin actions)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuditLogConfig {
+    pub enabled: bool,
+    /// Entries older than this are deleted
+    pub retention_days: i64,
+    /// How often expired entries are deleted
+    pub cleanup_interval_seconds: u64,
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: 90,
+            cleanup_interval_seconds: 3600,
+        }
+    }
+}
+
+/// Password rules for sign-up and password changes (the minimum length is
+/// `password_min_length`), plus Argon2id hashing cost
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicyConfig {
+    pub max_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// Minimum strength score, from 0 (trivially guessable) to 4 (very strong)
+    pub min_score: u8,
+    /// Reject passwords containing the user's email address or its local part
+    pub reject_email: bool,
+    /// Directory of Have I Been Pwned range files: `<5-char SHA-1 prefix>.txt` holding
+    /// `SUFFIX:COUNT` lines
+    pub breached_passwords_dir: Option<String>,
+    /// Reject passwords seen in at least this many breaches
+    pub breach_threshold: u64,
+    /// Previous passwords that may not be reused (0 disables the history)
+    pub history_size: usize,
+    /// Hashes made with other Argon2 parameters are upgraded at the next sign-in
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        Self {
+            max_length: 128,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_symbol: false,
+            min_score: 2,
+            reject_email: true,
+            breached_passwords_dir: None,
+            breach_threshold: 1,
+            history_size: 0,
+            // Argon2 crate defaults (OWASP minimum for Argon2id)
+            argon2_memory_kib: 19456,
+            argon2_iterations: 2,
+            argon2_parallelism: 1,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_rate_limit_rules() -> Vec<RateLimitRule> {
+    let ip_only = |path_prefix: &str, quota: RateLimitQuota| RateLimitRule {
+        path_prefix: path_prefix.to_string(),
+        per_ip: Some(quota),
+        per_user: None,
+        per_api_key: None,
+    };
+
+    vec![
+        RateLimitRule {
+            path_prefix: "/".to_string(),
+            per_ip: Some(RateLimitQuota::new(300, 60)),
+            per_user: Some(RateLimitQuota::new(600, 60)),
+            per_api_key: Some(RateLimitQuota::new(1200, 60)),
+        },
+        // Credential and one-time-code endpoints are brute-force targets
+        ip_only("/auth/signin", RateLimitQuota::new(10, 300)),
+        ip_only("/auth/password/reset", RateLimitQuota::new(5, 3600)),
+        ip_only("/auth/otp", RateLimitQuota::new(5, 3600)),
+        ip_only("/auth/verify", RateLimitQuota::new(10, 300)),
+    ]
+}
+
+fn default_jwt_algorithm() -> String {
+    "HS256".to_string()
+}
+
+fn default_refresh_token_reuse_interval() -> i64 {
+    10
+}
+
+fn default_saml_clock_skew() -> i64 {
+    120
+}
+
+fn default_authorization_code_expiry() -> i64 {
+    600
+}
+
+fn default_id_token_expiration() -> i64 {
+    3600
+}
+
+fn default_hook_timeout() -> u64 {
+    2000
+}
+
+fn default_otp_expiry() -> i64 {
+    300
+}
+
+fn default_otp_max_sends() -> u32 {
+    5
+}
+
+fn default_otp_resend_interval() -> i64 {
+    60
+}
+
+fn default_otp_max_attempts() -> i32 {
+    5
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -85,6 +458,59 @@ pub struct EmailConfig {
     pub smtp_password: String,
     pub from_email: String,
     pub from_name: String,
+    /// `starttls` upgrades a plain connection, `tls` connects over TLS (usually port 465)
+    #[serde(default)]
+    pub smtp_tls: SmtpTlsMode,
+    /// `smtp` delivers mail; `file` and `log` capture it for development and tests
+    #[serde(default)]
+    pub transport: EmailTransportKind,
+    /// Directory `file` transport writes `.eml` files to
+    #[serde(default = "default_email_file_dir")]
+    pub file_dir: String,
+    /// Directory of `<locale>/<template>.{subject,html,txt}` files overriding the built-in templates
+    pub templates_dir: Option<String>,
+    #[serde(default = "default_email_locale")]
+    pub default_locale: String,
+    /// Base URL of the site that links in emails point to
+    #[serde(default = "default_site_url")]
+    pub site_url: String,
+    /// Delivery attempts before an outbox message is marked failed
+    #[serde(default = "default_email_max_attempts")]
+    pub max_attempts: i32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTlsMode {
+    #[default]
+    Starttls,
+    Tls,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransportKind {
+    #[default]
+    Smtp,
+    File,
+    Log,
+}
+
+fn default_email_file_dir() -> String {
+    "./data/mail".to_string()
+}
+
+fn default_email_locale() -> String {
+    "en".to_string()
+}
+
+fn default_site_url() -> String {
+    "http://localhost:3000".to_string()
+}
+
+fn default_email_max_attempts() -> i32 {
+    5
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -124,11 +550,25 @@ impl Default for AuthConfig {
         Self {
             jwt_secret: "dev-secret-key-change-in-production".to_string(),
             jwt_expiration: 3600,
+            jwt_algorithm: default_jwt_algorithm(),
+            legacy_token_grace_seconds: 0,
             refresh_token_expiration: 2592000,
+            refresh_token_reuse_interval: default_refresh_token_reuse_interval(),
             password_min_length: 8,
+            password_policy: PasswordPolicyConfig::default(),
             enable_email_verification: true,
             enable_magic_links: true,
             oauth_providers: HashMap::new(),
+            saml: None,
+            oidc_provider: None,
+            rate_limit: RateLimitConfig::default(),
+            brute_force: BruteForceConfig::default(),
+            sms: None,
+            anonymous: AnonymousUsersConfig::default(),
+            max_sessions_per_user: None,
+            trusted_proxies: Vec::new(),
+            hooks: AuthHooksConfig::default(),
+            audit_log: AuditLogConfig::default(),
         }
     }
 }
@@ -156,6 +596,13 @@ impl Default for EmailConfig {
             smtp_password: String::new(),
             from_email: "noreply@forgebase.dev".to_string(),
             from_name: "ForgeBase".to_string(),
+            smtp_tls: SmtpTlsMode::Starttls,
+            transport: EmailTransportKind::Smtp,
+            file_dir: default_email_file_dir(),
+            templates_dir: None,
+            default_locale: default_email_locale(),
+            site_url: default_site_url(),
+            max_attempts: default_email_max_attempts(),
         }
     }
 }
@@ -0,0 +1,16 @@
+//! Invocation of deployed functions by other services
+
+use crate::Result;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Runs a deployed function with a JSON payload and returns its JSON output
+/// (auth hooks backed by functions)
+#[async_trait]
+pub trait FunctionInvoker: Send + Sync {
+    async fn invoke_function(
+        &self,
+        function_id: Uuid,
+        payload: serde_json::Value,
+    ) -> Result<serde_json::Value>;
+}
@@ -1,8 +1,12 @@
+pub mod cleanup;
 pub mod config;
 pub mod error;
+pub mod functions;
 pub mod types;
 pub mod utils;
 
+pub use cleanup::*;
 pub use config::*;
 pub use error::*;
+pub use functions::*;
 pub use types::*;
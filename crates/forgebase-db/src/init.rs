@@ -22,6 +22,103 @@ pub async fn init_database(pool: &PgPool) -> Result<()> {
             up_sql: include_str!("../../../migrations/002_create_sites_tables.sql").to_string(),
             down_sql: "-- Not implemented".to_string(),
         },
+        Migration {
+            version: 3,
+            name: "create_saml_tables".to_string(),
+            up_sql: include_str!("../../../migrations/003_create_saml_tables.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS saml_requests; DROP TABLE IF EXISTS saml_providers;".to_string(),
+        },
+        Migration {
+            version: 4,
+            name: "rotate_refresh_tokens".to_string(),
+            up_sql: include_str!("../../../migrations/004_rotate_refresh_tokens.sql").to_string(),
+            // Hashed tokens cannot be restored, so rolling back signs everyone out
+            down_sql: "DROP TABLE IF EXISTS refresh_tokens; DELETE FROM sessions; ALTER TABLE sessions ADD COLUMN refresh_token TEXT NOT NULL UNIQUE;".to_string(),
+        },
+        Migration {
+            version: 5,
+            name: "create_signing_keys".to_string(),
+            up_sql: include_str!("../../../migrations/005_create_signing_keys.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS jwt_signing_keys;".to_string(),
+        },
+        Migration {
+            version: 6,
+            name: "create_rate_limits".to_string(),
+            up_sql: include_str!("../../../migrations/006_create_rate_limits.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS rate_limits;".to_string(),
+        },
+        Migration {
+            version: 7,
+            name: "create_sign_in_lockouts".to_string(),
+            up_sql: include_str!("../../../migrations/007_create_sign_in_lockouts.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS sign_in_lockouts; DROP TABLE IF EXISTS sign_in_failures;".to_string(),
+        },
+        Migration {
+            version: 8,
+            name: "create_email_outbox".to_string(),
+            up_sql: include_str!("../../../migrations/008_create_email_outbox.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS email_outbox;".to_string(),
+        },
+        Migration {
+            version: 9,
+            name: "phone_auth".to_string(),
+            up_sql: include_str!("../../../migrations/009_phone_auth.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS mfa_factors; DROP TABLE IF EXISTS phone_otps; ALTER TABLE sessions DROP COLUMN IF EXISTS aal; DROP INDEX IF EXISTS idx_users_phone; ALTER TABLE users ALTER COLUMN email SET NOT NULL;".to_string(),
+        },
+        Migration {
+            version: 10,
+            name: "session_activity".to_string(),
+            up_sql: include_str!("../../../migrations/010_session_activity.sql").to_string(),
+            down_sql: "DROP INDEX IF EXISTS idx_sessions_user_last_active; ALTER TABLE sessions DROP COLUMN IF EXISTS last_active_at;".to_string(),
+        },
+        Migration {
+            version: 11,
+            name: "admin_users".to_string(),
+            up_sql: include_str!("../../../migrations/011_admin_users.sql").to_string(),
+            down_sql: "DROP INDEX IF EXISTS idx_users_deleted_at; ALTER TABLE users DROP COLUMN IF EXISTS deleted_at; ALTER TABLE users DROP COLUMN IF EXISTS banned_until;".to_string(),
+        },
+        Migration {
+            version: 12,
+            name: "password_history".to_string(),
+            up_sql: include_str!("../../../migrations/012_password_history.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS password_history;".to_string(),
+        },
+        Migration {
+            version: 13,
+            name: "organizations".to_string(),
+            up_sql: include_str!("../../../migrations/013_organizations.sql").to_string(),
+            down_sql: "ALTER TABLE IF EXISTS functions DROP COLUMN IF EXISTS organization_id; ALTER TABLE IF EXISTS storage_buckets DROP COLUMN IF EXISTS organization_id; DROP INDEX IF EXISTS idx_sites_organization; ALTER TABLE sites DROP COLUMN IF EXISTS organization_id; DROP TABLE IF EXISTS organization_invitations; DROP TABLE IF EXISTS organization_members; DROP TABLE IF EXISTS organizations;".to_string(),
+        },
+        Migration {
+            version: 14,
+            name: "oauth_provider".to_string(),
+            up_sql: include_str!("../../../migrations/014_oauth_provider.sql").to_string(),
+            down_sql: "ALTER TABLE sessions DROP COLUMN IF EXISTS scope; ALTER TABLE sessions DROP COLUMN IF EXISTS client_id; DROP TABLE IF EXISTS oauth_consents; DROP TABLE IF EXISTS oauth_authorization_codes; DROP TABLE IF EXISTS oauth_clients;".to_string(),
+        },
+        Migration {
+            version: 15,
+            name: "auth_audit_log".to_string(),
+            up_sql: include_str!("../../../migrations/015_auth_audit_log.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS auth_audit_log;".to_string(),
+        },
+        Migration {
+            version: 16,
+            name: "storage_s3_credentials".to_string(),
+            up_sql: include_str!("../../../migrations/016_storage_s3_credentials.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS storage_s3_credentials;".to_string(),
+        },
+        Migration {
+            version: 17,
+            name: "storage_objects".to_string(),
+            up_sql: include_str!("../../../migrations/017_storage_objects.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS storage_objects;".to_string(),
+        },
+        Migration {
+            version: 18,
+            name: "storage_policies".to_string(),
+            up_sql: include_str!("../../../migrations/018_storage_policies.sql").to_string(),
+            down_sql: "DROP TABLE IF EXISTS storage_policies;".to_string(),
+        },
     ];
 
     // Run migrations
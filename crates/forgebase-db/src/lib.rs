@@ -9,6 +9,8 @@ pub mod migrations;
 pub mod schema;
 pub mod backups;
 pub mod init;
+#[cfg(feature = "test-support")]
+pub mod testing;
 
 pub use pool::*;
 pub use query::*;
@@ -1,7 +1,7 @@
 //! Database migration management
 
 use forgebase_core::{ForgeBaseError, Result};
-use sqlx::PgPool;
+use sqlx::{Executor, PgPool};
 use std::collections::HashMap;
 
 /// Migration metadata
@@ -67,9 +67,8 @@ impl MigrationManager {
             .await
             .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
 
-        // Execute migration SQL
-        sqlx::query(&migration.up_sql)
-            .execute(&mut *tx)
+        // Execute migration SQL; unprepared, as migrations hold several statements
+        tx.execute(migration.up_sql.as_str())
             .await
             .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
 
@@ -99,8 +98,7 @@ impl MigrationManager {
             .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
 
         // Execute rollback SQL
-        sqlx::query(&migration.down_sql)
-            .execute(&mut *tx)
+        tx.execute(migration.down_sql.as_str())
             .await
             .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
 
@@ -0,0 +1,33 @@
+//! Database for tests that need one
+
+use crate::init::init_database;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use tokio::sync::OnceCell;
+
+/// Environment variable with the URL of the database tests may use (and fill with test data)
+pub const TEST_DATABASE_URL_ENV: &str = "FORGEBASE_TEST_DATABASE_URL";
+
+static MIGRATED: OnceCell<()> = OnceCell::const_new();
+
+/// A pool on the migrated test database, or `None` when `FORGEBASE_TEST_DATABASE_URL` is
+/// not set, in which case the calling test should return early
+pub async fn test_pool() -> Option<PgPool> {
+    let Ok(url) = std::env::var(TEST_DATABASE_URL_ENV) else {
+        eprintln!("{} is not set, skipping database test", TEST_DATABASE_URL_ENV);
+        return None;
+    };
+
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&url)
+        .await
+        .expect("Failed to connect to the test database");
+    MIGRATED
+        .get_or_init(|| async {
+            init_database(&pool)
+                .await
+                .expect("Failed to migrate the test database")
+        })
+        .await;
+    Some(pool)
+}
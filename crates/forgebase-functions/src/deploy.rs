@@ -1,6 +1,7 @@
 //! Function deployment
 
 use crate::models::*;
+use forgebase_auth::organization::{OrganizationRepository, OrganizationRole};
 use forgebase_core::{ForgeBaseError, Result};
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -9,15 +10,23 @@ use uuid::Uuid;
 /// Function deployer
 pub struct FunctionDeployer {
     pool: PgPool,
+    organizations: OrganizationRepository,
 }
 
 impl FunctionDeployer {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            organizations: OrganizationRepository::new(pool.clone()),
+            pool,
+        }
     }
 
-    /// Deploy a new function
+    /// Deploy a new function; deploying for an organization takes at least the developer role
     pub async fn deploy(&self, request: DeployRequest, owner_id: Uuid) -> Result<DeployResponse> {
+        if let Some(organization_id) = request.organization_id {
+            ensure_can_deploy(self.organizations.member_role(organization_id, owner_id).await?)?;
+        }
+
         // Validate function code
         self.validate_code(&request.runtime, &request.code)?;
 
@@ -37,6 +46,7 @@ impl FunctionDeployer {
             is_active: true,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
+            organization_id: request.organization_id,
         };
 
         // Save to database
@@ -97,42 +107,28 @@ impl FunctionDeployer {
 
     /// List functions for a user
     pub async fn list_functions(&self, owner_id: Uuid) -> Result<Vec<Function>> {
-        let rows = sqlx::query_as::<_, (Uuid, String, Uuid, String, Vec<u8>, String, Vec<u8>, i32, i32, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
-            "SELECT id, name, owner_id, runtime, code, entry_point, environment, memory_limit_mb, timeout_seconds, is_active, created_at, updated_at FROM functions WHERE owner_id = $1 ORDER BY created_at DESC"
+        let rows = sqlx::query_as::<_, FunctionRow>(
+            "SELECT id, name, owner_id, runtime, code, entry_point, environment, memory_limit_mb, timeout_seconds, is_active, created_at, updated_at, organization_id FROM functions WHERE owner_id = $1 ORDER BY created_at DESC"
         )
         .bind(owner_id)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
 
-        Ok(rows
-            .into_iter()
-            .map(|row| {
-                let runtime = match row.3.as_str() {
-                    "wasm" => crate::models::FunctionRuntime::Wasm,
-                    "javascript" => crate::models::FunctionRuntime::JavaScript,
-                    "python" => crate::models::FunctionRuntime::Python,
-                    "rust" => crate::models::FunctionRuntime::Rust,
-                    _ => crate::models::FunctionRuntime::Wasm,
-                };
-                let environment: HashMap<String, String> =
-                    serde_json::from_slice(&row.6).unwrap_or_default();
-                Function {
-                    id: row.0,
-                    name: row.1,
-                    owner_id: row.2,
-                    runtime,
-                    code: row.4,
-                    entry_point: row.5,
-                    environment,
-                    memory_limit_mb: row.7,
-                    timeout_seconds: row.8,
-                    is_active: row.9,
-                    created_at: row.10,
-                    updated_at: row.11,
-                }
-            })
-            .collect())
+        Ok(rows.into_iter().map(function_from_row).collect())
+    }
+
+    /// List functions owned by an organization
+    pub async fn list_organization_functions(&self, organization_id: Uuid) -> Result<Vec<Function>> {
+        let rows = sqlx::query_as::<_, FunctionRow>(
+            "SELECT id, name, owner_id, runtime, code, entry_point, environment, memory_limit_mb, timeout_seconds, is_active, created_at, updated_at, organization_id FROM functions WHERE organization_id = $1 ORDER BY created_at DESC"
+        )
+        .bind(organization_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(function_from_row).collect())
     }
 
     /// Validate function code
@@ -165,8 +161,8 @@ impl FunctionDeployer {
         sqlx::query(
             r#"
             INSERT INTO functions 
-            (id, name, owner_id, runtime, code, entry_point, environment, memory_limit_mb, timeout_seconds, is_active, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            (id, name, owner_id, runtime, code, entry_point, environment, memory_limit_mb, timeout_seconds, is_active, created_at, updated_at, organization_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             "#,
         )
         .bind(function.id)
@@ -181,6 +177,7 @@ impl FunctionDeployer {
         .bind(function.is_active)
         .bind(function.created_at)
         .bind(function.updated_at)
+        .bind(function.organization_id)
         .execute(&self.pool)
         .await
         .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
@@ -188,3 +185,118 @@ impl FunctionDeployer {
         Ok(())
     }
 }
+
+/// Reject deploys by non-members and viewers of an organization
+fn ensure_can_deploy(role: Option<OrganizationRole>) -> Result<()> {
+    match role {
+        Some(role) if role.can_deploy() => Ok(()),
+        _ => Err(ForgeBaseError::Authorization(
+            "Deploying for this organization takes the developer role".to_string(),
+        )),
+    }
+}
+
+type FunctionRow = (Uuid, String, Uuid, String, Vec<u8>, String, Vec<u8>, i32, i32, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>, Option<Uuid>);
+
+fn function_from_row(row: FunctionRow) -> Function {
+    let runtime = match row.3.as_str() {
+        "wasm" => crate::models::FunctionRuntime::Wasm,
+        "javascript" => crate::models::FunctionRuntime::JavaScript,
+        "python" => crate::models::FunctionRuntime::Python,
+        "rust" => crate::models::FunctionRuntime::Rust,
+        _ => crate::models::FunctionRuntime::Wasm,
+    };
+    let environment: HashMap<String, String> = serde_json::from_slice(&row.6).unwrap_or_default();
+    Function {
+        id: row.0,
+        name: row.1,
+        owner_id: row.2,
+        runtime,
+        code: row.4,
+        entry_point: row.5,
+        environment,
+        memory_limit_mb: row.7,
+        timeout_seconds: row.8,
+        is_active: row.9,
+        created_at: row.10,
+        updated_at: row.11,
+        organization_id: row.12,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use forgebase_auth::models::Organization;
+    use forgebase_db::testing::test_pool;
+
+    async fn create_user(pool: &PgPool) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query("INSERT INTO users (id, email) VALUES ($1, $2)")
+            .bind(id)
+            .bind(format!("{}@example.com", id))
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    fn request(organization_id: Uuid) -> DeployRequest {
+        DeployRequest {
+            name: "hello".to_string(),
+            runtime: FunctionRuntime::JavaScript,
+            code: "export default () => 'hello'".to_string(),
+            entry_point: "default".to_string(),
+            environment: None,
+            memory_limit_mb: None,
+            timeout_seconds: None,
+            organization_id: Some(organization_id),
+        }
+    }
+
+    #[test]
+    fn test_ensure_can_deploy() {
+        assert!(ensure_can_deploy(Some(OrganizationRole::Developer)).is_ok());
+        assert!(ensure_can_deploy(Some(OrganizationRole::Owner)).is_ok());
+        assert!(matches!(
+            ensure_can_deploy(Some(OrganizationRole::Viewer)),
+            Err(ForgeBaseError::Authorization(_))
+        ));
+        assert!(matches!(ensure_can_deploy(None), Err(ForgeBaseError::Authorization(_))));
+    }
+
+    #[tokio::test]
+    async fn test_deploy_into_organization_requires_membership() {
+        let Some(pool) = test_pool().await else { return };
+        let owner = create_user(&pool).await;
+        let organization_id = Uuid::new_v4();
+        let organizations = OrganizationRepository::new(pool.clone());
+        organizations
+            .create(
+                &Organization {
+                    id: organization_id,
+                    name: "Acme".to_string(),
+                    slug: format!("acme-{}", organization_id),
+                    created_by: Some(owner),
+                    created_at: chrono::Utc::now(),
+                    updated_at: chrono::Utc::now(),
+                },
+                owner,
+            )
+            .await
+            .unwrap();
+        let deployer = FunctionDeployer::new(pool.clone());
+
+        let outsider = create_user(&pool).await;
+        let result = deployer.deploy(request(organization_id), outsider).await;
+        assert!(matches!(result, Err(ForgeBaseError::Authorization(_))));
+
+        let viewer = create_user(&pool).await;
+        organizations
+            .add_member(organization_id, viewer, OrganizationRole::Viewer)
+            .await
+            .unwrap();
+        let result = deployer.deploy(request(organization_id), viewer).await;
+        assert!(matches!(result, Err(ForgeBaseError::Authorization(_))));
+    }
+}
@@ -2,7 +2,8 @@
 
 use crate::models::*;
 use crate::runtime::{FunctionRuntime as WasmRuntime, RuntimeConfig};
-use forgebase_core::{ForgeBaseError, Result};
+use async_trait::async_trait;
+use forgebase_core::{ForgeBaseError, FunctionInvoker, Result};
 use sqlx::PgPool;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -107,8 +108,8 @@ impl FunctionExecutor {
 
     /// Get function by ID
     async fn get_function(&self, function_id: Uuid) -> Result<Function> {
-        sqlx::query_as::<_, (Uuid, String, Uuid, String, Vec<u8>, String, Vec<u8>, i32, i32, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
-            "SELECT id, name, owner_id, runtime, code, entry_point, environment, memory_limit_mb, timeout_seconds, is_active, created_at, updated_at FROM functions WHERE id = $1"
+        sqlx::query_as::<_, (Uuid, String, Uuid, String, Vec<u8>, String, Vec<u8>, i32, i32, bool, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>, Option<Uuid>)>(
+            "SELECT id, name, owner_id, runtime, code, entry_point, environment, memory_limit_mb, timeout_seconds, is_active, created_at, updated_at, organization_id FROM functions WHERE id = $1"
         )
         .bind(function_id)
         .fetch_optional(&self.pool)
@@ -136,6 +137,7 @@ impl FunctionExecutor {
                 is_active: row.9,
                 created_at: row.10,
                 updated_at: row.11,
+                organization_id: row.12,
             }
         })
         .ok_or_else(|| ForgeBaseError::NotFound("Function not found".to_string()))
@@ -170,3 +172,29 @@ impl FunctionExecutor {
         Ok(())
     }
 }
+
+#[async_trait]
+impl FunctionInvoker for FunctionExecutor {
+    async fn invoke_function(
+        &self,
+        function_id: Uuid,
+        payload: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let response = self
+            .execute(InvocationRequest {
+                function_id,
+                payload,
+                headers: HashMap::new(),
+                query_params: HashMap::new(),
+            })
+            .await?;
+
+        if response.status_code >= 400 {
+            return Err(ForgeBaseError::ExternalService(format!(
+                "Function {} failed: {}",
+                function_id, response.body
+            )));
+        }
+        Ok(response.body)
+    }
+}
@@ -19,6 +19,8 @@ pub struct Function {
     pub is_active: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Set when the function belongs to an organization; `owner_id` is then its creator
+    pub organization_id: Option<Uuid>,
 }
 
 /// Function runtime type
@@ -60,6 +62,9 @@ pub struct DeployRequest {
     pub environment: Option<HashMap<String, String>>,
     pub memory_limit_mb: Option<i32>,
     pub timeout_seconds: Option<i32>,
+    /// Deploy on behalf of an organization the caller belongs to
+    #[serde(default)]
+    pub organization_id: Option<Uuid>,
 }
 
 /// Function deployment response
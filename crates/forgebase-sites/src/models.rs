@@ -21,6 +21,7 @@ pub struct Site {
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub organization_id: Option<Uuid>, // Owning organization; user_id is then the creator
 }
 
 /// Site framework detection
@@ -22,6 +22,48 @@ impl SiteRepository {
         // TODO: Implement
         unimplemented!()
     }
+
+    /// Take all of a user's personal sites offline
+    pub async fn deactivate_for_user(&self, user_id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE sites SET is_active = FALSE, updated_at = NOW() WHERE user_id = $1 AND organization_id IS NULL",
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to deactivate sites: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Hand a user's organization sites to another owner of each organization
+    pub async fn reassign_organization_sites(&self, user_id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE sites s SET user_id = m.user_id, updated_at = NOW()
+            FROM organization_members m
+            WHERE s.user_id = $1 AND m.organization_id = s.organization_id
+              AND m.role = 'owner' AND m.user_id <> $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to reassign sites: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Delete all of a user's sites with their deployments and domains
+    pub async fn delete_for_user(&self, user_id: Uuid) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM sites WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to delete sites: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
 }
 
 pub struct DeploymentRepository {
@@ -1,8 +1,10 @@
 // Service layer for sites
 use crate::{deployment::DeploymentManager, domain::DomainManager, repository::*, models::*};
-use forgebase_core::Result;
+use async_trait::async_trait;
+use forgebase_core::{Result, UserDataCleanup};
 use sqlx::PgPool;
 use std::path::PathBuf;
+use uuid::Uuid;
 
 pub struct SitesService {
     site_repo: SiteRepository,
@@ -25,3 +27,17 @@ impl SitesService {
 
     // TODO: Implement service methods
 }
+
+#[async_trait]
+impl UserDataCleanup for SitesService {
+    async fn cleanup_user_data(&self, user_id: Uuid, hard: bool) -> Result<()> {
+        if hard {
+            // Organization sites outlive their creator
+            self.site_repo.reassign_organization_sites(user_id).await?;
+            self.site_repo.delete_for_user(user_id).await?;
+        } else {
+            self.site_repo.deactivate_for_user(user_id).await?;
+        }
+        Ok(())
+    }
+}
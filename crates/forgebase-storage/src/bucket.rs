@@ -1,8 +1,12 @@
 //! Bucket management
 
-use forgebase_core::{ForgeBaseError, Result};
+use crate::service::StorageService;
+use async_trait::async_trait;
+use forgebase_auth::organization::OrganizationRole;
+use forgebase_core::{ForgeBaseError, Result, UserDataCleanup};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Bucket configuration
@@ -16,6 +20,8 @@ pub struct Bucket {
     pub allowed_mime_types: Option<Vec<String>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Set when the bucket belongs to an organization; `owner_id` is then its creator
+    pub organization_id: Option<Uuid>,
 }
 
 /// Bucket manager
@@ -30,11 +36,11 @@ impl BucketManager {
 
     /// Create a new bucket
     pub async fn create_bucket(&self, bucket: &Bucket) -> Result<Bucket> {
-        let result = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
+        let result = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>, Option<Uuid>)>(
             r#"
-            INSERT INTO storage_buckets (id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at
+            INSERT INTO storage_buckets (id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at, organization_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at, organization_id
             "#,
         )
         .bind(bucket.id)
@@ -45,6 +51,7 @@ impl BucketManager {
         .bind(&bucket.allowed_mime_types)
         .bind(bucket.created_at)
         .bind(bucket.updated_at)
+        .bind(bucket.organization_id)
         .fetch_one(&self.pool)
         .await
         .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
@@ -58,13 +65,14 @@ impl BucketManager {
             allowed_mime_types: result.5,
             created_at: result.6,
             updated_at: result.7,
+            organization_id: result.8,
         })
     }
 
     /// Get a bucket by name
     pub async fn get_bucket(&self, name: &str) -> Result<Option<Bucket>> {
-        let result = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
-            "SELECT id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at FROM storage_buckets WHERE name = $1",
+        let result = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>, Option<Uuid>)>(
+            "SELECT id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at, organization_id FROM storage_buckets WHERE name = $1",
         )
         .bind(name)
         .fetch_optional(&self.pool)
@@ -80,13 +88,14 @@ impl BucketManager {
             allowed_mime_types: r.5,
             created_at: r.6,
             updated_at: r.7,
+            organization_id: r.8,
         }))
     }
 
     /// List buckets for a user
     pub async fn list_buckets(&self, owner_id: Uuid) -> Result<Vec<Bucket>> {
-        let results = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
-            "SELECT id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at FROM storage_buckets WHERE owner_id = $1 ORDER BY created_at DESC",
+        let results = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>, Option<Uuid>)>(
+            "SELECT id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at, organization_id FROM storage_buckets WHERE owner_id = $1 ORDER BY created_at DESC",
         )
         .bind(owner_id)
         .fetch_all(&self.pool)
@@ -104,18 +113,45 @@ impl BucketManager {
                 allowed_mime_types: r.5,
                 created_at: r.6,
                 updated_at: r.7,
+                organization_id: r.8,
+            })
+            .collect())
+    }
+
+    /// List buckets owned by an organization
+    pub async fn list_organization_buckets(&self, organization_id: Uuid) -> Result<Vec<Bucket>> {
+        let results = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>, Option<Uuid>)>(
+            "SELECT id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at, organization_id FROM storage_buckets WHERE organization_id = $1 ORDER BY created_at DESC",
+        )
+        .bind(organization_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        Ok(results
+            .into_iter()
+            .map(|r| Bucket {
+                id: r.0,
+                name: r.1,
+                owner_id: r.2,
+                is_public: r.3,
+                max_file_size: r.4,
+                allowed_mime_types: r.5,
+                created_at: r.6,
+                updated_at: r.7,
+                organization_id: r.8,
             })
             .collect())
     }
 
     /// Update a bucket
     pub async fn update_bucket(&self, bucket: &Bucket) -> Result<Bucket> {
-        let result = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
+        let result = sqlx::query_as::<_, (Uuid, String, Uuid, bool, Option<i64>, Option<Vec<String>>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>, Option<Uuid>)>(
             r#"
             UPDATE storage_buckets 
             SET is_public = $1, max_file_size = $2, allowed_mime_types = $3, updated_at = $4
             WHERE id = $5
-            RETURNING id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at
+            RETURNING id, name, owner_id, is_public, max_file_size, allowed_mime_types, created_at, updated_at, organization_id
             "#,
         )
         .bind(bucket.is_public)
@@ -136,6 +172,7 @@ impl BucketManager {
             allowed_mime_types: result.5,
             created_at: result.6,
             updated_at: result.7,
+            organization_id: result.8,
         })
     }
 
@@ -149,4 +186,89 @@ impl BucketManager {
 
         Ok(())
     }
+
+    /// Make every personal (not organization) bucket of an owner private
+    pub async fn make_private_for_owner(&self, owner_id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE storage_buckets SET is_public = FALSE, updated_at = NOW() WHERE owner_id = $1 AND organization_id IS NULL",
+        )
+        .bind(owner_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Hand an owner's organization buckets to another owner of each organization
+    pub async fn reassign_organization_buckets(&self, owner_id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE storage_buckets b SET owner_id = m.user_id, updated_at = NOW()
+            FROM organization_members m
+            WHERE b.owner_id = $1 AND m.organization_id = b.organization_id
+              AND m.role = 'owner' AND m.user_id <> $1
+            "#,
+        )
+        .bind(owner_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// A user's role on a bucket: their role in the organization owning it, or owner of their
+    /// own buckets; `None` when they have no part in it
+    pub async fn member_role(&self, bucket: &Bucket, user_id: Uuid) -> Result<Option<OrganizationRole>> {
+        let Some(organization_id) = bucket.organization_id else {
+            return Ok((bucket.owner_id == user_id).then_some(OrganizationRole::Owner));
+        };
+
+        sqlx::query_scalar::<_, OrganizationRole>(
+            "SELECT role FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))
+    }
+}
+
+/// Removes a deleted user's buckets and files; organization buckets they created pass to
+/// another owner of the organization when there is one.
+///
+/// Soft deletion only makes the buckets private so nothing stays publicly served.
+pub struct StorageUserCleanup {
+    buckets: BucketManager,
+    storage: Arc<StorageService>,
+}
+
+impl StorageUserCleanup {
+    pub fn new(pool: PgPool, storage: Arc<StorageService>) -> Self {
+        Self {
+            buckets: BucketManager::new(pool),
+            storage,
+        }
+    }
+}
+
+#[async_trait]
+impl UserDataCleanup for StorageUserCleanup {
+    async fn cleanup_user_data(&self, user_id: Uuid, hard: bool) -> Result<()> {
+        if !hard {
+            self.buckets.make_private_for_owner(user_id).await?;
+            return Ok(());
+        }
+
+        self.buckets.reassign_organization_buckets(user_id).await?;
+        // Whatever is still theirs has no other owner to go to
+        for bucket in self.buckets.list_buckets(user_id).await? {
+            self.storage.delete_all_files(&bucket.name).await?;
+            self.buckets.delete_bucket(bucket.id).await?;
+        }
+
+        Ok(())
+    }
 }
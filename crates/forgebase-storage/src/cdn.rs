@@ -5,6 +5,9 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Most entries the cache holds, however small they are
+const MAX_CACHE_ENTRIES: usize = 1000;
+
 /// CDN cache entry
 #[derive(Debug, Clone)]
 struct CacheEntry {
@@ -13,6 +16,27 @@ struct CacheEntry {
     expires_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Cached entries and their total size
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    bytes: usize,
+}
+
+impl CacheState {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.data.len();
+        }
+    }
+
+    fn evict_expired(&mut self) {
+        let now = chrono::Utc::now();
+        self.entries.retain(|_, entry| entry.expires_at > now);
+        self.bytes = self.entries.values().map(|entry| entry.data.len()).sum();
+    }
+}
+
 /// CDN configuration
 #[derive(Debug, Clone)]
 pub struct CdnConfig {
@@ -36,14 +60,14 @@ impl Default for CdnConfig {
 /// CDN manager
 pub struct CdnManager {
     config: CdnConfig,
-    cache: Arc<RwLock<HashMap<String, CacheEntry>>>,
+    cache: Arc<RwLock<CacheState>>,
 }
 
 impl CdnManager {
     pub fn new(config: CdnConfig) -> Self {
         Self {
             config,
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            cache: Arc::new(RwLock::new(CacheState::default())),
         }
     }
 
@@ -54,7 +78,7 @@ impl CdnManager {
         }
 
         let cache = self.cache.read().await;
-        if let Some(entry) = cache.get(key) {
+        if let Some(entry) = cache.entries.get(key) {
             if entry.expires_at > chrono::Utc::now() {
                 return Some((entry.data.clone(), entry.content_type.clone()));
             }
@@ -64,13 +88,17 @@ impl CdnManager {
     }
 
     /// Cache a file
+    ///
+    /// The cache is held to `max_cache_size_mb` and [`MAX_CACHE_ENTRIES`]: expired entries go
+    /// first, then those closest to expiring. Files larger than the whole budget are not cached.
     pub async fn cache_file(
         &self,
         key: &str,
         data: bytes::Bytes,
         content_type: Option<String>,
     ) -> Result<()> {
-        if !self.config.enable_caching {
+        let budget = self.config.max_cache_size_mb * 1024 * 1024;
+        if !self.config.enable_caching || data.len() > budget {
             return Ok(());
         }
 
@@ -84,12 +112,28 @@ impl CdnManager {
         };
 
         let mut cache = self.cache.write().await;
-        cache.insert(key.to_string(), entry);
+        cache.remove(key);
 
-        // Simple cache size management
-        if cache.len() > 1000 {
-            self.evict_expired(&mut cache).await;
+        let over_budget = |cache: &CacheState| {
+            cache.bytes + entry.data.len() > budget || cache.entries.len() >= MAX_CACHE_ENTRIES
+        };
+        if over_budget(&cache) {
+            cache.evict_expired();
         }
+        while over_budget(&cache) {
+            let Some(oldest) = cache
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            cache.remove(&oldest);
+        }
+
+        cache.bytes += entry.data.len();
+        cache.entries.insert(key.to_string(), entry);
 
         Ok(())
     }
@@ -104,16 +148,10 @@ impl CdnManager {
     /// Clear all cache
     pub async fn clear_cache(&self) -> Result<()> {
         let mut cache = self.cache.write().await;
-        cache.clear();
+        *cache = CacheState::default();
         Ok(())
     }
 
-    /// Evict expired entries
-    async fn evict_expired(&self, cache: &mut HashMap<String, CacheEntry>) {
-        let now = chrono::Utc::now();
-        cache.retain(|_, entry| entry.expires_at > now);
-    }
-
     /// Get CDN URL for a file
     pub fn get_cdn_url(&self, bucket: &str, key: &str) -> String {
         if let Some(ref domain) = self.config.cdn_domain {
@@ -142,3 +180,46 @@ impl Clone for CdnManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cache_size_budget() {
+        let cdn = CdnManager::new(CdnConfig {
+            max_cache_size_mb: 1,
+            ..Default::default()
+        });
+        let half = bytes::Bytes::from(vec![0u8; 600 * 1024]);
+
+        cdn.cache_file("a", half.clone(), None).await.unwrap();
+        cdn.cache_file("b", half.clone(), None).await.unwrap();
+        // Only one fits; the entry closest to expiring made way
+        assert!(cdn.get_cached("a").await.is_none());
+        assert!(cdn.get_cached("b").await.is_some());
+
+        // Replacing an entry does not count it twice
+        cdn.cache_file("b", half, None).await.unwrap();
+        assert!(cdn.get_cached("b").await.is_some());
+
+        let too_big = bytes::Bytes::from(vec![0u8; 2 * 1024 * 1024]);
+        cdn.cache_file("c", too_big, None).await.unwrap();
+        assert!(cdn.get_cached("c").await.is_none());
+        assert!(cdn.get_cached("b").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cache_entry_limit() {
+        let cdn = CdnManager::new(CdnConfig::default());
+        let tiny = bytes::Bytes::from_static(b"x");
+
+        for i in 0..MAX_CACHE_ENTRIES + 10 {
+            cdn.cache_file(&i.to_string(), tiny.clone(), None).await.unwrap();
+        }
+
+        assert_eq!(cdn.cache.read().await.entries.len(), MAX_CACHE_ENTRIES);
+        let newest = (MAX_CACHE_ENTRIES + 9).to_string();
+        assert!(cdn.get_cached(&newest).await.is_some());
+    }
+}
@@ -0,0 +1,171 @@
+//! File download handling: byte ranges and conditional requests
+
+use std::ops::Range;
+
+/// The requested range lies outside the file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeNotSatisfiable;
+
+/// Resolve a `Range` header against a file of `size` bytes
+///
+/// Returns `Ok(None)` when the header should be ignored and the whole file served: malformed
+/// values, other units and multi-range requests all fall back to a full response.
+pub fn parse_range(header: &str, size: u64) -> Result<Option<Range<u64>>, RangeNotSatisfiable> {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // Suffix range: the last `end` bytes
+        let Ok(length) = end.parse::<u64>() else {
+            return Ok(None);
+        };
+        if length == 0 || size == 0 {
+            return Err(RangeNotSatisfiable);
+        }
+        return Ok(Some(size.saturating_sub(length)..size));
+    }
+
+    let Ok(start) = start.parse::<u64>() else {
+        return Ok(None);
+    };
+    let end = if end.is_empty() {
+        None
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return Ok(None),
+        }
+    };
+
+    if start >= size {
+        return Err(RangeNotSatisfiable);
+    }
+    let end = end.map_or(size, |end| end.saturating_add(1).min(size));
+
+    Ok(Some(start..end))
+}
+
+/// Whether a conditional GET can be answered with 304 Not Modified
+///
+/// `If-None-Match` takes precedence; `If-Modified-Since` is only consulted without it.
+pub fn is_not_modified(
+    if_none_match: Option<&str>,
+    if_modified_since: Option<&str>,
+    etag: Option<&str>,
+    last_modified: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    if let Some(if_none_match) = if_none_match {
+        let Some(etag) = etag else {
+            return if_none_match.trim() == "*";
+        };
+        return if_none_match.split(',').map(str::trim).any(|candidate| {
+            candidate == "*" || weak_etag(candidate) == weak_etag(etag)
+        });
+    }
+
+    match if_modified_since.and_then(parse_http_date) {
+        Some(since) => last_modified.timestamp() <= since.timestamp(),
+        None => false,
+    }
+}
+
+/// Whether the `Range` header should be honoured given an `If-Range` validator
+pub fn if_range_matches(
+    if_range: Option<&str>,
+    etag: Option<&str>,
+    last_modified: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    let Some(validator) = if_range.map(str::trim) else {
+        return true;
+    };
+
+    if validator.starts_with('"') || validator.starts_with("W/") {
+        // Only strong validators can match
+        return !validator.starts_with("W/") && etag == Some(validator);
+    }
+
+    parse_http_date(validator).is_some_and(|date| date.timestamp() == last_modified.timestamp())
+}
+
+/// Quote a backend ETag for use in HTTP headers
+pub fn quote_etag(etag: &str) -> String {
+    if etag.starts_with('"') || etag.starts_with("W/\"") {
+        etag.to_string()
+    } else {
+        format!("\"{}\"", etag)
+    }
+}
+
+/// Format a timestamp as an HTTP date (RFC 9110 IMF-fixdate)
+pub fn http_date(date: chrono::DateTime<chrono::Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn parse_http_date(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&chrono::Utc))
+}
+
+fn weak_etag(etag: &str) -> &str {
+    etag.strip_prefix("W/").unwrap_or(etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Ok(Some(0..100)));
+        assert_eq!(parse_range("bytes=900-", 1000), Ok(Some(900..1000)));
+        assert_eq!(parse_range("bytes=-100", 1000), Ok(Some(900..1000)));
+        assert_eq!(parse_range("bytes=-5000", 1000), Ok(Some(0..1000)));
+        assert_eq!(parse_range("bytes=990-5000", 1000), Ok(Some(990..1000)));
+
+        assert_eq!(parse_range("bytes=1000-", 1000), Err(RangeNotSatisfiable));
+        assert_eq!(parse_range("bytes=-0", 1000), Err(RangeNotSatisfiable));
+        assert_eq!(parse_range("bytes=0-", 0), Err(RangeNotSatisfiable));
+
+        assert_eq!(parse_range("items=0-10", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=10-5", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=abc", 1000), Ok(None));
+    }
+
+    #[test]
+    fn test_conditional_requests() {
+        let modified = chrono::Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let etag = Some("\"abc\"");
+
+        assert!(is_not_modified(Some("\"abc\""), None, etag, modified));
+        assert!(is_not_modified(Some("W/\"abc\", \"def\""), None, etag, modified));
+        assert!(is_not_modified(Some("*"), None, etag, modified));
+        assert!(!is_not_modified(Some("\"def\""), None, etag, modified));
+        // If-None-Match wins over a matching date
+        assert!(!is_not_modified(
+            Some("\"def\""),
+            Some("Fri, 01 Mar 2024 12:00:00 GMT"),
+            etag,
+            modified
+        ));
+
+        assert!(is_not_modified(None, Some("Fri, 01 Mar 2024 12:00:00 GMT"), etag, modified));
+        assert!(!is_not_modified(None, Some("Thu, 29 Feb 2024 12:00:00 GMT"), etag, modified));
+        assert!(!is_not_modified(None, Some("not a date"), etag, modified));
+
+        assert!(if_range_matches(None, etag, modified));
+        assert!(if_range_matches(Some("\"abc\""), etag, modified));
+        assert!(!if_range_matches(Some("W/\"abc\""), etag, modified));
+        assert!(if_range_matches(Some(&http_date(modified)), etag, modified));
+    }
+}
@@ -1,35 +1,206 @@
 //! HTTP handlers for storage operations
 
+use crate::download::{http_date, if_range_matches, is_not_modified, parse_range, quote_etag};
 use crate::models::*;
+use crate::policy::{CreateStoragePolicyRequest, StorageAuthorizer, StorageOperation};
+use crate::render::{ImageRenderer, RenderOptions};
 use crate::service::StorageService;
+use crate::signed_url::SignedUrlGrant;
+use crate::sigv4::query_pairs;
+use crate::upload::parse_content_type;
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Extension, Json, Router,
 };
+use forgebase_auth::jwt::Claims;
+use forgebase_core::ForgeBaseError;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::sync::Arc;
+use uuid::Uuid;
+
+/// Prefix of request headers carrying custom object metadata
+pub const METADATA_HEADER_PREFIX: &str = "x-forgebase-meta-";
 
 #[derive(Clone)]
 pub struct StorageState {
     pub service: Arc<StorageService>,
+    pub authorizer: Arc<StorageAuthorizer>,
+    pub renderer: Arc<ImageRenderer>,
+}
+
+/// Create storage routes
+///
+/// Object routes verify signed URL parameters with [`verify_signed_url`]. Every route is
+/// authorized against the caller's claims, so layer `forgebase_auth::optional_auth` over them.
+pub fn create_storage_routes(state: StorageState) -> Router<StorageState> {
+    Router::new()
+        .route("/storage/presigned", post(presigned_url_handler))
+        .route("/storage/search", post(search_files_handler))
+        .route("/storage/render/image/:bucket/*key", get(render_image_handler))
+        .route(
+            "/storage/policies/:bucket",
+            get(list_policies_handler).post(create_policy_handler),
+        )
+        .route("/storage/policies/:bucket/:id", delete(delete_policy_handler))
+        .route("/storage/:bucket", get(list_files_handler))
+        .route(
+            "/storage/:bucket/*key",
+            get(download_file_handler)
+                .put(upload_file_handler)
+                .post(upload_file_handler)
+                .delete(delete_file_handler)
+                .route_layer(middleware::from_fn_with_state(state, verify_signed_url)),
+        )
+}
+
+/// Check the signature of a signed storage URL
+///
+/// Unsigned requests pass through. A signed request must be unexpired, use the method its
+/// operation allows and stay within its `Content-Length` limit; its [`SignedUrlGrant`](crate::signed_url::SignedUrlGrant) is then
+/// added to the request extensions.
+pub async fn verify_signed_url(
+    State(state): State<StorageState>,
+    Path((bucket, key)): Path<(String, String)>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let query = query_pairs(request.uri());
+    let grant = match state
+        .service
+        .url_signer()
+        .verify(&bucket, &key, &query, chrono::Utc::now())
+    {
+        Ok(Some(grant)) => grant,
+        Ok(None) => return next.run(request).await,
+        Err(e) => return error_response(e),
+    };
+
+    if !grant.allows(request.method()) {
+        return error_response(ForgeBaseError::Authorization(
+            "Signed URL does not allow this operation".to_string(),
+        ));
+    }
+    if let Some(max_content_length) = grant.max_content_length {
+        let declared = header_str(request.headers(), header::CONTENT_LENGTH)
+            .and_then(|value| value.parse::<i64>().ok());
+        match declared {
+            Some(length) if length <= max_content_length => {}
+            Some(_) => {
+                return (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("Signed URL allows at most {} bytes", max_content_length),
+                )
+                    .into_response()
+            }
+            None => return StatusCode::LENGTH_REQUIRED.into_response(),
+        }
+    }
+
+    request.extensions_mut().insert(grant);
+    next.run(request).await
+}
+
+pub(crate) fn error_response(error: ForgeBaseError) -> Response {
+    let status =
+        StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, error.to_string()).into_response()
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Check that the caller may perform an operation, unless a verified signed URL grants it
+///
+/// Returns the error response when the request is not allowed.
+async fn authorize(
+    state: &StorageState,
+    claims: Option<&Claims>,
+    grant: Option<&SignedUrlGrant>,
+    bucket: &str,
+    operation: StorageOperation,
+    path: &str,
+) -> Option<Response> {
+    let granted = grant.is_some_and(|grant| match grant.operation {
+        PresignedUrlOperation::Upload => operation == StorageOperation::Upload,
+        PresignedUrlOperation::Download => operation == StorageOperation::Download,
+    });
+    if granted {
+        return None;
+    }
+
+    state
+        .authorizer
+        .authorize(claims, bucket, operation, path)
+        .await
+        .err()
+        .map(error_response)
 }
 
 /// Upload file handler
+///
+/// The request body is streamed straight to the backend. The content type comes from the
+/// `Content-Type` header and custom metadata from `x-forgebase-meta-*` headers.
 pub async fn upload_file_handler(
     State(state): State<StorageState>,
-    Json(request): Json<UploadRequest>,
-    body: bytes::Bytes,
-) -> impl IntoResponse {
+    Path((bucket, key)): Path<(String, String)>,
+    claims: Option<Extension<Claims>>,
+    grant: Option<Extension<SignedUrlGrant>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let claims = claims.map(|Extension(claims)| claims);
+    let grant = grant.map(|Extension(grant)| grant);
+    if let Some(response) = authorize(
+        &state,
+        claims.as_ref(),
+        grant.as_ref(),
+        &bucket,
+        StorageOperation::Upload,
+        &key,
+    )
+    .await
+    {
+        return response;
+    }
+
+    if let Some(max_size) = state.service.max_file_size() {
+        let declared =
+            header_str(&headers, header::CONTENT_LENGTH).and_then(|v| v.parse::<i64>().ok());
+        if declared.is_some_and(|length| length > max_size) {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("File exceeds maximum allowed size {}", max_size),
+            )
+                .into_response();
+        }
+    }
+
+    let content_type = header_str(&headers, header::CONTENT_TYPE)
+        .map(str::to_string)
+        .unwrap_or_else(|| parse_content_type(&key));
+    let custom_metadata: HashMap<String, String> = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = name.as_str().strip_prefix(METADATA_HEADER_PREFIX)?;
+            Some((name.to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect();
     let metadata = UploadMetadata {
-        content_type: request.content_type,
-        custom_metadata: request.metadata.unwrap_or_default(),
+        content_type: Some(content_type),
+        custom_metadata,
+        owner_id: claims.and_then(|claims| claims.user_id().ok()),
     };
 
     match state
         .service
-        .upload_file(&request.bucket, &request.key, body, metadata)
+        .upload_stream(&bucket, &key, body.into_data_stream(), metadata)
         .await
     {
         Ok(object) => {
@@ -37,18 +208,158 @@ pub async fn upload_file_handler(
             let response = UploadResponse { object, url };
             (StatusCode::CREATED, Json(response)).into_response()
         }
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => error_response(e),
     }
 }
 
 /// Download file handler
+///
+/// Streams the file and honours `Range`/`If-Range`, `If-None-Match` and `If-Modified-Since`.
 pub async fn download_file_handler(
     State(state): State<StorageState>,
     Path((bucket, key)): Path<(String, String)>,
-) -> impl IntoResponse {
-    match state.service.download_file(&bucket, &key).await {
-        Ok(data) => (StatusCode::OK, data).into_response(),
-        Err(_) => (StatusCode::NOT_FOUND, "File not found").into_response(),
+    claims: Option<Extension<Claims>>,
+    grant: Option<Extension<SignedUrlGrant>>,
+    method: Method,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = authorize(
+        &state,
+        claims.as_deref(),
+        grant.as_deref(),
+        &bucket,
+        StorageOperation::Download,
+        &key,
+    )
+    .await
+    {
+        return response;
+    }
+
+    let meta = match state.service.get_file_metadata(&bucket, &key).await {
+        Ok(meta) => meta,
+        Err(_) => return (StatusCode::NOT_FOUND, "File not found").into_response(),
+    };
+
+    file_response(&state.service, &bucket, &key, meta, &headers, method != Method::HEAD).await
+}
+
+/// Respond with a file, honouring range and conditional request headers
+pub(crate) async fn file_response(
+    service: &StorageService,
+    bucket: &str,
+    key: &str,
+    meta: FileMetadata,
+    headers: &HeaderMap,
+    include_body: bool,
+) -> Response {
+    let size = meta.size as u64;
+    let etag = meta.etag.as_deref().map(quote_etag);
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Ok(value) = HeaderValue::from_str(&http_date(meta.last_modified)) {
+        response_headers.insert(header::LAST_MODIFIED, value);
+    }
+    if let Some(value) = etag.as_deref().and_then(|e| HeaderValue::from_str(e).ok()) {
+        response_headers.insert(header::ETAG, value);
+    }
+
+    if is_not_modified(
+        header_str(headers, header::IF_NONE_MATCH),
+        header_str(headers, header::IF_MODIFIED_SINCE),
+        etag.as_deref(),
+        meta.last_modified,
+    ) {
+        return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+    }
+
+    let if_range = header_str(headers, header::IF_RANGE);
+    let requested = header_str(headers, header::RANGE)
+        .filter(|_| if_range_matches(if_range, etag.as_deref(), meta.last_modified));
+    let range = match requested.map(|value| parse_range(value, size)) {
+        Some(Ok(range)) => range,
+        Some(Err(_)) => {
+            response_headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{}", size)).expect("valid header value"),
+            );
+            return (StatusCode::RANGE_NOT_SATISFIABLE, response_headers).into_response();
+        }
+        None => None,
+    };
+
+    let content_type = meta.content_type.unwrap_or_else(|| parse_content_type(key));
+    if let Ok(value) = HeaderValue::from_str(&content_type) {
+        response_headers.insert(header::CONTENT_TYPE, value);
+    }
+    let (status, length) = match &range {
+        Some(range) => {
+            response_headers.insert(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {}-{}/{}", range.start, range.end - 1, size))
+                    .expect("valid header value"),
+            );
+            (StatusCode::PARTIAL_CONTENT, range.end - range.start)
+        }
+        None => (StatusCode::OK, size),
+    };
+    response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+
+    if !include_body {
+        return (status, response_headers).into_response();
+    }
+    let stream = match service.download_stream(bucket, key, range).await {
+        Ok(stream) => stream,
+        Err(e) => return error_response(e),
+    };
+
+    (status, response_headers, Body::from_stream(stream)).into_response()
+}
+
+/// Render image handler
+///
+/// Serves a resized and re-encoded variant of a stored image; see [`RenderOptions`].
+pub async fn render_image_handler(
+    State(state): State<StorageState>,
+    Path((bucket, key)): Path<(String, String)>,
+    claims: Option<Extension<Claims>>,
+    Query(options): Query<RenderOptions>,
+) -> Response {
+    if let Some(response) = authorize(
+        &state,
+        claims.as_deref(),
+        None,
+        &bucket,
+        StorageOperation::Download,
+        &key,
+    )
+    .await
+    {
+        return response;
+    }
+
+    match state
+        .renderer
+        .render(&state.service, &bucket, &key, &options)
+        .await
+    {
+        Ok(image) => {
+            let mut response_headers = HeaderMap::new();
+            if let Ok(value) = HeaderValue::from_str(&image.content_type) {
+                response_headers.insert(header::CONTENT_TYPE, value);
+            }
+            for (name, value) in state.renderer.cache_headers() {
+                if let (Ok(name), Ok(value)) = (
+                    header::HeaderName::try_from(name),
+                    HeaderValue::from_str(&value),
+                ) {
+                    response_headers.insert(name, value);
+                }
+            }
+            (StatusCode::OK, response_headers, image.data).into_response()
+        }
+        Err(e) => error_response(e),
     }
 }
 
@@ -56,7 +367,21 @@ pub async fn download_file_handler(
 pub async fn delete_file_handler(
     State(state): State<StorageState>,
     Path((bucket, key)): Path<(String, String)>,
+    claims: Option<Extension<Claims>>,
 ) -> impl IntoResponse {
+    if let Some(response) = authorize(
+        &state,
+        claims.as_deref(),
+        None,
+        &bucket,
+        StorageOperation::Delete,
+        &key,
+    )
+    .await
+    {
+        return response;
+    }
+
     match state.service.delete_file(&bucket, &key).await {
         Ok(_) => StatusCode::NO_CONTENT.into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
@@ -67,33 +392,101 @@ pub async fn delete_file_handler(
 #[derive(Deserialize)]
 pub struct ListQuery {
     prefix: Option<String>,
+    limit: Option<i32>,
+    offset: Option<i32>,
+    cursor: Option<String>,
+    #[serde(default)]
+    sort_by: ListSortBy,
+    #[serde(default)]
+    order: SortOrder,
+    delimiter: Option<String>,
+    search: Option<String>,
 }
 
 pub async fn list_files_handler(
     State(state): State<StorageState>,
     Path(bucket): Path<String>,
+    claims: Option<Extension<Claims>>,
     Query(query): Query<ListQuery>,
 ) -> impl IntoResponse {
-    match state
-        .service
-        .list_files(&bucket, query.prefix.as_deref())
-        .await
+    let request = ListFilesRequest {
+        bucket,
+        prefix: query.prefix,
+        limit: query.limit,
+        offset: query.offset,
+        cursor: query.cursor,
+        sort_by: query.sort_by,
+        order: query.order,
+        delimiter: query.delimiter,
+        search: query.search,
+        metadata: None,
+    };
+
+    search_files_handler(State(state), claims, Json(request)).await
+}
+
+/// Search files handler
+///
+/// Takes the full [`ListFilesRequest`], including the custom metadata filter.
+pub async fn search_files_handler(
+    State(state): State<StorageState>,
+    claims: Option<Extension<Claims>>,
+    Json(request): Json<ListFilesRequest>,
+) -> Response {
+    if let Some(response) = authorize(
+        &state,
+        claims.as_deref(),
+        None,
+        &request.bucket,
+        StorageOperation::List,
+        request.prefix.as_deref().unwrap_or(""),
+    )
+    .await
     {
-        Ok(files) => (StatusCode::OK, Json(files)).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        return response;
+    }
+
+    match state.service.query_files(&request).await {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(e) => error_response(e),
     }
 }
 
 /// Generate presigned URL handler
+///
+/// The caller must be allowed the operation the URL grants.
 pub async fn presigned_url_handler(
     State(state): State<StorageState>,
+    claims: Option<Extension<Claims>>,
     Json(request): Json<PresignedUrlRequest>,
 ) -> impl IntoResponse {
+    let operation = match request.operation {
+        PresignedUrlOperation::Upload => StorageOperation::Upload,
+        PresignedUrlOperation::Download => StorageOperation::Download,
+    };
+    if let Some(response) = authorize(
+        &state,
+        claims.as_deref(),
+        None,
+        &request.bucket,
+        operation,
+        &request.key,
+    )
+    .await
+    {
+        return response;
+    }
+
     let result = match request.operation {
         PresignedUrlOperation::Upload => {
             state
                 .service
-                .generate_upload_url(&request.bucket, &request.key, request.expires_in)
+                .generate_upload_url(
+                    &request.bucket,
+                    &request.key,
+                    request.expires_in,
+                    request.max_content_length,
+                )
                 .await
         }
         PresignedUrlOperation::Download => {
@@ -111,6 +504,70 @@ pub async fn presigned_url_handler(
             let response = PresignedUrlResponse { url, expires_at };
             (StatusCode::OK, Json(response)).into_response()
         }
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// List a bucket's policies
+pub async fn list_policies_handler(
+    State(state): State<StorageState>,
+    Path(bucket): Path<String>,
+    claims: Option<Extension<Claims>>,
+) -> Response {
+    let bucket = match state
+        .authorizer
+        .authorize_management(claims.as_deref(), &bucket)
+        .await
+    {
+        Ok(bucket) => bucket,
+        Err(e) => return error_response(e),
+    };
+
+    match state.authorizer.list_policies(bucket.id).await {
+        Ok(policies) => (StatusCode::OK, Json(policies)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Add a policy to a bucket
+pub async fn create_policy_handler(
+    State(state): State<StorageState>,
+    Path(bucket): Path<String>,
+    claims: Option<Extension<Claims>>,
+    Json(request): Json<CreateStoragePolicyRequest>,
+) -> Response {
+    let bucket = match state
+        .authorizer
+        .authorize_management(claims.as_deref(), &bucket)
+        .await
+    {
+        Ok(bucket) => bucket,
+        Err(e) => return error_response(e),
+    };
+
+    match state.authorizer.create_policy(bucket.id, request).await {
+        Ok(policy) => (StatusCode::CREATED, Json(policy)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Remove a policy from a bucket
+pub async fn delete_policy_handler(
+    State(state): State<StorageState>,
+    Path((bucket, id)): Path<(String, Uuid)>,
+    claims: Option<Extension<Claims>>,
+) -> Response {
+    let bucket = match state
+        .authorizer
+        .authorize_management(claims.as_deref(), &bucket)
+        .await
+    {
+        Ok(bucket) => bucket,
+        Err(e) => return error_response(e),
+    };
+
+    match state.authorizer.delete_policy(bucket.id, id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e),
     }
 }
@@ -7,11 +7,28 @@ pub mod handlers;
 pub mod models;
 pub mod bucket;
 pub mod cdn;
+pub mod download;
 pub mod upload;
+pub mod tus;
+pub mod object_index;
+pub mod policy;
+pub mod render;
+pub mod s3;
+pub mod s3_credentials;
+pub mod signed_url;
+pub mod sigv4;
 
 pub use service::*;
 pub use handlers::*;
 pub use models::*;
 pub use bucket::*;
 pub use cdn::*;
+pub use download::*;
 pub use upload::*;
+pub use tus::*;
+pub use object_index::*;
+pub use policy::*;
+pub use render::*;
+pub use s3::*;
+pub use s3_credentials::*;
+pub use signed_url::*;
@@ -14,6 +14,9 @@ pub struct StorageObject {
     pub content_type: Option<String>,
     pub etag: Option<String>,
     pub metadata: HashMap<String, String>,
+    /// User who uploaded the object, when known
+    #[serde(default)]
+    pub owner_id: Option<Uuid>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -23,6 +26,8 @@ pub struct StorageObject {
 pub struct UploadMetadata {
     pub content_type: Option<String>,
     pub custom_metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub owner_id: Option<Uuid>,
 }
 
 /// File metadata
@@ -31,6 +36,17 @@ pub struct FileMetadata {
     pub size: i64,
     pub last_modified: chrono::DateTime<chrono::Utc>,
     pub etag: Option<String>,
+    /// Content type recorded at upload, when the object index has it
+    pub content_type: Option<String>,
+}
+
+/// Object listing entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectSummary {
+    pub key: String,
+    pub size: i64,
+    pub last_modified: chrono::DateTime<chrono::Utc>,
+    pub etag: Option<String>,
 }
 
 /// Upload request
@@ -63,16 +79,28 @@ pub struct PresignedUrlRequest {
     pub key: String,
     pub expires_in: i64,
     pub operation: PresignedUrlOperation,
+    /// Largest `Content-Length` an upload URL accepts (ForgeBase-signed URLs only)
+    #[serde(default)]
+    pub max_content_length: Option<i64>,
 }
 
 /// Presigned URL operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum PresignedUrlOperation {
     Upload,
     Download,
 }
 
+impl PresignedUrlOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Upload => "upload",
+            Self::Download => "download",
+        }
+    }
+}
+
 /// Presigned URL response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PresignedUrlResponse {
@@ -87,6 +115,42 @@ pub struct ListFilesRequest {
     pub prefix: Option<String>,
     pub limit: Option<i32>,
     pub offset: Option<i32>,
+    /// `next_cursor` of the previous page; takes the place of `offset`
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort_by: ListSortBy,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// List one folder level: keys continuing past the delimiter are rolled up into `folders`
+    #[serde(default)]
+    pub delimiter: Option<String>,
+    /// Case-insensitive prefix of the file name (the key after its last `/`)
+    #[serde(default)]
+    pub search: Option<String>,
+    /// Only objects whose custom metadata contains all of these pairs
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Sort key of a file listing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListSortBy {
+    #[default]
+    Name,
+    Size,
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// Sort direction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
 }
 
 /// List files response
@@ -95,6 +159,12 @@ pub struct ListFilesResponse {
     pub files: Vec<StorageObject>,
     pub total: i32,
     pub has_more: bool,
+    /// Cursor for the next page, when there is one
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+    /// Folders directly under the prefix, on the first page of a delimiter listing
+    #[serde(default)]
+    pub folders: Vec<String>,
 }
 
 /// Delete request
@@ -112,3 +182,34 @@ pub struct CopyRequest {
     pub dest_bucket: String,
     pub dest_key: String,
 }
+
+/// State of a resumable (tus) upload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumableUpload {
+    pub id: Uuid,
+    pub bucket: String,
+    pub key: String,
+    /// Total size announced with `Upload-Length`
+    pub length: u64,
+    /// Bytes received so far
+    pub offset: u64,
+    pub content_type: Option<String>,
+    pub metadata: HashMap<String, String>,
+    /// `Upload-Metadata` exactly as sent on creation, echoed back on HEAD
+    pub raw_metadata: Option<String>,
+    /// User who created the upload, recorded as the object's owner
+    #[serde(default)]
+    pub owner_id: Option<Uuid>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ResumableUpload {
+    pub fn is_complete(&self) -> bool {
+        self.offset >= self.length
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now()
+    }
+}
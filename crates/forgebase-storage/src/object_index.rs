@@ -0,0 +1,406 @@
+//! Object metadata index
+//!
+//! Mirrors stored objects into the `storage_objects` table, keeping content type, custom
+//! metadata and owner, and answering paged, sorted and filtered listings without walking the
+//! backend.
+
+use crate::models::{ListFilesRequest, ListFilesResponse, ListSortBy, SortOrder, StorageObject};
+use base64::Engine;
+use forgebase_core::{ForgeBaseError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const DEFAULT_LIST_LIMIT: i32 = 100;
+const MAX_LIST_LIMIT: i32 = 1000;
+
+/// Filter shared by listing and counting
+///
+/// `$1` bucket, `$2` escaped key prefix pattern, `$3` delimiter, `$4` position after the prefix,
+/// `$5` escaped lowercase file name pattern, `$6` metadata to contain.
+const OBJECT_FILTER: &str = r#"
+    bucket = $1
+    AND key LIKE $2 ESCAPE '\'
+    AND ($3::text IS NULL OR strpos(substr(key, $4), $3) = 0)
+    AND ($5::text IS NULL OR lower(regexp_replace(key, '^.*/', '')) LIKE $5 ESCAPE '\')
+    AND ($6::jsonb IS NULL OR metadata @> $6)
+"#;
+
+const OBJECT_COLUMNS: &str =
+    "id, bucket, key, size, content_type, etag, metadata, owner_id, created_at, updated_at";
+
+#[derive(sqlx::FromRow)]
+struct ObjectRow {
+    id: Uuid,
+    bucket: String,
+    key: String,
+    size: i64,
+    content_type: Option<String>,
+    etag: Option<String>,
+    metadata: Json<HashMap<String, String>>,
+    owner_id: Option<Uuid>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<ObjectRow> for StorageObject {
+    fn from(row: ObjectRow) -> Self {
+        Self {
+            id: row.id,
+            bucket: row.bucket,
+            key: row.key,
+            size: row.size,
+            content_type: row.content_type,
+            etag: row.etag,
+            metadata: row.metadata.0,
+            owner_id: row.owner_id,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// Position in a sorted listing: the sort value and key of the last object returned
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ListCursor {
+    value: Option<String>,
+    key: String,
+}
+
+impl ListCursor {
+    fn after(object: &StorageObject, sort_by: ListSortBy) -> Self {
+        let value = match sort_by {
+            ListSortBy::Name => None,
+            ListSortBy::Size => Some(object.size.to_string()),
+            ListSortBy::CreatedAt => Some(object.created_at.to_rfc3339()),
+            ListSortBy::UpdatedAt => Some(object.updated_at.to_rfc3339()),
+        };
+        Self {
+            value,
+            key: object.key.clone(),
+        }
+    }
+
+    fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor serializes");
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+
+    fn decode(cursor: &str, sort_by: ListSortBy) -> Result<Self> {
+        let invalid = || ForgeBaseError::Validation("Invalid cursor".to_string());
+        let json = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| invalid())?;
+        let cursor: Self = serde_json::from_slice(&json).map_err(|_| invalid())?;
+
+        // A cursor only makes sense for the sort it was issued under
+        let valid = match sort_by {
+            ListSortBy::Name => cursor.value.is_none(),
+            ListSortBy::Size => cursor.value.as_deref().is_some_and(|v| v.parse::<i64>().is_ok()),
+            ListSortBy::CreatedAt | ListSortBy::UpdatedAt => cursor
+                .value
+                .as_deref()
+                .is_some_and(|v| chrono::DateTime::parse_from_rfc3339(v).is_ok()),
+        };
+        if !valid {
+            return Err(invalid());
+        }
+        Ok(cursor)
+    }
+}
+
+/// Index of stored objects
+pub struct ObjectIndex {
+    pool: PgPool,
+}
+
+impl ObjectIndex {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Record an uploaded object, keeping the id and creation time of the one it replaces
+    pub async fn upsert(&self, object: &StorageObject) -> Result<StorageObject> {
+        let row = sqlx::query_as::<_, ObjectRow>(&format!(
+            r#"
+            INSERT INTO storage_objects (id, bucket, key, size, content_type, etag, metadata, owner_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (bucket, key) DO UPDATE SET
+                size = EXCLUDED.size,
+                content_type = EXCLUDED.content_type,
+                etag = EXCLUDED.etag,
+                metadata = EXCLUDED.metadata,
+                owner_id = EXCLUDED.owner_id,
+                updated_at = NOW()
+            RETURNING {}
+            "#,
+            OBJECT_COLUMNS
+        ))
+        .bind(object.id)
+        .bind(&object.bucket)
+        .bind(&object.key)
+        .bind(object.size)
+        .bind(&object.content_type)
+        .bind(&object.etag)
+        .bind(Json(&object.metadata))
+        .bind(object.owner_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to index object: {}", e)))?;
+
+        Ok(row.into())
+    }
+
+    pub async fn get(&self, bucket: &str, key: &str) -> Result<Option<StorageObject>> {
+        let row = sqlx::query_as::<_, ObjectRow>(&format!(
+            "SELECT {} FROM storage_objects WHERE bucket = $1 AND key = $2",
+            OBJECT_COLUMNS
+        ))
+        .bind(bucket)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to read object index: {}", e)))?;
+
+        Ok(row.map(Into::into))
+    }
+
+    /// Index a copy under its new name; `None` when the source was not indexed
+    pub async fn copy(
+        &self,
+        source_bucket: &str,
+        source_key: &str,
+        dest_bucket: &str,
+        dest_key: &str,
+        etag: Option<&str>,
+    ) -> Result<Option<StorageObject>> {
+        let row = sqlx::query_as::<_, ObjectRow>(&format!(
+            r#"
+            INSERT INTO storage_objects (id, bucket, key, size, content_type, etag, metadata, owner_id)
+            SELECT $1, $4, $5, size, content_type, $6, metadata, owner_id
+            FROM storage_objects WHERE bucket = $2 AND key = $3
+            ON CONFLICT (bucket, key) DO UPDATE SET
+                size = EXCLUDED.size,
+                content_type = EXCLUDED.content_type,
+                etag = EXCLUDED.etag,
+                metadata = EXCLUDED.metadata,
+                owner_id = EXCLUDED.owner_id,
+                updated_at = NOW()
+            RETURNING {}
+            "#,
+            OBJECT_COLUMNS
+        ))
+        .bind(Uuid::new_v4())
+        .bind(source_bucket)
+        .bind(source_key)
+        .bind(dest_bucket)
+        .bind(dest_key)
+        .bind(etag)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to index object copy: {}", e)))?;
+
+        Ok(row.map(Into::into))
+    }
+
+    pub async fn delete(&self, bucket: &str, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM storage_objects WHERE bucket = $1 AND key = $2")
+            .bind(bucket)
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to remove indexed object: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Remove every indexed object whose key starts with `prefix`
+    pub async fn delete_prefix(&self, bucket: &str, prefix: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM storage_objects WHERE bucket = $1 AND key LIKE $2 ESCAPE '\\'")
+            .bind(bucket)
+            .bind(like_prefix(prefix))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to remove indexed objects: {}", e)))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// One page of objects matching a listing request
+    pub async fn list(&self, request: &ListFilesRequest) -> Result<ListFilesResponse> {
+        let limit = request.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+        if !(1..=MAX_LIST_LIMIT).contains(&limit) {
+            return Err(ForgeBaseError::Validation(format!(
+                "limit must be between 1 and {}",
+                MAX_LIST_LIMIT
+            )));
+        }
+        let offset = request.offset.unwrap_or(0);
+        if offset < 0 {
+            return Err(ForgeBaseError::Validation("offset must not be negative".to_string()));
+        }
+        let cursor = request
+            .cursor
+            .as_deref()
+            .map(|cursor| ListCursor::decode(cursor, request.sort_by))
+            .transpose()?;
+
+        let prefix = request.prefix.as_deref().unwrap_or("");
+        let delimiter = request.delimiter.as_deref().filter(|d| !d.is_empty());
+        let after_prefix = prefix.chars().count() as i32 + 1;
+        let search = request
+            .search
+            .as_deref()
+            .filter(|search| !search.is_empty())
+            .map(|search| like_prefix(&search.to_lowercase()));
+        let metadata = request.metadata.as_ref().map(Json);
+
+        let (sort_column, cursor_filter) = match request.sort_by {
+            ListSortBy::Name => ("key", "key {} $7"),
+            ListSortBy::Size => ("size", "(size, key) {} ($8::text::bigint, $7)"),
+            ListSortBy::CreatedAt => ("created_at", "(created_at, key) {} ($8::text::timestamptz, $7)"),
+            ListSortBy::UpdatedAt => ("updated_at", "(updated_at, key) {} ($8::text::timestamptz, $7)"),
+        };
+        let (direction, comparison) = match request.order {
+            SortOrder::Asc => ("ASC", ">"),
+            SortOrder::Desc => ("DESC", "<"),
+        };
+        let order_by = if sort_column == "key" {
+            format!("key {}", direction)
+        } else {
+            format!("{} {}, key {}", sort_column, direction, direction)
+        };
+        let sql = format!(
+            "SELECT {} FROM storage_objects WHERE {} AND ($7::text IS NULL OR {}) ORDER BY {} LIMIT $9 OFFSET $10",
+            OBJECT_COLUMNS,
+            OBJECT_FILTER,
+            cursor_filter.replace("{}", comparison),
+            order_by
+        );
+
+        let rows = sqlx::query_as::<_, ObjectRow>(&sql)
+            .bind(&request.bucket)
+            .bind(like_prefix(prefix))
+            .bind(delimiter)
+            .bind(after_prefix)
+            .bind(&search)
+            .bind(metadata)
+            .bind(cursor.as_ref().map(|cursor| cursor.key.as_str()))
+            .bind(cursor.as_ref().and_then(|cursor| cursor.value.as_deref()))
+            .bind(limit as i64 + 1)
+            .bind(if cursor.is_some() { 0 } else { offset as i64 })
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(format!("Failed to list objects: {}", e)))?;
+
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM storage_objects WHERE {}",
+            OBJECT_FILTER
+        ))
+        .bind(&request.bucket)
+        .bind(like_prefix(prefix))
+        .bind(delimiter)
+        .bind(after_prefix)
+        .bind(&search)
+        .bind(metadata)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(format!("Failed to count objects: {}", e)))?;
+
+        let first_page = cursor.is_none() && offset == 0;
+        let folders = match delimiter {
+            Some(delimiter) if first_page => {
+                sqlx::query_scalar::<_, String>(
+                    r#"
+                    SELECT DISTINCT $3 || split_part(substr(key, $4), $5, 1) || $5 AS folder
+                    FROM storage_objects
+                    WHERE bucket = $1 AND key LIKE $2 ESCAPE '\' AND strpos(substr(key, $4), $5) > 0
+                    ORDER BY folder
+                    "#,
+                )
+                .bind(&request.bucket)
+                .bind(like_prefix(prefix))
+                .bind(prefix)
+                .bind(after_prefix)
+                .bind(delimiter)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| ForgeBaseError::Database(format!("Failed to list folders: {}", e)))?
+            }
+            _ => Vec::new(),
+        };
+
+        let mut files: Vec<StorageObject> = rows.into_iter().map(Into::into).collect();
+        let has_more = files.len() > limit as usize;
+        files.truncate(limit as usize);
+        let next_cursor = files
+            .last()
+            .filter(|_| has_more)
+            .map(|last| ListCursor::after(last, request.sort_by).encode());
+
+        Ok(ListFilesResponse {
+            files,
+            total: total as i32,
+            has_more,
+            next_cursor,
+            folders,
+        })
+    }
+}
+
+/// `LIKE` pattern matching strings that start with `prefix`
+fn like_prefix(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like_prefix() {
+        assert_eq!(like_prefix(""), "%");
+        assert_eq!(like_prefix("photos/"), "photos/%");
+        assert_eq!(like_prefix("100%_off\\"), "100\\%\\_off\\\\%");
+    }
+
+    #[test]
+    fn test_list_cursor() {
+        let now = chrono::Utc::now();
+        let object = StorageObject {
+            id: Uuid::new_v4(),
+            bucket: "docs".to_string(),
+            key: "a/b.txt".to_string(),
+            size: 42,
+            content_type: None,
+            etag: None,
+            metadata: HashMap::new(),
+            owner_id: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let cursor = ListCursor::after(&object, ListSortBy::Size);
+        let decoded = ListCursor::decode(&cursor.encode(), ListSortBy::Size).unwrap();
+        assert_eq!(decoded, cursor);
+        assert_eq!(decoded.value.as_deref(), Some("42"));
+
+        // Cursors are tied to the sort they were issued for
+        assert!(ListCursor::decode(&cursor.encode(), ListSortBy::Name).is_err());
+        assert!(ListCursor::decode(&cursor.encode(), ListSortBy::CreatedAt).is_err());
+        assert!(ListCursor::decode("not-a-cursor", ListSortBy::Name).is_err());
+
+        let by_date = ListCursor::after(&object, ListSortBy::UpdatedAt);
+        assert!(ListCursor::decode(&by_date.encode(), ListSortBy::UpdatedAt).is_ok());
+    }
+}
@@ -0,0 +1,510 @@
+//! Storage authorization
+//!
+//! Every upload, download, list and delete is checked against the caller's claims:
+//!
+//! 1. the service role may do anything;
+//! 2. anyone may download from a public bucket;
+//! 3. the bucket owner may do anything; in an organization's bucket viewers may download and
+//!    list, and developers and above may also upload and delete;
+//! 4. otherwise a bucket policy must grant the operation on the object's key.
+//!
+//! Policies are managed by the service role, the owner of a personal bucket, or an admin or
+//! owner of the organization owning the bucket.
+//!
+//! Policies are declarative: each grants some operations on keys under a prefix that may refer
+//! to the caller, e.g. `{auth.uid}/` for "users may read and write their own folder".
+
+use crate::bucket::{Bucket, BucketManager};
+use forgebase_auth::jwt::Claims;
+use forgebase_auth::organization::OrganizationRole;
+use forgebase_core::{ForgeBaseError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Storage operation being authorized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageOperation {
+    Upload,
+    Download,
+    List,
+    Delete,
+}
+
+impl StorageOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Upload => "upload",
+            Self::Download => "download",
+            Self::List => "list",
+            Self::Delete => "delete",
+        }
+    }
+
+    /// Whether a member with `role` may perform the operation on the bucket's objects
+    pub fn allowed_for(&self, role: OrganizationRole) -> bool {
+        match self {
+            Self::Download | Self::List => true,
+            Self::Upload | Self::Delete => role.can_deploy(),
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "upload" => Some(Self::Upload),
+            "download" => Some(Self::Download),
+            "list" => Some(Self::List),
+            "delete" => Some(Self::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// Per-bucket access policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoragePolicy {
+    pub id: Uuid,
+    pub bucket_id: Uuid,
+    pub name: String,
+    pub operations: Vec<StorageOperation>,
+    /// Key prefix the policy covers, with `{auth.uid}`, `{auth.email}` or `{auth.role}`
+    /// standing for the caller's claims; empty for the whole bucket
+    pub prefix: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl StoragePolicy {
+    /// Whether the policy lets `claims` perform `operation` on `path`
+    ///
+    /// For uploads, downloads and deletes `path` is the object key; for listings it is the
+    /// listed prefix, which must lie inside the policy's prefix.
+    pub fn allows(&self, claims: &Claims, operation: StorageOperation, path: &str) -> bool {
+        if !self.operations.contains(&operation) {
+            return false;
+        }
+        match expand_prefix(&self.prefix, claims) {
+            Some(prefix) => path.starts_with(&prefix),
+            None => false,
+        }
+    }
+}
+
+/// Create storage policy request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStoragePolicyRequest {
+    pub name: String,
+    pub operations: Vec<StorageOperation>,
+    #[serde(default)]
+    pub prefix: String,
+}
+
+/// Substitute the caller's claims into a policy prefix
+///
+/// Returns `None` when the prefix refers to an unknown or missing claim, so the policy does not
+/// apply.
+pub fn expand_prefix(template: &str, claims: &Claims) -> Option<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..].find('}')? + start;
+        let value = match &rest[start + 1..end] {
+            "auth.uid" => claims.sub.as_str(),
+            "auth.email" => claims.email.as_str(),
+            "auth.role" => claims.role.as_deref()?,
+            _ => return None,
+        };
+        // A claim must not be able to widen the prefix
+        if value.is_empty() || value.contains('/') {
+            return None;
+        }
+        expanded.push_str(value);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    Some(expanded)
+}
+
+#[derive(sqlx::FromRow)]
+struct PolicyRow {
+    id: Uuid,
+    bucket_id: Uuid,
+    name: String,
+    operations: Vec<String>,
+    prefix: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<PolicyRow> for StoragePolicy {
+    fn from(row: PolicyRow) -> Self {
+        Self {
+            id: row.id,
+            bucket_id: row.bucket_id,
+            name: row.name,
+            operations: row
+                .operations
+                .iter()
+                .filter_map(|operation| StorageOperation::parse(operation))
+                .collect(),
+            prefix: row.prefix,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Authorizes storage operations and manages bucket policies
+pub struct StorageAuthorizer {
+    pool: PgPool,
+    buckets: BucketManager,
+}
+
+impl StorageAuthorizer {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            buckets: BucketManager::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Check that the caller may perform `operation` on `path` in a bucket, returning the bucket
+    ///
+    /// Fails with `Auth` when an anonymous caller needs to sign in and `Authorization` when the
+    /// caller is not allowed.
+    pub async fn authorize(
+        &self,
+        claims: Option<&Claims>,
+        bucket: &str,
+        operation: StorageOperation,
+        path: &str,
+    ) -> Result<Bucket> {
+        let bucket = self.bucket(bucket).await?;
+
+        if claims.is_some_and(Claims::is_service_role)
+            || (bucket.is_public && operation == StorageOperation::Download)
+        {
+            return Ok(bucket);
+        }
+        let Some(claims) = claims else {
+            return Err(ForgeBaseError::Auth("Authentication required".to_string()));
+        };
+
+        if let Ok(user_id) = claims.user_id() {
+            if let Some(role) = self.buckets.member_role(&bucket, user_id).await? {
+                if operation.allowed_for(role) {
+                    return Ok(bucket);
+                }
+            }
+        }
+
+        let policies = self.list_policies(bucket.id).await?;
+        if policies
+            .iter()
+            .any(|policy| policy.allows(claims, operation, path))
+        {
+            return Ok(bucket);
+        }
+
+        Err(ForgeBaseError::Authorization(format!(
+            "Not allowed to {} '{}' in bucket '{}'",
+            operation.as_str(),
+            path,
+            bucket.name
+        )))
+    }
+
+    /// Check that the caller may manage a bucket's policies: the service role, the owner of a
+    /// personal bucket, or an admin or owner of the organization owning it
+    pub async fn authorize_management(
+        &self,
+        claims: Option<&Claims>,
+        bucket: &str,
+    ) -> Result<Bucket> {
+        let bucket = self.bucket(bucket).await?;
+        let Some(claims) = claims else {
+            return Err(ForgeBaseError::Auth("Authentication required".to_string()));
+        };
+        if claims.is_service_role() {
+            return Ok(bucket);
+        }
+        if let Ok(user_id) = claims.user_id() {
+            if let Some(role) = self.buckets.member_role(&bucket, user_id).await? {
+                if can_manage_policies(role) {
+                    return Ok(bucket);
+                }
+            }
+        }
+
+        Err(ForgeBaseError::Authorization(format!(
+            "Not allowed to manage policies of bucket '{}'",
+            bucket.name
+        )))
+    }
+
+    async fn bucket(&self, name: &str) -> Result<Bucket> {
+        self.buckets
+            .get_bucket(name)
+            .await?
+            .ok_or_else(|| ForgeBaseError::NotFound(format!("Bucket '{}' not found", name)))
+    }
+
+    /// Add a policy to a bucket
+    pub async fn create_policy(
+        &self,
+        bucket_id: Uuid,
+        request: CreateStoragePolicyRequest,
+    ) -> Result<StoragePolicy> {
+        if request.operations.is_empty() {
+            return Err(ForgeBaseError::Validation(
+                "A policy must grant at least one operation".to_string(),
+            ));
+        }
+        validate_prefix(&request.prefix)?;
+
+        let operations: Vec<&str> = request
+            .operations
+            .iter()
+            .map(StorageOperation::as_str)
+            .collect();
+        let row = sqlx::query_as::<_, PolicyRow>(
+            r#"
+            INSERT INTO storage_policies (id, bucket_id, name, operations, prefix)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, bucket_id, name, operations, prefix, created_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(bucket_id)
+        .bind(&request.name)
+        .bind(&operations)
+        .bind(&request.prefix)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => ForgeBaseError::Conflict(
+                format!("A policy named '{}' already exists", request.name),
+            ),
+            e => ForgeBaseError::Database(e.to_string()),
+        })?;
+
+        Ok(row.into())
+    }
+
+    pub async fn list_policies(&self, bucket_id: Uuid) -> Result<Vec<StoragePolicy>> {
+        let rows = sqlx::query_as::<_, PolicyRow>(
+            r#"
+            SELECT id, bucket_id, name, operations, prefix, created_at
+            FROM storage_policies WHERE bucket_id = $1 ORDER BY created_at
+            "#,
+        )
+        .bind(bucket_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn delete_policy(&self, bucket_id: Uuid, id: Uuid) -> Result<()> {
+        let result = sqlx::query("DELETE FROM storage_policies WHERE id = $1 AND bucket_id = $2")
+            .bind(id)
+            .bind(bucket_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ForgeBaseError::NotFound(
+                "Storage policy not found".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a member with `role` may change who can access a bucket
+fn can_manage_policies(role: OrganizationRole) -> bool {
+    role >= OrganizationRole::Admin
+}
+
+/// Reject prefixes with unknown placeholders
+fn validate_prefix(prefix: &str) -> Result<()> {
+    let mut rest = prefix;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| end + start)
+            .ok_or_else(|| {
+                ForgeBaseError::Validation("Unclosed placeholder in prefix".to_string())
+            })?;
+        let name = &rest[start + 1..end];
+        if !matches!(name, "auth.uid" | "auth.email" | "auth.role") {
+            return Err(ForgeBaseError::Validation(format!(
+                "Unknown placeholder '{{{}}}' in prefix",
+                name
+            )));
+        }
+        rest = &rest[end + 1..];
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(sub: &str, role: Option<&str>) -> Claims {
+        Claims {
+            sub: sub.to_string(),
+            email: "user@example.com".to_string(),
+            exp: 0,
+            iat: 0,
+            role: role.map(str::to_string),
+            permissions: Vec::new(),
+            aal: None,
+            is_anonymous: false,
+            session_id: None,
+            act: None,
+            client_id: None,
+            scope: None,
+            custom: Default::default(),
+        }
+    }
+
+    fn policy(prefix: &str, operations: &[StorageOperation]) -> StoragePolicy {
+        StoragePolicy {
+            id: Uuid::new_v4(),
+            bucket_id: Uuid::new_v4(),
+            name: "test".to_string(),
+            operations: operations.to_vec(),
+            prefix: prefix.to_string(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_expand_prefix() {
+        let user = claims("u1", None);
+        assert_eq!(expand_prefix("{auth.uid}/", &user).as_deref(), Some("u1/"));
+        assert_eq!(
+            expand_prefix("users/{auth.uid}/docs/", &user).as_deref(),
+            Some("users/u1/docs/")
+        );
+        assert_eq!(expand_prefix("shared/", &user).as_deref(), Some("shared/"));
+        // Missing, unknown and unclosed placeholders never match
+        assert_eq!(expand_prefix("{auth.role}/", &user), None);
+        assert_eq!(expand_prefix("{auth.other}/", &user), None);
+        assert_eq!(expand_prefix("{auth.uid/", &user), None);
+        // A claim value cannot reach into another folder
+        assert_eq!(expand_prefix("{auth.uid}/", &claims("a/b", None)), None);
+    }
+
+    #[test]
+    fn test_policy_allows() {
+        use StorageOperation::*;
+        let own_folder = policy("{auth.uid}/", &[Upload, Download, List]);
+        let user = claims("u1", None);
+
+        assert!(own_folder.allows(&user, Upload, "u1/photo.jpg"));
+        assert!(own_folder.allows(&user, List, "u1/"));
+        assert!(!own_folder.allows(&user, Delete, "u1/photo.jpg"));
+        assert!(!own_folder.allows(&user, Download, "u2/photo.jpg"));
+        assert!(!own_folder.allows(&user, Download, "u1-photo.jpg"));
+        assert!(!own_folder.allows(&user, List, ""));
+
+        let everything = policy("", &[Download]);
+        assert!(everything.allows(&user, Download, "any/key"));
+    }
+
+    #[test]
+    fn test_validate_prefix() {
+        assert!(validate_prefix("{auth.uid}/").is_ok());
+        assert!(validate_prefix("").is_ok());
+        assert!(validate_prefix("{auth.password}/").is_err());
+        assert!(validate_prefix("{auth.uid").is_err());
+    }
+
+    #[test]
+    fn test_organization_roles() {
+        use StorageOperation::*;
+        for operation in [Download, List] {
+            assert!(operation.allowed_for(OrganizationRole::Viewer));
+        }
+        for operation in [Upload, Delete] {
+            assert!(!operation.allowed_for(OrganizationRole::Viewer));
+            assert!(operation.allowed_for(OrganizationRole::Developer));
+        }
+        assert!(!can_manage_policies(OrganizationRole::Developer));
+        assert!(can_manage_policies(OrganizationRole::Admin));
+        assert!(can_manage_policies(OrganizationRole::Owner));
+    }
+
+    async fn create_user(pool: &PgPool) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query("INSERT INTO users (id, email) VALUES ($1, $2)")
+            .bind(id)
+            .bind(format!("{}@example.com", id))
+            .execute(pool)
+            .await
+            .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn test_organization_viewer_cannot_write_or_manage() {
+        use forgebase_auth::organization::OrganizationRepository;
+        let Some(pool) = forgebase_db::testing::test_pool().await else { return };
+
+        let owner = create_user(&pool).await;
+        let organization = forgebase_auth::models::Organization {
+            id: Uuid::new_v4(),
+            name: "Acme".to_string(),
+            slug: format!("acme-{}", Uuid::new_v4()),
+            created_by: Some(owner),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        let organizations = OrganizationRepository::new(pool.clone());
+        organizations.create(&organization, owner).await.unwrap();
+        let mut members = Vec::new();
+        for role in [OrganizationRole::Viewer, OrganizationRole::Developer, OrganizationRole::Admin] {
+            let user = create_user(&pool).await;
+            organizations.add_member(organization.id, user, role).await.unwrap();
+            members.push(claims(&user.to_string(), None));
+        }
+        let [viewer, developer, admin] = &members[..] else { unreachable!() };
+
+        let bucket = BucketManager::new(pool.clone())
+            .create_bucket(&Bucket {
+                id: Uuid::new_v4(),
+                name: format!("org-{}", Uuid::new_v4()),
+                owner_id: owner,
+                is_public: false,
+                max_file_size: None,
+                allowed_mime_types: None,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+                organization_id: Some(organization.id),
+            })
+            .await
+            .unwrap();
+        let authorizer = StorageAuthorizer::new(pool.clone());
+        let authorize = |claims, operation| authorizer.authorize(Some(claims), &bucket.name, operation, "a.txt");
+        let denied = |result: Result<Bucket>| matches!(result, Err(ForgeBaseError::Authorization(_)));
+
+        assert!(authorize(viewer, StorageOperation::Download).await.is_ok());
+        assert!(denied(authorize(viewer, StorageOperation::Upload).await));
+        assert!(denied(authorize(viewer, StorageOperation::Delete).await));
+        assert!(denied(authorizer.authorize_management(Some(viewer), &bucket.name).await));
+
+        assert!(authorize(developer, StorageOperation::Upload).await.is_ok());
+        assert!(authorize(developer, StorageOperation::Delete).await.is_ok());
+        assert!(denied(authorizer.authorize_management(Some(developer), &bucket.name).await));
+
+        assert!(authorizer.authorize_management(Some(admin), &bucket.name).await.is_ok());
+    }
+}
@@ -0,0 +1,420 @@
+//! On-the-fly image transformations
+//!
+//! Stored images are decoded, resized and re-encoded on request, and each variant is cached
+//! through the [`CdnManager`] under the source's ETag, so overwriting an image never serves a
+//! stale variant. Decoding is bounded by pixel dimensions, decoded size and file size, so a
+//! small file cannot expand into an enormous bitmap, and only a few renders run at once.
+
+use crate::cdn::CdnManager;
+use crate::service::StorageService;
+use bytes::Bytes;
+use forgebase_core::{ForgeBaseError, Result};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageError, ImageFormat, ImageReader, Limits};
+use serde::Deserialize;
+use std::io::Cursor;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Largest width or height a variant may be rendered at
+pub const MAX_RENDER_DIMENSION: u32 = 2500;
+
+const DEFAULT_MAX_SOURCE_DIMENSION: u32 = 8_000;
+const DEFAULT_MAX_SOURCE_BYTES: u64 = 25 * 1024 * 1024;
+const DEFAULT_MAX_DECODE_BYTES: u64 = 256 * 1024 * 1024;
+const DEFAULT_QUALITY: u8 = 80;
+/// rav1e speed preset, 1 (slowest) to 10; renders happen on the request path
+const AVIF_SPEED: u8 = 8;
+
+/// How an image is fitted to the requested size
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeMode {
+    /// Fill the whole box, cropping what overflows
+    #[default]
+    Cover,
+    /// Fit inside the box, keeping the whole image
+    Contain,
+}
+
+/// Output format of a rendered image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderFormat {
+    /// Lossless WebP
+    Webp,
+    Avif,
+}
+
+/// Render image query
+///
+/// With only one of `width` and `height` the other follows the aspect ratio. Without a format
+/// the source format is kept when it can be encoded, PNG otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct RenderOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub resize: ResizeMode,
+    pub format: Option<RenderFormat>,
+    /// 1 to 100, used by AVIF and JPEG output
+    pub quality: Option<u8>,
+}
+
+impl RenderOptions {
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [("width", self.width), ("height", self.height)] {
+            if let Some(value) = value {
+                if value == 0 || value > MAX_RENDER_DIMENSION {
+                    return Err(ForgeBaseError::Validation(format!(
+                        "{} must be between 1 and {}",
+                        name, MAX_RENDER_DIMENSION
+                    )));
+                }
+            }
+        }
+        if let Some(quality) = self.quality {
+            if quality == 0 || quality > 100 {
+                return Err(ForgeBaseError::Validation(
+                    "quality must be between 1 and 100".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cache_key(&self, bucket: &str, key: &str, version: &str) -> String {
+        format!(
+            "render:{}/{}:{}:{:?}x{:?}:{:?}:{:?}:{:?}",
+            bucket, key, version, self.width, self.height, self.resize, self.format, self.quality
+        )
+    }
+}
+
+/// A rendered image variant
+#[derive(Debug, Clone)]
+pub struct RenderedImage {
+    pub data: Bytes,
+    pub content_type: String,
+}
+
+/// Renders and caches image variants
+#[derive(Clone)]
+pub struct ImageRenderer {
+    cdn: CdnManager,
+    renders: Arc<Semaphore>,
+    max_source_dimension: u32,
+    max_source_bytes: u64,
+    max_decode_bytes: u64,
+}
+
+impl ImageRenderer {
+    pub fn new(cdn: CdnManager) -> Self {
+        let parallelism = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            cdn,
+            renders: Arc::new(Semaphore::new(parallelism)),
+            max_source_dimension: DEFAULT_MAX_SOURCE_DIMENSION,
+            max_source_bytes: DEFAULT_MAX_SOURCE_BYTES,
+            max_decode_bytes: DEFAULT_MAX_DECODE_BYTES,
+        }
+    }
+
+    /// Most renders that may run at once; defaults to the number of CPUs
+    pub fn with_max_concurrent_renders(mut self, renders: usize) -> Self {
+        self.renders = Arc::new(Semaphore::new(renders.max(1)));
+        self
+    }
+
+    /// Largest source width or height that will be decoded
+    pub fn with_max_source_dimension(mut self, pixels: u32) -> Self {
+        self.max_source_dimension = pixels;
+        self
+    }
+
+    /// Largest source file that will be read
+    pub fn with_max_source_bytes(mut self, bytes: u64) -> Self {
+        self.max_source_bytes = bytes;
+        self
+    }
+
+    /// Most memory the decoder may allocate for one source image
+    pub fn with_max_decode_bytes(mut self, bytes: u64) -> Self {
+        self.max_decode_bytes = bytes;
+        self
+    }
+
+    /// Cache-Control headers for rendered variants
+    pub fn cache_headers(&self) -> std::collections::HashMap<String, String> {
+        self.cdn.get_cache_headers()
+    }
+
+    /// Render a stored image, serving the cached variant when there is one
+    pub async fn render(
+        &self,
+        service: &StorageService,
+        bucket: &str,
+        key: &str,
+        options: &RenderOptions,
+    ) -> Result<RenderedImage> {
+        options.validate()?;
+
+        let meta = service.get_file_metadata(bucket, key).await?;
+        if meta.size as u64 > self.max_source_bytes {
+            return Err(ForgeBaseError::Validation(format!(
+                "Image exceeds the {} byte render limit",
+                self.max_source_bytes
+            )));
+        }
+
+        let version = meta
+            .etag
+            .clone()
+            .unwrap_or_else(|| format!("{}-{}", meta.last_modified.timestamp_millis(), meta.size));
+        let cache_key = options.cache_key(bucket, key, &version);
+        if let Some((data, content_type)) = self.cdn.get_cached(&cache_key).await {
+            return Ok(RenderedImage {
+                data,
+                content_type: content_type.unwrap_or_default(),
+            });
+        }
+
+        // Held until the render is done; waiting requests queue here rather than piling up
+        // decoded images
+        let _permit = self
+            .renders
+            .acquire()
+            .await
+            .map_err(|e| ForgeBaseError::Internal(e.to_string()))?;
+        let source = service.download_file(bucket, key).await?;
+        let renderer = self.clone();
+        let options = options.clone();
+        let rendered = tokio::task::spawn_blocking(move || renderer.transform(&source, &options))
+            .await
+            .map_err(|e| ForgeBaseError::Internal(format!("Image render failed: {}", e)))??;
+
+        self.cdn
+            .cache_file(
+                &cache_key,
+                rendered.data.clone(),
+                Some(rendered.content_type.clone()),
+            )
+            .await?;
+
+        Ok(rendered)
+    }
+
+    /// Decode, resize and re-encode an image
+    pub fn transform(&self, source: &[u8], options: &RenderOptions) -> Result<RenderedImage> {
+        let mut reader = ImageReader::new(Cursor::new(source))
+            .with_guessed_format()
+            .map_err(|e| ForgeBaseError::Internal(e.to_string()))?;
+        let source_format = reader
+            .format()
+            .ok_or_else(|| ForgeBaseError::Validation("Not a supported image".to_string()))?;
+
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.max_source_dimension);
+        limits.max_image_height = Some(self.max_source_dimension);
+        limits.max_alloc = Some(self.max_decode_bytes);
+        reader.limits(limits);
+
+        let image = reader.decode().map_err(|e| match e {
+            ImageError::Limits(_) => ForgeBaseError::Validation(format!(
+                "Image exceeds the render limits of {}x{} pixels and {} decoded bytes",
+                self.max_source_dimension, self.max_source_dimension, self.max_decode_bytes
+            )),
+            e => ForgeBaseError::Validation(format!("Could not decode image: {}", e)),
+        })?;
+
+        let image = resize(image, options);
+        let format = match options.format {
+            Some(RenderFormat::Webp) => ImageFormat::WebP,
+            Some(RenderFormat::Avif) => ImageFormat::Avif,
+            None => match source_format {
+                ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Gif => {
+                    source_format
+                }
+                _ => ImageFormat::Png,
+            },
+        };
+        let data = encode(&image, format, options.quality.unwrap_or(DEFAULT_QUALITY))?;
+
+        Ok(RenderedImage {
+            data: Bytes::from(data),
+            content_type: format.to_mime_type().to_string(),
+        })
+    }
+}
+
+/// Fit an image to the requested box
+fn resize(image: DynamicImage, options: &RenderOptions) -> DynamicImage {
+    let (width, height) = (image.width().max(1), image.height().max(1));
+    match (options.width, options.height) {
+        (None, None) => image,
+        (Some(w), Some(h)) => match options.resize {
+            ResizeMode::Cover => image.resize_to_fill(w, h, FilterType::Lanczos3),
+            ResizeMode::Contain => image.resize(w, h, FilterType::Lanczos3),
+        },
+        (Some(w), None) => {
+            let h = scaled(height, w, width);
+            image.resize_exact(w, h, FilterType::Lanczos3)
+        }
+        (None, Some(h)) => {
+            let w = scaled(width, h, height);
+            image.resize_exact(w, h, FilterType::Lanczos3)
+        }
+    }
+}
+
+/// `value * numerator / denominator`, rounded and at least 1
+fn scaled(value: u32, numerator: u32, denominator: u32) -> u32 {
+    let scaled = (u64::from(value) * u64::from(numerator) + u64::from(denominator) / 2)
+        / u64::from(denominator);
+    scaled.clamp(1, u64::from(MAX_RENDER_DIMENSION)) as u32
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    // The encoders take 8-bit RGB(A); JPEG has no alpha channel
+    let image = if format == ImageFormat::Jpeg || !image.color().has_alpha() {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    } else {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    };
+
+    let result = match format {
+        ImageFormat::Avif => image.write_with_encoder(AvifEncoder::new_with_speed_quality(
+            &mut data, AVIF_SPEED, quality,
+        )),
+        ImageFormat::WebP => image.write_with_encoder(WebPEncoder::new_lossless(&mut data)),
+        ImageFormat::Jpeg => {
+            image.write_with_encoder(JpegEncoder::new_with_quality(&mut data, quality))
+        }
+        format => image.write_to(Cursor::new(&mut data), format),
+    };
+    result.map_err(|e| ForgeBaseError::Internal(format!("Could not encode image: {}", e)))?;
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdn::CdnConfig;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::ImageRgb8(image::RgbImage::new(width, height));
+        let mut data = Vec::new();
+        image
+            .write_to(Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    fn dimensions(data: &[u8]) -> (u32, u32) {
+        let image = image::load_from_memory(data).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[test]
+    fn test_transform() {
+        let renderer = ImageRenderer::new(CdnManager::new(CdnConfig::default()));
+        let source = png(400, 200);
+        let options = |width, height, resize, format| RenderOptions {
+            width,
+            height,
+            resize,
+            format,
+            quality: None,
+        };
+
+        let cover = renderer
+            .transform(
+                &source,
+                &options(Some(100), Some(100), ResizeMode::Cover, None),
+            )
+            .unwrap();
+        assert_eq!(cover.content_type, "image/png");
+        assert_eq!(dimensions(&cover.data), (100, 100));
+
+        let contain = renderer
+            .transform(
+                &source,
+                &options(Some(100), Some(100), ResizeMode::Contain, None),
+            )
+            .unwrap();
+        assert_eq!(dimensions(&contain.data), (100, 50));
+
+        let webp = renderer
+            .transform(
+                &source,
+                &options(None, Some(50), ResizeMode::Cover, Some(RenderFormat::Webp)),
+            )
+            .unwrap();
+        assert_eq!(webp.content_type, "image/webp");
+        assert_eq!(dimensions(&webp.data), (100, 50));
+
+        let avif = renderer
+            .transform(
+                &source,
+                &options(Some(40), None, ResizeMode::Cover, Some(RenderFormat::Avif)),
+            )
+            .unwrap();
+        assert_eq!(avif.content_type, "image/avif");
+        assert!(!avif.data.is_empty());
+
+        assert!(renderer
+            .transform(
+                b"not an image",
+                &options(None, None, ResizeMode::Cover, None)
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_source_dimension_limit() {
+        let renderer =
+            ImageRenderer::new(CdnManager::new(CdnConfig::default())).with_max_source_dimension(64);
+        let err = renderer
+            .transform(&png(65, 10), &RenderOptions::default())
+            .unwrap_err();
+        assert!(matches!(err, ForgeBaseError::Validation(_)));
+        assert!(renderer
+            .transform(&png(64, 10), &RenderOptions::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_decode_allocation_limit() {
+        // 64x10 RGB decodes to 1920 bytes
+        let renderer =
+            ImageRenderer::new(CdnManager::new(CdnConfig::default())).with_max_decode_bytes(1000);
+        assert!(matches!(
+            renderer.transform(&png(64, 10), &RenderOptions::default()),
+            Err(ForgeBaseError::Validation(_))
+        ));
+        assert!(renderer
+            .transform(&png(10, 10), &RenderOptions::default())
+            .is_ok());
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let mut options = RenderOptions {
+            width: Some(MAX_RENDER_DIMENSION),
+            quality: Some(100),
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+        options.width = Some(MAX_RENDER_DIMENSION + 1);
+        assert!(options.validate().is_err());
+        options.width = Some(10);
+        options.quality = Some(0);
+        assert!(options.validate().is_err());
+    }
+}
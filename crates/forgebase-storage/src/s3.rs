@@ -0,0 +1,1191 @@
+//! S3-compatible API
+//!
+//! Path-style requests under `/storage/s3/{bucket}/{key}` are authenticated with AWS Signature
+//! Version 4 against ForgeBase-issued access keys and mapped onto [`BucketManager`] and
+//! [`StorageService`]. Supported operations: ListBuckets, HeadBucket, ListObjects (v1 and v2),
+//! Get/Put/Head/DeleteObject, CopyObject and multipart uploads.
+
+use crate::bucket::{Bucket, BucketManager};
+use crate::download::quote_etag;
+use crate::handlers::{error_response, file_response};
+use crate::models::{ObjectSummary, UploadMetadata};
+use crate::policy::StorageOperation;
+use crate::s3_credentials::{CreateS3CredentialRequest, S3CredentialManager};
+use crate::service::{FileStream, StorageService};
+use crate::sigv4::{
+    decode_aws_chunked, query_pairs, uri_encode, verify_payload_hash, ChunkSigner, SignedRequest,
+    STREAMING_PAYLOAD, STREAMING_UNSIGNED_PAYLOAD_TRAILER, UNSIGNED_PAYLOAD,
+};
+use crate::upload::{parse_content_type, UploadValidator};
+use axum::{
+    body::Body,
+    extract::{OriginalUri, Path, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::{any, delete, get},
+    Extension, Json, Router,
+};
+use base64::Engine;
+use bytes::{Bytes, BytesMut};
+use forgebase_auth::jwt::Claims;
+use forgebase_core::ForgeBaseError;
+use futures::{StreamExt, TryStreamExt};
+use percent_encoding::percent_decode_str;
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Base path of the S3-compatible API
+pub const S3_ENDPOINT: &str = "/storage/s3";
+/// Reserved bucket holding the parts of in-progress multipart uploads
+pub const S3_MULTIPART_BUCKET: &str = "__s3_multipart";
+
+const S3_XMLNS: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+const AMZ_META_PREFIX: &str = "x-amz-meta-";
+const MAX_KEYS: usize = 1000;
+const MAX_PART_NUMBER: u32 = 10_000;
+const MAX_XML_BODY: usize = 1024 * 1024;
+
+/// S3 error, rendered as an S3 XML error document
+#[derive(Debug, Clone)]
+pub struct S3Error {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl S3Error {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn access_denied(message: &str) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "AccessDenied", message)
+    }
+
+    pub fn authorization_malformed(message: &str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "AuthorizationHeaderMalformed", message)
+    }
+
+    pub fn invalid_access_key_id() -> Self {
+        Self::new(
+            StatusCode::FORBIDDEN,
+            "InvalidAccessKeyId",
+            "The access key ID you provided does not exist in our records",
+        )
+    }
+
+    pub fn signature_does_not_match() -> Self {
+        Self::new(
+            StatusCode::FORBIDDEN,
+            "SignatureDoesNotMatch",
+            "The request signature we calculated does not match the signature you provided",
+        )
+    }
+
+    pub fn request_time_too_skewed() -> Self {
+        Self::new(
+            StatusCode::FORBIDDEN,
+            "RequestTimeTooSkewed",
+            "The difference between the request time and the server's time is too large",
+        )
+    }
+
+    pub fn invalid_request(message: &str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "InvalidRequest", message)
+    }
+
+    pub fn invalid_argument(message: &str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "InvalidArgument", message)
+    }
+
+    pub fn invalid_part(message: &str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "InvalidPart", message)
+    }
+
+    pub fn malformed_xml() -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            "MalformedXML",
+            "The XML you provided was not well-formed or did not validate",
+        )
+    }
+
+    pub fn no_such_bucket(bucket: &str) -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "NoSuchBucket",
+            format!("The bucket '{}' does not exist", bucket),
+        )
+    }
+
+    pub fn no_such_key() -> Self {
+        Self::new(StatusCode::NOT_FOUND, "NoSuchKey", "The specified key does not exist")
+    }
+
+    pub fn no_such_upload() -> Self {
+        Self::new(
+            StatusCode::NOT_FOUND,
+            "NoSuchUpload",
+            "The specified multipart upload does not exist",
+        )
+    }
+
+    pub fn not_implemented() -> Self {
+        Self::new(
+            StatusCode::NOT_IMPLEMENTED,
+            "NotImplemented",
+            "This operation is not supported",
+        )
+    }
+
+    /// S3 error code, e.g. `NoSuchKey`
+    pub fn code(&self) -> &str {
+        self.code
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl From<ForgeBaseError> for S3Error {
+    fn from(error: ForgeBaseError) -> Self {
+        match error {
+            ForgeBaseError::NotFound(_) => Self::no_such_key(),
+            ForgeBaseError::Validation(message) => {
+                if message.contains("exceeds maximum") {
+                    Self::new(StatusCode::BAD_REQUEST, "EntityTooLarge", message)
+                } else {
+                    Self::invalid_argument(&message)
+                }
+            }
+            ForgeBaseError::InvalidInput(message) => Self::invalid_request(&message),
+            ForgeBaseError::Auth(_) => Self::signature_does_not_match(),
+            ForgeBaseError::Authorization(message) => Self::access_denied(&message),
+            ForgeBaseError::Conflict(message) => {
+                Self::new(StatusCode::CONFLICT, "OperationAborted", message)
+            }
+            other => {
+                tracing::error!(error = %other, "S3 request failed");
+                Self::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "InternalError",
+                    "We encountered an internal error. Please try again.",
+                )
+            }
+        }
+    }
+}
+
+impl IntoResponse for S3Error {
+    fn into_response(self) -> Response {
+        let body = format!(
+            "<Error><Code>{}</Code><Message>{}</Message><RequestId>{}</RequestId></Error>",
+            self.code,
+            escape(&self.message),
+            Uuid::new_v4().simple()
+        );
+        xml_response(self.status, body)
+    }
+}
+
+/// S3 gateway state
+pub struct S3Gateway {
+    service: Arc<StorageService>,
+    buckets: BucketManager,
+    credentials: S3CredentialManager,
+}
+
+/// An authenticated S3 request
+struct S3Caller {
+    user_id: Uuid,
+    request: SignedRequest,
+    secret: String,
+}
+
+impl S3Gateway {
+    pub fn new(pool: PgPool, service: Arc<StorageService>) -> Self {
+        Self {
+            service,
+            buckets: BucketManager::new(pool.clone()),
+            credentials: S3CredentialManager::new(pool),
+        }
+    }
+
+    pub fn credentials(&self) -> &S3CredentialManager {
+        &self.credentials
+    }
+
+    /// Verify the request signature against the caller's access key
+    async fn authenticate(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+    ) -> Result<S3Caller, S3Error> {
+        let request = SignedRequest::from_request(uri, headers)?;
+        let (credential, secret) = self
+            .credentials
+            .find_active(&request.credential.access_key_id)
+            .await?
+            .ok_or_else(S3Error::invalid_access_key_id)?;
+
+        request.verify(method, uri, headers, &secret, chrono::Utc::now())?;
+        self.credentials.touch(&credential).await?;
+
+        Ok(S3Caller {
+            user_id: credential.user_id,
+            request,
+            secret,
+        })
+    }
+
+    /// Look up a bucket the caller may perform `operation` in
+    async fn bucket(
+        &self,
+        caller: &S3Caller,
+        name: &str,
+        operation: StorageOperation,
+    ) -> Result<Bucket, S3Error> {
+        let bucket = self
+            .buckets
+            .get_bucket(name)
+            .await?
+            .ok_or_else(|| S3Error::no_such_bucket(name))?;
+
+        let role = self.buckets.member_role(&bucket, caller.user_id).await?;
+        if !role.is_some_and(|role| operation.allowed_for(role)) {
+            return Err(S3Error::access_denied("Access Denied"));
+        }
+
+        Ok(bucket)
+    }
+
+    /// The request body as data, checked against how its payload was signed
+    fn body(&self, caller: &S3Caller, body: Body) -> Result<FileStream, S3Error> {
+        let stream = body.into_data_stream();
+        let payload_hash = caller.request.payload_hash.as_str();
+
+        match payload_hash {
+            UNSIGNED_PAYLOAD => Ok(stream
+                .map_err(|e| {
+                    ForgeBaseError::InvalidInput(format!("Failed to read request body: {}", e))
+                })
+                .boxed()),
+            STREAMING_PAYLOAD => Ok(decode_aws_chunked(
+                stream,
+                Some(ChunkSigner::new(&caller.request, &caller.secret)),
+            )),
+            STREAMING_UNSIGNED_PAYLOAD_TRAILER => Ok(decode_aws_chunked(stream, None)),
+            hash if hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                Ok(verify_payload_hash(stream, hash))
+            }
+            _ => Err(S3Error::not_implemented()),
+        }
+    }
+}
+
+/// Create the S3-compatible API routes
+pub fn create_s3_routes() -> Router<Arc<S3Gateway>> {
+    Router::new()
+        .route(S3_ENDPOINT, get(list_buckets_handler))
+        .route("/storage/s3/", get(list_buckets_handler))
+        .route("/storage/s3/:bucket", any(bucket_handler))
+        .route("/storage/s3/:bucket/", any(bucket_handler))
+        .route("/storage/s3/:bucket/*key", any(object_handler))
+}
+
+/// Create routes for managing the current user's S3 access keys
+pub fn create_s3_credential_routes() -> Router<Arc<S3Gateway>> {
+    Router::new()
+        .route(
+            "/storage/s3-credentials",
+            get(list_s3_credentials_handler).post(create_s3_credential_handler),
+        )
+        .route("/storage/s3-credentials/:id", delete(revoke_s3_credential_handler))
+}
+
+/// ListBuckets
+pub async fn list_buckets_handler(
+    State(gateway): State<Arc<S3Gateway>>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Result<Response, S3Error> {
+    let caller = gateway.authenticate(&method, &uri, &headers).await?;
+    let buckets = gateway.buckets.list_buckets(caller.user_id).await?;
+
+    let mut body = format!(
+        "<ListAllMyBucketsResult xmlns=\"{}\"><Owner><ID>{}</ID></Owner><Buckets>",
+        S3_XMLNS, caller.user_id
+    );
+    for bucket in &buckets {
+        let _ = write!(
+            body,
+            "<Bucket><Name>{}</Name><CreationDate>{}</CreationDate></Bucket>",
+            escape(&bucket.name),
+            s3_timestamp(bucket.created_at)
+        );
+    }
+    body.push_str("</Buckets></ListAllMyBucketsResult>");
+
+    Ok(xml_response(StatusCode::OK, body))
+}
+
+/// HeadBucket and ListObjects (v1 and v2)
+pub async fn bucket_handler(
+    State(gateway): State<Arc<S3Gateway>>,
+    Path(bucket): Path<String>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Result<Response, S3Error> {
+    let caller = gateway.authenticate(&method, &uri, &headers).await?;
+    let bucket = gateway
+        .bucket(&caller, &bucket, StorageOperation::List)
+        .await?;
+    let query = query_pairs(&uri);
+
+    match method {
+        Method::HEAD => Ok(StatusCode::OK.into_response()),
+        Method::GET if is_listing(&query) => list_objects(&gateway, &bucket, &query).await,
+        Method::GET => Err(S3Error::not_implemented()),
+        _ => Err(S3Error::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "MethodNotAllowed",
+            "The specified method is not allowed against this resource",
+        )),
+    }
+}
+
+/// Object operations, dispatched on method and query
+pub async fn object_handler(
+    State(gateway): State<Arc<S3Gateway>>,
+    Path((bucket, key)): Path<(String, String)>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, S3Error> {
+    let caller = gateway.authenticate(&method, &uri, &headers).await?;
+    let operation = match method {
+        Method::GET | Method::HEAD => StorageOperation::Download,
+        Method::DELETE => StorageOperation::Delete,
+        _ => StorageOperation::Upload,
+    };
+    let bucket = gateway.bucket(&caller, &bucket, operation).await?;
+    let query = query_pairs(&uri);
+    let upload_id = param(&query, "uploadId");
+
+    match method {
+        Method::GET | Method::HEAD if upload_id.is_some() => Err(S3Error::not_implemented()),
+        Method::GET | Method::HEAD => {
+            let meta = gateway
+                .service
+                .get_file_metadata(&bucket.name, &key)
+                .await
+                .map_err(|_| S3Error::no_such_key())?;
+            Ok(file_response(
+                &gateway.service,
+                &bucket.name,
+                &key,
+                meta,
+                &headers,
+                method == Method::GET,
+            )
+            .await)
+        }
+        Method::PUT => match (upload_id, param(&query, "partNumber")) {
+            (Some(upload_id), Some(part_number)) => {
+                let upload = load_upload(&gateway, &caller, &bucket, &key, upload_id).await?;
+                upload_part(&gateway, &caller, &upload, part_number, body).await
+            }
+            (None, None) if headers.contains_key("x-amz-copy-source") => {
+                copy_object(&gateway, &caller, &bucket, &key, &headers).await
+            }
+            (None, None) => put_object(&gateway, &caller, &bucket, &key, &headers, body).await,
+            _ => Err(S3Error::invalid_request(
+                "partNumber and uploadId must be given together",
+            )),
+        },
+        Method::POST if has_param(&query, "uploads") => {
+            create_multipart_upload(&gateway, &caller, &bucket, &key, &headers).await
+        }
+        Method::POST => match upload_id {
+            Some(upload_id) => {
+                let upload = load_upload(&gateway, &caller, &bucket, &key, upload_id).await?;
+                complete_multipart_upload(&gateway, &caller, &bucket, &upload, body).await
+            }
+            None => Err(S3Error::not_implemented()),
+        },
+        Method::DELETE => match upload_id {
+            Some(upload_id) => {
+                let upload = load_upload(&gateway, &caller, &bucket, &key, upload_id).await?;
+                gateway
+                    .service
+                    .delete_prefix(S3_MULTIPART_BUCKET, &format!("{}/", upload.upload_id))
+                    .await?;
+                Ok(StatusCode::NO_CONTENT.into_response())
+            }
+            None => {
+                match gateway.service.delete_file(&bucket.name, &key).await {
+                    Ok(()) | Err(ForgeBaseError::NotFound(_)) => {}
+                    Err(e) => return Err(e.into()),
+                }
+                Ok(StatusCode::NO_CONTENT.into_response())
+            }
+        },
+        _ => Err(S3Error::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "MethodNotAllowed",
+            "The specified method is not allowed against this resource",
+        )),
+    }
+}
+
+async fn put_object(
+    gateway: &S3Gateway,
+    caller: &S3Caller,
+    bucket: &Bucket,
+    key: &str,
+    headers: &HeaderMap,
+    body: Body,
+) -> Result<Response, S3Error> {
+    let metadata = upload_metadata(caller, key, headers);
+    let validator = validator(gateway, bucket);
+    if let Some(length) = decoded_content_length(headers) {
+        validator.validate_size(length)?;
+    }
+    validator.validate_content_type(metadata.content_type.as_deref())?;
+
+    let body = gateway.body(caller, body)?;
+    let object = gateway
+        .service
+        .upload_stream(&bucket.name, key, body, metadata)
+        .await?;
+
+    Ok(etag_response(object.etag.as_deref()))
+}
+
+async fn copy_object(
+    gateway: &S3Gateway,
+    caller: &S3Caller,
+    bucket: &Bucket,
+    key: &str,
+    headers: &HeaderMap,
+) -> Result<Response, S3Error> {
+    let source = headers
+        .get("x-amz-copy-source")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_copy_source)
+        .ok_or_else(|| S3Error::invalid_argument("Invalid x-amz-copy-source"))?;
+    let source_bucket = gateway
+        .bucket(caller, &source.0, StorageOperation::Download)
+        .await?;
+
+    gateway
+        .service
+        .copy_file(&source_bucket.name, &source.1, &bucket.name, key)
+        .await?;
+    let meta = gateway.service.get_file_metadata(&bucket.name, key).await?;
+
+    let body = format!(
+        "<CopyObjectResult xmlns=\"{}\"><LastModified>{}</LastModified><ETag>{}</ETag></CopyObjectResult>",
+        S3_XMLNS,
+        s3_timestamp(meta.last_modified),
+        escape(&meta.etag.as_deref().map(quote_etag).unwrap_or_default())
+    );
+    Ok(xml_response(StatusCode::OK, body))
+}
+
+/// Multipart upload state, stored next to its parts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MultipartUpload {
+    upload_id: Uuid,
+    bucket: String,
+    key: String,
+    user_id: Uuid,
+    metadata: UploadMetadata,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl MultipartUpload {
+    fn info_key(upload_id: Uuid) -> String {
+        format!("{}/info.json", upload_id)
+    }
+
+    fn part_key(&self, part_number: u32) -> String {
+        format!("{}/parts/{:05}", self.upload_id, part_number)
+    }
+}
+
+/// Load a multipart upload the caller started for this bucket and key
+async fn load_upload(
+    gateway: &S3Gateway,
+    caller: &S3Caller,
+    bucket: &Bucket,
+    key: &str,
+    upload_id: &str,
+) -> Result<MultipartUpload, S3Error> {
+    let upload_id = Uuid::parse_str(upload_id).map_err(|_| S3Error::no_such_upload())?;
+    let info = gateway
+        .service
+        .download_file(S3_MULTIPART_BUCKET, &MultipartUpload::info_key(upload_id))
+        .await
+        .map_err(|_| S3Error::no_such_upload())?;
+    let upload: MultipartUpload =
+        serde_json::from_slice(&info).map_err(|e| ForgeBaseError::Internal(e.to_string()))?;
+
+    if upload.bucket != bucket.name || upload.key != key || upload.user_id != caller.user_id {
+        return Err(S3Error::no_such_upload());
+    }
+
+    Ok(upload)
+}
+
+async fn create_multipart_upload(
+    gateway: &S3Gateway,
+    caller: &S3Caller,
+    bucket: &Bucket,
+    key: &str,
+    headers: &HeaderMap,
+) -> Result<Response, S3Error> {
+    let metadata = upload_metadata(caller, key, headers);
+    validator(gateway, bucket).validate_content_type(metadata.content_type.as_deref())?;
+
+    let upload = MultipartUpload {
+        upload_id: Uuid::new_v4(),
+        bucket: bucket.name.clone(),
+        key: key.to_string(),
+        user_id: caller.user_id,
+        metadata,
+        created_at: chrono::Utc::now(),
+    };
+    let info = serde_json::to_vec(&upload).map_err(|e| ForgeBaseError::Internal(e.to_string()))?;
+    gateway
+        .service
+        .upload_file(
+            S3_MULTIPART_BUCKET,
+            &MultipartUpload::info_key(upload.upload_id),
+            Bytes::from(info),
+            UploadMetadata {
+                content_type: Some("application/json".to_string()),
+                custom_metadata: HashMap::new(),
+                owner_id: None,
+            },
+        )
+        .await?;
+
+    let body = format!(
+        "<InitiateMultipartUploadResult xmlns=\"{}\"><Bucket>{}</Bucket><Key>{}</Key><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+        S3_XMLNS,
+        escape(&upload.bucket),
+        escape(&upload.key),
+        upload.upload_id
+    );
+    Ok(xml_response(StatusCode::OK, body))
+}
+
+async fn upload_part(
+    gateway: &S3Gateway,
+    caller: &S3Caller,
+    upload: &MultipartUpload,
+    part_number: &str,
+    body: Body,
+) -> Result<Response, S3Error> {
+    let part_number = part_number
+        .parse::<u32>()
+        .ok()
+        .filter(|number| (1..=MAX_PART_NUMBER).contains(number))
+        .ok_or_else(|| {
+            S3Error::invalid_argument("Part number must be an integer between 1 and 10000")
+        })?;
+
+    let body = gateway.body(caller, body)?;
+    let object = gateway
+        .service
+        .upload_stream(
+            S3_MULTIPART_BUCKET,
+            &upload.part_key(part_number),
+            body,
+            UploadMetadata {
+                content_type: Some("application/octet-stream".to_string()),
+                custom_metadata: HashMap::new(),
+                owner_id: None,
+            },
+        )
+        .await?;
+
+    Ok(etag_response(object.etag.as_deref()))
+}
+
+/// `CompleteMultipartUpload` request body
+#[derive(Debug, Deserialize)]
+#[serde(rename = "CompleteMultipartUpload")]
+struct CompleteMultipartUpload {
+    #[serde(rename = "Part", default)]
+    parts: Vec<CompletedPart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletedPart {
+    #[serde(rename = "PartNumber")]
+    part_number: u32,
+    #[serde(rename = "ETag")]
+    etag: String,
+}
+
+async fn complete_multipart_upload(
+    gateway: &S3Gateway,
+    caller: &S3Caller,
+    bucket: &Bucket,
+    upload: &MultipartUpload,
+    body: Body,
+) -> Result<Response, S3Error> {
+    let mut body = gateway.body(caller, body)?;
+    let mut xml = BytesMut::new();
+    while let Some(chunk) = body.next().await {
+        xml.extend_from_slice(&chunk?);
+        if xml.len() > MAX_XML_BODY {
+            return Err(S3Error::malformed_xml());
+        }
+    }
+    let xml = std::str::from_utf8(&xml).map_err(|_| S3Error::malformed_xml())?;
+    let request: CompleteMultipartUpload =
+        quick_xml::de::from_str(xml).map_err(|_| S3Error::malformed_xml())?;
+
+    let parts = gateway
+        .service
+        .list_objects(S3_MULTIPART_BUCKET, &format!("{}/parts/", upload.upload_id))
+        .await?;
+    let uploaded: HashMap<String, Option<String>> = parts
+        .iter()
+        .map(|part| (part.key.clone(), part.etag.clone()))
+        .collect();
+    let keys = completed_part_keys(upload, &request.parts, &uploaded)?;
+
+    let size: i64 = parts
+        .iter()
+        .filter(|part| keys.contains(&part.key))
+        .map(|part| part.size)
+        .sum();
+    validator(gateway, bucket).validate_size(size as u64)?;
+
+    let stream = gateway.service.concat_stream(S3_MULTIPART_BUCKET, keys);
+    let object = gateway
+        .service
+        .upload_stream(&upload.bucket, &upload.key, stream, upload.metadata.clone())
+        .await?;
+    gateway
+        .service
+        .delete_prefix(S3_MULTIPART_BUCKET, &format!("{}/", upload.upload_id))
+        .await?;
+
+    let body = format!(
+        "<CompleteMultipartUploadResult xmlns=\"{}\"><Location>{}/{}/{}</Location><Bucket>{}</Bucket><Key>{}</Key><ETag>{}</ETag></CompleteMultipartUploadResult>",
+        S3_XMLNS,
+        S3_ENDPOINT,
+        escape(&upload.bucket),
+        escape(&encode_key(&upload.key)),
+        escape(&upload.bucket),
+        escape(&upload.key),
+        escape(&object.etag.as_deref().map(quote_etag).unwrap_or_default())
+    );
+    Ok(xml_response(StatusCode::OK, body))
+}
+
+/// Resolve the parts named by a completion request to their stored keys
+///
+/// Parts must be listed in ascending order and match the ETag returned when they were uploaded.
+fn completed_part_keys(
+    upload: &MultipartUpload,
+    parts: &[CompletedPart],
+    uploaded: &HashMap<String, Option<String>>,
+) -> Result<Vec<String>, S3Error> {
+    if parts.is_empty() {
+        return Err(S3Error::malformed_xml());
+    }
+    if parts.windows(2).any(|pair| pair[0].part_number >= pair[1].part_number) {
+        return Err(S3Error::new(
+            StatusCode::BAD_REQUEST,
+            "InvalidPartOrder",
+            "The list of parts was not in ascending order",
+        ));
+    }
+
+    parts
+        .iter()
+        .map(|part| {
+            let key = upload.part_key(part.part_number);
+            let etag = uploaded.get(&key).ok_or_else(|| {
+                S3Error::invalid_part(&format!("Part {} was not uploaded", part.part_number))
+            })?;
+            if etag.as_deref().map(unquote) != Some(unquote(&part.etag)) {
+                return Err(S3Error::invalid_part(&format!(
+                    "ETag of part {} does not match",
+                    part.part_number
+                )));
+            }
+            Ok(key)
+        })
+        .collect()
+}
+
+async fn list_objects(
+    gateway: &S3Gateway,
+    bucket: &Bucket,
+    query: &[(String, String)],
+) -> Result<Response, S3Error> {
+    let v2 = param(query, "list-type") == Some("2");
+    let prefix = param(query, "prefix").unwrap_or("");
+    let delimiter = param(query, "delimiter").filter(|d| !d.is_empty());
+    let url_encoded = match param(query, "encoding-type") {
+        None => false,
+        Some("url") => true,
+        Some(_) => return Err(S3Error::invalid_argument("Invalid Encoding Method specified")),
+    };
+    let max_keys = match param(query, "max-keys") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| S3Error::invalid_argument("max-keys must be a non-negative integer"))?
+            .min(MAX_KEYS),
+        None => MAX_KEYS,
+    };
+
+    let continuation_token = param(query, "continuation-token").filter(|_| v2);
+    let after = match continuation_token {
+        Some(token) => Some(decode_continuation_token(token)?),
+        None if v2 => param(query, "start-after").map(str::to_string),
+        None => param(query, "marker").map(str::to_string),
+    };
+
+    let objects = gateway.service.list_objects(&bucket.name, prefix).await?;
+    let page = paginate(objects, prefix, delimiter, after.as_deref(), max_keys);
+
+    let key = |value: &str| {
+        if url_encoded {
+            encode_key(value)
+        } else {
+            escape(value).into_owned()
+        }
+    };
+
+    let mut body = format!(
+        "<ListBucketResult xmlns=\"{}\"><Name>{}</Name><Prefix>{}</Prefix><MaxKeys>{}</MaxKeys><IsTruncated>{}</IsTruncated>",
+        S3_XMLNS,
+        escape(&bucket.name),
+        key(prefix),
+        max_keys,
+        page.is_truncated
+    );
+    if let Some(delimiter) = delimiter {
+        let _ = write!(body, "<Delimiter>{}</Delimiter>", key(delimiter));
+    }
+    if url_encoded {
+        body.push_str("<EncodingType>url</EncodingType>");
+    }
+    if v2 {
+        let _ = write!(body, "<KeyCount>{}</KeyCount>", page.len());
+        if let Some(token) = continuation_token {
+            let _ = write!(body, "<ContinuationToken>{}</ContinuationToken>", escape(token));
+        }
+        if let Some(start_after) = param(query, "start-after") {
+            let _ = write!(body, "<StartAfter>{}</StartAfter>", key(start_after));
+        }
+        if let Some(next) = &page.next_marker {
+            let _ = write!(
+                body,
+                "<NextContinuationToken>{}</NextContinuationToken>",
+                encode_continuation_token(next)
+            );
+        }
+    } else {
+        let _ = write!(body, "<Marker>{}</Marker>", key(after.as_deref().unwrap_or("")));
+        if let (Some(next), Some(_)) = (&page.next_marker, delimiter) {
+            let _ = write!(body, "<NextMarker>{}</NextMarker>", key(next));
+        }
+    }
+
+    for object in &page.objects {
+        let _ = write!(
+            body,
+            "<Contents><Key>{}</Key><LastModified>{}</LastModified><ETag>{}</ETag><Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+            key(&object.key),
+            s3_timestamp(object.last_modified),
+            escape(&object.etag.as_deref().map(quote_etag).unwrap_or_default()),
+            object.size
+        );
+    }
+    for common_prefix in &page.common_prefixes {
+        let _ = write!(
+            body,
+            "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+            key(common_prefix)
+        );
+    }
+    body.push_str("</ListBucketResult>");
+
+    Ok(xml_response(StatusCode::OK, body))
+}
+
+/// One page of a bucket listing
+#[derive(Debug, Default)]
+struct ListPage {
+    objects: Vec<ObjectSummary>,
+    common_prefixes: Vec<String>,
+    is_truncated: bool,
+    /// Last key or common prefix returned, when there are more
+    next_marker: Option<String>,
+}
+
+impl ListPage {
+    fn len(&self) -> usize {
+        self.objects.len() + self.common_prefixes.len()
+    }
+}
+
+/// Page through sorted objects, rolling keys up to common prefixes at the delimiter
+///
+/// Listing resumes after `after`, which may be a key or a common prefix from a previous page.
+fn paginate(
+    objects: Vec<ObjectSummary>,
+    prefix: &str,
+    delimiter: Option<&str>,
+    after: Option<&str>,
+    max_keys: usize,
+) -> ListPage {
+    let mut page = ListPage::default();
+    let mut last = None;
+
+    for object in objects {
+        if after.is_some_and(|after| object.key.as_str() <= after) {
+            continue;
+        }
+        let common_prefix = delimiter.and_then(|delimiter| {
+            let rest = object.key.get(prefix.len()..)?;
+            rest.find(delimiter)
+                .map(|index| object.key[..prefix.len() + index + delimiter.len()].to_string())
+        });
+        if let Some(common_prefix) = &common_prefix {
+            if after.is_some_and(|after| common_prefix.as_str() <= after)
+                || page.common_prefixes.last() == Some(common_prefix)
+            {
+                continue;
+            }
+        }
+
+        if page.len() == max_keys {
+            page.is_truncated = true;
+            break;
+        }
+        match common_prefix {
+            Some(common_prefix) => {
+                last = Some(common_prefix.clone());
+                page.common_prefixes.push(common_prefix);
+            }
+            None => {
+                last = Some(object.key.clone());
+                page.objects.push(object);
+            }
+        }
+    }
+
+    if page.is_truncated {
+        page.next_marker = last;
+    }
+    page
+}
+
+/// List the current user's S3 access keys
+pub async fn list_s3_credentials_handler(
+    State(gateway): State<Arc<S3Gateway>>,
+    Extension(claims): Extension<Claims>,
+) -> Response {
+    let result = match claims.user_id() {
+        Ok(user_id) => gateway.credentials.list(user_id).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(credentials) => (StatusCode::OK, Json(credentials)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Issue an S3 access key for the current user; the secret is only returned here
+pub async fn create_s3_credential_handler(
+    State(gateway): State<Arc<S3Gateway>>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<CreateS3CredentialRequest>,
+) -> Response {
+    let result = match claims.forbid_impersonation().and_then(|()| claims.user_id()) {
+        Ok(user_id) => gateway.credentials.create(user_id, request.description).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(created) => (StatusCode::CREATED, Json(created)).into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Revoke one of the current user's S3 access keys
+pub async fn revoke_s3_credential_handler(
+    State(gateway): State<Arc<S3Gateway>>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    let result = match claims.forbid_impersonation().and_then(|()| claims.user_id()) {
+        Ok(user_id) => gateway.credentials.revoke(user_id, id).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn validator(gateway: &S3Gateway, bucket: &Bucket) -> UploadValidator {
+    let mut max_size = gateway.service.max_file_size();
+    if let Some(limit) = bucket.max_file_size {
+        max_size = Some(max_size.map_or(limit, |max| max.min(limit)));
+    }
+    UploadValidator::new(max_size, bucket.allowed_mime_types.clone())
+}
+
+fn upload_metadata(caller: &S3Caller, key: &str, headers: &HeaderMap) -> UploadMetadata {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| parse_content_type(key));
+    let custom_metadata = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = name.as_str().strip_prefix(AMZ_META_PREFIX)?;
+            Some((name.to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect();
+
+    UploadMetadata {
+        content_type: Some(content_type),
+        custom_metadata,
+        owner_id: Some(caller.user_id),
+    }
+}
+
+/// Size of the object being uploaded, which differs from `Content-Length` for chunked bodies
+fn decoded_content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get("x-amz-decoded-content-length")
+        .or_else(|| headers.get(header::CONTENT_LENGTH))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Split `x-amz-copy-source` (`[/]bucket/key[?versionId=…]`, URL-encoded) into bucket and key
+fn parse_copy_source(value: &str) -> Option<(String, String)> {
+    let value = value.split_once('?').map_or(value, |(path, _)| path);
+    let value = percent_decode_str(value).decode_utf8().ok()?;
+    let (bucket, key) = value.trim_start_matches('/').split_once('/')?;
+    if bucket.is_empty() || key.is_empty() {
+        return None;
+    }
+    Some((bucket.to_string(), key.to_string()))
+}
+
+fn is_listing(query: &[(String, String)]) -> bool {
+    const LISTING_PARAMS: &[&str] = &[
+        "list-type",
+        "prefix",
+        "delimiter",
+        "max-keys",
+        "marker",
+        "continuation-token",
+        "start-after",
+        "encoding-type",
+        "fetch-owner",
+    ];
+    query
+        .iter()
+        .all(|(name, _)| LISTING_PARAMS.contains(&name.as_str()) || name.starts_with("X-Amz-"))
+}
+
+fn param<'a>(query: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    query
+        .iter()
+        .find(|(param, _)| param == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn has_param(query: &[(String, String)], name: &str) -> bool {
+    query.iter().any(|(param, _)| param == name)
+}
+
+fn encode_continuation_token(after: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(after)
+}
+
+fn decode_continuation_token(token: &str) -> Result<String, S3Error> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(token)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| S3Error::invalid_argument("The continuation token provided is incorrect"))
+}
+
+/// URL-encode a key for `encoding-type=url`, keeping `/` separators
+fn encode_key(key: &str) -> String {
+    key.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+}
+
+fn unquote(etag: &str) -> &str {
+    etag.trim().trim_matches('"')
+}
+
+fn s3_timestamp(date: chrono::DateTime<chrono::Utc>) -> String {
+    date.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+fn etag_response(etag: Option<&str>) -> Response {
+    let mut headers = HeaderMap::new();
+    if let Some(value) = etag.and_then(|etag| HeaderValue::from_str(&quote_etag(etag)).ok()) {
+        headers.insert(header::ETAG, value);
+    }
+    (StatusCode::OK, headers).into_response()
+}
+
+fn xml_response(status: StatusCode, body: String) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/xml")],
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}", body),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(key: &str) -> ObjectSummary {
+        ObjectSummary {
+            key: key.to_string(),
+            size: 1,
+            last_modified: chrono::Utc::now(),
+            etag: None,
+        }
+    }
+
+    fn keys(page: &ListPage) -> Vec<&str> {
+        page.objects.iter().map(|object| object.key.as_str()).collect()
+    }
+
+    #[test]
+    fn test_paginate_with_delimiter() {
+        let objects = || {
+            ["a.txt", "photos/2023/x.jpg", "photos/2024/y.jpg", "photos/z.jpg", "videos/v.mp4"]
+                .into_iter()
+                .map(object)
+                .collect::<Vec<_>>()
+        };
+
+        let page = paginate(objects(), "", Some("/"), None, 1000);
+        assert_eq!(keys(&page), vec!["a.txt"]);
+        assert_eq!(page.common_prefixes, vec!["photos/", "videos/"]);
+        assert!(!page.is_truncated);
+
+        let photos: Vec<_> = objects()
+            .into_iter()
+            .filter(|object| object.key.starts_with("photos/"))
+            .collect();
+        let page = paginate(photos, "photos/", Some("/"), None, 1000);
+        assert_eq!(keys(&page), vec!["photos/z.jpg"]);
+        assert_eq!(page.common_prefixes, vec!["photos/2023/", "photos/2024/"]);
+
+        // A common prefix counts once towards max-keys and resumes past all its keys
+        let first = paginate(objects(), "", Some("/"), None, 2);
+        assert_eq!(keys(&first), vec!["a.txt"]);
+        assert_eq!(first.common_prefixes, vec!["photos/"]);
+        assert!(first.is_truncated);
+        assert_eq!(first.next_marker.as_deref(), Some("photos/"));
+
+        let second = paginate(objects(), "", Some("/"), first.next_marker.as_deref(), 2);
+        assert!(keys(&second).is_empty());
+        assert_eq!(second.common_prefixes, vec!["videos/"]);
+        assert!(!second.is_truncated);
+        assert_eq!(second.next_marker, None);
+    }
+
+    #[test]
+    fn test_paginate_without_delimiter() {
+        let objects: Vec<_> = ["a", "b", "c"].into_iter().map(object).collect();
+
+        let page = paginate(objects.clone(), "", None, None, 2);
+        assert_eq!(keys(&page), vec!["a", "b"]);
+        assert_eq!(page.next_marker.as_deref(), Some("b"));
+
+        let page = paginate(objects, "", None, Some("b"), 2);
+        assert_eq!(keys(&page), vec!["c"]);
+        assert!(!page.is_truncated);
+    }
+
+    #[test]
+    fn test_complete_multipart_request() {
+        let xml = r#"<CompleteMultipartUpload xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+            <Part><PartNumber>1</PartNumber><ETag>"etag-1"</ETag></Part>
+            <Part><ETag>etag-2</ETag><PartNumber>2</PartNumber></Part>
+        </CompleteMultipartUpload>"#;
+        let request: CompleteMultipartUpload = quick_xml::de::from_str(xml).unwrap();
+
+        let upload = MultipartUpload {
+            upload_id: Uuid::new_v4(),
+            bucket: "bucket".to_string(),
+            key: "key".to_string(),
+            user_id: Uuid::new_v4(),
+            metadata: UploadMetadata {
+                content_type: None,
+                custom_metadata: HashMap::new(),
+                owner_id: None,
+            },
+            created_at: chrono::Utc::now(),
+        };
+        let mut uploaded = HashMap::new();
+        uploaded.insert(upload.part_key(1), Some("etag-1".to_string()));
+        uploaded.insert(upload.part_key(2), Some("\"etag-2\"".to_string()));
+
+        let keys = completed_part_keys(&upload, &request.parts, &uploaded).unwrap();
+        assert_eq!(keys, vec![upload.part_key(1), upload.part_key(2)]);
+
+        uploaded.insert(upload.part_key(2), Some("other".to_string()));
+        let error = completed_part_keys(&upload, &request.parts, &uploaded).unwrap_err();
+        assert_eq!(error.code(), "InvalidPart");
+
+        let reversed: Vec<_> = request.parts.into_iter().rev().collect();
+        let error = completed_part_keys(&upload, &reversed, &uploaded).unwrap_err();
+        assert_eq!(error.code(), "InvalidPartOrder");
+    }
+
+    #[test]
+    fn test_parse_copy_source() {
+        assert_eq!(
+            parse_copy_source("/src/dir/my%20file.txt?versionId=1"),
+            Some(("src".to_string(), "dir/my file.txt".to_string()))
+        );
+        assert_eq!(
+            parse_copy_source("src/key"),
+            Some(("src".to_string(), "key".to_string()))
+        );
+        assert_eq!(parse_copy_source("/src"), None);
+    }
+}
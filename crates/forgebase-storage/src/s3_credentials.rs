@@ -0,0 +1,162 @@
+//! Access keys for the S3-compatible API
+
+use forgebase_core::{utils::generate_token, ForgeBaseError, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Prefix of ForgeBase-issued access key IDs
+pub const ACCESS_KEY_PREFIX: &str = "FB";
+const ACCESS_KEY_ID_LENGTH: usize = 18;
+const SECRET_ACCESS_KEY_LENGTH: usize = 40;
+/// How stale `last_used_at` may get before a request refreshes it
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+/// S3 access key; the secret is only shown once, on creation
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct S3Credential {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub access_key_id: String,
+    pub description: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Create S3 credential request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateS3CredentialRequest {
+    pub description: Option<String>,
+}
+
+/// Newly created S3 credential including its secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedS3Credential {
+    #[serde(flatten)]
+    pub credential: S3Credential,
+    pub secret_access_key: String,
+}
+
+/// S3 credential manager
+pub struct S3CredentialManager {
+    pool: PgPool,
+}
+
+impl S3CredentialManager {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Issue a new access key for a user
+    pub async fn create(&self, user_id: Uuid, description: Option<String>) -> Result<CreatedS3Credential> {
+        let access_key_id = format!(
+            "{}{}",
+            ACCESS_KEY_PREFIX,
+            generate_token(ACCESS_KEY_ID_LENGTH).to_ascii_uppercase()
+        );
+        let secret_access_key = generate_token(SECRET_ACCESS_KEY_LENGTH);
+
+        let credential = sqlx::query_as::<_, S3Credential>(
+            r#"
+            INSERT INTO storage_s3_credentials (id, user_id, access_key_id, secret_access_key, description)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, user_id, access_key_id, description, created_at, last_used_at, revoked_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(&access_key_id)
+        .bind(&secret_access_key)
+        .bind(description)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        Ok(CreatedS3Credential {
+            credential,
+            secret_access_key,
+        })
+    }
+
+    /// List a user's access keys, revoked ones included
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<S3Credential>> {
+        sqlx::query_as::<_, S3Credential>(
+            r#"
+            SELECT id, user_id, access_key_id, description, created_at, last_used_at, revoked_at
+            FROM storage_s3_credentials WHERE user_id = $1 ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))
+    }
+
+    /// Revoke one of a user's access keys
+    pub async fn revoke(&self, user_id: Uuid, id: Uuid) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE storage_s3_credentials SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ForgeBaseError::NotFound("S3 credential not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Look up an unrevoked access key and its secret
+    ///
+    /// Keys of disabled or deleted users are not found; a ban that has run out no longer
+    /// counts, as at sign-in.
+    pub async fn find_active(&self, access_key_id: &str) -> Result<Option<(S3Credential, String)>> {
+        let row = sqlx::query_as::<_, CredentialWithSecret>(
+            r#"
+            SELECT c.id, c.user_id, c.access_key_id, c.description, c.created_at, c.last_used_at,
+                   c.revoked_at, c.secret_access_key
+            FROM storage_s3_credentials c
+            JOIN users u ON u.id = c.user_id
+            WHERE c.access_key_id = $1 AND c.revoked_at IS NULL
+              AND (u.is_active OR (u.deleted_at IS NULL AND u.banned_until <= NOW()))
+            "#,
+        )
+        .bind(access_key_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        Ok(row.map(|row| (row.credential, row.secret_access_key)))
+    }
+
+    /// Record that an access key signed a verified request
+    ///
+    /// Only writes when `last_used_at` is more than a minute old, so busy keys do not cost a
+    /// write per request.
+    pub async fn touch(&self, credential: &S3Credential) -> Result<()> {
+        let cutoff = chrono::Utc::now() - chrono::Duration::seconds(LAST_USED_RESOLUTION_SECONDS);
+        if credential.last_used_at.is_some_and(|used| used > cutoff) {
+            return Ok(());
+        }
+
+        sqlx::query("UPDATE storage_s3_credentials SET last_used_at = NOW() WHERE id = $1")
+            .bind(credential.id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| ForgeBaseError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct CredentialWithSecret {
+    #[sqlx(flatten)]
+    credential: S3Credential,
+    secret_access_key: String,
+}
@@ -1,11 +1,21 @@
 //! Storage service implementation
 
 use crate::models::*;
+use crate::object_index::ObjectIndex;
+use crate::signed_url::{SignedUrlGrant, UrlSigner, MAX_SIGNED_URL_EXPIRY_SECONDS};
+use crate::upload::UploadValidator;
+use bytes::Bytes;
 use forgebase_core::{ForgeBaseError, Result};
-use futures::stream::StreamExt;
-use object_store::{aws::AmazonS3Builder, local::LocalFileSystem, ObjectStore};
+use futures::stream::{BoxStream, Stream, StreamExt, TryStreamExt};
+use object_store::{
+    aws::AmazonS3Builder, local::LocalFileSystem, signer::Signer, GetOptions, GetRange, ObjectStore,
+};
+use percent_encoding::percent_decode_str;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
 /// Storage backend type
@@ -21,15 +31,27 @@ pub enum StorageBackend {
     },
 }
 
+/// Buckets starting with this prefix hold internal state and are not indexed
+pub const RESERVED_BUCKET_PREFIX: &str = "__";
+
+/// Stream of file bytes read from the backend
+pub type FileStream = BoxStream<'static, Result<Bytes>>;
+
 /// Storage service
 pub struct StorageService {
     backend: Arc<dyn ObjectStore>,
-    backend_type: StorageBackend,
+    max_file_size: Option<i64>,
+    /// Presigns URLs on backends that support it natively
+    signer: Option<Arc<dyn Signer>>,
+    /// Signs ForgeBase URLs for backends that don't
+    url_signer: UrlSigner,
+    index: Option<ObjectIndex>,
 }
 
 impl StorageService {
     /// Create a new storage service
     pub async fn new(backend: StorageBackend) -> Result<Self> {
+        let mut signer: Option<Arc<dyn Signer>> = None;
         let store: Arc<dyn ObjectStore> = match &backend {
             StorageBackend::Local(path) => {
                 tokio::fs::create_dir_all(path)
@@ -55,20 +77,71 @@ impl StorageService {
                     builder = builder.with_endpoint(endpoint);
                 }
 
-                Arc::new(
+                let s3 = Arc::new(
                     builder
                         .build()
                         .map_err(|e| ForgeBaseError::Internal(format!("Failed to initialize S3 storage: {}", e)))?,
-                )
+                );
+                signer = Some(s3.clone());
+                s3
             }
         };
 
         Ok(Self {
             backend: store,
-            backend_type: backend,
+            max_file_size: None,
+            signer,
+            url_signer: UrlSigner::random(),
+            index: None,
         })
     }
 
+    /// Keep object metadata in `index`, enabling [`query_files`](Self::query_files)
+    pub fn with_index(mut self, index: ObjectIndex) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// The index to keep in sync for a bucket, if any
+    fn index_for(&self, bucket: &str) -> Option<&ObjectIndex> {
+        self.index
+            .as_ref()
+            .filter(|_| !bucket.starts_with(RESERVED_BUCKET_PREFIX))
+    }
+
+    /// Record an uploaded object in the index, taking its stable id from there
+    async fn index_object(&self, object: StorageObject) -> Result<StorageObject> {
+        match self.index_for(&object.bucket) {
+            Some(index) => index.upsert(&object).await,
+            None => Ok(object),
+        }
+    }
+
+    /// Sign ForgeBase URLs with `key`
+    ///
+    /// Without it a random key is used, so URLs stop verifying on restart and are not shared
+    /// between instances.
+    pub fn with_url_signing_key(mut self, key: impl AsRef<[u8]>) -> Self {
+        self.url_signer = UrlSigner::new(key);
+        self
+    }
+
+    /// Signer for ForgeBase URLs
+    pub fn url_signer(&self) -> &UrlSigner {
+        &self.url_signer
+    }
+
+    /// Reject uploads larger than `bytes`
+    pub fn with_max_file_size(mut self, bytes: i64) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    /// Maximum upload size in bytes, if any
+    pub fn max_file_size(&self) -> Option<i64> {
+        self.max_file_size
+    }
+
     /// Upload a file
     pub async fn upload_file(
         &self,
@@ -78,23 +151,100 @@ impl StorageService {
         metadata: UploadMetadata,
     ) -> Result<StorageObject> {
         let path = object_store::path::Path::from(format!("{}/{}", bucket, key));
+        UploadValidator::new(self.max_file_size, None).validate_size(data.len() as u64)?;
 
-        self.backend
+        let result = self
+            .backend
             .put(&path, data.clone())
             .await
             .map_err(|e| ForgeBaseError::Internal(format!("Failed to upload file: {}", e)))?;
 
-        Ok(StorageObject {
+        self.index_object(StorageObject {
             id: Uuid::new_v4(),
             bucket: bucket.to_string(),
             key: key.to_string(),
             size: data.len() as i64,
             content_type: metadata.content_type,
-            etag: None,
+            etag: result.e_tag,
             metadata: metadata.custom_metadata,
+            owner_id: metadata.owner_id,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         })
+        .await
+    }
+
+    /// Upload a file from a stream of chunks without buffering it in memory
+    ///
+    /// The chunks go into a multipart put, which is aborted if the stream fails or grows past
+    /// the maximum file size.
+    pub async fn upload_stream<S, E>(
+        &self,
+        bucket: &str,
+        key: &str,
+        mut body: S,
+        metadata: UploadMetadata,
+    ) -> Result<StorageObject>
+    where
+        S: Stream<Item = std::result::Result<Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let path = object_store::path::Path::from(format!("{}/{}", bucket, key));
+        let validator = UploadValidator::new(self.max_file_size, None);
+
+        let (multipart_id, mut writer) = self
+            .backend
+            .put_multipart(&path)
+            .await
+            .map_err(|e| ForgeBaseError::Internal(format!("Failed to start upload: {}", e)))?;
+
+        let written = async {
+            let mut size = 0u64;
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk.map_err(|e| {
+                    ForgeBaseError::InvalidInput(format!("Failed to read upload body: {}", e))
+                })?;
+                size += chunk.len() as u64;
+                validator.validate_size(size)?;
+                writer
+                    .write_all(&chunk)
+                    .await
+                    .map_err(|e| ForgeBaseError::Internal(format!("Failed to upload file: {}", e)))?;
+            }
+            writer
+                .shutdown()
+                .await
+                .map_err(|e| ForgeBaseError::Internal(format!("Failed to upload file: {}", e)))?;
+            Ok::<_, ForgeBaseError>(size)
+        }
+        .await;
+
+        let size = match written {
+            Ok(size) => size,
+            Err(e) => {
+                if let Err(abort) = self.backend.abort_multipart(&path, &multipart_id).await {
+                    tracing::warn!("Failed to abort upload of {}: {}", path, abort);
+                }
+                return Err(e);
+            }
+        };
+
+        let etag = self.backend.head(&path).await.ok().and_then(|meta| meta.e_tag);
+        let now = chrono::Utc::now();
+
+        self.index_object(StorageObject {
+            id: Uuid::new_v4(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            size: size as i64,
+            content_type: metadata.content_type,
+            etag,
+            metadata: metadata.custom_metadata,
+            owner_id: metadata.owner_id,
+            created_at: now,
+            updated_at: now,
+        })
+        .await
     }
 
     /// Download a file
@@ -115,6 +265,34 @@ impl StorageService {
         Ok(bytes)
     }
 
+    /// Stream a file, or only the given byte range of it
+    pub async fn download_stream(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Option<Range<u64>>,
+    ) -> Result<FileStream> {
+        let path = object_store::path::Path::from(format!("{}/{}", bucket, key));
+        let options = GetOptions {
+            range: range.map(|r| GetRange::Bounded(r.start as usize..r.end as usize)),
+            ..Default::default()
+        };
+
+        let result = self.backend.get_opts(&path, options).await.map_err(|e| match e {
+            object_store::Error::NotFound { .. } => {
+                ForgeBaseError::NotFound(format!("File not found: {}", e))
+            }
+            e => ForgeBaseError::Internal(format!("Failed to read file: {}", e)),
+        })?;
+
+        Ok(result
+            .into_stream()
+            .map(|chunk| {
+                chunk.map_err(|e| ForgeBaseError::Storage(format!("Failed to read file: {}", e)))
+            })
+            .boxed())
+    }
+
     /// Delete a file
     pub async fn delete_file(&self, bucket: &str, key: &str) -> Result<()> {
         let path = object_store::path::Path::from(format!("{}/{}", bucket, key));
@@ -123,10 +301,29 @@ impl StorageService {
             .delete(&path)
             .await
             .map_err(|e| ForgeBaseError::Internal(format!("Failed to delete file: {}", e)))?;
+        if let Some(index) = self.index_for(bucket) {
+            index.delete(bucket, key).await?;
+        }
 
         Ok(())
     }
 
+    /// Delete every file in a bucket, returning how many were removed
+    pub async fn delete_all_files(&self, bucket: &str) -> Result<u64> {
+        let files = self.list_files(bucket, None).await?;
+        for location in &files {
+            self.backend
+                .delete(&object_store::path::Path::from(location.as_str()))
+                .await
+                .map_err(|e| ForgeBaseError::Internal(format!("Failed to delete file: {}", e)))?;
+        }
+        if let Some(index) = self.index_for(bucket) {
+            index.delete_prefix(bucket, "").await?;
+        }
+
+        Ok(files.len() as u64)
+    }
+
     /// List files in a bucket
     pub async fn list_files(&self, bucket: &str, prefix: Option<&str>) -> Result<Vec<String>> {
         let prefix_path = if let Some(prefix) = prefix {
@@ -150,6 +347,65 @@ impl StorageService {
         Ok(files)
     }
 
+    /// List the objects of a bucket whose key starts with `prefix`, sorted by key
+    pub async fn list_objects(&self, bucket: &str, prefix: &str) -> Result<Vec<ObjectSummary>> {
+        // The backend lists by path segment, so narrow down to the prefix's directory first
+        let directory = prefix.rsplit_once('/').map_or("", |(directory, _)| directory);
+        let path = object_store::path::Path::from(format!("{}/{}", bucket, directory));
+        let bucket_path = object_store::path::Path::from(bucket);
+
+        let mut list_stream = self.backend.list(Some(&path));
+        let mut objects = Vec::new();
+        while let Some(result) = list_stream.next().await {
+            let meta =
+                result.map_err(|e| ForgeBaseError::Internal(format!("Failed to list files: {}", e)))?;
+            let Some(parts) = meta.location.prefix_match(&bucket_path) else {
+                continue;
+            };
+            let key = parts
+                .map(|part| percent_decode_str(part.as_ref()).decode_utf8_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if key.starts_with(prefix) {
+                objects.push(ObjectSummary {
+                    key,
+                    size: meta.size as i64,
+                    last_modified: meta.last_modified,
+                    etag: meta.e_tag,
+                });
+            }
+        }
+
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    /// Delete every file whose key starts with `prefix`, returning how many were removed
+    pub async fn delete_prefix(&self, bucket: &str, prefix: &str) -> Result<u64> {
+        let objects = self.list_objects(bucket, prefix).await?;
+        for object in &objects {
+            self.delete_file(bucket, &object.key).await?;
+        }
+
+        Ok(objects.len() as u64)
+    }
+
+    /// Read several files back to back as one stream
+    pub fn concat_stream(self: &Arc<Self>, bucket: &str, keys: Vec<String>) -> FileStream {
+        let service = Arc::clone(self);
+        let bucket = bucket.to_string();
+
+        futures::stream::iter(keys)
+            .then(move |key| {
+                let service = service.clone();
+                let bucket = bucket.clone();
+                async move { service.download_stream(&bucket, &key, None).await }
+            })
+            .try_flatten()
+            .boxed()
+    }
+
     /// Get file metadata
     pub async fn get_file_metadata(&self, bucket: &str, key: &str) -> Result<FileMetadata> {
         let path = object_store::path::Path::from(format!("{}/{}", bucket, key));
@@ -160,11 +416,17 @@ impl StorageService {
             .await
             .map_err(|e| ForgeBaseError::NotFound(format!("File not found: {}", e)))?;
 
+        let content_type = match self.index_for(bucket) {
+            Some(index) => index.get(bucket, key).await?.and_then(|object| object.content_type),
+            None => None,
+        };
+
         Ok(FileMetadata {
             size: meta.size as i64,
             last_modified: chrono::DateTime::from_timestamp(meta.last_modified.timestamp(), 0)
                 .unwrap_or_else(chrono::Utc::now),
             etag: meta.e_tag,
+            content_type,
         })
     }
 
@@ -179,45 +441,71 @@ impl StorageService {
         let source_path = object_store::path::Path::from(format!("{}/{}", source_bucket, source_key));
         let dest_path = object_store::path::Path::from(format!("{}/{}", dest_bucket, dest_key));
 
-        let data = self
-            .backend
-            .get(&source_path)
-            .await
-            .map_err(|e| ForgeBaseError::NotFound(format!("Source file not found: {}", e)))?;
-
-        let bytes = data
-            .bytes()
-            .await
-            .map_err(|e| ForgeBaseError::Internal(format!("Failed to read source file: {}", e)))?;
-
+        // Copied by the backend itself so the file never passes through memory
         self.backend
-            .put(&dest_path, bytes)
+            .copy(&source_path, &dest_path)
             .await
-            .map_err(|e| ForgeBaseError::Internal(format!("Failed to copy file: {}", e)))?;
+            .map_err(|e| match e {
+                object_store::Error::NotFound { .. } => {
+                    ForgeBaseError::NotFound(format!("Source file not found: {}", e))
+                }
+                e => ForgeBaseError::Internal(format!("Failed to copy file: {}", e)),
+            })?;
+
+        if let Some(index) = self.index_for(dest_bucket) {
+            let meta = self
+                .backend
+                .head(&dest_path)
+                .await
+                .map_err(|e| ForgeBaseError::Internal(format!("Failed to copy file: {}", e)))?;
+            let copied = index
+                .copy(source_bucket, source_key, dest_bucket, dest_key, meta.e_tag.as_deref())
+                .await?;
+            if copied.is_none() {
+                // The source predates the index
+                let now = chrono::Utc::now();
+                index
+                    .upsert(&StorageObject {
+                        id: Uuid::new_v4(),
+                        bucket: dest_bucket.to_string(),
+                        key: dest_key.to_string(),
+                        size: meta.size as i64,
+                        content_type: None,
+                        etag: meta.e_tag,
+                        metadata: Default::default(),
+                        owner_id: None,
+                        created_at: now,
+                        updated_at: now,
+                    })
+                    .await?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Page through a bucket's objects with sorting, folder listing and search
+    ///
+    /// Requires an object index.
+    pub async fn query_files(&self, request: &ListFilesRequest) -> Result<ListFilesResponse> {
+        let index = self
+            .index_for(&request.bucket)
+            .ok_or_else(|| ForgeBaseError::Config("Object index is not configured".to_string()))?;
+        index.list(request).await
+    }
+
     /// Generate a presigned URL for file upload
+    ///
+    /// `max_content_length` caps the upload size; the S3 backend cannot enforce it.
     pub async fn generate_upload_url(
         &self,
         bucket: &str,
         key: &str,
-        _expires_in: i64,
+        expires_in: i64,
+        max_content_length: Option<i64>,
     ) -> Result<String> {
-        // This is a simplified implementation
-        // In production, you'd generate a real presigned URL with proper signing
-        let url = match &self.backend_type {
-            StorageBackend::Local(_) => {
-                format!("/storage/{}/{}", bucket, key)
-            }
-            StorageBackend::S3 { .. } => {
-                // Would use AWS SDK to generate presigned URL
-                format!("https://s3.amazonaws.com/{}/{}", bucket, key)
-            }
-        };
-
-        Ok(url)
+        self.presign(PresignedUrlOperation::Upload, bucket, key, expires_in, max_content_length)
+            .await
     }
 
     /// Generate a presigned URL for file download
@@ -227,6 +515,152 @@ impl StorageService {
         key: &str,
         expires_in: i64,
     ) -> Result<String> {
-        self.generate_upload_url(bucket, key, expires_in).await
+        self.presign(PresignedUrlOperation::Download, bucket, key, expires_in, None)
+            .await
+    }
+
+    async fn presign(
+        &self,
+        operation: PresignedUrlOperation,
+        bucket: &str,
+        key: &str,
+        expires_in: i64,
+        max_content_length: Option<i64>,
+    ) -> Result<String> {
+        if !(1..=MAX_SIGNED_URL_EXPIRY_SECONDS).contains(&expires_in) {
+            return Err(ForgeBaseError::Validation(format!(
+                "expires_in must be between 1 and {} seconds",
+                MAX_SIGNED_URL_EXPIRY_SECONDS
+            )));
+        }
+        if max_content_length.is_some_and(|length| length < 0) {
+            return Err(ForgeBaseError::Validation(
+                "max_content_length must not be negative".to_string(),
+            ));
+        }
+
+        let Some(signer) = &self.signer else {
+            let grant = SignedUrlGrant {
+                operation,
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                expires: chrono::Utc::now().timestamp() + expires_in,
+                max_content_length,
+            };
+            return Ok(self.url_signer.sign(&grant));
+        };
+
+        if max_content_length.is_some() {
+            return Err(ForgeBaseError::Validation(
+                "Content length limits are not supported by the S3 backend".to_string(),
+            ));
+        }
+        let method = match operation {
+            PresignedUrlOperation::Upload => reqwest::Method::PUT,
+            PresignedUrlOperation::Download => reqwest::Method::GET,
+        };
+        let path = object_store::path::Path::from(format!("{}/{}", bucket, key));
+        let url = signer
+            .signed_url(method, &path, Duration::from_secs(expires_in as u64))
+            .await
+            .map_err(|e| ForgeBaseError::Storage(format!("Failed to sign URL: {}", e)))?;
+
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn local_service() -> StorageService {
+        let root = std::env::temp_dir().join(format!("forgebase-storage-{}", Uuid::new_v4()));
+        StorageService::new(StorageBackend::Local(root)).await.unwrap()
+    }
+
+    fn metadata() -> UploadMetadata {
+        UploadMetadata {
+            content_type: Some("text/plain".to_string()),
+            custom_metadata: Default::default(),
+            owner_id: None,
+        }
+    }
+
+    fn chunks(
+        parts: &[&'static str],
+    ) -> impl Stream<Item = std::result::Result<Bytes, std::io::Error>> + Unpin {
+        let parts: Vec<_> = parts.iter().map(|p| Ok(Bytes::from_static(p.as_bytes()))).collect();
+        futures::stream::iter(parts)
+    }
+
+    #[tokio::test]
+    async fn test_streamed_upload_and_ranged_download() {
+        let service = local_service().await;
+
+        let object = service
+            .upload_stream("docs", "a/b.txt", chunks(&["hello ", "streamed ", "world"]), metadata())
+            .await
+            .unwrap();
+        assert_eq!(object.size, 20);
+        assert!(object.etag.is_some());
+
+        let full: Vec<Bytes> = service
+            .download_stream("docs", "a/b.txt", None)
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(full.concat(), b"hello streamed world");
+
+        let part: Vec<Bytes> = service
+            .download_stream("docs", "a/b.txt", Some(6..14))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(part.concat(), b"streamed");
+    }
+
+    #[tokio::test]
+    async fn test_streamed_upload_over_limit_is_aborted() {
+        let service = local_service().await.with_max_file_size(8);
+
+        let result = service
+            .upload_stream("docs", "big.txt", chunks(&["12345", "67890"]), metadata())
+            .await;
+        assert!(matches!(result, Err(ForgeBaseError::Validation(_))));
+        assert!(service.get_file_metadata("docs", "big.txt").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_presigned_urls() {
+        let service = local_service().await.with_url_signing_key("secret");
+        let url = service
+            .generate_upload_url("docs", "a.txt", 300, Some(1024))
+            .await
+            .unwrap();
+        assert!(url.starts_with("/storage/docs/a.txt?operation=upload&expires="));
+        assert!(url.contains("&max_content_length=1024&signature="));
+        assert!(service.generate_download_url("docs", "a.txt", 0).await.is_err());
+
+        let s3 = StorageService::new(StorageBackend::S3 {
+            bucket: "forgebase".to_string(),
+            region: "us-east-1".to_string(),
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "secret".to_string(),
+            endpoint: None,
+        })
+        .await
+        .unwrap();
+        let url = s3.generate_download_url("docs", "a.txt", 300).await.unwrap();
+        assert!(url.contains("/docs/a.txt?"));
+        assert!(url.contains("X-Amz-Expires=300"));
+        assert!(url.contains("X-Amz-Signature="));
+        assert!(s3
+            .generate_upload_url("docs", "a.txt", 300, Some(1024))
+            .await
+            .is_err());
     }
 }
@@ -0,0 +1,204 @@
+//! HMAC-signed storage URLs
+//!
+//! Presigned URLs for the local backend, which has no object store to sign them. Each URL is
+//! bound to one operation, bucket and key, expires, and for uploads may cap the request's
+//! `Content-Length`.
+
+use crate::models::PresignedUrlOperation;
+use crate::sigv4::uri_encode;
+use axum::http::Method;
+use forgebase_core::{utils::generate_token, ForgeBaseError, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Longest lifetime of a presigned URL, matching S3's limit
+pub const MAX_SIGNED_URL_EXPIRY_SECONDS: i64 = 7 * 24 * 3600;
+
+const OPERATION_PARAM: &str = "operation";
+const EXPIRES_PARAM: &str = "expires";
+const MAX_CONTENT_LENGTH_PARAM: &str = "max_content_length";
+const SIGNATURE_PARAM: &str = "signature";
+
+/// What a signed URL allows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedUrlGrant {
+    pub operation: PresignedUrlOperation,
+    pub bucket: String,
+    pub key: String,
+    /// Expiry as a Unix timestamp
+    pub expires: i64,
+    /// Largest `Content-Length` an upload may declare
+    pub max_content_length: Option<i64>,
+}
+
+impl SignedUrlGrant {
+    /// Whether the grant covers a request method
+    pub fn allows(&self, method: &Method) -> bool {
+        match self.operation {
+            PresignedUrlOperation::Download => method == Method::GET || method == Method::HEAD,
+            PresignedUrlOperation::Upload => method == Method::PUT || method == Method::POST,
+        }
+    }
+}
+
+/// Signs and verifies storage URLs with a server-side key
+#[derive(Clone)]
+pub struct UrlSigner {
+    key: Vec<u8>,
+}
+
+impl UrlSigner {
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: key.as_ref().to_vec(),
+        }
+    }
+
+    /// Signer with a random key; its URLs only verify within this process
+    pub fn random() -> Self {
+        Self::new(generate_token(64))
+    }
+
+    /// Path and query of a signed URL for the grant
+    pub fn sign(&self, grant: &SignedUrlGrant) -> String {
+        let key = grant.key.split('/').map(uri_encode).collect::<Vec<_>>().join("/");
+        let mut url = format!(
+            "/storage/{}/{}?{}={}&{}={}",
+            uri_encode(&grant.bucket),
+            key,
+            OPERATION_PARAM,
+            grant.operation.as_str(),
+            EXPIRES_PARAM,
+            grant.expires
+        );
+        if let Some(max_content_length) = grant.max_content_length {
+            url.push_str(&format!("&{}={}", MAX_CONTENT_LENGTH_PARAM, max_content_length));
+        }
+        url.push_str(&format!(
+            "&{}={}",
+            SIGNATURE_PARAM,
+            hex::encode(self.mac(grant).finalize().into_bytes())
+        ));
+        url
+    }
+
+    /// Check the signature parameters of a request for `bucket`/`key`
+    ///
+    /// Returns `Ok(None)` for unsigned requests.
+    pub fn verify(
+        &self,
+        bucket: &str,
+        key: &str,
+        query: &[(String, String)],
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<SignedUrlGrant>> {
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(param, _)| param == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let Some(signature) = param(SIGNATURE_PARAM) else {
+            return Ok(None);
+        };
+        let invalid = || ForgeBaseError::Authorization("Invalid URL signature".to_string());
+
+        let operation = match param(OPERATION_PARAM) {
+            Some("upload") => PresignedUrlOperation::Upload,
+            Some("download") => PresignedUrlOperation::Download,
+            _ => return Err(invalid()),
+        };
+        let expires = param(EXPIRES_PARAM)
+            .and_then(|value| value.parse::<i64>().ok())
+            .ok_or_else(invalid)?;
+        let max_content_length = match param(MAX_CONTENT_LENGTH_PARAM) {
+            Some(value) => Some(value.parse::<i64>().map_err(|_| invalid())?),
+            None => None,
+        };
+        let grant = SignedUrlGrant {
+            operation,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            expires,
+            max_content_length,
+        };
+
+        let signature = hex::decode(signature).map_err(|_| invalid())?;
+        self.mac(&grant)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        if now.timestamp() > expires {
+            return Err(ForgeBaseError::Authorization("Signed URL has expired".to_string()));
+        }
+
+        Ok(Some(grant))
+    }
+
+    fn mac(&self, grant: &SignedUrlGrant) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        let max_content_length = grant
+            .max_content_length
+            .map(|length| length.to_string())
+            .unwrap_or_default();
+        for field in [
+            grant.operation.as_str(),
+            &grant.bucket,
+            &grant.key,
+            &grant.expires.to_string(),
+            &max_content_length,
+        ] {
+            mac.update(field.as_bytes());
+            mac.update(b"\n");
+        }
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sigv4::query_pairs;
+    use axum::http::Uri;
+
+    fn verify(
+        signer: &UrlSigner,
+        url: &str,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<SignedUrlGrant>> {
+        let uri: Uri = url.parse().unwrap();
+        signer.verify("photos", "2024/a b.jpg", &query_pairs(&uri), now)
+    }
+
+    #[test]
+    fn test_signed_url_round_trip() {
+        let signer = UrlSigner::new("secret");
+        let now = chrono::Utc::now();
+        let grant = SignedUrlGrant {
+            operation: PresignedUrlOperation::Upload,
+            bucket: "photos".to_string(),
+            key: "2024/a b.jpg".to_string(),
+            expires: now.timestamp() + 60,
+            max_content_length: Some(1024),
+        };
+
+        let url = signer.sign(&grant);
+        assert!(url.starts_with("/storage/photos/2024/a%20b.jpg?operation=upload&"));
+        assert_eq!(verify(&signer, &url, now).unwrap(), Some(grant.clone()));
+        assert!(grant.allows(&Method::PUT));
+        assert!(!grant.allows(&Method::GET));
+
+        // Expired
+        let later = now + chrono::Duration::seconds(61);
+        assert!(verify(&signer, &url, later).is_err());
+        // Tampered constraint
+        let raised = url.replace("max_content_length=1024", "max_content_length=4096");
+        assert!(verify(&signer, &raised, now).is_err());
+        // Other key
+        assert!(verify(&UrlSigner::new("other"), &url, now).is_err());
+        // Unsigned
+        assert_eq!(verify(&signer, "/storage/photos/x", now).unwrap(), None);
+    }
+}
@@ -0,0 +1,770 @@
+//! Resumable uploads over the tus 1.0 protocol
+//!
+//! Supports the core protocol plus the creation, termination and expiration extensions.
+//! Upload state and the received chunks live in the object store under a reserved prefix;
+//! when the last byte arrives the chunks are validated and assembled into the target object.
+
+use crate::bucket::BucketManager;
+use crate::download::http_date;
+use crate::models::{ResumableUpload, UploadMetadata};
+use crate::policy::{StorageAuthorizer, StorageOperation};
+use crate::service::{FileStream, StorageService};
+use crate::upload::{parse_content_type, UploadProgress, UploadValidator};
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::options,
+    Extension, Router,
+};
+use base64::Engine;
+use bytes::Bytes;
+use forgebase_auth::jwt::Claims;
+use forgebase_core::{ForgeBaseError, Result};
+use futures::stream::{Stream, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Protocol version spoken by this server
+pub const TUS_VERSION: &str = "1.0.0";
+/// Extensions advertised in `Tus-Extension`
+pub const TUS_EXTENSIONS: &str = "creation,termination,expiration";
+/// Route the tus endpoint is mounted at
+pub const TUS_ENDPOINT: &str = "/storage/upload/resumable";
+/// Reserved storage area holding in-progress uploads
+pub const TUS_UPLOADS_BUCKET: &str = "__resumable_uploads";
+
+const OFFSET_OCTET_STREAM: &str = "application/offset+octet-stream";
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+const UPLOAD_PROGRESS: HeaderName = HeaderName::from_static("upload-progress");
+
+/// Resumable upload configuration
+#[derive(Debug, Clone)]
+pub struct TusConfig {
+    /// How long an upload stays resumable after its last chunk
+    pub expiration_seconds: i64,
+    /// Largest `Upload-Length` accepted; the storage service limit applies when unset
+    pub max_size: Option<i64>,
+}
+
+impl Default for TusConfig {
+    fn default() -> Self {
+        Self {
+            expiration_seconds: 86400,
+            max_size: None,
+        }
+    }
+}
+
+/// Resumable upload manager
+pub struct TusManager {
+    service: Arc<StorageService>,
+    buckets: Option<BucketManager>,
+    authorizer: Option<Arc<StorageAuthorizer>>,
+    config: TusConfig,
+    in_flight: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+/// Held while a PATCH writes to an upload so concurrent requests can't interleave
+struct UploadLock {
+    id: Uuid,
+    in_flight: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+impl Drop for UploadLock {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&self.id);
+        }
+    }
+}
+
+impl TusManager {
+    pub fn new(service: Arc<StorageService>, config: TusConfig) -> Self {
+        Self {
+            service,
+            buckets: None,
+            authorizer: None,
+            config,
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Check uploads against their bucket's size and MIME type limits
+    pub fn with_buckets(mut self, buckets: BucketManager) -> Self {
+        self.buckets = Some(buckets);
+        self
+    }
+
+    /// Require the creator of an upload to be allowed to upload to its target
+    ///
+    /// Later requests are not re-authorized: the unguessable upload URL is the credential.
+    pub fn with_authorizer(mut self, authorizer: Arc<StorageAuthorizer>) -> Self {
+        self.authorizer = Some(authorizer);
+        self
+    }
+
+    /// Largest upload accepted, if limited
+    pub fn max_size(&self) -> Option<i64> {
+        self.config.max_size.or(self.service.max_file_size())
+    }
+
+    /// Create an upload from its `Upload-Length` and `Upload-Metadata`
+    ///
+    /// The metadata must name the target with `bucketName` and `objectName`; `contentType` is
+    /// optional and every other key becomes custom object metadata.
+    pub async fn create(
+        &self,
+        length: u64,
+        raw_metadata: Option<&str>,
+        claims: Option<&Claims>,
+    ) -> Result<ResumableUpload> {
+        if i64::try_from(length).is_err() {
+            return Err(ForgeBaseError::Validation(format!(
+                "Upload-Length {} is too large",
+                length
+            )));
+        }
+        let mut metadata = match raw_metadata {
+            Some(raw) => parse_upload_metadata(raw)?,
+            None => HashMap::new(),
+        };
+        let bucket = metadata.remove("bucketName").flatten().ok_or_else(|| {
+            ForgeBaseError::Validation("bucketName metadata is required".to_string())
+        })?;
+        let key = metadata.remove("objectName").flatten().ok_or_else(|| {
+            ForgeBaseError::Validation("objectName metadata is required".to_string())
+        })?;
+        let content_type = metadata
+            .remove("contentType")
+            .flatten()
+            .unwrap_or_else(|| parse_content_type(&key));
+        if let Some(authorizer) = &self.authorizer {
+            authorizer
+                .authorize(claims, &bucket, StorageOperation::Upload, &key)
+                .await?;
+        }
+
+        let upload = ResumableUpload {
+            id: Uuid::new_v4(),
+            bucket,
+            key,
+            length,
+            offset: 0,
+            content_type: Some(content_type),
+            metadata: metadata
+                .into_iter()
+                .map(|(k, v)| (k, v.unwrap_or_default()))
+                .collect(),
+            raw_metadata: raw_metadata.map(str::to_string),
+            owner_id: claims.and_then(|claims| claims.user_id().ok()),
+            created_at: chrono::Utc::now(),
+            expires_at: chrono::Utc::now()
+                + chrono::Duration::seconds(self.config.expiration_seconds),
+        };
+
+        // Reject what can be rejected before any bytes are sent
+        let validator = self.validator(&upload).await?;
+        validator.validate_size(upload.length)?;
+        validator.validate_content_type(upload.content_type.as_deref())?;
+
+        if upload.is_complete() {
+            self.complete(&upload).await?;
+        } else {
+            self.save(&upload).await?;
+        }
+
+        Ok(upload)
+    }
+
+    /// Get an upload's current state
+    pub async fn get(&self, id: Uuid) -> Result<ResumableUpload> {
+        let data = self
+            .service
+            .download_file(TUS_UPLOADS_BUCKET, &info_key(id))
+            .await
+            .map_err(|_| ForgeBaseError::NotFound("Upload not found".to_string()))?;
+
+        serde_json::from_slice(&data)
+            .map_err(|e| ForgeBaseError::Internal(format!("Corrupt upload state: {}", e)))
+    }
+
+    /// Append a PATCH body at `offset`, completing the upload once all bytes are in
+    ///
+    /// A body that breaks off midway still counts for the bytes received, so the client can
+    /// resume from the new offset.
+    pub async fn append<S, E>(&self, id: Uuid, offset: u64, body: S) -> Result<ResumableUpload>
+    where
+        S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+        E: Send + 'static,
+    {
+        let _lock = self.lock(id)?;
+
+        let mut upload = self.get(id).await?;
+        if upload.is_expired() {
+            return Err(ForgeBaseError::NotFound("Upload has expired".to_string()));
+        }
+        if offset != upload.offset {
+            return Err(ForgeBaseError::Conflict(format!(
+                "Upload-Offset {} does not match current offset {}",
+                offset, upload.offset
+            )));
+        }
+
+        let chunk_body = limited_chunk(body, upload.length - upload.offset);
+        let chunk_key = chunk_key(id, upload.offset);
+        let metadata = UploadMetadata {
+            content_type: None,
+            custom_metadata: HashMap::new(),
+            owner_id: None,
+        };
+        let chunk = self
+            .service
+            .upload_stream(TUS_UPLOADS_BUCKET, &chunk_key, chunk_body, metadata)
+            .await?;
+
+        if chunk.size == 0 {
+            self.service
+                .delete_file(TUS_UPLOADS_BUCKET, &chunk_key)
+                .await?;
+            return Ok(upload);
+        }
+
+        upload.offset += chunk.size as u64;
+        upload.expires_at =
+            chrono::Utc::now() + chrono::Duration::seconds(self.config.expiration_seconds);
+
+        if upload.is_complete() {
+            self.complete(&upload).await?;
+        } else {
+            self.save(&upload).await?;
+        }
+
+        Ok(upload)
+    }
+
+    /// Cancel an upload and remove everything received for it
+    pub async fn terminate(&self, id: Uuid) -> Result<()> {
+        let _lock = self.lock(id)?;
+        self.get(id).await?;
+        self.remove(id).await
+    }
+
+    /// Remove uploads that expired before completing, returning how many were removed
+    pub async fn delete_expired_uploads(&self) -> Result<u64> {
+        let mut deleted = 0;
+        for location in self.service.list_files(TUS_UPLOADS_BUCKET, None).await? {
+            let Some(id) = location
+                .strip_prefix(&format!("{}/", TUS_UPLOADS_BUCKET))
+                .and_then(|key| key.strip_suffix("/info.json"))
+                .and_then(|id| Uuid::parse_str(id).ok())
+            else {
+                continue;
+            };
+
+            let Ok(_lock) = self.lock(id) else {
+                continue;
+            };
+            if self.get(id).await.map(|u| u.is_expired()).unwrap_or(true) {
+                self.remove(id).await?;
+                deleted += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Periodically remove expired uploads
+    pub fn spawn_cleanup(
+        self: Arc<Self>,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.delete_expired_uploads().await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::info!("Deleted {} expired resumable uploads", deleted),
+                    Err(e) => tracing::error!("Resumable upload cleanup failed: {}", e),
+                }
+            }
+        })
+    }
+
+    async fn validator(&self, upload: &ResumableUpload) -> Result<UploadValidator> {
+        let mut max_size = self.max_size();
+        let mut allowed_mime_types = None;
+
+        if let Some(buckets) = &self.buckets {
+            let bucket = buckets.get_bucket(&upload.bucket).await?.ok_or_else(|| {
+                ForgeBaseError::NotFound(format!("Bucket '{}' not found", upload.bucket))
+            })?;
+            if let Some(limit) = bucket.max_file_size {
+                max_size = Some(max_size.map_or(limit, |max| max.min(limit)));
+            }
+            allowed_mime_types = bucket.allowed_mime_types;
+        }
+
+        Ok(UploadValidator::new(max_size, allowed_mime_types))
+    }
+
+    /// Validate the finished upload and assemble its chunks into the target object
+    async fn complete(&self, upload: &ResumableUpload) -> Result<()> {
+        let validator = self.validator(upload).await?;
+        if let Err(e) = validator
+            .validate_size(upload.length)
+            .and_then(|_| validator.validate_content_type(upload.content_type.as_deref()))
+        {
+            self.remove(upload.id).await?;
+            return Err(e);
+        }
+
+        // Chunk keys are zero-padded offsets, so key order is byte order
+        let chunks = self
+            .service
+            .list_objects(TUS_UPLOADS_BUCKET, &format!("{}/chunks/", upload.id))
+            .await?
+            .into_iter()
+            .map(|object| object.key)
+            .collect();
+        let body = self.service.concat_stream(TUS_UPLOADS_BUCKET, chunks);
+        let metadata = UploadMetadata {
+            content_type: upload.content_type.clone(),
+            custom_metadata: upload.metadata.clone(),
+            owner_id: upload.owner_id,
+        };
+        self.service
+            .upload_stream(&upload.bucket, &upload.key, body, metadata)
+            .await?;
+
+        self.remove(upload.id).await
+    }
+
+    async fn save(&self, upload: &ResumableUpload) -> Result<()> {
+        let data = serde_json::to_vec(upload).map_err(|e| {
+            ForgeBaseError::Internal(format!("Failed to serialize upload state: {}", e))
+        })?;
+        let metadata = UploadMetadata {
+            content_type: Some("application/json".to_string()),
+            custom_metadata: HashMap::new(),
+            owner_id: None,
+        };
+
+        self.service
+            .upload_file(
+                TUS_UPLOADS_BUCKET,
+                &info_key(upload.id),
+                data.into(),
+                metadata,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove(&self, id: Uuid) -> Result<()> {
+        self.service
+            .delete_prefix(TUS_UPLOADS_BUCKET, &format!("{}/", id))
+            .await?;
+        Ok(())
+    }
+
+    fn lock(&self, id: Uuid) -> Result<UploadLock> {
+        let mut in_flight = self
+            .in_flight
+            .lock()
+            .map_err(|_| ForgeBaseError::Internal("Upload lock poisoned".to_string()))?;
+        if !in_flight.insert(id) {
+            return Err(ForgeBaseError::Conflict(
+                "Upload is being written by another request".to_string(),
+            ));
+        }
+
+        Ok(UploadLock {
+            id,
+            in_flight: Arc::clone(&self.in_flight),
+        })
+    }
+}
+
+/// A PATCH body that ends quietly where the connection broke and fails past `remaining` bytes
+fn limited_chunk<S, E>(body: S, remaining: u64) -> FileStream
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    let mut received = 0u64;
+    body.take_while(|chunk| futures::future::ready(chunk.is_ok()))
+        .map(move |chunk| {
+            let chunk = chunk.unwrap_or_default();
+            received += chunk.len() as u64;
+            if received > remaining {
+                return Err(ForgeBaseError::Validation(format!(
+                    "Chunk exceeds the remaining {} bytes of the upload",
+                    remaining
+                )));
+            }
+            Ok(chunk)
+        })
+        .boxed()
+}
+
+fn info_key(id: Uuid) -> String {
+    format!("{}/info.json", id)
+}
+
+fn chunk_key(id: Uuid, offset: u64) -> String {
+    format!("{}/chunks/{:020}", id, offset)
+}
+
+/// Parse an `Upload-Metadata` header: comma-separated keys, each optionally followed by a
+/// space and a base64 value
+pub fn parse_upload_metadata(header: &str) -> Result<HashMap<String, Option<String>>> {
+    let mut metadata = HashMap::new();
+
+    for pair in header.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = match pair.split_once(' ') {
+            Some((key, value)) => {
+                let decoded = base64::engine::general_purpose::STANDARD
+                    .decode(value.trim())
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| {
+                        ForgeBaseError::Validation(format!(
+                            "Invalid Upload-Metadata value for '{}'",
+                            key
+                        ))
+                    })?;
+                (key, Some(decoded))
+            }
+            None => (pair, None),
+        };
+
+        if metadata.insert(key.to_string(), value).is_some() {
+            return Err(ForgeBaseError::Validation(format!(
+                "Duplicate Upload-Metadata key '{}'",
+                key
+            )));
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Create tus routes
+pub fn create_tus_routes() -> Router<Arc<TusManager>> {
+    Router::new()
+        .route(
+            TUS_ENDPOINT,
+            options(tus_options_handler).post(tus_create_handler),
+        )
+        .route(
+            &format!("{}/:id", TUS_ENDPOINT),
+            options(tus_options_handler)
+                .head(tus_head_handler)
+                .patch(tus_patch_handler)
+                .delete(tus_terminate_handler),
+        )
+}
+
+fn tus_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+    headers
+}
+
+fn tus_error(error: ForgeBaseError) -> Response {
+    let status =
+        StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    (status, tus_headers(), error.to_string()).into_response()
+}
+
+/// Every request but OPTIONS must speak our protocol version; returns the rejection if not
+fn reject_version(headers: &HeaderMap) -> Option<Response> {
+    match headers.get(TUS_RESUMABLE).and_then(|v| v.to_str().ok()) {
+        Some(TUS_VERSION) => None,
+        _ => {
+            let mut response_headers = tus_headers();
+            response_headers.insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+            Some((StatusCode::PRECONDITION_FAILED, response_headers).into_response())
+        }
+    }
+}
+
+fn header_u64(headers: &HeaderMap, name: HeaderName) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn insert_expires(headers: &mut HeaderMap, upload: &ResumableUpload) {
+    if let Ok(value) = HeaderValue::from_str(&http_date(upload.expires_at)) {
+        headers.insert(UPLOAD_EXPIRES, value);
+    }
+}
+
+/// Server capabilities handler
+pub async fn tus_options_handler(State(manager): State<Arc<TusManager>>) -> Response {
+    let mut headers = tus_headers();
+    headers.insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+    headers.insert(TUS_EXTENSION, HeaderValue::from_static(TUS_EXTENSIONS));
+    if let Some(max_size) = manager.max_size() {
+        headers.insert(TUS_MAX_SIZE, HeaderValue::from(max_size));
+    }
+
+    (StatusCode::NO_CONTENT, headers).into_response()
+}
+
+/// Create upload handler
+pub async fn tus_create_handler(
+    State(manager): State<Arc<TusManager>>,
+    claims: Option<Extension<Claims>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = reject_version(&headers) {
+        return response;
+    }
+
+    let Some(length) = header_u64(&headers, UPLOAD_LENGTH) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            tus_headers(),
+            "Upload-Length is required",
+        )
+            .into_response();
+    };
+    if manager
+        .max_size()
+        .is_some_and(|max| !i64::try_from(length).is_ok_and(|length| length <= max))
+    {
+        return (StatusCode::PAYLOAD_TOO_LARGE, tus_headers()).into_response();
+    }
+
+    let raw_metadata = headers.get(UPLOAD_METADATA).and_then(|v| v.to_str().ok());
+    match manager.create(length, raw_metadata, claims.as_deref()).await {
+        Ok(upload) => {
+            let mut response_headers = tus_headers();
+            if let Ok(location) = HeaderValue::from_str(&format!("{}/{}", TUS_ENDPOINT, upload.id))
+            {
+                response_headers.insert(header::LOCATION, location);
+            }
+            if !upload.is_complete() {
+                insert_expires(&mut response_headers, &upload);
+            }
+            (StatusCode::CREATED, response_headers).into_response()
+        }
+        Err(e) => tus_error(e),
+    }
+}
+
+/// Upload status handler
+pub async fn tus_head_handler(
+    State(manager): State<Arc<TusManager>>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = reject_version(&headers) {
+        return response;
+    }
+
+    let upload = match manager.get(id).await {
+        Ok(upload) if upload.is_expired() => {
+            return (StatusCode::GONE, tus_headers()).into_response();
+        }
+        Ok(upload) => upload,
+        Err(_) => return (StatusCode::NOT_FOUND, tus_headers()).into_response(),
+    };
+
+    let mut progress = UploadProgress::new(upload.length as usize);
+    progress.update(upload.offset as usize);
+
+    let mut response_headers = tus_headers();
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response_headers.insert(UPLOAD_OFFSET, HeaderValue::from(upload.offset));
+    response_headers.insert(UPLOAD_LENGTH, HeaderValue::from(upload.length));
+    if let Ok(value) = HeaderValue::from_str(&format!("{:.2}", progress.percentage)) {
+        response_headers.insert(UPLOAD_PROGRESS, value);
+    }
+    if let Some(value) = upload
+        .raw_metadata
+        .as_deref()
+        .and_then(|raw| HeaderValue::from_str(raw).ok())
+    {
+        response_headers.insert(UPLOAD_METADATA, value);
+    }
+    insert_expires(&mut response_headers, &upload);
+
+    (StatusCode::OK, response_headers).into_response()
+}
+
+/// Append chunk handler
+pub async fn tus_patch_handler(
+    State(manager): State<Arc<TusManager>>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    if let Some(response) = reject_version(&headers) {
+        return response;
+    }
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if content_type != Some(OFFSET_OCTET_STREAM) {
+        return (StatusCode::UNSUPPORTED_MEDIA_TYPE, tus_headers()).into_response();
+    }
+    let Some(offset) = header_u64(&headers, UPLOAD_OFFSET) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            tus_headers(),
+            "Upload-Offset is required",
+        )
+            .into_response();
+    };
+
+    match manager.append(id, offset, body.into_data_stream()).await {
+        Ok(upload) => {
+            let mut response_headers = tus_headers();
+            response_headers.insert(UPLOAD_OFFSET, HeaderValue::from(upload.offset));
+            if !upload.is_complete() {
+                insert_expires(&mut response_headers, &upload);
+            }
+            (StatusCode::NO_CONTENT, response_headers).into_response()
+        }
+        Err(e) => tus_error(e),
+    }
+}
+
+/// Terminate upload handler
+pub async fn tus_terminate_handler(
+    State(manager): State<Arc<TusManager>>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = reject_version(&headers) {
+        return response;
+    }
+
+    match manager.terminate(id).await {
+        Ok(()) => (StatusCode::NO_CONTENT, tus_headers()).into_response(),
+        Err(e) => tus_error(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::StorageBackend;
+
+    async fn manager() -> TusManager {
+        let root = std::env::temp_dir().join(format!("forgebase-tus-{}", Uuid::new_v4()));
+        let service = StorageService::new(StorageBackend::Local(root))
+            .await
+            .unwrap();
+        TusManager::new(Arc::new(service), TusConfig::default())
+    }
+
+    fn body(data: &'static str) -> impl Stream<Item = std::result::Result<Bytes, std::io::Error>> {
+        futures::stream::iter(vec![Ok(Bytes::from_static(data.as_bytes()))])
+    }
+
+    #[test]
+    fn test_parse_upload_metadata() {
+        let metadata =
+            parse_upload_metadata("bucketName YXZhdGFycw==,objectName bWUucG5n, is_confidential")
+                .unwrap();
+        assert_eq!(metadata["bucketName"].as_deref(), Some("avatars"));
+        assert_eq!(metadata["objectName"].as_deref(), Some("me.png"));
+        assert_eq!(metadata["is_confidential"], None);
+
+        assert!(parse_upload_metadata("a YQ==,a Yg==").is_err());
+        assert!(parse_upload_metadata("a !!!").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resumable_upload_lifecycle() {
+        let manager = manager().await;
+        // bucketName=docs, objectName=notes.txt
+        let upload = manager
+            .create(11, Some("bucketName ZG9jcw==,objectName bm90ZXMudHh0"), None)
+            .await
+            .unwrap();
+        assert_eq!(upload.content_type.as_deref(), Some("text/plain"));
+
+        let upload = manager.append(upload.id, 0, body("hello ")).await.unwrap();
+        assert_eq!(upload.offset, 6);
+        assert!(matches!(
+            manager.append(upload.id, 0, body("hello ")).await,
+            Err(ForgeBaseError::Conflict(_))
+        ));
+        assert!(matches!(
+            manager.append(upload.id, 6, body("world and more")).await,
+            Err(ForgeBaseError::InvalidInput(_))
+        ));
+        assert_eq!(manager.get(upload.id).await.unwrap().offset, 6);
+
+        let upload = manager.append(upload.id, 6, body("world")).await.unwrap();
+        assert!(upload.is_complete());
+        assert_eq!(
+            manager
+                .service
+                .download_file("docs", "notes.txt")
+                .await
+                .unwrap(),
+            Bytes::from_static(b"hello world")
+        );
+        assert!(manager.get(upload.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upload_length_beyond_i64_is_rejected() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+        headers.insert(UPLOAD_LENGTH, HeaderValue::from_static("18446744073709551615"));
+        headers.insert(
+            UPLOAD_METADATA,
+            HeaderValue::from_static("bucketName ZG9jcw==,objectName bm90ZXMudHh0"),
+        );
+
+        let mut limited = manager().await;
+        limited.config.max_size = Some(1024);
+        let response = tus_create_handler(State(Arc::new(limited)), None, headers.clone()).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let unlimited = Arc::new(manager().await);
+        let response = tus_create_handler(State(unlimited.clone()), None, headers).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(unlimited.create(u64::MAX, None, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_chunk_keeps_received_bytes() {
+        let manager = manager().await;
+        let upload = manager
+            .create(10, Some("bucketName ZG9jcw==,objectName bm90ZXMudHh0"), None)
+            .await
+            .unwrap();
+
+        let broken = futures::stream::iter(vec![
+            Ok(Bytes::from_static(b"1234")),
+            Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "reset",
+            )),
+        ]);
+        let upload = manager.append(upload.id, 0, broken).await.unwrap();
+        assert_eq!(upload.offset, 4);
+
+        manager.terminate(upload.id).await.unwrap();
+        assert!(manager.get(upload.id).await.is_err());
+    }
+}
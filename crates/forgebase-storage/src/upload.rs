@@ -19,18 +19,27 @@ impl UploadValidator {
 
     /// Validate upload
     pub fn validate(&self, data: &Bytes, content_type: Option<&str>) -> Result<()> {
-        // Check file size
+        self.validate_size(data.len() as u64)?;
+        self.validate_content_type(content_type)
+    }
+
+    /// Check a file size, or the bytes received so far of a streamed upload
+    pub fn validate_size(&self, size: u64) -> Result<()> {
         if let Some(max_size) = self.max_file_size {
-            if data.len() as i64 > max_size {
+            // Sizes beyond i64 are over any limit rather than wrapping negative
+            if !i64::try_from(size).is_ok_and(|size| size <= max_size) {
                 return Err(ForgeBaseError::Validation(format!(
                     "File size {} exceeds maximum allowed size {}",
-                    data.len(),
-                    max_size
+                    size, max_size
                 )));
             }
         }
 
-        // Check MIME type
+        Ok(())
+    }
+
+    /// Check a content type against the allowed MIME types
+    pub fn validate_content_type(&self, content_type: Option<&str>) -> Result<()> {
         if let Some(ref allowed_types) = self.allowed_mime_types {
             if let Some(content_type) = content_type {
                 if !allowed_types.iter().any(|t| {
@@ -120,3 +129,19 @@ impl UploadProgress {
         self.uploaded_bytes >= self.total_bytes
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_size() {
+        let validator = UploadValidator::new(Some(1024), None);
+        assert!(validator.validate_size(1024).is_ok());
+        assert!(validator.validate_size(1025).is_err());
+        assert!(validator.validate_size(u64::MAX).is_err());
+        assert!(validator.validate_size(1 << 63).is_err());
+
+        assert!(UploadValidator::new(None, None).validate_size(u64::MAX).is_ok());
+    }
+}
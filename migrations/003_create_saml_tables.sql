@@ -0,0 +1,27 @@
+-- Create saml_providers table
+CREATE TABLE IF NOT EXISTS saml_providers (
+    id UUID PRIMARY KEY,
+    entity_id TEXT NOT NULL UNIQUE,
+    sso_url TEXT NOT NULL,
+    certificates TEXT[] NOT NULL DEFAULT '{}',
+    metadata_xml TEXT NOT NULL,
+    metadata_url TEXT,
+    domains TEXT[] NOT NULL DEFAULT '{}',
+    attribute_mapping JSONB NOT NULL DEFAULT '{}',
+    jit_provisioning BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_saml_providers_domains ON saml_providers USING GIN(domains);
+
+-- Create saml_requests table (pending AuthnRequests, consumed by the ACS endpoint)
+CREATE TABLE IF NOT EXISTS saml_requests (
+    id TEXT PRIMARY KEY,
+    provider_id UUID NOT NULL REFERENCES saml_providers(id) ON DELETE CASCADE,
+    redirect_to TEXT,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_saml_requests_expires_at ON saml_requests(expires_at);
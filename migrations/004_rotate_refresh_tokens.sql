@@ -0,0 +1,24 @@
+-- Refresh tokens are rotated on every use and stored only as SHA-256 hashes.
+-- Each session is a token family; `parent` links a token to the one it replaced.
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+    id UUID PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    parent TEXT,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_refresh_tokens_session_id ON refresh_tokens(session_id);
+CREATE INDEX idx_refresh_tokens_parent ON refresh_tokens(parent);
+
+-- Keep existing sessions signed in by hashing their current token
+INSERT INTO refresh_tokens (id, session_id, user_id, token_hash, parent, revoked, created_at, updated_at)
+SELECT gen_random_uuid(), id, user_id, encode(sha256(convert_to(refresh_token, 'UTF8')), 'hex'),
+       NULL, FALSE, created_at, NOW()
+FROM sessions;
+
+DROP INDEX IF EXISTS idx_sessions_refresh_token;
+ALTER TABLE sessions DROP COLUMN refresh_token;
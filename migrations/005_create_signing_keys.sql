@@ -0,0 +1,17 @@
+-- Asymmetric JWT signing keys. At most one key is `current` (signs tokens);
+-- `next` is published ahead of rotation and `previous` still verifies older tokens.
+CREATE TABLE IF NOT EXISTS jwt_signing_keys (
+    id UUID PRIMARY KEY,
+    kid TEXT NOT NULL UNIQUE,
+    algorithm VARCHAR(10) NOT NULL,
+    status VARCHAR(10) NOT NULL CHECK (status IN ('next', 'current', 'previous', 'revoked')),
+    private_key_pem TEXT NOT NULL,
+    public_jwk JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    activated_at TIMESTAMP WITH TIME ZONE,
+    retired_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE UNIQUE INDEX idx_jwt_signing_keys_current ON jwt_signing_keys(status) WHERE status = 'current';
+CREATE UNIQUE INDEX idx_jwt_signing_keys_next ON jwt_signing_keys(status) WHERE status = 'next';
+CREATE INDEX idx_jwt_signing_keys_status ON jwt_signing_keys(status);
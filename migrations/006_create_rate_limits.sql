@@ -0,0 +1,9 @@
+-- Shared rate limit counters (sliding window). Unlogged: losing counters on crash is fine.
+CREATE UNLOGGED TABLE IF NOT EXISTS rate_limits (
+    key TEXT NOT NULL,
+    window_start TIMESTAMP WITH TIME ZONE NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (key, window_start)
+);
+
+CREATE INDEX idx_rate_limits_window_start ON rate_limits(window_start);
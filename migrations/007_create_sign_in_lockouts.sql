@@ -0,0 +1,18 @@
+-- Failed sign-in attempts, counted per account and per IP within a sliding window
+CREATE TABLE IF NOT EXISTS sign_in_failures (
+    id BIGSERIAL PRIMARY KEY,
+    email TEXT NOT NULL,
+    ip_address VARCHAR(45),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_sign_in_failures_email ON sign_in_failures(email, created_at);
+CREATE INDEX idx_sign_in_failures_ip ON sign_in_failures(ip_address, created_at);
+
+-- Temporary lockouts keyed by 'email:<address>' or 'ip:<address>'.
+-- Keyed by email rather than user so unknown addresses lock the same way.
+CREATE TABLE IF NOT EXISTS sign_in_lockouts (
+    key TEXT PRIMARY KEY,
+    locked_until TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
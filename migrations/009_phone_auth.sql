@@ -0,0 +1,36 @@
+-- Phone-only users have no email address
+ALTER TABLE users ALTER COLUMN email DROP NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_phone ON users(phone) WHERE phone IS NOT NULL;
+
+-- Authenticator assurance level: aal1 (one factor) or aal2 (second factor verified)
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS aal VARCHAR(10) NOT NULL DEFAULT 'aal1';
+
+-- One-time codes sent by SMS. Used codes are kept (consumed) so they still count
+-- towards the per-number send limit until cleanup.
+CREATE TABLE IF NOT EXISTS phone_otps (
+    id UUID PRIMARY KEY,
+    phone VARCHAR(20) NOT NULL,
+    purpose VARCHAR(20) NOT NULL, -- sign_in, phone_change, mfa
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    consumed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_phone_otps_phone ON phone_otps(phone, created_at);
+
+-- Second factors enrolled by users
+CREATE TABLE IF NOT EXISTS mfa_factors (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    factor_type VARCHAR(20) NOT NULL, -- sms
+    phone VARCHAR(20),
+    verified BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE(user_id, factor_type, phone)
+);
+
+CREATE INDEX idx_mfa_factors_user_id ON mfa_factors(user_id);
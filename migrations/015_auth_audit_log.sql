@@ -0,0 +1,21 @@
+-- Security events written by the auth service. user_id has no foreign key so entries
+-- outlive deleted users.
+CREATE TABLE IF NOT EXISTS auth_audit_log (
+    id UUID PRIMARY KEY,
+    action TEXT NOT NULL,
+    outcome TEXT NOT NULL CHECK (outcome IN ('success', 'failure')),
+    user_id UUID,
+    actor_id TEXT,
+    email TEXT,
+    ip_address TEXT,
+    user_agent TEXT,
+    factor TEXT,
+    session_id UUID,
+    reason TEXT,
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_auth_audit_log_created ON auth_audit_log(created_at, id);
+CREATE INDEX IF NOT EXISTS idx_auth_audit_log_user_created ON auth_audit_log(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_auth_audit_log_action_created ON auth_audit_log(action, created_at);
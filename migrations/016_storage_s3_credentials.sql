@@ -0,0 +1,14 @@
+-- Access keys for the S3-compatible storage API. SigV4 signs every request with the
+-- secret itself, so it is stored as issued rather than hashed.
+CREATE TABLE IF NOT EXISTS storage_s3_credentials (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    access_key_id TEXT NOT NULL UNIQUE,
+    secret_access_key TEXT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_storage_s3_credentials_user ON storage_s3_credentials(user_id);
@@ -0,0 +1,37 @@
+-- Buckets were never created by a migration, so define them for fresh databases
+CREATE TABLE IF NOT EXISTS storage_buckets (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    owner_id UUID NOT NULL,
+    is_public BOOLEAN NOT NULL DEFAULT FALSE,
+    max_file_size BIGINT,
+    allowed_mime_types TEXT[],
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    organization_id UUID REFERENCES organizations(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_storage_buckets_owner ON storage_buckets(owner_id);
+
+-- Metadata of every stored object, kept in sync by the storage service
+CREATE TABLE IF NOT EXISTS storage_objects (
+    id UUID PRIMARY KEY,
+    bucket TEXT NOT NULL,
+    key TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    content_type TEXT,
+    etag TEXT,
+    metadata JSONB NOT NULL DEFAULT '{}',
+    owner_id UUID,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (bucket, key)
+);
+
+-- text_pattern_ops lets prefix LIKE queries use the index
+CREATE INDEX IF NOT EXISTS idx_storage_objects_key_prefix ON storage_objects(bucket, key text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_storage_objects_size ON storage_objects(bucket, size, key);
+CREATE INDEX IF NOT EXISTS idx_storage_objects_created ON storage_objects(bucket, created_at, key);
+CREATE INDEX IF NOT EXISTS idx_storage_objects_updated ON storage_objects(bucket, updated_at, key);
+CREATE INDEX IF NOT EXISTS idx_storage_objects_metadata ON storage_objects USING GIN (metadata);
+CREATE INDEX IF NOT EXISTS idx_storage_objects_owner ON storage_objects(owner_id);
@@ -0,0 +1,13 @@
+-- Declarative per-bucket access policies. Each grants some operations on keys under a
+-- prefix, which may contain placeholders such as {auth.uid} filled in from the caller's claims.
+CREATE TABLE IF NOT EXISTS storage_policies (
+    id UUID PRIMARY KEY,
+    bucket_id UUID NOT NULL REFERENCES storage_buckets(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    operations TEXT[] NOT NULL,
+    prefix TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (bucket_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_storage_policies_bucket ON storage_policies(bucket_id);
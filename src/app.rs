@@ -5,7 +5,8 @@ use axum::{
     Json, Router,
 };
 use serde_json::json;
-use forgebase_core::Config;
+use forgebase_auth::AuthService;
+use forgebase_core::{AuthConfig, Config};
 use forgebase_db::DatabasePool;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
@@ -20,6 +21,9 @@ pub struct AppState {
 
 /// Create the application router
 pub async fn create_app(db_pool: DatabasePool, config: Config) -> Router {
+    // Expired auth data is cleaned up even before the auth routes are mounted
+    Arc::new(auth_service(&db_pool, &config.auth)).spawn_maintenance_tasks();
+
     let state = AppState {
         db: db_pool,
         config: Arc::new(config),
@@ -45,6 +49,26 @@ pub async fn create_app(db_pool: DatabasePool, config: Config) -> Router {
     app
 }
 
+/// Auth service set up from configuration
+fn auth_service(db_pool: &DatabasePool, config: &AuthConfig) -> AuthService {
+    let mut service = AuthService::new(
+        db_pool.pool().clone(),
+        config.jwt_secret.clone(),
+        config.jwt_expiration,
+        config.refresh_token_expiration / 86_400,
+    )
+    .with_brute_force_protection(config.brute_force.clone())
+    .with_anonymous_users(config.anonymous.clone())
+    .with_audit_log(config.audit_log.clone());
+    if let Some(saml) = &config.saml {
+        service = service.with_saml(saml.clone());
+    }
+    if let Some(sms) = &config.sms {
+        service = service.with_sms(sms.clone(), forgebase_auth::sms::provider_from_config(sms));
+    }
+    service
+}
+
 async fn root_handler(axum::extract::State(state): axum::extract::State<AppState>) -> Json<serde_json::Value> {
     Json(json!({
         "name": "ForgeBase",